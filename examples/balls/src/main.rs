@@ -1,5 +1,6 @@
 use raytracer::*;
 use raytracer::hittable::hittables::*;
+use raytracer::hittable::plane::*;
 use raytracer::hittable::sphere::*;
 use raytracer::hittable::*;
 use raytracer::vector3d::*;
@@ -15,9 +16,9 @@ fn main() {
         Point3D::with_values(0.0, 0.0, -1.0),
         0.5,
     )));
-    world.push(Rc::new(Sphere::new(
-        Point3D::with_values(0.0, -100.5, -1.0),
-        100.0,
+    world.push(Rc::new(Plane::new(
+        Point3D::with_values(0.0, -0.5, 0.0),
+        Vector3D::with_values(0.0, 1.0, 0.0),
     )));
 
     // Camera
@@ -0,0 +1,26 @@
+use raytracer::camera::*;
+use raytracer::import::gltf;
+use raytracer::integrator::path_tracer::PathTracer;
+use std::env;
+
+fn main() {
+    // Scene
+
+    let path = env::args()
+        .nth(1)
+        .expect("Usage: cargo run -- <scene.gltf|scene.glb> > image.ppm");
+    let scene = gltf::load(&path).expect("Failed to load the scene");
+
+    // Camera, the first one of the scene if there is one
+
+    let mut cam: Camera = match scene.cameras().first() {
+        Some(camera) => camera.to_camera(
+            400, // image_width
+            100, // samples_per_pixel
+            50,  // max_depth
+        ),
+        None => Camera::new(16.0 / 9.0, 400, 100, 50),
+    };
+
+    cam.render_with(scene.world(), &PathTracer::new(scene.lights().clone()));
+}
@@ -0,0 +1,249 @@
+//! Module for representing axis-aligned bounding boxes in the context of a raytracer.
+//!
+//! Used to quickly skip groups of objects a ray can't possibly hit.
+
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vector3d::{Point3D, Vector3D};
+
+/// Widens the far bound of the slab test by 1 + 2γ₃, where γₙ = nε / (1 - nε) bounds
+/// the rounding of n operations, so rays grazing a box never miss what lies within.
+const FAR_PADDING: f64 = 1.0 + 2.0 * (3.0 * f64::EPSILON / 2.0) / (1.0 - 3.0 * f64::EPSILON / 2.0);
+
+/// Represents a box spanned by its minimum and maximum corner, aligned to the axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Point3D,
+    max: Point3D,
+}
+
+impl Default for Aabb {
+    /// Creates an empty box, containing nothing.
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
+impl Aabb {
+    /// Creates an empty box (with +∞ as the minimum and -∞ as the maximum corner).
+    pub const fn new_empty() -> Self {
+        Aabb {
+            min: Point3D::with_values(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3D::with_values(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// Creates the smallest box containing the two given points.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - One corner of the box.
+    /// * `b` - The opposite corner of the box.
+    pub fn new(a: Point3D, b: Point3D) -> Self {
+        Self::new_empty().grow(a).grow(b)
+    }
+
+    /// Gets the minimum corner of the box.
+    pub fn min(&self) -> Point3D {
+        self.min
+    }
+
+    /// Gets the maximum corner of the box.
+    pub fn max(&self) -> Point3D {
+        self.max
+    }
+
+    /// Checks if the box contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    /// Returns the smallest box containing the box and the point `p`.
+    pub fn grow(self, p: Point3D) -> Self {
+        Aabb {
+            min: Point3D::with_values(
+                self.min.x().min(p.x()),
+                self.min.y().min(p.y()),
+                self.min.z().min(p.z()),
+            ),
+            max: Point3D::with_values(
+                self.max.x().max(p.x()),
+                self.max.y().max(p.y()),
+                self.max.z().max(p.z()),
+            ),
+        }
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(self, other: &Aabb) -> Self {
+        if other.is_empty() {
+            return self;
+        }
+        self.grow(other.min).grow(other.max)
+    }
+
+    /// Gets the center of the box.
+    pub fn centroid(&self) -> Point3D {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Gets the vector from the minimum to the maximum corner, zero for empty boxes.
+    pub fn diagonal(&self) -> Vector3D {
+        if self.is_empty() {
+            Vector3D::new()
+        } else {
+            self.max - self.min
+        }
+    }
+
+    /// Finds the axis the box is longest along, 0 (x), 1 (y) or 2 (z).
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Computes the surface area of the box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Checks if a ray passes through the box.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`             - The ray to check.
+    /// * `inv_direction` - The componentwise inverse of the ray's direction.
+    /// * `ray_t`         - The interval along the ray to consider.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the part of the ray within `ray_t` passes through the box.
+    pub fn hit(&self, r: &Ray, inv_direction: Vector3D, ray_t: Interval) -> bool {
+        let (mut t_min, mut t_max) = (ray_t.min, ray_t.max);
+
+        for axis in 0..3 {
+            let origin = r.origin().axis(axis);
+            let inv = inv_direction.axis(axis);
+            let t0 = (self.min.axis(axis) - origin) * inv;
+            let t1 = (self.max.axis(axis) - origin) * inv;
+            let (t0, t1) = if inv < 0.0 { (t1, t0) } else { (t0, t1) };
+            let t1 = t1 * FAR_PADDING;
+
+            // Rays within a face of the box give NaN, which `max`/`min` skip
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aabb::Aabb;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+
+    fn inverse(d: Vector3D) -> Vector3D {
+        Vector3D::with_values(1.0 / d.x(), 1.0 / d.y(), 1.0 / d.z())
+    }
+
+    #[test]
+    fn aabb_new() {
+        let aabb = Aabb::new(
+            Point3D::with_values(1.0, -1.0, 2.0),
+            Point3D::with_values(-1.0, 1.0, 0.0),
+        );
+
+        assert_eq!(aabb.min(), Point3D::with_values(-1.0, -1.0, 0.0));
+        assert_eq!(aabb.max(), Point3D::with_values(1.0, 1.0, 2.0));
+        assert_eq!(aabb.centroid(), Point3D::with_values(0.0, 0.0, 1.0));
+        assert_eq!(aabb.surface_area(), 24.0);
+        assert!(Aabb::default().is_empty(), "Default box not empty");
+        assert_eq!(Aabb::default().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn aabb_union() {
+        let a = Aabb::new(Point3D::new(), Point3D::with_values(1.0, 1.0, 1.0));
+        let b = Aabb::new(
+            Point3D::with_values(2.0, 0.0, 0.0),
+            Point3D::with_values(4.0, 1.0, 1.0),
+        );
+
+        let union = a.union(&b);
+        assert_eq!(
+            union,
+            Aabb::new(Point3D::new(), Point3D::with_values(4.0, 1.0, 1.0))
+        );
+        assert_eq!(union.longest_axis(), 0);
+        assert_eq!(a.union(&Aabb::new_empty()), a, "Empty box grew the union");
+        assert_eq!(Aabb::new_empty().union(&a), a);
+    }
+
+    #[test]
+    fn aabb_hit() {
+        //           +-----+ (3,1)
+        //   o------>|-----|---->      through the box,
+        // (0,0)     |     |
+        //           +-----+           grazing its side,
+        //   o-------------------->    or stopping short of it
+        //  (0,-1) (1,-1)
+        let aabb = Aabb::new(
+            Point3D::with_values(1.0, -1.0, -1.0),
+            Point3D::with_values(3.0, 1.0, 1.0),
+        );
+        let d = Vector3D::with_values(1.0, 0.0, 0.0);
+        let through = Ray::create(Point3D::new(), d);
+        let side = Ray::create(Point3D::with_values(0.0, -1.0, 0.0), d);
+        let everything = Interval::new(0.0, f64::INFINITY);
+
+        assert!(aabb.hit(&through, inverse(d), everything), "Box not hit");
+        assert!(aabb.hit(&side, inverse(d), everything), "Side not grazed");
+        assert!(!aabb.hit(&through, inverse(d), Interval::new(0.0, 0.5)));
+        assert!(
+            !aabb.hit(&through, inverse(-d), everything),
+            "Box behind hit"
+        );
+
+        let above = Ray::create(
+            Point3D::with_values(0.0, 2.0, 0.0),
+            Vector3D::with_values(1.0, 0.1, 0.0),
+        );
+        assert!(!aabb.hit(&above, inverse(above.direction()), everything));
+    }
+
+    #[test]
+    fn aabb_hit_grazing() {
+        // A ray through a corner of a flat triangle passes through the box around it,
+        // even though the slab distances of its axes round apart
+        let vertices = [
+            Point3D::with_values(0.1, 0.7, 0.3),
+            Point3D::with_values(1.0 / 3.0, 0.7, 0.3),
+            Point3D::with_values(0.2, 0.9, 0.3),
+        ];
+        let aabb = Aabb::new(vertices[0], vertices[1]).grow(vertices[2]);
+        let r = Ray::create(
+            Point3D::with_values(-0.524589836963349, 0.9762642054003545, -0.36377662800879174),
+            Vector3D::with_values(0.8579231702966823, -0.27626420540035457, 0.6637766280087918),
+        );
+        let everything = Interval::new(0.0, f64::INFINITY);
+
+        assert!(crate::hittable::triangle::intersect(&r, &vertices, everything).is_some());
+        assert!(
+            aabb.hit(&r, inverse(r.direction()), everything),
+            "Grazing ray missed the box"
+        );
+    }
+}
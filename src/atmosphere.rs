@@ -0,0 +1,549 @@
+//! Module for a participating medium filling the whole scene, like air or fog,
+//! in the context of a raytracer.
+//!
+//! Every layer of the atmosphere thins out exponentially with height above the
+//! ground and keeps the density of the ground below it. Along a ray the light of
+//! whatever lies behind gets dimmed and light scattered by the layers gets added,
+//! so distant objects fade into the haze.
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::{HenyeyGreenstein, Material, Rayleigh};
+use crate::ray::Ray;
+use crate::sampler::random_f64;
+use crate::vector3d::{Point3D, Vector3D};
+use std::rc::Rc;
+
+/// Represents a layer of particles, like air molecules, aerosols or fog droplets.
+#[derive(Clone)]
+struct Layer {
+    scattering: Color,
+    absorption: Color,
+    scale_height: f64,
+    phase_function: Rc<dyn Material>,
+}
+
+impl Layer {
+    /// Computes the density relative to the ground at the height `y`.
+    fn density(&self, y: f64, ground: f64) -> f64 {
+        (-(y - ground).max(0.0) / self.scale_height).exp()
+    }
+
+    /// Gets the chance of light getting scattered or absorbed per unit distance at the ground.
+    fn extinction(&self) -> Color {
+        self.scattering + self.absorption
+    }
+
+    /// Integrates the relative density along a straight line.
+    ///
+    /// # Arguments
+    ///
+    /// * `y`        - The height the line starts at.
+    /// * `dy`       - The change of height per unit distance along the line.
+    /// * `distance` - The length of the line, possibly infinite.
+    /// * `ground`   - The height of the ground.
+    fn depth(&self, y: f64, dy: f64, distance: f64, ground: f64) -> f64 {
+        // Distance until the line crosses the ground, if ever
+        let crossing = if dy != 0.0 {
+            (ground - y) / dy
+        } else {
+            f64::INFINITY
+        };
+
+        if y < ground || (y == ground && dy < 0.0) {
+            let below = if crossing > 0.0 {
+                crossing
+            } else {
+                f64::INFINITY
+            };
+            // Lines never rising above the ground stay at its density all along
+            if below >= distance {
+                return distance;
+            }
+            below + self.depth_above(0.0, dy, distance - below)
+        } else {
+            let above = if crossing > 0.0 {
+                crossing
+            } else {
+                f64::INFINITY
+            };
+            let above = above.min(distance);
+            let rest = distance - above;
+            let below = if rest > 0.0 { rest } else { 0.0 };
+            self.depth_above(y - ground, dy, above) + below
+        }
+    }
+
+    /// Integrates the density along a line starting `h` above the ground and staying above it.
+    fn depth_above(&self, h: f64, dy: f64, distance: f64) -> f64 {
+        if distance <= 0.0 {
+            return 0.0;
+        }
+
+        let start = (-h / self.scale_height).exp();
+        if dy == 0.0 || dy.abs() * distance < 1e-9 * self.scale_height {
+            return if start > 0.0 { start * distance } else { 0.0 };
+        }
+
+        let end = (-(h + dy * distance).max(0.0) / self.scale_height).exp();
+        self.scale_height / dy * (start - end)
+    }
+}
+
+/// Represents a medium filling the whole scene, made of layers of particles
+/// thinning out with height.
+#[derive(Clone)]
+pub struct Atmosphere {
+    layers: Vec<Layer>,
+    ground: f64,
+}
+
+impl Default for Atmosphere {
+    /// Creates an empty atmosphere with its ground at y = 0.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Atmosphere {
+    /// Creates an empty atmosphere with its ground at y = 0.
+    pub fn new() -> Self {
+        Atmosphere {
+            layers: Vec::new(),
+            ground: 0.0,
+        }
+    }
+
+    /// Creates the clear air of the earth, blue from Rayleigh scattering by its
+    /// molecules and hazy from Mie scattering by its aerosols.
+    ///
+    /// # Arguments
+    ///
+    /// * `meters_per_unit` - How many meters one unit of the scene spans.
+    pub fn earth(meters_per_unit: f64) -> Self {
+        // Coefficients per meter at sea level (Hillaire, 2020)
+        let per_unit = |c: Color| c * meters_per_unit;
+
+        Self::new()
+            .with_rayleigh(
+                per_unit(Color::with_values(5.802e-6, 13.558e-6, 33.1e-6)),
+                8000.0 / meters_per_unit,
+            )
+            .with_mie(
+                per_unit(Color::with_values(3.996e-6, 3.996e-6, 3.996e-6)),
+                per_unit(Color::with_values(4.4e-6, 4.4e-6, 4.4e-6)),
+                1200.0 / meters_per_unit,
+                0.8,
+            )
+    }
+
+    /// Moves the ground, the height the layers thin out above.
+    pub fn with_ground(mut self, height: f64) -> Self {
+        self.ground = height;
+        self
+    }
+
+    /// Adds a layer of particles far smaller than the wavelength of light, like air molecules.
+    ///
+    /// # Arguments
+    ///
+    /// * `scattering`   - The chance of scattering per unit distance at the ground, per color.
+    /// * `scale_height` - The height above the ground the density drops to 1/e.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale_height` isn't positive.
+    pub fn with_rayleigh(mut self, scattering: Color, scale_height: f64) -> Self {
+        assert!(scale_height > 0.0, "Scale height not positive");
+        self.layers.push(Layer {
+            scattering,
+            absorption: Color::new(),
+            scale_height,
+            phase_function: Rc::new(Rayleigh::new(Color::with_values(1.0, 1.0, 1.0))),
+        });
+        self
+    }
+
+    /// Adds a layer of particles about as large as the wavelength of light, like aerosols.
+    ///
+    /// # Arguments
+    ///
+    /// * `scattering`   - The chance of scattering per unit distance at the ground, per color.
+    /// * `absorption`   - The chance of absorption per unit distance at the ground, per color.
+    /// * `scale_height` - The height above the ground the density drops to 1/e.
+    /// * `g`            - The mean cosine of scattering, mostly forwards for aerosols.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale_height` isn't positive.
+    pub fn with_mie(
+        mut self,
+        scattering: Color,
+        absorption: Color,
+        scale_height: f64,
+        g: f64,
+    ) -> Self {
+        assert!(scale_height > 0.0, "Scale height not positive");
+        self.layers.push(Layer {
+            scattering,
+            absorption,
+            scale_height,
+            phase_function: Rc::new(HenyeyGreenstein::new(Color::with_values(1.0, 1.0, 1.0), g)),
+        });
+        self
+    }
+
+    /// Adds a fog thinning out quickly above the ground, scattering equally into every direction.
+    ///
+    /// # Arguments
+    ///
+    /// * `density`      - The chance of scattering or absorption per unit distance at the ground.
+    /// * `scale_height` - The height above the ground the density drops to 1/e.
+    /// * `albedo`       - The fraction of light scattered instead of absorbed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale_height` isn't positive.
+    pub fn with_height_fog(mut self, density: f64, scale_height: f64, albedo: Color) -> Self {
+        assert!(scale_height > 0.0, "Scale height not positive");
+        self.layers.push(Layer {
+            scattering: albedo * density,
+            absorption: (Color::with_values(1.0, 1.0, 1.0) - albedo) * density,
+            scale_height,
+            phase_function: Rc::new(HenyeyGreenstein::new(
+                Color::with_values(1.0, 1.0, 1.0),
+                0.0,
+            )),
+        });
+        self
+    }
+
+    /// Gets the height of the ground.
+    pub fn ground(&self) -> f64 {
+        self.ground
+    }
+
+    /// Returns `true` if the atmosphere has no layers and lets all light pass.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Computes the optical depth, per color, along a ray up to the parameter `t_max`.
+    fn optical_depth(&self, r: &Ray, t_max: f64) -> Color {
+        let length = r.direction().length();
+        let dy = r.direction().y() / length;
+        let distance = t_max * length;
+
+        self.layers.iter().fold(Color::new(), |depth, layer| {
+            depth + layer.extinction() * layer.depth(r.origin().y(), dy, distance, self.ground)
+        })
+    }
+
+    /// Computes the fraction of light passing along a ray unscattered, per color.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`     - The ray the light travels along.
+    /// * `t_max` - The parameter along the ray the light travels to, possibly infinite.
+    pub fn transmittance(&self, r: &Ray, t_max: f64) -> Color {
+        let depth = self.optical_depth(r, t_max);
+        Color::with_values((-depth.x()).exp(), (-depth.y()).exp(), (-depth.z()).exp())
+    }
+
+    /// Computes the chance of getting scattered or absorbed per unit distance at a point,
+    /// averaged over the colors.
+    fn mean_extinction(&self, p: Point3D) -> f64 {
+        self.layers
+            .iter()
+            .map(|layer| mean(layer.extinction()) * layer.density(p.y(), self.ground))
+            .sum()
+    }
+
+    /// Samples where along a ray light interacts with the atmosphere first.
+    ///
+    /// Distances are sampled by the optical depth averaged over the colors, the
+    /// returned weight corrects for every color differing from the average.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`     - The ray.
+    /// * `t_max` - The parameter along the ray the next surface is hit at, possibly infinite.
+    ///
+    /// # Returns
+    ///
+    /// Returns the parameter of the interaction, `None` if the ray passes up to
+    /// `t_max`, and the weight of the sample per color.
+    pub fn sample(&self, r: &Ray, t_max: f64) -> (Option<f64>, Color) {
+        if self.layers.is_empty() {
+            return (None, Color::with_values(1.0, 1.0, 1.0));
+        }
+
+        let target = -(1.0 - random_f64()).ln();
+        let total = mean(self.optical_depth(r, t_max));
+        if total <= target {
+            let transmittance = self.transmittance(r, t_max);
+            return (None, transmittance / (-total).exp());
+        }
+
+        // The optical depth grows monotonically along the ray, find where it reaches the target
+        let mut high = if t_max.is_finite() { t_max } else { 1.0 };
+        while mean(self.optical_depth(r, high)) < target {
+            high *= 2.0;
+        }
+        let mut low = 0.0;
+        for _ in 0..64 {
+            let mid = 0.5 * (low + high);
+            if mean(self.optical_depth(r, mid)) < target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let t = 0.5 * (low + high);
+
+        // Density of sampling t per unit distance: σ̄(t)·e^(-τ̄(t))
+        let pdf = self.mean_extinction(r.at(t)) * (-mean(self.optical_depth(r, t))).exp();
+        if pdf <= 0.0 {
+            return (Some(t), Color::new());
+        }
+        (Some(t), self.transmittance(r, t) / pdf)
+    }
+
+    /// Gets the phase function scattering light at a point within the atmosphere,
+    /// including the chance of scattering per unit distance there.
+    pub fn scattering_at(&self, p: Point3D) -> Rc<dyn Material> {
+        let lobes = self
+            .layers
+            .iter()
+            .map(|layer| {
+                (
+                    layer.scattering * layer.density(p.y(), self.ground),
+                    layer.phase_function.clone(),
+                )
+            })
+            .collect();
+
+        Rc::new(Scattering { lobes })
+    }
+}
+
+/// Averages the components of a color.
+fn mean(c: Color) -> f64 {
+    (c.x() + c.y() + c.z()) / 3.0
+}
+
+/// The scattering of all layers at a point, each phase function weighted by its
+/// chance of scattering per unit distance.
+struct Scattering {
+    lobes: Vec<(Color, Rc<dyn Material>)>,
+}
+
+impl Material for Scattering {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        // Choose a phase function by how much it scatters
+        let total: f64 = self.lobes.iter().map(|(c, _)| mean(*c)).sum();
+        if total <= 0.0 {
+            return (false, Color::new(), *r_in);
+        }
+
+        let mut u = random_f64() * total;
+        let mut chosen = &self.lobes[self.lobes.len() - 1].1;
+        for (c, phase_function) in &self.lobes {
+            if u < mean(*c) {
+                chosen = phase_function;
+                break;
+            }
+            u -= mean(*c);
+        }
+
+        let (_, _, scattered) = chosen.scatter(r_in, rec);
+        let wo = -r_in.direction().unit_vector();
+        let wi = scattered.direction().unit_vector();
+        let pdf = self.pdf(rec, wo, wi);
+        if pdf <= 0.0 {
+            return (false, Color::new(), scattered);
+        }
+
+        (true, self.eval(rec, wo, wi) / pdf, scattered)
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> Color {
+        self.lobes
+            .iter()
+            .fold(Color::new(), |sum, (c, phase_function)| {
+                sum + *c * phase_function.eval(rec, wo, wi)
+            })
+    }
+
+    fn pdf(&self, rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> f64 {
+        let total: f64 = self.lobes.iter().map(|(c, _)| mean(*c)).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        self.lobes
+            .iter()
+            .map(|(c, phase_function)| mean(*c) * phase_function.pdf(rec, wo, wi))
+            .sum::<f64>()
+            / total
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::atmosphere::Atmosphere;
+    use crate::color::Color;
+    use crate::hittable::HitRecord;
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+
+    #[test]
+    fn atmosphere_transmittance() {
+        //  ~~~~~~~~~~~~~~~~~~~~~~    A layer barely thinning out, seen
+        //  o------------------->X    horizontally over 10 units: e^(-σ·10)
+        // ======================= ground
+        let fog = Atmosphere::new().with_height_fog(0.1, 1e12, Color::with_values(0.5, 0.5, 0.5));
+        let ray = Ray::create(
+            Point3D::with_values(0.0, 1.0, 0.0),
+            Vector3D::with_values(2.0, 0.0, 0.0),
+        );
+
+        let transmittance = fog.transmittance(&ray, 5.0);
+        assert!(
+            (transmittance.x() - (-1.0f64).exp()).abs() < 1e-9,
+            "Transmittance {}",
+            transmittance
+        );
+        assert_eq!(
+            fog.transmittance(&ray, f64::INFINITY),
+            Color::new(),
+            "Endless fog passed"
+        );
+
+        // Straight up through an exponential layer: e^(-σ·H·e^(-h/H))
+        let air = Atmosphere::new().with_rayleigh(Color::with_values(0.1, 0.2, 0.4), 2.0);
+        let up = Ray::create(
+            Point3D::with_values(0.0, 1.0, 0.0),
+            Vector3D::with_values(0.0, 1.0, 0.0),
+        );
+        let expected = (-0.4f64 * 2.0 * (-0.5f64).exp()).exp();
+        let transmittance = air.transmittance(&up, f64::INFINITY);
+        assert!(
+            (transmittance.z() - expected).abs() < 1e-9,
+            "Transmittance {}",
+            transmittance
+        );
+        assert!(
+            transmittance.x() > transmittance.z(),
+            "Blue not scattered most"
+        );
+
+        // Down through the ground, the density stays the one at the ground
+        let down = Ray::create(
+            Point3D::with_values(0.0, 1.0, 0.0),
+            Vector3D::with_values(0.0, -1.0, 0.0),
+        );
+        let expected = 0.4 * (2.0 * (1.0 - (-0.5f64).exp()) + 1.0);
+        let transmittance = air.transmittance(&down, 2.0);
+        assert!((transmittance.z() - (-expected).exp()).abs() < 1e-9);
+
+        // Horizontally below the ground, at the density of the ground all along
+        let buried = Ray::create(
+            Point3D::with_values(0.0, -1.0, 0.0),
+            Vector3D::with_values(1.0, 0.0, 0.0),
+        );
+        let transmittance = air.transmittance(&buried, 2.0);
+        assert!((transmittance.z() - (-0.8f64).exp()).abs() < 1e-9);
+        assert_eq!(
+            air.transmittance(&buried, f64::INFINITY),
+            Color::new(),
+            "Endless ground passed"
+        );
+        assert!(Atmosphere::new().is_empty());
+    }
+
+    #[test]
+    fn atmosphere_sample() {
+        // Weighted by the sampled distances, the light passing is the transmittance of every color
+        let air = Atmosphere::new().with_rayleigh(Color::with_values(0.05, 0.1, 0.2), 3.0);
+        let ray = Ray::create(
+            Point3D::with_values(0.0, 0.5, 0.0),
+            Vector3D::with_values(1.0, 0.3, 0.0),
+        );
+        let t_max = 5.0;
+
+        let samples = 20000;
+        let mut passed = Color::new();
+        for _ in 0..samples {
+            let (t, weight) = air.sample(&ray, t_max);
+            match t {
+                Some(t) => {
+                    assert!(
+                        t > 0.0 && t < t_max,
+                        "Interaction at {} beyond the surface",
+                        t
+                    )
+                }
+                None => passed += weight / f64::from(samples),
+            }
+        }
+
+        let transmittance = air.transmittance(&ray, t_max);
+        assert!(
+            (passed - transmittance).length() < 0.02,
+            "Passing {} not {}",
+            passed,
+            transmittance
+        );
+    }
+
+    #[test]
+    fn atmosphere_scattering() {
+        let air =
+            Atmosphere::earth(1.0).with_height_fog(0.01, 10.0, Color::with_values(0.9, 0.9, 0.9));
+        let rec = HitRecord::new(
+            Point3D::new(),
+            Vector3D::with_values(1.0, 0.0, 0.0),
+            1.0,
+            true,
+        );
+        let phase = air.scattering_at(rec.p());
+        assert!(phase.is_volumetric());
+
+        // The mixed phase functions are sampled by their own density
+        let ray = Ray::create(Point3D::new(), Vector3D::with_values(0.0, 0.0, -1.0));
+        let wo = -ray.direction();
+        for _ in 0..100 {
+            let (scattered, weight, scattered_ray) = phase.scatter(&ray, &rec);
+            assert!(scattered);
+            let wi = scattered_ray.direction().unit_vector();
+            let expected = phase.eval(&rec, wo, wi) / phase.pdf(&rec, wo, wi);
+            assert!(
+                (weight - expected).near_zero(),
+                "Weight {} not {}",
+                weight,
+                expected
+            );
+        }
+
+        // High above the ground hardly anything scatters
+        let thin = air.scattering_at(Point3D::with_values(0.0, 1e6, 0.0));
+        assert!(thin.eval(&rec, wo, -wo).length() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn atmosphere_flat_fog() {
+        Atmosphere::new().with_height_fog(0.1, 0.0, Color::with_values(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn atmosphere_negative_scale_height() {
+        Atmosphere::new().with_rayleigh(Color::with_values(0.1, 0.2, 0.4), -8.0);
+    }
+}
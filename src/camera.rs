@@ -1,12 +1,15 @@
 //! Module for representing a camera in the context of a raytracer.
 
 use crate::color::Color;
+use crate::environment::Environment;
 use crate::hittable::hittables::HittableList;
 use crate::hittable::{HitRecord, Hittable};
-use crate::interval::{Interval, EMPTY, UNIVERSE};
+use crate::integrator::path_tracer::PathTracer;
+use crate::integrator::{ImageIntegrator, Integrator};
+use crate::interval::Interval;
 use crate::ray::Ray;
+use crate::sampler::random_f64;
 use crate::vector3d::{Point3D, Vector3D};
-use rand::prelude::*;
 use std::io::Write;
 
 /// Represents a camera in a 3D scene.
@@ -16,13 +19,16 @@ pub struct Camera {
     samples_per_pixel: u16,
     max_depth: u16,
     image_height: u16,
+    vertical_fov: f64,
     center: Point3D,
+    direction: Vector3D,
+    up: Vector3D,
     pixel00_loc: Point3D,
-    pixel_Δu: Vector3D,
-    pixel_Δv: Vector3D,
+    pixel_delta_u: Vector3D,
+    pixel_delta_v: Vector3D,
 }
 
-impl Camera {
+impl Default for Camera {
     /// Creates a new `Camera` with default parameters and initializes its settings.
     ///
     /// The `default` method initializes a `Camera` instance with default settings,
@@ -44,10 +50,12 @@ impl Camera {
     /// // Create a default camera with initialized settings.
     /// let camera = Camera::default();
     /// ```
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self::new(1.0, 100, 10, 10)
     }
+}
 
+impl Camera {
     /// Creates a new `Camera` with the specified aspect ratio and image width,
     /// and initializes its settings for rendering.
     ///
@@ -83,20 +91,49 @@ impl Camera {
         max_depth: u16,
     ) -> Self {
         let mut cam: Camera = Camera {
-            aspect_ratio: aspect_ratio,
-            image_width: image_width,
-            samples_per_pixel: samples_per_pixel,
-            max_depth: max_depth,
+            aspect_ratio,
+            image_width,
+            samples_per_pixel,
+            max_depth,
             image_height: 0,
+            vertical_fov: 90.0,
             center: Point3D::new(),
+            direction: Vector3D::with_values(0.0, 0.0, -1.0),
+            up: Vector3D::with_values(0.0, 1.0, 0.0),
             pixel00_loc: Point3D::new(),
-            pixel_Δu: Vector3D::new(),
-            pixel_Δv: Vector3D::new(),
+            pixel_delta_u: Vector3D::new(),
+            pixel_delta_v: Vector3D::new(),
         };
         cam.initialize();
         cam
     }
 
+    /// Sets how wide the camera sees, 90° unless set.
+    ///
+    /// # Arguments
+    ///
+    /// * `degrees` - The angle between the top and the bottom edge of the image.
+    pub fn with_vertical_fov(mut self, degrees: f64) -> Self {
+        self.vertical_fov = degrees;
+        self.initialize();
+        self
+    }
+
+    /// Places the camera in the scene, at the origin looking along -z unless set.
+    ///
+    /// # Arguments
+    ///
+    /// * `center`    - The position of the camera.
+    /// * `direction` - The direction the camera looks in.
+    /// * `up`        - The direction pointing up in the image, needn't be perpendicular to `direction`.
+    pub fn with_view(mut self, center: Point3D, direction: Vector3D, up: Vector3D) -> Self {
+        self.center = center;
+        self.direction = direction.unit_vector();
+        self.up = up;
+        self.initialize();
+        self
+    }
+
     /// Initializes the camera settings based on the aspect ratio and image width.
     fn initialize(&mut self) {
         self.image_height = (f64::from(self.image_width) / self.aspect_ratio) as u16;
@@ -106,79 +143,191 @@ impl Camera {
             self.image_height
         };
 
-        self.center = Point3D::new();
-
         // Determine viewport dimensions
         let focal_length: f64 = 1.0;
-        let viewport_height: f64 = 2.0;
+        let h = (self.vertical_fov.to_radians() / 2.0).tan();
+        let viewport_height: f64 = 2.0 * h * focal_length;
         let viewport_width: f64 =
             viewport_height * f64::from(self.image_width) / f64::from(self.image_height);
 
+        // Calculate the unit basis vectors of the camera frame, looking along -w.
+        let w = -self.direction;
+        let u = self.up.cross(w).unit_vector();
+        let v = w.cross(u);
+
         // Calculate the vectors across the horizontal and down the vertical viewport edges.
-        let viewport_u: Vector3D = Vector3D::with_values(viewport_width, 0.0, 0.0);
-        let viewport_v: Vector3D = Vector3D::with_values(0.0, -viewport_height, 0.0);
+        let viewport_u: Vector3D = viewport_width * u;
+        let viewport_v: Vector3D = viewport_height * -v;
 
         // Calculate the horizontal and vertical delta vectors from pixel to pixel
-        self.pixel_Δu = viewport_u / f64::from(self.image_width);
-        self.pixel_Δv = viewport_v / f64::from(self.image_height);
+        self.pixel_delta_u = viewport_u / f64::from(self.image_width);
+        self.pixel_delta_v = viewport_v / f64::from(self.image_height);
 
         // Calculate the location of the upper left pixel
-        let viewport_upper_left: Point3D = self.center
-            - Vector3D::with_values(0.0, 0.0, focal_length)
-            - viewport_u / 2.0
-            - viewport_v / 2.0;
-        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_Δu + self.pixel_Δv);
+        let viewport_upper_left: Point3D =
+            self.center - focal_length * w - viewport_u / 2.0 - viewport_v / 2.0;
+        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
     }
 
-    /// Computes the color of a ray using the provided hit record and world geometry.
-    fn ray_color(r: &Ray, depth: u16, world: &HittableList) -> Color {
+    /// Computes the color of a ray using the provided hit record and world geometry,
+    /// seeing the environment where the ray escapes the scene.
+    pub(crate) fn ray_color(
+        r: &Ray,
+        depth: u16,
+        world: &HittableList,
+        environment: &dyn Environment,
+    ) -> Color {
         // If we've exceeded the ray bounce limit, no more light is gathered.
-        if depth <= 0 {
+        if depth == 0 {
             return Color::new();
         }
 
         let mut rec = HitRecord::default();
 
         if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
-            let direction = rec.normal() + Vector3D::random_unit_vector();
-            return 0.5 * Self::ray_color(&Ray::create(rec.p(), direction), depth - 1, world);
+            let emitted = rec.material().emitted(&rec);
+            let (scattered, attenuation, scattered_ray) = rec.material().scatter(r, &rec);
+            if scattered {
+                return emitted
+                    + attenuation * Self::ray_color(&scattered_ray, depth - 1, world, environment);
+            }
+            return emitted;
         }
 
-        let unit_direction: &Vector3D = &r.direction().unit_vector();
-        let a: f64 = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - a) * Color::with_values(1.0, 1.0, 1.0) + a * Color::with_values(0.5, 0.7, 1.0)
+        environment.radiance(r.direction())
+    }
+
+    /// Gets the angle between the top and the bottom edge of the image in degrees.
+    pub fn vertical_fov(&self) -> f64 {
+        self.vertical_fov
+    }
+
+    /// Gets the position of the camera.
+    pub fn center(&self) -> Point3D {
+        self.center
+    }
+
+    /// Gets the unit direction the camera looks in.
+    pub fn direction(&self) -> Vector3D {
+        self.direction
+    }
+
+    /// Gets the width of the image in pixels.
+    pub fn image_width(&self) -> u16 {
+        self.image_width
+    }
+
+    /// Gets the height of the image in pixels.
+    pub fn image_height(&self) -> u16 {
+        self.image_height
+    }
+
+    /// Gets how many samples there are for one pixel.
+    pub fn samples_per_pixel(&self) -> u16 {
+        self.samples_per_pixel
+    }
+
+    /// Gets how many times rays may scatter.
+    pub fn max_depth(&self) -> u16 {
+        self.max_depth
     }
 
     /// Get a randomly sampled camera ray for the pixel at location i,j.
-    fn ray(&self, i: u16, j: u16) -> Ray {
-        let pixel_center =
-            self.pixel00_loc + (f64::from(i) * self.pixel_Δu) + (f64::from(j) * self.pixel_Δv);
+    pub fn ray(&self, i: u16, j: u16) -> Ray {
+        let pixel_center = self.pixel00_loc
+            + (f64::from(i) * self.pixel_delta_u)
+            + (f64::from(j) * self.pixel_delta_v);
         let pixel_sample = pixel_center + self.pixel_sample_square();
 
         Ray::create(self.center, pixel_sample - self.center)
     }
 
+    /// Finds the pixel a point is seen through.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The point in the scene.
+    ///
+    /// # Returns
+    ///
+    /// Returns the column and row of the pixel, `None` for points behind the camera
+    /// or outside the image.
+    pub fn pixel_at(&self, p: Point3D) -> Option<(u16, u16)> {
+        let to_p = p - self.center;
+        let distance = to_p.dot(self.direction);
+        if distance <= 0.0 {
+            return None;
+        }
+
+        // Where the point is seen on the viewport, from its upper left corner
+        let offset = to_p / distance + self.center
+            - (self.pixel00_loc - 0.5 * (self.pixel_delta_u + self.pixel_delta_v));
+        let x = offset.dot(self.pixel_delta_u) / self.pixel_delta_u.length_squared();
+        let y = offset.dot(self.pixel_delta_v) / self.pixel_delta_v.length_squared();
+        if x < 0.0
+            || y < 0.0
+            || x >= f64::from(self.image_width)
+            || y >= f64::from(self.image_height)
+        {
+            return None;
+        }
+        Some((x as u16, y as u16))
+    }
+
+    /// Computes the importance of light arriving at the camera against `direction`,
+    /// normalized over the whole image, 0 for directions outside of it.
+    ///
+    /// Light traced from the lights counts towards the pixel it's seen through
+    /// with this importance and the cosine to the view direction, divided by the
+    /// squared distance it travels to the camera.
+    pub fn importance(&self, direction: Vector3D) -> f64 {
+        if self.pixel_at(self.center + direction).is_none() {
+            return 0.0;
+        }
+        let cos_theta = direction.unit_vector().dot(self.direction);
+        1.0 / (self.viewport_area() * cos_theta.powi(4))
+    }
+
+    /// Computes the density per solid angle of a ray of a uniformly chosen pixel
+    /// leaving the camera in `direction`, 0 for directions outside the image.
+    pub fn pdf(&self, direction: Vector3D) -> f64 {
+        if self.pixel_at(self.center + direction).is_none() {
+            return 0.0;
+        }
+        let cos_theta = direction.unit_vector().dot(self.direction);
+        1.0 / (self.viewport_area() * cos_theta.powi(3))
+    }
+
+    /// Computes the area of the viewport the camera rays pass through.
+    fn viewport_area(&self) -> f64 {
+        self.pixel_delta_u.length()
+            * self.pixel_delta_v.length()
+            * f64::from(self.image_width)
+            * f64::from(self.image_height)
+    }
+
     /// Returns a random point in the square surrounding a pixel at the origin.
     fn pixel_sample_square(&self) -> Vector3D {
-        let mut rng = rand::thread_rng();
+        let px: f64 = -0.5 + random_f64();
+        let py: f64 = -0.5 + random_f64();
 
-        let px: f64 = -0.5 + rng.gen::<f64>();
-        let py: f64 = -0.5 + rng.gen::<f64>();
-
-        (px * self.pixel_Δu) + (py * self.pixel_Δv)
+        (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
     }
 
     /// Renders the scene using the camera and provided world geometry.
     pub fn render(&mut self, world: &HittableList) {
-        let mut buffer = Vec::new();
+        self.render_with(world, &PathTracer::default());
+    }
 
-        // Render
-        write!(
-            buffer,
-            "P3\n{} {}\n255\n",
-            self.image_width, self.image_height
-        )
-        .expect("Failed to write header");
+    /// Renders the scene using the camera, the provided world geometry and
+    /// an `Integrator` computing the color of every camera ray.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`      - The geometry of the scene.
+    /// * `integrator` - The integrator turning camera rays into colors.
+    pub fn render_with(&mut self, world: &HittableList, integrator: &dyn Integrator) {
+        let mut pixels: Vec<Color> = Vec::new();
 
         for j in 0..self.image_height {
             eprintln!("Scanlines remaining: {}", self.image_height - j);
@@ -187,16 +336,47 @@ impl Camera {
             for i in 0..self.image_width {
                 let mut pixel_color: Color = Color::new();
 
-                for sample in 0..self.samples_per_pixel {
+                for _ in 0..self.samples_per_pixel {
                     let r: Ray = self.ray(i, j);
-                    pixel_color += Self::ray_color(&r, self.max_depth, &world);
+                    pixel_color += integrator.ray_color(&r, self.max_depth, world);
                 }
 
-                pixel_color
-                    .write(&mut buffer, self.samples_per_pixel)
-                    .expect("Failed to write color");
+                pixels.push(pixel_color / f64::from(self.samples_per_pixel));
             }
         }
+
+        self.write_image(&pixels);
+    }
+
+    /// Renders the scene using the camera, the provided world geometry and
+    /// an `ImageIntegrator` computing the color of all pixels at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `world`      - The geometry of the scene.
+    /// * `integrator` - The integrator computing the image.
+    pub fn render_image(&mut self, world: &HittableList, integrator: &dyn ImageIntegrator) {
+        let pixels = integrator.render_image(self, world);
+        self.write_image(&pixels);
+    }
+
+    /// Writes the pixel colors, row by row from the top left, as PPM image to stdout.
+    fn write_image(&self, pixels: &[Color]) {
+        let mut buffer = Vec::new();
+
+        write!(
+            buffer,
+            "P3\n{} {}\n255\n",
+            self.image_width, self.image_height
+        )
+        .expect("Failed to write header");
+
+        for pixel_color in pixels {
+            pixel_color
+                .write(&mut buffer, 1)
+                .expect("Failed to write color");
+        }
+
         println!("{}", String::from_utf8_lossy(&buffer));
         eprintln!("Done.");
     }
@@ -204,6 +384,62 @@ impl Camera {
 
 #[cfg(test)]
 mod tests {
+    use crate::camera::Camera;
+    use crate::vector3d::{Point3D, Vector3D};
+
+    #[test]
+    fn camera_view() {
+        //      up +y
+        //       ^
+        //  o--->|    looking along +x from (0,0,0)
+        //      / \   45° up to the top edge
+        let cam = Camera::new(2.0, 200, 1, 1)
+            .with_vertical_fov(90.0)
+            .with_view(
+                Point3D::new(),
+                Vector3D::with_values(2.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+            );
+        assert_eq!(cam.direction(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+        // The middle of the image lies between the four central pixels
+        let center = cam.ray(100, 50).direction() + cam.ray(99, 49).direction();
+        let center = center.unit_vector();
+        assert!(
+            (center - cam.direction()).length() < 0.02,
+            "Not looking along +x"
+        );
+
+        // The top left pixel is 45° up and, as the image is twice as wide, further left
+        let corner = cam.ray(0, 0).direction();
+        assert!(corner.y() / corner.x() > 0.98 && corner.y() / corner.x() < 1.0);
+        assert!(corner.z() / corner.x() < -1.96, "Left not towards -z");
+        assert_eq!(Camera::default().vertical_fov(), 90.0);
+    }
+
+    #[test]
+    fn camera_pixel_at() {
+        //   o------>    looking along -z from (0,0,0), the 2x2 viewport
+        //               1 away holding 4x2 pixels
+        let cam = Camera::new(2.0, 4, 1, 1);
+
+        for (i, j) in [(0, 0), (3, 0), (1, 1), (2, 1)] {
+            let p = cam.center() + 5.0 * cam.ray(i, j).direction();
+            assert_eq!(cam.pixel_at(p), Some((i, j)), "Pixel not found again");
+        }
+        assert_eq!(cam.pixel_at(Point3D::with_values(0.0, 0.0, 1.0)), None);
+        assert_eq!(cam.pixel_at(Point3D::with_values(0.0, 2.0, -1.0)), None);
+
+        // The viewport is 4 wide and 2 high, 45° off axis rays meet it √2 away
+        let view = Vector3D::with_values(0.0, 0.0, -1.0);
+        let corner = Vector3D::with_values(1.0, 0.0, -1.0);
+        assert!((cam.pdf(view) - 1.0 / 8.0).abs() < 1e-12);
+        assert!((cam.pdf(corner) - 2.0_f64.sqrt() / 4.0).abs() < 1e-12);
+        assert!((cam.importance(corner) - 0.5).abs() < 1e-12);
+        assert_eq!(cam.pdf(-view), 0.0);
+        assert_eq!(cam.importance(Vector3D::with_values(0.0, 2.0, -1.0)), 0.0);
+    }
+
     #[test]
     #[should_panic]
     fn todo() {
@@ -36,15 +36,20 @@ impl Color {
 
         // Write the translated [0, 255] value of each color component.
         let interval = Interval::new(0.000, 0.999);
-        write!(
+        writeln!(
             out,
-            "{} {} {}\n",
+            "{} {} {}",
             (255.999 * interval.clamp(r)) as u8,
             (255.999 * interval.clamp(g)) as u8,
             (255.999 * interval.clamp(b)) as u8,
         )
     }
 
+    /// Computes the luminance (Y of CIE XYZ) of a linear sRGB color.
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
+
     /// Convert from linear color space into gamma color space.
     fn linear_to_gamma(linear_component: f64) -> f64 {
         linear_component.sqrt()
@@ -53,6 +58,22 @@ impl Color {
 
 #[cfg(test)]
 mod tests {
+    use crate::color::Color;
+
+    #[test]
+    fn luminance() {
+        assert_eq!(Color::new().luminance(), 0.0, "Black not dark");
+        assert!(
+            (Color::with_values(1.0, 1.0, 1.0).luminance() - 1.0).abs() < 1e-12,
+            "White not of luminance 1"
+        );
+        assert!(
+            Color::with_values(0.0, 1.0, 0.0).luminance()
+                > Color::with_values(1.0, 0.0, 1.0).luminance(),
+            "Green not brighter than magenta"
+        );
+    }
+
     #[test]
     #[should_panic]
     fn todo() {
@@ -0,0 +1,230 @@
+//! Module for sampling piecewise-constant distributions in the context of a raytracer.
+//!
+//! Used to importance sample things like environment maps, where directions
+//! with more light should be chosen more often.
+
+// --- 1D DISTRIBUTION ---------------------------------------------------------
+
+/// Represents a piecewise-constant function over [0, 1) and how to sample it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Creates a new distribution proportional to `func`.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` - The non-negative value of every equally wide piece.
+    ///
+    /// A function being zero everywhere is sampled uniformly.
+    pub fn new(func: &[f64]) -> Self {
+        let n = func.len().max(1);
+        let func: Vec<f64> = if func.is_empty() {
+            vec![0.0]
+        } else {
+            func.iter().map(|f| f.max(0.0)).collect()
+        };
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + func[i] / n as f64;
+        }
+        let integral = cdf[n];
+
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Gets how many pieces the function has.
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Gets the integral of the function over [0, 1).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Finds the piece the cumulative distribution reaches `u` in.
+    fn find(&self, u: f64) -> usize {
+        let index = self.cdf.partition_point(|&c| c <= u);
+        index.saturating_sub(1).min(self.count() - 1)
+    }
+
+    /// Samples a point within [0, 1) proportional to the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` - A random number within [0, 1).
+    ///
+    /// # Returns
+    ///
+    /// Returns the point, its probability density and the index of its piece.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self.find(u);
+
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        let x = ((offset as f64 + du) / self.count() as f64).min(1.0 - f64::EPSILON);
+
+        (x, self.pdf(x), offset)
+    }
+
+    /// Samples a piece proportional to the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` - A random number within [0, 1).
+    ///
+    /// # Returns
+    ///
+    /// Returns the index of the piece and the probability of choosing it.
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let offset = self.find(u);
+        (offset, self.cdf[offset + 1] - self.cdf[offset])
+    }
+
+    /// Computes the probability density of sampling the point `x` within [0, 1).
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+
+        if self.integral > 0.0 {
+            self.func[offset] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+// --- 2D DISTRIBUTION ---------------------------------------------------------
+
+/// Represents a piecewise-constant function over [0, 1)² and how to sample it.
+///
+/// Samples a row by the marginal distribution first, then a column within it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Creates a new distribution proportional to `func`.
+    ///
+    /// # Arguments
+    ///
+    /// * `func`  - The non-negative value of every piece, row by row.
+    /// * `nu`    - How many pieces there are per row.
+    /// * `nv`    - How many rows there are.
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> = (0..nv)
+            .map(|v| Distribution1D::new(&func[v * nu..(v + 1) * nu]))
+            .collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|d| d.integral()).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    /// Samples a point within [0, 1)² proportional to the function.
+    ///
+    /// # Arguments
+    ///
+    /// * `u0` - A random number within [0, 1) choosing the column.
+    /// * `u1` - A random number within [0, 1) choosing the row.
+    ///
+    /// # Returns
+    ///
+    /// Returns the coordinates (u, v) of the point and its probability density.
+    pub fn sample(&self, u0: f64, u1: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+
+        (u, v, pdf_u * pdf_v)
+    }
+
+    /// Computes the probability density of sampling the point (u, v).
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.conditional.len() as f64) as usize).min(self.conditional.len() - 1);
+        self.conditional[row].pdf(u) * self.marginal.pdf(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::distribution::{Distribution1D, Distribution2D};
+
+    #[test]
+    fn distribution_1d() {
+        //  f
+        //  3 |    ___
+        //  1 |___|   |___
+        //  0 +-----------+-- x
+        //    0  1/3 2/3  1
+        let d = Distribution1D::new(&[1.0, 3.0, 1.0]);
+
+        assert!((d.integral() - 5.0 / 3.0).abs() < 1e-12, "Wrong integral");
+        let (index, pmf) = d.sample_discrete(0.1);
+        assert_eq!(index, 0);
+        assert!((pmf - 0.2).abs() < 1e-12, "Wrong probability {}", pmf);
+        assert_eq!(d.sample_discrete(0.5).0, 1);
+        assert_eq!(d.sample_discrete(0.9).0, 2);
+
+        let (x, pdf, offset) = d.sample_continuous(0.5);
+        assert!((x - 0.5).abs() < 1e-12, "Median {} not centered", x);
+        assert!((pdf - 1.8).abs() < 1e-12);
+        assert_eq!(offset, 1);
+        assert!((d.pdf(0.1) - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn distribution_1d_zero() {
+        let d = Distribution1D::new(&[0.0, 0.0]);
+
+        assert_eq!(d.pdf(0.7), 1.0, "Zero function not uniform");
+        let (x, pdf, _) = d.sample_continuous(0.75);
+        assert!((x - 0.75).abs() < 1e-12);
+        assert_eq!(pdf, 1.0);
+    }
+
+    #[test]
+    fn distribution_2d() {
+        //  v
+        //  1 +---+---+
+        //    | 0 | 6 |
+        //  ½ +---+---+
+        //    | 1 | 1 |
+        //  0 +---+---+ u
+        //    0   ½   1
+        let d = Distribution2D::new(&[1.0, 1.0, 0.0, 6.0], 2, 2);
+
+        for (u0, u1) in [(0.1, 0.1), (0.5, 0.9), (0.99, 0.5), (0.3, 0.7)] {
+            let (u, v, pdf) = d.sample(u0, u1);
+            assert!(pdf > 0.0, "Sampled ({}, {}) with zero density", u, v);
+            assert!((d.pdf(u, v) - pdf).abs() < 1e-12, "Densities disagree");
+        }
+        assert_eq!(d.pdf(0.25, 0.75), 0.0, "Dark piece sampled");
+        assert!((d.pdf(0.75, 0.75) - 3.0).abs() < 1e-12);
+        assert!((d.pdf(0.25, 0.25) - 0.5).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,944 @@
+//! Module for representing the light arriving from infinitely far away in the
+//! context of a raytracer, seen by every ray missing the scene.
+
+use crate::color::Color;
+use crate::vector3d::Vector3D;
+
+pub trait Environment {
+    /// Gets the radiance arriving from the given direction.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - The direction towards the environment, not necessarily of unit length.
+    fn radiance(&self, direction: Vector3D) -> Color;
+
+    /// Samples a direction towards the environment, preferring bright ones.
+    ///
+    /// # Returns
+    ///
+    /// Returns the unit direction and its probability density per solid angle,
+    /// `None` for environments which can't be importance sampled.
+    fn sample(&self) -> Option<(Vector3D, f64)> {
+        None
+    }
+
+    /// Computes the probability density (per solid angle) of `sample` choosing
+    /// the unit `direction`, 0 for environments which can't be importance sampled.
+    fn pdf(&self, _direction: Vector3D) -> f64 {
+        0.0
+    }
+}
+
+// --- GRADIENT ----------------------------------------------------------------
+
+pub mod gradient {
+    //! Module for a simple sky blending between two colors.
+
+    use crate::color::Color;
+    use crate::environment::Environment;
+    use crate::vector3d::Vector3D;
+
+    /// Represents a sky blending linearly from the color straight down to the
+    /// color straight up.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Gradient {
+        bottom: Color,
+        top: Color,
+    }
+
+    impl Default for Gradient {
+        /// Creates the white to light blue sky.
+        fn default() -> Self {
+            Self::new(
+                Color::with_values(1.0, 1.0, 1.0),
+                Color::with_values(0.5, 0.7, 1.0),
+            )
+        }
+    }
+
+    impl Gradient {
+        /// Creates a new gradient sky.
+        ///
+        /// # Arguments
+        ///
+        /// * `bottom` - The color seen looking straight down.
+        /// * `top`    - The color seen looking straight up.
+        pub fn new(bottom: Color, top: Color) -> Self {
+            Gradient { bottom, top }
+        }
+    }
+
+    impl Environment for Gradient {
+        fn radiance(&self, direction: Vector3D) -> Color {
+            let unit_direction = direction.unit_vector();
+            let a: f64 = 0.5 * (unit_direction.y() + 1.0);
+            (1.0 - a) * self.bottom + a * self.top
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::environment::gradient::Gradient;
+        use crate::environment::Environment;
+        use crate::vector3d::Vector3D;
+
+        #[test]
+        fn gradient_radiance() {
+            let sky = Gradient::default();
+
+            assert_eq!(
+                sky.radiance(Vector3D::with_values(0.0, -2.0, 0.0)),
+                Color::with_values(1.0, 1.0, 1.0)
+            );
+            assert_eq!(
+                sky.radiance(Vector3D::with_values(0.0, 1.0, 0.0)),
+                Color::with_values(0.5, 0.7, 1.0)
+            );
+            assert_eq!(
+                sky.radiance(Vector3D::with_values(1.0, 0.0, 0.0)),
+                Color::with_values(0.75, 0.85, 1.0),
+                "Horizon not halfway"
+            );
+            assert_eq!(sky.sample(), None, "Gradient importance sampled");
+        }
+    }
+}
+
+// --- UNIFORM -----------------------------------------------------------------
+
+pub mod uniform {
+    //! Module for an environment of the same color in every direction.
+
+    use crate::color::Color;
+    use crate::environment::Environment;
+    use crate::vector3d::Vector3D;
+
+    /// Represents an environment of the same radiance in every direction.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Uniform {
+        radiance: Color,
+    }
+
+    impl Uniform {
+        /// Creates a new uniform environment with the given radiance.
+        pub fn new(radiance: Color) -> Self {
+            Uniform { radiance }
+        }
+    }
+
+    impl Environment for Uniform {
+        fn radiance(&self, _direction: Vector3D) -> Color {
+            self.radiance
+        }
+    }
+}
+
+// --- ENVIRONMENT MAP ---------------------------------------------------------
+
+pub mod map {
+    //! Module for image based lighting with equirectangular HDR images.
+
+    use crate::color::Color;
+    use crate::distribution::Distribution2D;
+    use crate::environment::Environment;
+    use crate::sampler::random_f64;
+    use crate::vector3d::Vector3D;
+    use std::f64::consts::PI;
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader, Read};
+    use std::path::Path;
+
+    /// Represents an environment given by an equirectangular (latitude-longitude) image.
+    ///
+    /// The top row of the image is straight up, its left edge looks along -x
+    /// and its center along +x. Directions get chosen proportional to the
+    /// brightness of the image for importance sampling.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct EnvironmentMap {
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+        rotation: f64,
+        intensity: f64,
+        distribution: Distribution2D,
+    }
+
+    impl EnvironmentMap {
+        /// Creates a new environment map.
+        ///
+        /// # Arguments
+        ///
+        /// * `width`  - The width of the image in pixels.
+        /// * `height` - The height of the image in pixels.
+        /// * `pixels` - The linear radiance of every pixel, row by row from the top left.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the image is empty or `pixels` doesn't match its size.
+        pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+            assert!(width > 0 && height > 0, "Empty environment map");
+            assert_eq!(pixels.len(), width * height, "Pixels not matching the size");
+
+            // Rows near the poles cover less solid angle
+            let func: Vec<f64> = pixels
+                .iter()
+                .enumerate()
+                .map(|(i, pixel)| {
+                    let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                    pixel.luminance().max(0.0) * theta.sin()
+                })
+                .collect();
+
+            EnvironmentMap {
+                width,
+                height,
+                distribution: Distribution2D::new(&func, width, height),
+                pixels,
+                rotation: 0.0,
+                intensity: 1.0,
+            }
+        }
+
+        /// Loads an environment map from a Radiance HDR (.hdr) image.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the image.
+        pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            let mut reader = BufReader::new(File::open(path)?);
+            let (width, height, pixels) = read_hdr(&mut reader)?;
+            Ok(Self::new(width, height, pixels))
+        }
+
+        /// Rotates the environment around the y axis.
+        ///
+        /// # Arguments
+        ///
+        /// * `degrees` - The counterclockwise rotation seen from above.
+        pub fn with_rotation(mut self, degrees: f64) -> Self {
+            self.rotation = degrees.to_radians();
+            self
+        }
+
+        /// Scales the radiance of the environment.
+        pub fn with_intensity(mut self, intensity: f64) -> Self {
+            self.intensity = intensity;
+            self
+        }
+
+        /// Gets the width of the image in pixels.
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        /// Gets the height of the image in pixels.
+        pub fn height(&self) -> usize {
+            self.height
+        }
+
+        /// Gets the rotation around the y axis in degrees.
+        pub fn rotation(&self) -> f64 {
+            self.rotation.to_degrees()
+        }
+
+        /// Gets the scale of the radiance.
+        pub fn intensity(&self) -> f64 {
+            self.intensity
+        }
+
+        /// Rotates a direction around the y axis by `angle` radians.
+        fn rotate(direction: Vector3D, angle: f64) -> Vector3D {
+            let (sin, cos) = angle.sin_cos();
+            Vector3D::with_values(
+                cos * direction.x() + sin * direction.z(),
+                direction.y(),
+                -sin * direction.x() + cos * direction.z(),
+            )
+        }
+
+        /// Maps a unit direction in the space of the image to its coordinates within [0, 1)².
+        fn uv(direction: Vector3D) -> (f64, f64) {
+            let phi = (-direction.z()).atan2(direction.x()) + PI;
+            let theta = direction.y().clamp(-1.0, 1.0).acos();
+            ((phi / (2.0 * PI)).fract(), theta / PI)
+        }
+
+        /// Maps coordinates of the image to the unit direction in its space.
+        fn direction(u: f64, v: f64) -> Vector3D {
+            let phi = 2.0 * PI * u;
+            let theta = PI * v;
+            Vector3D::with_values(
+                -theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+        }
+    }
+
+    impl Environment for EnvironmentMap {
+        fn radiance(&self, direction: Vector3D) -> Color {
+            let (u, v) = Self::uv(Self::rotate(direction.unit_vector(), -self.rotation));
+            let i = ((u * self.width as f64) as usize).min(self.width - 1);
+            let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+            self.pixels[j * self.width + i] * self.intensity
+        }
+
+        fn sample(&self) -> Option<(Vector3D, f64)> {
+            let (u, v, pdf_uv) = self.distribution.sample(random_f64(), random_f64());
+            if pdf_uv <= 0.0 || (PI * v).sin() <= 0.0 {
+                return None;
+            }
+
+            // Directions on the border between pixels may round into the neighbouring
+            // one, so report the density `pdf` agrees with
+            let direction = Self::rotate(Self::direction(u, v), self.rotation);
+            let pdf = self.pdf(direction);
+            if pdf <= 0.0 {
+                return None;
+            }
+
+            Some((direction, pdf))
+        }
+
+        fn pdf(&self, direction: Vector3D) -> f64 {
+            let (u, v) = Self::uv(Self::rotate(direction, -self.rotation));
+            let sin_theta = (PI * v).sin();
+            if sin_theta <= 0.0 {
+                return 0.0;
+            }
+
+            self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+        }
+    }
+
+    /// Reads a Radiance HDR image with flat or run-length encoded RGBE scanlines.
+    ///
+    /// # Returns
+    ///
+    /// Returns the width, height and linear colors of the image, row by row from the top.
+    pub fn read_hdr<R: BufRead>(reader: &mut R) -> io::Result<(usize, usize, Vec<Color>)> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // Header lines up to an empty line
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            return Err(invalid("Not a Radiance HDR image"));
+        }
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("Missing image size"));
+            }
+            let header = line.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(format) = header.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(invalid("Unsupported pixel format"));
+                }
+            }
+        }
+
+        // Resolution line, only the standard orientation is supported
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size: Vec<&str> = line.split_whitespace().collect();
+        let (height, width) = match size.as_slice() {
+            ["-Y", height, "+X", width] => (
+                height
+                    .parse::<usize>()
+                    .map_err(|_| invalid("Invalid height"))?,
+                width
+                    .parse::<usize>()
+                    .map_err(|_| invalid("Invalid width"))?,
+            ),
+            _ => return Err(invalid("Unsupported image orientation")),
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid("Empty image"));
+        }
+        if width.checked_mul(height).is_none() {
+            return Err(invalid("Image too large"));
+        }
+
+        // Grow the image as scanlines arrive, so crafted sizes can't allocate more than the file holds
+        let mut pixels = Vec::new();
+        let mut scanline = Vec::new();
+        for _ in 0..height {
+            read_scanline(reader, width, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_color(rgbe)));
+        }
+
+        Ok((width, height, pixels))
+    }
+
+    /// Reads one scanline of RGBE pixels, run-length encoded if it starts with the marker.
+    fn read_scanline<R: Read>(
+        reader: &mut R,
+        width: usize,
+        scanline: &mut Vec<[u8; 4]>,
+    ) -> io::Result<()> {
+        let mut first = [0u8; 4];
+        reader.read_exact(&mut first)?;
+
+        let encoded =
+            (8..0x8000).contains(&width) && first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0;
+        if !encoded {
+            scanline.clear();
+            scanline.push(first);
+            for _ in 1..width {
+                let mut pixel = [0u8; 4];
+                reader.read_exact(&mut pixel)?;
+                scanline.push(pixel);
+            }
+            return Ok(());
+        }
+        scanline.resize(width, [0u8; 4]);
+
+        if (usize::from(first[2]) << 8 | usize::from(first[3])) != width {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Scanline width mismatch",
+            ));
+        }
+
+        // Every component is encoded separately in runs and literals
+        for component in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let mut count = [0u8; 1];
+                reader.read_exact(&mut count)?;
+                let (run, count) = if count[0] > 128 {
+                    (true, usize::from(count[0] - 128))
+                } else {
+                    (false, usize::from(count[0]))
+                };
+                if count == 0 || x + count > width {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Invalid run length",
+                    ));
+                }
+
+                let mut value = [0u8; 1];
+                if run {
+                    reader.read_exact(&mut value)?;
+                }
+                for pixel in scanline[x..x + count].iter_mut() {
+                    if !run {
+                        reader.read_exact(&mut value)?;
+                    }
+                    pixel[component] = value[0];
+                }
+                x += count;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts a pixel with shared exponent into a linear color.
+    fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+        if rgbe[3] == 0 {
+            return Color::new();
+        }
+
+        let f = 2f64.powi(i32::from(rgbe[3]) - 136);
+        Color::with_values(
+            f64::from(rgbe[0]) * f,
+            f64::from(rgbe[1]) * f,
+            f64::from(rgbe[2]) * f,
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::environment::map::{read_hdr, EnvironmentMap};
+        use crate::environment::Environment;
+        use crate::vector3d::Vector3D;
+        use std::io::Cursor;
+
+        /// A 4x2 image, dark but for one bright pixel in the top row.
+        fn bright_spot() -> EnvironmentMap {
+            let mut pixels = vec![Color::with_values(0.1, 0.1, 0.1); 8];
+            pixels[1] = Color::with_values(100.0, 100.0, 100.0);
+            EnvironmentMap::new(4, 2, pixels)
+        }
+
+        #[test]
+        fn environment_map_radiance() {
+            //  +------+------+------+------+   up
+            //  |      |  *   |      |      |
+            //  +------+------+------+------+   horizon
+            //  |      |      |      |      |
+            //  +------+------+------+------+   down
+            //  -x     +z     +x     -z     -x
+            let map = bright_spot();
+            let bright = Color::with_values(100.0, 100.0, 100.0);
+            let toward_z = Vector3D::with_values(0.0, 0.5, 1.0);
+
+            assert_eq!(map.radiance(toward_z), bright, "Bright spot not along +z");
+            assert_eq!(
+                map.radiance(Vector3D::with_values(0.0, -0.5, 1.0)),
+                Color::with_values(0.1, 0.1, 0.1)
+            );
+
+            // Turning the map by 90° counterclockwise moves +z to +x
+            let rotated = bright_spot().with_rotation(90.0).with_intensity(2.0);
+            assert_eq!(
+                rotated.radiance(Vector3D::with_values(1.0, 0.5, 0.0)),
+                2.0 * bright
+            );
+            assert_ne!(rotated.radiance(toward_z), 2.0 * bright);
+        }
+
+        #[test]
+        fn environment_map_sample() {
+            let map = bright_spot().with_rotation(30.0);
+            let mut bright = 0;
+
+            for _ in 0..1000 {
+                let (direction, pdf) = map.sample().expect("Map not importance sampled");
+                assert!(
+                    (direction.length() - 1.0).abs() < 1e-9,
+                    "Not a unit direction"
+                );
+                assert!(
+                    (map.pdf(direction) - pdf).abs() < 1e-9 * pdf,
+                    "Densities disagree"
+                );
+                if map.radiance(direction).x() > 1.0 {
+                    bright += 1;
+                }
+            }
+
+            assert!(bright > 900, "Bright spot only sampled {} times", bright);
+        }
+
+        #[test]
+        fn environment_map_pdf_normalized() {
+            // Integrate the density over the sphere by latitude and longitude
+            let map = bright_spot();
+            let n = 400;
+            let mut integral = 0.0;
+            for j in 0..n {
+                let theta = std::f64::consts::PI * (f64::from(j) + 0.5) / f64::from(n);
+                for i in 0..n {
+                    let phi = 2.0 * std::f64::consts::PI * (f64::from(i) + 0.5) / f64::from(n);
+                    let direction = Vector3D::with_values(
+                        theta.sin() * phi.cos(),
+                        theta.cos(),
+                        theta.sin() * phi.sin(),
+                    );
+                    integral += map.pdf(direction) * theta.sin();
+                }
+            }
+            integral *= 2.0 * std::f64::consts::PI * std::f64::consts::PI / f64::from(n * n);
+
+            assert!(
+                (integral - 1.0).abs() < 0.01,
+                "Density integrating to {}",
+                integral
+            );
+        }
+
+        #[test]
+        fn read_hdr_flat() {
+            let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+            data.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+
+            let (width, height, pixels) = read_hdr(&mut Cursor::new(data)).unwrap();
+            assert_eq!((width, height), (2, 1));
+            assert_eq!(
+                pixels,
+                vec![Color::with_values(1.0, 0.5, 0.0), Color::new()],
+                "Shared exponent not applied"
+            );
+        }
+
+        #[test]
+        fn read_hdr_run_length_encoded() {
+            let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+            data.extend([2, 2, 0, 8]);
+            data.extend([136, 128]); // red: a run of 8
+            data.extend([8, 0, 16, 32, 48, 64, 80, 96, 112]); // green: 8 literals
+            data.extend([132, 0, 132, 128]); // blue: two runs of 4
+            data.extend([136, 129]); // exponent: a run of 8
+
+            let (_, _, pixels) = read_hdr(&mut Cursor::new(data)).unwrap();
+            assert_eq!(pixels[0], Color::with_values(1.0, 0.0, 0.0));
+            assert_eq!(pixels[7], Color::with_values(1.0, 0.875, 1.0));
+        }
+
+        #[test]
+        fn read_hdr_invalid() {
+            for data in [
+                b"P3\n2 1\n255\n".to_vec(),
+                b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n".to_vec(),
+                b"#?RADIANCE\n\n+Y 1 +X 1\n".to_vec(),
+                b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80".to_vec(),
+                b"#?RADIANCE\n\n-Y 4611686018427387904 +X 8\n\x80\x80".to_vec(),
+                b"#?RADIANCE\n\n-Y 1 +X 1000000000000\n\x80\x80\x80\x80".to_vec(),
+            ] {
+                assert!(
+                    read_hdr(&mut Cursor::new(data)).is_err(),
+                    "Invalid image read"
+                );
+            }
+        }
+    }
+}
+
+// --- PHYSICAL SKY ------------------------------------------------------------
+
+pub mod sky {
+    //! Module for an analytic daylight sky with a matching sun.
+
+    use crate::color::Color;
+    use crate::environment::Environment;
+    use crate::light::directional::DirectionalLight;
+    use crate::vector3d::Vector3D;
+    use std::f64::consts::PI;
+
+    /// The radiance of 1 in units of the model, which gives luminance in kcd/m².
+    const UNIT: f64 = 10.0;
+
+    /// The illuminance of the sun outside the atmosphere in klx.
+    const SOLAR_ILLUMINANCE: f64 = 128.0;
+
+    /// The apparent diameter of the sun in degrees.
+    const SUN_DIAMETER: f64 = 0.53;
+
+    /// How far below the horizon in degrees the sun sets the sky dark, the end of civil twilight.
+    const TWILIGHT: f64 = 6.0;
+
+    /// Represents a clear daylight sky after Preetham, Shirley and Smits, "A Practical
+    /// Analytic Model for Daylight" (1999), over a diffuse ground.
+    ///
+    /// The sun itself is not part of the sky, `sun` gives the light it casts.
+    /// A radiance of 1 corresponds to a luminance of 10 kcd/m², so a clear sky
+    /// ranges around 0.1 to 1 and the sun shines with an irradiance of about 10.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PhysicalSky {
+        sun_direction: Vector3D,
+        turbidity: f64,
+        ground_albedo: Color,
+        intensity: f64,
+        zenith: [f64; 3],
+        perez: [[f64; 5]; 3],
+        sun_zenith_angle: f64,
+        sun_irradiance: Color,
+        ground: Color,
+    }
+
+    impl PhysicalSky {
+        /// Creates a new sky.
+        ///
+        /// # Arguments
+        ///
+        /// * `elevation`     - The angle of the sun above the horizon in degrees, the sky fading to black 6° below it.
+        /// * `azimuth`       - The angle of the sun in degrees around the y axis, 0 along -z, 90 along +x.
+        /// * `turbidity`     - The haziness of the atmosphere, from 2 for clear to 10 for hazy skies.
+        /// * `ground_albedo` - The fraction of light reflected by the ground below the horizon.
+        pub fn new(elevation: f64, azimuth: f64, turbidity: f64, ground_albedo: Color) -> Self {
+            let elevation = elevation.clamp(-90.0, 90.0).to_radians();
+            let azimuth = azimuth.to_radians();
+            let t = turbidity.clamp(1.7, 10.0);
+
+            let sun_direction = Vector3D::with_values(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                -elevation.cos() * azimuth.cos(),
+            );
+
+            // The model only holds for the sun above the horizon, twilight dims the sunset sky
+            let theta_s = (PI / 2.0 - elevation).min(PI / 2.0);
+            let twilight = (1.0 + elevation.to_degrees() / TWILIGHT).clamp(0.0, 1.0);
+
+            let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+            let zenith_luminance =
+                ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * twilight;
+            let zenith_chromaticity = |t2: [f64; 4], t1: [f64; 4], t0: [f64; 4]| {
+                let powers = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+                (0..4)
+                    .map(|i| (t * t * t2[i] + t * t1[i] + t0[i]) * powers[i])
+                    .sum::<f64>()
+            };
+            let zenith_x = zenith_chromaticity(
+                [0.00166, -0.00375, 0.00209, 0.0],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            );
+            let zenith_y = zenith_chromaticity(
+                [0.00275, -0.00610, 0.00317, 0.0],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            );
+
+            let perez = [
+                [
+                    0.1787 * t - 1.4630,
+                    -0.3554 * t + 0.4275,
+                    -0.0227 * t + 5.3251,
+                    0.1206 * t - 2.5771,
+                    -0.0670 * t + 0.3703,
+                ],
+                [
+                    -0.0193 * t - 0.2592,
+                    -0.0665 * t + 0.0008,
+                    -0.0004 * t + 0.2125,
+                    -0.0641 * t - 0.8989,
+                    -0.0033 * t + 0.0452,
+                ],
+                [
+                    -0.0167 * t - 0.2608,
+                    -0.0950 * t + 0.0092,
+                    -0.0079 * t + 0.2102,
+                    -0.0441 * t - 1.6537,
+                    -0.0109 * t + 0.0529,
+                ],
+            ];
+
+            let mut sky = PhysicalSky {
+                sun_direction,
+                turbidity: t,
+                ground_albedo,
+                intensity: 1.0,
+                zenith: [zenith_luminance.max(0.0), zenith_x, zenith_y],
+                perez,
+                sun_zenith_angle: theta_s,
+                sun_irradiance: Self::sun_irradiance(elevation, t),
+                ground: Color::new(),
+            };
+            sky.ground = sky.ground_radiance();
+            sky
+        }
+
+        /// Scales the radiance of the sky and the irradiance of its sun.
+        pub fn with_intensity(mut self, intensity: f64) -> Self {
+            self.intensity = intensity;
+            self
+        }
+
+        /// Gets the unit direction towards the sun.
+        pub fn sun_direction(&self) -> Vector3D {
+            self.sun_direction
+        }
+
+        /// Gets the haziness of the atmosphere.
+        pub fn turbidity(&self) -> f64 {
+            self.turbidity
+        }
+
+        /// Gets the fraction of light reflected by the ground.
+        pub fn ground_albedo(&self) -> Color {
+            self.ground_albedo
+        }
+
+        /// Creates the light of the sun matching the sky, a disk of 0.53°.
+        ///
+        /// Below the horizon the sun casts no light.
+        pub fn sun(&self) -> DirectionalLight {
+            DirectionalLight::new(-self.sun_direction, self.sun_irradiance * self.intensity)
+                .with_angular_diameter(SUN_DIAMETER)
+        }
+
+        /// Approximates the sunlight passing through the atmosphere by Rayleigh and
+        /// aerosol scattering at representative wavelengths for red, green and blue.
+        fn sun_irradiance(elevation: f64, turbidity: f64) -> Color {
+            if elevation <= 0.0 {
+                return Color::new();
+            }
+
+            // Relative optical mass of the air towards the sun (Kasten, 1966)
+            let theta_s = PI / 2.0 - elevation;
+            let mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+            let beta = 0.04608 * turbidity - 0.04586;
+
+            let transmittance = |lambda: f64| {
+                let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+                let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+                rayleigh * aerosol
+            };
+
+            Color::with_values(
+                transmittance(0.680),
+                transmittance(0.550),
+                transmittance(0.440),
+            ) * (SOLAR_ILLUMINANCE / UNIT)
+        }
+
+        /// The Perez distribution of a quantity over the sky.
+        fn perez(c: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+            (1.0 + c[0] * (c[1] / cos_theta).exp())
+                * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+        }
+
+        /// Computes the unscaled radiance of the sky in the unit direction above the horizon.
+        fn sky_radiance(&self, direction: Vector3D) -> Color {
+            let cos_theta = direction.y().max(0.001);
+            let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+            let [luminance, x, y] = [0, 1, 2].map(|k| {
+                self.zenith[k] * Self::perez(&self.perez[k], cos_theta, gamma)
+                    / Self::perez(&self.perez[k], 1.0, self.sun_zenith_angle)
+            });
+            if y <= 0.0 {
+                return Color::new();
+            }
+
+            // CIE xyY to linear sRGB
+            let big_x = x / y * luminance;
+            let big_z = (1.0 - x - y) / y * luminance;
+            let rgb = Color::with_values(
+                3.2404542 * big_x - 1.5371385 * luminance - 0.4985314 * big_z,
+                -0.9692660 * big_x + 1.8760108 * luminance + 0.0415560 * big_z,
+                0.0556434 * big_x - 0.2040259 * luminance + 1.0572252 * big_z,
+            );
+
+            Color::with_values(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0)) / UNIT
+        }
+
+        /// Computes the unscaled radiance of the diffuse ground lit by sky and sun.
+        fn ground_radiance(&self) -> Color {
+            let (n_theta, n_phi) = (16, 32);
+            let d_theta = PI / 2.0 / f64::from(n_theta);
+            let d_phi = 2.0 * PI / f64::from(n_phi);
+
+            let mut irradiance = self.sun_irradiance * self.sun_direction.y().max(0.0);
+            for j in 0..n_theta {
+                let theta = (f64::from(j) + 0.5) * d_theta;
+                for i in 0..n_phi {
+                    let phi = (f64::from(i) + 0.5) * d_phi;
+                    let direction = Vector3D::with_values(
+                        theta.sin() * phi.cos(),
+                        theta.cos(),
+                        theta.sin() * phi.sin(),
+                    );
+                    irradiance += self.sky_radiance(direction)
+                        * (theta.cos() * theta.sin() * d_theta * d_phi);
+                }
+            }
+
+            self.ground_albedo * irradiance / PI
+        }
+    }
+
+    impl Environment for PhysicalSky {
+        fn radiance(&self, direction: Vector3D) -> Color {
+            let direction = direction.unit_vector();
+            if direction.y() < 0.0 {
+                return self.ground * self.intensity;
+            }
+
+            self.sky_radiance(direction) * self.intensity
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::environment::sky::PhysicalSky;
+        use crate::environment::Environment;
+        use crate::light::Light;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        #[test]
+        fn sky_brighter_towards_sun() {
+            let sky = PhysicalSky::new(30.0, 90.0, 3.0, Color::new());
+            assert!((sky.sun_direction() - Vector3D::with_values(0.866, 0.5, 0.0)).length() < 1e-3);
+
+            let near_sun = sky
+                .radiance(Vector3D::with_values(1.0, 0.7, 0.0))
+                .luminance();
+            let opposite = sky
+                .radiance(Vector3D::with_values(-1.0, 0.7, 0.0))
+                .luminance();
+            assert!(
+                near_sun > 2.0 * opposite,
+                "Sky not brightening around the sun"
+            );
+
+            let zenith = sky.radiance(Vector3D::with_values(0.0, 1.0, 0.0));
+            assert!(zenith.z() > zenith.x(), "Sky {} not blue", zenith);
+            assert!(
+                (0.05..2.0).contains(&zenith.luminance()),
+                "Zenith {} not of daylight brightness",
+                zenith
+            );
+        }
+
+        #[test]
+        fn sky_ground() {
+            let down = Vector3D::with_values(0.3, -1.0, 0.2);
+
+            let dark = PhysicalSky::new(45.0, 0.0, 3.0, Color::new());
+            assert_eq!(dark.radiance(down), Color::new(), "Black ground lit");
+
+            let albedo = Color::with_values(0.3, 0.3, 0.3);
+            let ground = PhysicalSky::new(45.0, 0.0, 3.0, albedo);
+            let radiance = ground.radiance(down);
+            assert!(radiance.luminance() > 0.0, "Ground not lit");
+            assert_eq!(
+                radiance,
+                ground.radiance(Vector3D::with_values(0.0, -1.0, 0.0))
+            );
+
+            // Lit by at least the sun, at most by the sun and a sky as bright as the zenith
+            let sun = ground.sun().irradiance().luminance() * 45f64.to_radians().sin();
+            let zenith = ground
+                .radiance(Vector3D::with_values(0.0, 1.0, 0.0))
+                .luminance();
+            let lit = radiance.luminance() * std::f64::consts::PI / 0.3;
+            assert!(lit > sun && lit < sun + std::f64::consts::PI * zenith * 3.0);
+
+            let brighter = ground.with_intensity(2.0).radiance(down);
+            assert!((brighter - 2.0 * radiance).near_zero(), "Intensity ignored");
+        }
+
+        #[test]
+        fn sky_sun() {
+            let noon = PhysicalSky::new(60.0, 0.0, 3.0, Color::new()).sun();
+            let sunset = PhysicalSky::new(5.0, 0.0, 3.0, Color::new()).sun();
+            let night = PhysicalSky::new(-10.0, 0.0, 3.0, Color::new()).sun();
+
+            assert!(
+                (noon.angular_diameter() - 0.53).abs() < 1e-9,
+                "Sun not 0.53° wide"
+            );
+            assert!(noon.direction().y() < 0.0, "Sun not shining down");
+            let sample = noon.sample(Point3D::new());
+            assert!(sample.direction.y() > 0.8, "Sun not high at noon");
+
+            let (noon, sunset) = (noon.irradiance(), sunset.irradiance());
+            assert!(
+                noon.luminance() > sunset.luminance(),
+                "Sunset brighter than noon"
+            );
+            assert!(
+                sunset.x() / sunset.z() > noon.x() / noon.z(),
+                "Sunset {} not redder than noon {}",
+                sunset,
+                noon
+            );
+            assert_eq!(night.irradiance(), Color::new(), "Sun shining at night");
+
+            // The sky fading out after sunset
+            let up = Vector3D::with_values(0.0, 1.0, 0.0);
+            let dusk = [0.0, -3.0, -10.0]
+                .map(|elevation| PhysicalSky::new(elevation, 0.0, 3.0, Color::new()).radiance(up));
+            assert!(
+                dusk[0].luminance() > dusk[1].luminance() && dusk[1].luminance() > 0.0,
+                "Sky {} not dimming after sunset",
+                dusk[1]
+            );
+            assert_eq!(dusk[2], Color::new(), "Sky lit at night");
+        }
+    }
+}
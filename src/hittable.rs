@@ -1,54 +1,153 @@
 //! Module for representing hittable objects in the context of a raytracer.
 
-use crate::interval::{Interval, EMPTY, UNIVERSE};
+use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::interval::Interval;
+use crate::material::{same_material, Lambertian, Material};
 use crate::ray::Ray;
 use crate::vector3d::{Point3D, Vector3D};
+use std::fmt;
+use std::rc::Rc;
+
+thread_local! {
+    /// The material of surfaces without one, living as long as every hit record.
+    static DEFAULT_MATERIAL: &'static Lambertian = Box::leak(Box::new(Lambertian::DEFAULT));
+}
 
 /// Represents the information recorded when a ray hits an object.
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(Clone)]
 pub struct HitRecord {
     p: Point3D,
     normal: Vector3D,
+    mat: Option<Rc<dyn Material>>,
     t: f64,
+    u: f64,
+    v: f64,
     front_face: bool,
+    primitive_id: usize,
+    object_id: usize,
+    color: Color,
 }
 
-impl HitRecord {
+impl Default for HitRecord {
     /// Creates a new `HitRecord` with default values.
-    pub fn default() -> Self {
+    fn default() -> Self {
         HitRecord {
             p: Point3D::new(),
             normal: Vector3D::new(),
+            mat: None,
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
             front_face: false,
+            primitive_id: 0,
+            object_id: 0,
+            color: Color::with_values(1.0, 1.0, 1.0),
         }
     }
+}
 
+impl HitRecord {
     pub fn new(p: Point3D, normal: Vector3D, t: f64, front_face: bool) -> Self {
         HitRecord {
             p,
             normal,
+            mat: None,
             t,
+            u: 0.0,
+            v: 0.0,
             front_face,
+            primitive_id: 0,
+            object_id: 0,
+            color: Color::with_values(1.0, 1.0, 1.0),
         }
     }
 
-    pub fn p(self) -> Point3D {
+    /// Returns the hit record with the given surface coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` - The first surface coordinate of the hit.
+    /// * `v` - The second surface coordinate of the hit.
+    pub fn with_uv(mut self, u: f64, v: f64) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    /// Returns the hit record with the given color of the surface.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The color of the surface at the hit.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Returns the hit record with the given material.
+    ///
+    /// # Arguments
+    ///
+    /// * `mat` - The material of the surface that was hit.
+    pub fn with_material(mut self, mat: Rc<dyn Material>) -> Self {
+        self.mat = Some(mat);
+        self
+    }
+
+    pub fn p(&self) -> Point3D {
         self.p
     }
 
-    pub fn normal(self) -> Vector3D {
+    pub fn normal(&self) -> Vector3D {
         self.normal
     }
 
-    pub fn t(self) -> f64 {
+    pub fn t(&self) -> f64 {
         self.t
     }
 
-    pub fn front_face(self) -> bool {
+    /// Gets the first surface coordinate of the hit.
+    pub fn u(&self) -> f64 {
+        self.u
+    }
+
+    /// Gets the second surface coordinate of the hit.
+    pub fn v(&self) -> f64 {
+        self.v
+    }
+
+    pub fn front_face(&self) -> bool {
         self.front_face
     }
 
+    /// Gets the material of the surface that was hit.
+    ///
+    /// Surfaces without a material of their own are a diffuse 50% gray.
+    pub fn material(&self) -> &dyn Material {
+        match &self.mat {
+            Some(mat) => mat.as_ref(),
+            None => DEFAULT_MATERIAL.with(|mat| *mat),
+        }
+    }
+
+    /// Gets the color of the surface at the hit, like the one interpolated from
+    /// vertex colors, white for surfaces without a color of their own.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Gets the index of the hit primitive within its object, like the triangle
+    /// of a mesh, 0 for objects made of a single primitive.
+    pub fn primitive_id(&self) -> usize {
+        self.primitive_id
+    }
+
+    /// Gets the index of the hit object within the outermost `HittableList`.
+    pub fn object_id(&self) -> usize {
+        self.object_id
+    }
+
     /// Sets the face normal based on the given ray and outward normal.
     ///
     /// # Arguments
@@ -70,6 +169,39 @@ impl HitRecord {
     }
 }
 
+impl PartialEq for HitRecord {
+    // Materials are equal if they are the very same material
+    fn eq(&self, other: &Self) -> bool {
+        self.p == other.p
+            && self.normal == other.normal
+            && same_material(&self.mat, &other.mat)
+            && self.t == other.t
+            && self.u == other.u
+            && self.v == other.v
+            && self.front_face == other.front_face
+            && self.primitive_id == other.primitive_id
+            && self.object_id == other.object_id
+            && self.color == other.color
+    }
+}
+
+impl fmt::Debug for HitRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HitRecord")
+            .field("p", &self.p)
+            .field("normal", &self.normal)
+            .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+            .field("t", &self.t)
+            .field("u", &self.u)
+            .field("v", &self.v)
+            .field("front_face", &self.front_face)
+            .field("primitive_id", &self.primitive_id)
+            .field("object_id", &self.object_id)
+            .field("color", &self.color)
+            .finish()
+    }
+}
+
 pub trait Hittable {
     /// Checks if a ray intersects with the shape(s) and calculates the hit record.
     ///
@@ -85,6 +217,88 @@ pub trait Hittable {
     /// Returns `true` if the ray intersects with the shape(s), and the hit record is updated.
     /// Returns `false` otherwise.
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    /// Counts the objects and bounding boxes within the shape(s) a ray gets tested
+    /// against by `hit`, telling how costly the ray is.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`     - The ray to count the tests of.
+    /// * `ray_t` - The interval along the ray to look for the closest hit in.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of tests, 0 for shapes tested as a whole.
+    fn intersection_tests(&self, _r: &Ray, _ray_t: Interval) -> usize {
+        0
+    }
+
+    /// Gets the box around the shape(s), `None` for shapes without one.
+    fn extent(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Gets the surface area of the shape(s), 0 for shapes that can't be sampled.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Samples a point uniformly distributed over the surface of the shape(s).
+    ///
+    /// # Returns
+    ///
+    /// Returns a hit record of the sampled point with the outward normal as
+    /// front face, or `None` for shapes that can't be sampled.
+    fn sample_surface(&self) -> Option<HitRecord> {
+        None
+    }
+
+    /// Computes the density per solid angle, seen from `origin`, of `sample_surface`
+    /// choosing the point a ray along `direction` hits first.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin`    - The point the shape(s) are seen from.
+    /// * `direction` - The direction towards the sampled point.
+    ///
+    /// # Returns
+    ///
+    /// Returns the density of uniform area sampling converted to solid angle,
+    /// 0 if the ray misses or the shape(s) can't be sampled.
+    fn pdf(&self, origin: Point3D, direction: Vector3D) -> f64 {
+        let area = self.area();
+        let mut rec = HitRecord::default();
+        let ray = Ray::create(origin, direction);
+        if area <= 0.0 || !self.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let cosine = rec.normal.dot(direction.unit_vector()).abs();
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+        (rec.p - origin).length_squared() / (cosine * area)
+    }
+
+    /// Estimates the fraction of light passing along a ray through the shape(s) unscattered.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`     - The ray the light travels along.
+    /// * `ray_t` - The interval along the ray the light travels through.
+    ///
+    /// # Returns
+    ///
+    /// Returns 0 if a surface blocks the light, an unbiased estimate of the light
+    /// left after passing through participating media otherwise.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let mut rec = HitRecord::default();
+        if self.hit(r, ray_t, &mut rec) {
+            0.0
+        } else {
+            1.0
+        }
+    }
 }
 
 // --- HITTABLE LIST -----------------------------------------------------------
@@ -95,7 +309,6 @@ pub mod hittables {
     use crate::hittable::{HitRecord, Hittable};
     use crate::interval::*;
     use crate::ray::Ray;
-    use crate::vector3d::{Point3D, Vector3D};
     use std::rc::Rc;
     use std::vec::Vec;
 
@@ -115,27 +328,61 @@ pub mod hittables {
         /// # Returns
         ///
         /// Returns `true` if the ray intersects with any object, updating the hit record
+        /// and setting its object id to the index of the object hit.
         /// Returns `false` otherwise.
         fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
             let mut temp_rec = HitRecord::default();
             let mut hit_anything = false;
             let mut closest_so_far = ray_t.max;
 
-            for object in self {
+            for (id, object) in self.iter().enumerate() {
                 if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
                     hit_anything = true;
                     closest_so_far = temp_rec.t;
+                    temp_rec.object_id = id;
                     *rec = temp_rec.clone();
                 }
             }
 
             hit_anything
         }
+
+        /// Counts every object in the list and the tests within them.
+        fn intersection_tests(&self, r: &Ray, ray_t: Interval) -> usize {
+            let mut rec = HitRecord::default();
+            let mut closest_so_far = ray_t.max;
+            let mut tests = self.len();
+
+            for object in self.iter() {
+                let interval = Interval::new(ray_t.min, closest_so_far);
+                tests += object.intersection_tests(r, interval);
+                if object.hit(r, interval, &mut rec) {
+                    closest_so_far = rec.t;
+                }
+            }
+
+            tests
+        }
+
+        /// Estimates the fraction of light passing along a ray through every object in the list.
+        fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+            let mut transmittance = 1.0;
+            for object in self.iter() {
+                transmittance *= object.transmittance(r, ray_t);
+                if transmittance <= 0.0 {
+                    return 0.0;
+                }
+            }
+
+            transmittance
+        }
     }
 
     #[cfg(test)]
     mod tests {
+        use crate::color::Color;
         use crate::hittable::hittables::HittableList;
+        use crate::hittable::mesh::TriangleMesh;
         use crate::hittable::sphere::Sphere;
         use crate::hittable::{HitRecord, Hittable};
         use crate::interval::*;
@@ -175,12 +422,95 @@ pub mod hittables {
                     Vector3D::with_values(-1.0, 0.0, 0.0),
                     1.0,
                     true
-                ),
+                )
+                .with_uv(0.0, 0.5),
                 "Hit Record not as expected"
             );
 
             // TODO: Add further tests for longer more diverse lists
         }
+
+        #[test]
+        fn list_object_id() {
+            //   o----------->X     c0          c1
+            // (0,0)        (1,0) (2,0)       (5,0)
+
+            let hittables: HittableList = vec![
+                Rc::new(Sphere::new(Point3D::with_values(5.0, 0.0, 0.0), 1.0)),
+                Rc::new(Sphere::new(Point3D::with_values(2.0, 0.0, 0.0), 1.0)),
+            ];
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let ray_t: Interval = Interval::new(0.5, f64::INFINITY);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(hittables.hit(&ray, ray_t, rec), "Spheres in List not hit");
+            assert_eq!(rec.t(), 1.0, "Closest sphere not hit first");
+            assert_eq!(rec.object_id(), 1, "Object id not the list index");
+        }
+
+        #[test]
+        fn list_keeps_primitive_id() {
+            //   o----------->X    triangle 1 of the mesh
+            // (0,0)        (1,0)
+
+            let mesh = TriangleMesh::new(
+                vec![
+                    Point3D::with_values(1.0, -1.0, 0.0),
+                    Point3D::with_values(1.0, 1.0, 0.0),
+                    Point3D::with_values(1.0, 0.0, 2.0),
+                    Point3D::with_values(1.0, 0.0, -2.0),
+                ],
+                vec![[0, 1, 2], [1, 0, 3]],
+            );
+            let hittables: HittableList = vec![
+                Rc::new(Sphere::new(Point3D::with_values(5.0, 0.0, 0.0), 1.0)),
+                Rc::new(mesh),
+            ];
+
+            let ray: Ray = Ray::create(
+                Point3D::with_values(0.0, 0.0, -0.5),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let ray_t: Interval = Interval::new(0.5, f64::INFINITY);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(hittables.hit(&ray, ray_t, rec), "Mesh in List not hit");
+            assert_eq!(rec.object_id(), 1, "Object id not the list index");
+            assert_eq!(rec.primitive_id(), 1, "Primitive id not the triangle index");
+        }
+
+        #[test]
+        fn list_color_not_leaking() {
+            //   o----------->X     c           red mesh
+            // (0,0)        (1,0) (2,0)          x = 5
+
+            let mesh = TriangleMesh::new(
+                vec![
+                    Point3D::with_values(5.0, -1.0, -1.0),
+                    Point3D::with_values(5.0, 1.0, -1.0),
+                    Point3D::with_values(5.0, 0.0, 1.0),
+                ],
+                vec![[0, 1, 2]],
+            )
+            .with_colors(vec![Color::with_values(1.0, 0.0, 0.0); 3]);
+            let hittables: HittableList = vec![
+                Rc::new(mesh),
+                Rc::new(Sphere::new(Point3D::with_values(2.0, 0.0, 0.0), 1.0)),
+            ];
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let ray_t: Interval = Interval::new(0.5, f64::INFINITY);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(hittables.hit(&ray, ray_t, rec), "Objects in List not hit");
+            assert_eq!(rec.t(), 1.0, "Closest sphere not hit first");
+            assert_eq!(
+                rec.color(),
+                Color::with_values(1.0, 1.0, 1.0),
+                "Mesh color leaked onto the sphere"
+            );
+        }
     }
 }
 
@@ -189,24 +519,32 @@ pub mod hittables {
 // SPHERE
 pub mod sphere {
     //! Module for handling spheres in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
     use crate::hittable::{HitRecord, Hittable};
     use crate::interval::*;
+    use crate::material::{same_material, Material};
     use crate::ray::Ray;
     use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
 
     /// Represents a sphere in 3D space.
-    #[derive(Debug, PartialEq)]
     pub struct Sphere {
         center: Point3D,
         radius: f64,
+        mat: Option<Rc<dyn Material>>,
     }
 
-    impl Sphere {
+    impl Default for Sphere {
         /// Creates a new sphere with the default parameters (center at the origin, radius 0.0).
-        pub fn default() -> Self {
+        fn default() -> Self {
             Self::new(Point3D::new(), 0.0)
         }
+    }
 
+    impl Sphere {
         /// Creates a new sphere with the specified center and radius.
         ///
         /// # Arguments
@@ -214,18 +552,56 @@ pub mod sphere {
         /// * `center` - The center of the sphere.
         /// * `radius` - The radius of the sphere.
         pub fn new(center: Point3D, radius: f64) -> Self {
-            Sphere { center, radius }
+            Sphere {
+                center,
+                radius,
+                mat: None,
+            }
+        }
+
+        /// Creates a new sphere with the specified center, radius and material.
+        ///
+        /// # Arguments
+        ///
+        /// * `center` - The center of the sphere.
+        /// * `radius` - The radius of the sphere.
+        /// * `mat`    - The material of the sphere's surface.
+        pub fn with_material(center: Point3D, radius: f64, mat: Rc<dyn Material>) -> Self {
+            Sphere {
+                center,
+                radius,
+                mat: Some(mat),
+            }
         }
 
         /// Gets the center of the sphere.
-        pub fn center(self) -> Point3D {
+        pub fn center(&self) -> Point3D {
             self.center
         }
 
         /// Gets the radius of the sphere.
-        pub fn radius(self) -> f64 {
+        pub fn radius(&self) -> f64 {
             self.radius
         }
+
+        /// Computes the surface coordinates of a point on the unit sphere.
+        ///
+        /// `u` is the angle around the Y axis starting from X=-1, `v` the angle
+        /// from Y=-1 to Y=+1, both normalized to [0, 1].
+        ///
+        /// # Arguments
+        ///
+        /// * `p` - A point on the unit sphere centered at the origin.
+        ///
+        /// # Returns
+        ///
+        /// Returns the `(u, v)` surface coordinates of `p`.
+        pub fn uv(p: Point3D) -> (f64, f64) {
+            let theta: f64 = (-p.y()).acos();
+            let phi: f64 = (-p.z()).atan2(p.x()) + PI;
+
+            (phi / (2.0 * PI), theta / PI)
+        }
     }
 
     impl Hittable for Sphere {
@@ -254,9 +630,57 @@ pub mod sphere {
             rec.p = r.at(rec.t);
             let outward_normal: Vector3D = (rec.p - self.center) / self.radius;
             rec.set_face_normal(*r, outward_normal.unit_vector());
+            (rec.u, rec.v) = Self::uv(outward_normal);
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
 
             true
         }
+
+        fn extent(&self) -> Option<Aabb> {
+            let radius = Vector3D::with_values(self.radius, self.radius, self.radius);
+            Some(Aabb::new(self.center - radius, self.center + radius))
+        }
+
+        fn area(&self) -> f64 {
+            4.0 * PI * self.radius * self.radius
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            let outward_normal: Vector3D = Vector3D::random_unit_vector();
+            let (u, v) = Self::uv(outward_normal);
+
+            Some(HitRecord {
+                p: self.center + self.radius * outward_normal,
+                normal: outward_normal,
+                mat: self.mat.clone(),
+                t: 0.0,
+                u,
+                v,
+                front_face: true,
+                ..HitRecord::default()
+            })
+        }
+    }
+
+    impl PartialEq for Sphere {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.center == other.center
+                && self.radius == other.radius
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Sphere {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Sphere")
+                .field("center", &self.center)
+                .field("radius", &self.radius)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
     }
 
     #[cfg(test)]
@@ -280,6 +704,40 @@ pub mod sphere {
             assert_eq!(sphere.radius, 1.0, "Sphere radius not 1.0");
         }
 
+        #[test]
+        fn sphere_uv() {
+            assert_eq!(Sphere::uv(Point3D::with_values(1.0, 0.0, 0.0)), (0.5, 0.5));
+            assert_eq!(Sphere::uv(Point3D::with_values(0.0, 1.0, 0.0)), (0.5, 1.0));
+            assert_eq!(Sphere::uv(Point3D::with_values(-1.0, 0.0, 0.0)), (0.0, 0.5));
+            assert_eq!(Sphere::uv(Point3D::with_values(0.0, 0.0, 1.0)), (0.25, 0.5));
+            assert_eq!(Sphere::uv(Point3D::with_values(0.0, -1.0, 0.0)), (0.5, 0.0));
+            assert_eq!(
+                Sphere::uv(Point3D::with_values(0.0, 0.0, -1.0)),
+                (0.75, 0.5)
+            );
+        }
+
+        #[test]
+        fn sphere_sample_surface() {
+            let sphere: Sphere = Sphere::new(Point3D::with_values(2.0, 0.0, 0.0), 2.0);
+
+            assert_eq!(sphere.area(), 16.0 * std::f64::consts::PI);
+            for _ in 0..10 {
+                let rec = sphere.sample_surface().expect("Sphere not sampled");
+                assert!(
+                    ((rec.p() - sphere.center()).length() - 2.0).abs() < 1e-12,
+                    "Sample {} not on the surface",
+                    rec.p()
+                );
+                assert!(
+                    (rec.normal() - (rec.p() - sphere.center()) / 2.0).near_zero(),
+                    "Normal {} not pointing outwards",
+                    rec.normal()
+                );
+                assert!(rec.front_face(), "Sample not on the front face");
+            }
+        }
+
         #[test]
         fn sphere_hit_outside() {
             //      t=1.0--.    .-*****-.   ---
@@ -305,7 +763,8 @@ pub mod sphere {
                     Vector3D::with_values(-1.0, 0.0, 0.0),
                     1.0,
                     true
-                ),
+                )
+                .with_uv(0.0, 0.5),
                 "Hit Record not as expected"
             );
         }
@@ -335,7 +794,8 @@ pub mod sphere {
                     Vector3D::with_values(-1.0, 0.0, 0.0),
                     3.0,
                     false
-                ),
+                )
+                .with_uv(0.5, 0.5),
                 "Hit Record not as expected"
             );
         }
@@ -360,3 +820,4603 @@ pub mod sphere {
         }
     }
 }
+
+// TRIANGLE
+pub mod triangle {
+    //! Module for handling triangles in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a triangle in 3D space.
+    ///
+    /// The side the vertices are seen counterclockwise from is the outside,
+    /// unless per-vertex normals say otherwise.
+    pub struct Triangle {
+        vertices: [Point3D; 3],
+        normals: Option<[Vector3D; 3]>,
+        uvs: [(f64, f64); 3],
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Triangle {
+        /// Creates a new triangle with the specified vertices.
+        ///
+        /// # Arguments
+        ///
+        /// * `a` - The first vertex, at surface coordinates (0, 0).
+        /// * `b` - The second vertex, at surface coordinates (1, 0).
+        /// * `c` - The third vertex, at surface coordinates (0, 1).
+        pub fn new(a: Point3D, b: Point3D, c: Point3D) -> Self {
+            Triangle {
+                vertices: [a, b, c],
+                normals: None,
+                uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+                mat: None,
+            }
+        }
+
+        /// Creates a new triangle with the specified vertices and material.
+        ///
+        /// # Arguments
+        ///
+        /// * `a`   - The first vertex, at surface coordinates (0, 0).
+        /// * `b`   - The second vertex, at surface coordinates (1, 0).
+        /// * `c`   - The third vertex, at surface coordinates (0, 1).
+        /// * `mat` - The material of the triangle's surface.
+        pub fn with_material(a: Point3D, b: Point3D, c: Point3D, mat: Rc<dyn Material>) -> Self {
+            Triangle {
+                mat: Some(mat),
+                ..Self::new(a, b, c)
+            }
+        }
+
+        /// Returns the triangle with normals at its vertices, interpolated across its surface.
+        ///
+        /// # Arguments
+        ///
+        /// * `normals` - The normals at the vertices, in the order of the vertices.
+        pub fn with_normals(mut self, normals: [Vector3D; 3]) -> Self {
+            self.normals = Some(normals.map(Vector3D::unit_vector));
+            self
+        }
+
+        /// Returns the triangle with surface coordinates at its vertices.
+        ///
+        /// # Arguments
+        ///
+        /// * `uvs` - The `(u, v)` surface coordinates at the vertices, in the order of the vertices.
+        pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+            self.uvs = uvs;
+            self
+        }
+
+        /// Gets the vertices of the triangle.
+        pub fn vertices(&self) -> [Point3D; 3] {
+            self.vertices
+        }
+
+        /// Gets the normals at the vertices of the triangle, if it has any.
+        pub fn normals(&self) -> Option<[Vector3D; 3]> {
+            self.normals
+        }
+
+        /// Gets the surface coordinates at the vertices of the triangle.
+        pub fn uvs(&self) -> [(f64, f64); 3] {
+            self.uvs
+        }
+
+        /// Fills the hit record for the point with the given barycentric coordinates.
+        fn surface(&self, r: &Ray, t: f64, b: [f64; 3], rec: &mut HitRecord) {
+            rec.t = t;
+            (rec.p, rec.normal, rec.front_face) =
+                shade(r, &self.vertices, self.normals.as_ref(), b);
+            rec.u = b[0] * self.uvs[0].0 + b[1] * self.uvs[1].0 + b[2] * self.uvs[2].0;
+            rec.v = b[0] * self.uvs[0].1 + b[1] * self.uvs[1].1 + b[2] * self.uvs[2].1;
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+        }
+    }
+
+    /// Intersects a ray with a triangle without letting it slip through between
+    /// triangles sharing an edge (Woop et al., "Watertight Ray/Triangle Intersection", 2013).
+    ///
+    /// # Arguments
+    ///
+    /// * `r`        - The ray to check for intersection.
+    /// * `vertices` - The vertices of the triangle.
+    /// * `ray_t`    - The interval along the ray to consider for intersection.
+    ///
+    /// # Returns
+    ///
+    /// Returns the parameter along the ray and the barycentric coordinates of the hit,
+    /// `None` if the ray misses the triangle.
+    pub(crate) fn intersect(
+        r: &Ray,
+        vertices: &[Point3D; 3],
+        ray_t: Interval,
+    ) -> Option<(f64, [f64; 3])> {
+        let coords = |v: Vector3D| [v.x(), v.y(), v.z()];
+
+        // Look along the ray, its largest component becoming z
+        let d = coords(r.direction());
+        let kz = if d[0].abs() > d[1].abs() {
+            if d[0].abs() > d[2].abs() {
+                0
+            } else {
+                2
+            }
+        } else if d[1].abs() > d[2].abs() {
+            1
+        } else {
+            2
+        };
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+        if d[kz] == 0.0 {
+            return None;
+        }
+
+        // Shear the triangle into the space the ray points along +z in from the origin
+        let sx = -d[kx] / d[kz];
+        let sy = -d[ky] / d[kz];
+        let sz = 1.0 / d[kz];
+        let p = vertices.map(|v| {
+            let p = coords(v - r.origin());
+            [p[kx] + sx * p[kz], p[ky] + sy * p[kz], p[kz] * sz]
+        });
+
+        // The edge functions tell which side of every edge the ray passes by
+        let e = [
+            p[1][0] * p[2][1] - p[1][1] * p[2][0],
+            p[2][0] * p[0][1] - p[2][1] * p[0][0],
+            p[0][0] * p[1][1] - p[0][1] * p[1][0],
+        ];
+        if e.iter().any(|&e| e < 0.0) && e.iter().any(|&e| e > 0.0) {
+            return None;
+        }
+        let det = e[0] + e[1] + e[2];
+        if det == 0.0 {
+            return None;
+        }
+
+        let t = (e[0] * p[0][2] + e[1] * p[1][2] + e[2] * p[2][2]) / det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        Some((t, e.map(|e| e / det)))
+    }
+
+    /// Computes the point, normal and side of a hit on a triangle from its barycentric coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`        - The ray that hit the triangle.
+    /// * `vertices` - The vertices of the triangle.
+    /// * `normals`  - The unit normals at the vertices, if the triangle has any.
+    /// * `b`        - The barycentric coordinates of the hit.
+    ///
+    /// # Returns
+    ///
+    /// Returns the point hit, the normal facing against the ray and if the outside was hit.
+    pub(crate) fn shade(
+        r: &Ray,
+        vertices: &[Point3D; 3],
+        normals: Option<&[Vector3D; 3]>,
+        b: [f64; 3],
+    ) -> (Point3D, Vector3D, bool) {
+        let p = b[0] * vertices[0] + b[1] * vertices[1] + b[2] * vertices[2];
+        let mut outward_normal = (vertices[1] - vertices[0])
+            .cross(vertices[2] - vertices[0])
+            .unit_vector();
+
+        // Vertex normals smooth the shading, and tell which side is the outside
+        let shading_normal = match normals {
+            Some(n) => {
+                let shading_normal = (b[0] * n[0] + b[1] * n[1] + b[2] * n[2]).unit_vector();
+                if outward_normal.dot(shading_normal) < 0.0 {
+                    outward_normal = -outward_normal;
+                }
+                shading_normal
+            }
+            None => outward_normal,
+        };
+
+        let front_face = r.direction().dot(outward_normal) < 0.0;
+        let normal = if front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+
+        (p, normal, front_face)
+    }
+
+    /// Samples a point uniformly distributed over a triangle.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertices` - The vertices of the triangle.
+    /// * `normals`  - The unit normals at the vertices, if the triangle has any.
+    ///
+    /// # Returns
+    ///
+    /// Returns the barycentric coordinates of the point and a ray hitting it from the outside.
+    pub(crate) fn sample(
+        vertices: &[Point3D; 3],
+        normals: Option<&[Vector3D; 3]>,
+    ) -> ([f64; 3], Ray) {
+        // Fold the unit square onto the triangle, keeping the samples uniform
+        let su = random_f64().sqrt();
+        let v = random_f64();
+        let b = [1.0 - su, su * (1.0 - v), su * v];
+
+        let mut outward_normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+        if let Some(n) = normals {
+            if outward_normal.dot(b[0] * n[0] + b[1] * n[1] + b[2] * n[2]) < 0.0 {
+                outward_normal = -outward_normal;
+            }
+        }
+
+        (b, Ray::create(Point3D::new(), -outward_normal))
+    }
+
+    impl Hittable for Triangle {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            match intersect(r, &self.vertices, ray_t) {
+                Some((t, b)) => {
+                    self.surface(r, t, b, rec);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            let [a, b, c] = self.vertices;
+            Some(Aabb::new(a, b).grow(c))
+        }
+
+        fn area(&self) -> f64 {
+            let [a, b, c] = self.vertices;
+            0.5 * (b - a).cross(c - a).length()
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            let (b, probe) = sample(&self.vertices, self.normals.as_ref());
+            let mut rec = HitRecord::default();
+            self.surface(&probe, 0.0, b, &mut rec);
+
+            Some(rec)
+        }
+    }
+
+    impl PartialEq for Triangle {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.vertices == other.vertices
+                && self.normals == other.normals
+                && self.uvs == other.uvs
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Triangle {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Triangle")
+                .field("vertices", &self.vertices)
+                .field("normals", &self.normals)
+                .field("uvs", &self.uvs)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::triangle::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The triangle from (2,-1,-1) over (2,-1,1) to (2,1,-1), facing -x.
+        fn triangle() -> Triangle {
+            Triangle::new(
+                Point3D::with_values(2.0, -1.0, -1.0),
+                Point3D::with_values(2.0, -1.0, 1.0),
+                Point3D::with_values(2.0, 1.0, -1.0),
+            )
+        }
+
+        #[test]
+        fn triangle_new() {
+            let tri = triangle();
+
+            assert_eq!(tri.vertices()[2], Point3D::with_values(2.0, 1.0, -1.0));
+            assert_eq!(tri.normals(), None, "Triangle has vertex normals");
+            assert_eq!(tri.uvs(), [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+            assert_eq!(tri.area(), 2.0);
+            assert_ne!(
+                tri,
+                triangle().with_uvs([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]),
+                "Triangles with different surface coordinates equal"
+            );
+        }
+
+        #[test]
+        fn triangle_hit_front() {
+            //                 (2,1)
+            //      t=2.0--.    |\
+            //              \   | \          seen from above, the
+            //   o----------->X  \         triangle facing -x
+            // (0,0)     HIT AT |   \
+            //            (2,0) |____\
+            //               (2,-1)  (2,-1,1)
+            //          |        |
+            //          [1.5, 2.5] =: [tmin, tmax]
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let ray_t: Interval = Interval::new(1.5, 2.5);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                triangle().hit(&ray, ray_t, rec),
+                "Test triangle not hit by ray"
+            );
+            assert_eq!(
+                *rec,
+                HitRecord::new(
+                    Point3D::with_values(2.0, 0.0, 0.0),
+                    Vector3D::with_values(-1.0, 0.0, 0.0),
+                    2.0,
+                    true
+                )
+                .with_uv(0.5, 0.5),
+                "Hit Record not as expected"
+            );
+        }
+
+        #[test]
+        fn triangle_hit_back() {
+            //                  |\
+            //                  | \        .--t=2.0
+            //                  |  \      /
+            //          HIT AT  X<--------o
+            //           (2,0)  |    \  (4,0)
+            //                  |_____\
+            //                  |        |
+            //   [tmin, tmax] := [1.5, 2.5]
+
+            let ray: Ray = Ray::create(
+                Point3D::with_values(4.0, 0.0, 0.0),
+                Vector3D::with_values(-1.0, 0.0, 0.0),
+            );
+            let ray_t: Interval = Interval::new(1.5, 2.5);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                triangle().hit(&ray, ray_t, rec),
+                "Test triangle not hit by ray"
+            );
+            assert_eq!(
+                *rec,
+                HitRecord::new(
+                    Point3D::with_values(2.0, 0.0, 0.0),
+                    Vector3D::with_values(1.0, 0.0, 0.0),
+                    2.0,
+                    false
+                )
+                .with_uv(0.5, 0.5),
+                "Hit Record not as expected"
+            );
+        }
+
+        #[test]
+        fn triangle_not_hit() {
+            //                  |\
+            //                  | \
+            //   o-------------------->     passing by beyond the slanted edge,
+            // (0,0.5,0.5)      |   \       pointing away or out of the interval
+            //                  |____\
+
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            let above = Ray::create(
+                Point3D::with_values(0.0, 0.5, 0.5),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let away = Ray::create(Point3D::new(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            let along = Ray::create(Point3D::new(), Vector3D::with_values(0.0, 1.0, 0.0));
+            let ahead = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            assert!(!triangle().hit(&above, Interval::new(0.0, 10.0), rec));
+            assert!(!triangle().hit(&away, Interval::new(0.0, 10.0), rec));
+            assert!(!triangle().hit(&along, Interval::new(0.0, 10.0), rec));
+            assert!(!triangle().hit(&ahead, Interval::new(2.5, 3.5), rec));
+            assert_eq!(*rec, HitRecord::default(), "Hit Record not as expected");
+        }
+
+        #[test]
+        fn triangle_watertight() {
+            //   (2,1,-1) +-----+ (2,1,1)    rays through the diagonal
+            //            |    /|            both triangles share never
+            //            |  /  |            slip through between them
+            //  (2,-1,-1) +-----+ (2,-1,1)
+            let a = Point3D::with_values(2.0, -1.0, -1.0);
+            let b = Point3D::with_values(2.0, 1.0, -1.0);
+            let c = Point3D::with_values(2.0, 1.0, 1.0);
+            let d = Point3D::with_values(2.0, -1.0, 1.0);
+            let halves = [Triangle::new(a, b, d), Triangle::new(b, c, d)];
+            let origin = Point3D::with_values(-0.3, 0.1, 0.7);
+
+            for i in 0..=1000 {
+                let s = f64::from(i) / 1000.0 * 2.0 - 1.0;
+                let ray = Ray::create(origin, Point3D::with_values(2.0, s, -s) - origin);
+                let rec: &mut HitRecord = &mut HitRecord::default();
+                assert!(
+                    halves
+                        .iter()
+                        .any(|t| t.hit(&ray, Interval::new(0.0, f64::INFINITY), rec)),
+                    "Ray towards (2, {}, {}) slipped through",
+                    s,
+                    -s
+                );
+            }
+        }
+
+        #[test]
+        fn triangle_normals_uvs() {
+            let tri = triangle()
+                .with_normals([
+                    Vector3D::with_values(-1.0, -1.0, 0.0),
+                    Vector3D::with_values(-1.0, -1.0, 0.0),
+                    Vector3D::with_values(-1.0, 1.0, 0.0),
+                ])
+                .with_uvs([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            // Halfway between the vertex normals bending down and up
+            assert!(tri.hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert!(
+                (rec.normal() - Vector3D::with_values(-1.0, 0.0, 0.0)).near_zero(),
+                "Normal {} not interpolated",
+                rec.normal()
+            );
+            assert_eq!((rec.u(), rec.v()), (1.0, 0.5));
+            assert!(rec.front_face());
+
+            // Vertex normals pointing the other way turn the triangle around
+            let flipped = triangle().with_normals([Vector3D::with_values(1.0, 0.0, 0.0); 3]);
+            assert!(flipped.hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert!(!rec.front_face(), "Vertex normals not defining the outside");
+            assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+        }
+
+        #[test]
+        fn triangle_sample_surface() {
+            let tri = triangle();
+
+            for _ in 0..10 {
+                let rec = tri.sample_surface().expect("Triangle not sampled");
+                let p = rec.p();
+                assert!(
+                    (p.x() - 2.0).abs() < 1e-12 && p.y() >= -1.0 && p.z() >= -1.0,
+                    "Sample {} not on the surface",
+                    p
+                );
+                assert!(
+                    p.y() + p.z() <= 1e-12,
+                    "Sample {} beyond the slanted edge",
+                    p
+                );
+                assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+                assert!(rec.front_face(), "Sample not on the front face");
+            }
+        }
+    }
+}
+
+// TRIANGLE MESH
+pub mod mesh {
+    //! Module for handling meshes of triangles sharing their vertices in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::distribution::Distribution1D;
+    use crate::hittable::triangle::{intersect, sample, shade};
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::Material;
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// How many triangles a leaf of the hierarchy holds at most before it gets split.
+    const MAX_LEAF_SIZE: usize = 4;
+
+    /// How many buckets triangles get sorted into when looking for the best split.
+    const BUCKETS: usize = 12;
+
+    /// How deep the surface area heuristic splits, below it the triangles get halved.
+    /// Halving at most 2³² triangles keeps the hierarchy within the traversal stack.
+    const MAX_SAH_DEPTH: usize = 30;
+
+    /// How many nodes traversing the hierarchy keeps track of at most.
+    const STACK_SIZE: usize = 64;
+
+    /// Represents a node of the bounding volume hierarchy, stored depth first.
+    ///
+    /// Leaves hold `count` triangles starting at `offset` in the triangle order,
+    /// interior nodes have their first child right after them and their second at `offset`.
+    #[derive(Debug, Clone, Copy)]
+    struct Node {
+        bounds: Aabb,
+        offset: u32,
+        count: u16,
+        axis: u8,
+    }
+
+    /// Represents a mesh of triangles, storing every vertex once for all triangles sharing it.
+    ///
+    /// The triangles are kept in a bounding volume hierarchy, so rays only test
+    /// the few triangles close to them even for millions of triangles.
+    pub struct TriangleMesh {
+        positions: Vec<Point3D>,
+        normals: Vec<Vector3D>,
+        uvs: Vec<(f64, f64)>,
+        colors: Vec<Color>,
+        indices: Vec<[u32; 3]>,
+        mat: Option<Rc<dyn Material>>,
+        nodes: Vec<Node>,
+        order: Vec<u32>,
+        areas: Distribution1D,
+        area: f64,
+    }
+
+    impl TriangleMesh {
+        /// Creates a new triangle mesh and builds its bounding volume hierarchy.
+        ///
+        /// # Arguments
+        ///
+        /// * `positions` - The positions of the vertices.
+        /// * `indices`   - The indices of the three vertices of every triangle, counterclockwise seen from the outside.
+        ///
+        /// # Panics
+        ///
+        /// Panics if a triangle refers to a vertex that doesn't exist.
+        pub fn new(positions: Vec<Point3D>, indices: Vec<[u32; 3]>) -> Self {
+            assert!(
+                indices
+                    .iter()
+                    .flatten()
+                    .all(|&i| (i as usize) < positions.len()),
+                "Triangle refers to a vertex beyond the {} given",
+                positions.len()
+            );
+
+            let triangle_areas: Vec<f64> = indices
+                .iter()
+                .map(|&[a, b, c]| {
+                    let (a, b, c) = (
+                        positions[a as usize],
+                        positions[b as usize],
+                        positions[c as usize],
+                    );
+                    0.5 * (b - a).cross(c - a).length()
+                })
+                .collect();
+
+            let mut mesh = TriangleMesh {
+                positions,
+                normals: Vec::new(),
+                uvs: Vec::new(),
+                colors: Vec::new(),
+                indices,
+                mat: None,
+                nodes: Vec::new(),
+                order: Vec::new(),
+                areas: Distribution1D::new(&triangle_areas),
+                area: triangle_areas.iter().sum(),
+            };
+            mesh.build();
+
+            mesh
+        }
+
+        /// Returns the mesh with normals at its vertices, interpolated across its triangles.
+        ///
+        /// # Arguments
+        ///
+        /// * `normals` - The normal of every vertex, in the order of the positions.
+        ///
+        /// # Panics
+        ///
+        /// Panics if there isn't a normal for every vertex.
+        pub fn with_normals(mut self, normals: Vec<Vector3D>) -> Self {
+            assert_eq!(
+                normals.len(),
+                self.positions.len(),
+                "Not a normal per vertex"
+            );
+            self.normals = normals.into_iter().map(Vector3D::unit_vector).collect();
+            self
+        }
+
+        /// Returns the mesh with surface coordinates at its vertices.
+        ///
+        /// # Arguments
+        ///
+        /// * `uvs` - The `(u, v)` surface coordinates of every vertex, in the order of the positions.
+        ///
+        /// # Panics
+        ///
+        /// Panics if there aren't surface coordinates for every vertex.
+        pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+            assert_eq!(uvs.len(), self.positions.len(), "Not a uv per vertex");
+            self.uvs = uvs;
+            self
+        }
+
+        /// Returns the mesh with colors at its vertices, tinting the diffuse color of its material.
+        ///
+        /// # Arguments
+        ///
+        /// * `colors` - The linear color of every vertex, in the order of the positions.
+        ///
+        /// # Panics
+        ///
+        /// Panics if there isn't a color for every vertex.
+        pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+            assert_eq!(colors.len(), self.positions.len(), "Not a color per vertex");
+            self.colors = colors;
+            self
+        }
+
+        /// Returns the mesh with the given material on all of its triangles.
+        ///
+        /// # Arguments
+        ///
+        /// * `mat` - The material of the mesh's surface.
+        pub fn with_material(mut self, mat: Rc<dyn Material>) -> Self {
+            self.mat = Some(mat);
+            self
+        }
+
+        /// Gets the positions of the vertices.
+        pub fn positions(&self) -> &[Point3D] {
+            &self.positions
+        }
+
+        /// Gets the normals of the vertices, empty if the mesh has none.
+        pub fn normals(&self) -> &[Vector3D] {
+            &self.normals
+        }
+
+        /// Gets the surface coordinates of the vertices, empty if the mesh has none.
+        pub fn uvs(&self) -> &[(f64, f64)] {
+            &self.uvs
+        }
+
+        /// Gets the colors of the vertices, empty if the mesh has none.
+        pub fn colors(&self) -> &[Color] {
+            &self.colors
+        }
+
+        /// Gets the indices of the vertices of every triangle.
+        pub fn indices(&self) -> &[[u32; 3]] {
+            &self.indices
+        }
+
+        /// Gets how many triangles the mesh has.
+        pub fn len(&self) -> usize {
+            self.indices.len()
+        }
+
+        /// Checks if the mesh has no triangles.
+        pub fn is_empty(&self) -> bool {
+            self.indices.is_empty()
+        }
+
+        /// Gets the box bounding every triangle of the mesh.
+        pub fn bounding_box(&self) -> Aabb {
+            self.nodes.first().map_or(Aabb::new_empty(), |n| n.bounds)
+        }
+
+        /// Gets every triangle of the mesh as a primitive of its own, sharing the mesh's vertices.
+        pub fn triangles(self: &Rc<Self>) -> Vec<MeshTriangle> {
+            (0..self.len())
+                .map(|index| MeshTriangle {
+                    mesh: Rc::clone(self),
+                    index,
+                })
+                .collect()
+        }
+
+        /// Gets the vertices of the triangle at `index`.
+        fn vertices(&self, index: usize) -> [Point3D; 3] {
+            self.indices[index].map(|i| self.positions[i as usize])
+        }
+
+        /// Gets the normals at the vertices of the triangle at `index`, if the mesh has any.
+        fn vertex_normals(&self, index: usize) -> Option<[Vector3D; 3]> {
+            if self.normals.is_empty() {
+                None
+            } else {
+                Some(self.indices[index].map(|i| self.normals[i as usize]))
+            }
+        }
+
+        /// Fills the hit record for the point with the given barycentric coordinates
+        /// on the triangle at `index`.
+        fn surface(&self, r: &Ray, t: f64, index: usize, b: [f64; 3], rec: &mut HitRecord) {
+            rec.t = t;
+            rec.primitive_id = index;
+            (rec.p, rec.normal, rec.front_face) = shade(
+                r,
+                &self.vertices(index),
+                self.vertex_normals(index).as_ref(),
+                b,
+            );
+            (rec.u, rec.v) = if self.uvs.is_empty() {
+                (b[1], b[2])
+            } else {
+                let uvs = self.indices[index].map(|i| self.uvs[i as usize]);
+                (
+                    b[0] * uvs[0].0 + b[1] * uvs[1].0 + b[2] * uvs[2].0,
+                    b[0] * uvs[0].1 + b[1] * uvs[1].1 + b[2] * uvs[2].1,
+                )
+            };
+            rec.color = if self.colors.is_empty() {
+                Color::with_values(1.0, 1.0, 1.0)
+            } else {
+                let colors = self.indices[index].map(|i| self.colors[i as usize]);
+                b[0] * colors[0] + b[1] * colors[1] + b[2] * colors[2]
+            };
+            rec.mat = self.mat.clone();
+        }
+
+        /// Samples a point uniformly distributed over the triangle at `index`.
+        fn sample_triangle(&self, index: usize) -> HitRecord {
+            let (b, probe) = sample(&self.vertices(index), self.vertex_normals(index).as_ref());
+            let mut rec = HitRecord::default();
+            self.surface(&probe, 0.0, index, b, &mut rec);
+
+            rec
+        }
+
+        /// Builds the bounding volume hierarchy over all triangles.
+        fn build(&mut self) {
+            let mut primitives: Vec<(u32, Aabb)> = (0..self.indices.len())
+                .map(|index| {
+                    let [a, b, c] = self.vertices(index);
+                    (index as u32, Aabb::new(a, b).grow(c))
+                })
+                .collect();
+
+            if !primitives.is_empty() {
+                self.nodes.reserve(2 * primitives.len() / MAX_LEAF_SIZE + 1);
+                self.build_node(&mut primitives, 0, 0);
+            }
+            self.order = primitives.into_iter().map(|(index, _)| index).collect();
+        }
+
+        /// Builds the node over `primitives`, starting at `offset` in the triangle order,
+        /// splitting them where the surface area heuristic expects the fewest tests.
+        fn build_node(&mut self, primitives: &mut [(u32, Aabb)], offset: usize, depth: usize) {
+            let bounds = primitives
+                .iter()
+                .fold(Aabb::new_empty(), |bounds, (_, b)| bounds.union(b));
+            let node = self.nodes.len();
+            self.nodes.push(Node {
+                bounds,
+                offset: offset as u32,
+                count: primitives.len() as u16,
+                axis: 0,
+            });
+
+            let centroids = primitives.iter().fold(Aabb::new_empty(), |bounds, (_, b)| {
+                bounds.grow(b.centroid())
+            });
+            let axis = centroids.longest_axis();
+            let (min, extent) = (centroids.min().axis(axis), centroids.diagonal().axis(axis));
+
+            // Triangles all centered in the same point can't be told apart,
+            // unless there are more than a leaf can count
+            let fits = primitives.len() <= u16::MAX as usize;
+            if primitives.len() <= MAX_LEAF_SIZE || (extent <= 0.0 && fits) {
+                return;
+            }
+
+            let bucket = |b: &Aabb| {
+                let relative = (b.centroid().axis(axis) - min) / extent;
+                ((relative * BUCKETS as f64) as usize).min(BUCKETS - 1)
+            };
+
+            let mid = if extent > 0.0 && depth < MAX_SAH_DEPTH {
+                let mut counts = [0usize; BUCKETS];
+                let mut boxes = [Aabb::new_empty(); BUCKETS];
+                for (_, b) in primitives.iter() {
+                    let i = bucket(b);
+                    counts[i] += 1;
+                    boxes[i] = boxes[i].union(b);
+                }
+
+                // The cost of splitting after every bucket, sweeping from both sides
+                let mut costs = [0.0; BUCKETS - 1];
+                let (mut below, mut count) = (Aabb::new_empty(), 0);
+                for i in 0..BUCKETS - 1 {
+                    below = below.union(&boxes[i]);
+                    count += counts[i];
+                    costs[i] = count as f64 * below.surface_area();
+                }
+                let (mut above, mut count) = (Aabb::new_empty(), 0);
+                for i in (1..BUCKETS).rev() {
+                    above = above.union(&boxes[i]);
+                    count += counts[i];
+                    costs[i - 1] += count as f64 * above.surface_area();
+                }
+
+                let split = (0..BUCKETS - 1)
+                    .min_by(|&a, &b| costs[a].total_cmp(&costs[b]))
+                    .unwrap_or(0);
+                let mut mid = 0;
+                for i in 0..primitives.len() {
+                    if bucket(&primitives[i].1) <= split {
+                        primitives.swap(i, mid);
+                        mid += 1;
+                    }
+                }
+                mid
+            } else {
+                0
+            };
+
+            // Splitting at the median when the buckets can't tell the triangles apart or
+            // the hierarchy gets too deep
+            let mid = if mid == 0 || mid == primitives.len() {
+                let mid = primitives.len() / 2;
+                primitives.select_nth_unstable_by(mid, |a, b| {
+                    a.1.centroid()
+                        .axis(axis)
+                        .total_cmp(&b.1.centroid().axis(axis))
+                });
+                mid
+            } else {
+                mid
+            };
+
+            let (first, second) = primitives.split_at_mut(mid);
+            self.build_node(first, offset, depth + 1);
+            self.nodes[node].offset = self.nodes.len() as u32;
+            self.nodes[node].count = 0;
+            self.nodes[node].axis = axis as u8;
+            self.build_node(second, offset + mid, depth + 1);
+        }
+    }
+
+    impl TriangleMesh {
+        /// Finds the closest triangle a ray hits within `ray_t` by traversing the hierarchy.
+        ///
+        /// # Returns
+        ///
+        /// Returns the distance, index and barycentric coordinates of the closest hit if any,
+        /// along with how many boxes and triangles got tested when `COUNT` is set, 0 otherwise.
+        fn closest<const COUNT: bool>(
+            &self,
+            r: &Ray,
+            ray_t: Interval,
+        ) -> (Option<(f64, usize, [f64; 3])>, usize) {
+            if self.nodes.is_empty() {
+                return (None, 0);
+            }
+
+            let d = r.direction();
+            let inv_direction = Vector3D::with_values(1.0 / d.x(), 1.0 / d.y(), 1.0 / d.z());
+            let mut closest: Option<(f64, usize, [f64; 3])> = None;
+            let mut closest_so_far = ray_t.max;
+
+            let mut stack = [0u32; STACK_SIZE];
+            let mut size = 1;
+            let mut tests = 0;
+            while size > 0 {
+                size -= 1;
+                let index = stack[size] as usize;
+                let node = &self.nodes[index];
+                if COUNT {
+                    tests += 1;
+                }
+                if !node
+                    .bounds
+                    .hit(r, inv_direction, Interval::new(ray_t.min, closest_so_far))
+                {
+                    continue;
+                }
+
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    if COUNT {
+                        tests += node.count as usize;
+                    }
+                    for &triangle in &self.order[start..start + node.count as usize] {
+                        let triangle = triangle as usize;
+                        let vertices = self.vertices(triangle);
+                        let interval = Interval::new(ray_t.min, closest_so_far);
+                        if let Some((t, b)) = intersect(r, &vertices, interval) {
+                            closest_so_far = t;
+                            closest = Some((t, triangle, b));
+                        }
+                    }
+                } else {
+                    // Visit the child closer to the ray first, hoping to skip the other
+                    let (first, second) = (index as u32 + 1, node.offset);
+                    let (near, far) = if d.axis(node.axis as usize) < 0.0 {
+                        (second, first)
+                    } else {
+                        (first, second)
+                    };
+                    stack[size] = far;
+                    stack[size + 1] = near;
+                    size += 2;
+                }
+            }
+
+            (closest, tests)
+        }
+    }
+
+    impl Hittable for TriangleMesh {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            match self.closest::<false>(r, ray_t).0 {
+                Some((t, triangle, b)) => {
+                    self.surface(r, t, triangle, b, rec);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// Counts the boxes of the hierarchy and the triangles a ray gets tested against.
+        fn intersection_tests(&self, r: &Ray, ray_t: Interval) -> usize {
+            self.closest::<true>(r, ray_t).1
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            Some(self.bounding_box())
+        }
+
+        fn area(&self) -> f64 {
+            self.area
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            if self.area <= 0.0 {
+                return None;
+            }
+
+            let (index, _) = self.areas.sample_discrete(random_f64());
+            Some(self.sample_triangle(index))
+        }
+    }
+
+    impl fmt::Debug for TriangleMesh {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("TriangleMesh")
+                .field("vertices", &self.positions.len())
+                .field("triangles", &self.indices.len())
+                .field("nodes", &self.nodes.len())
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    /// Represents a single triangle of a mesh, referring to the mesh's vertices.
+    ///
+    /// Lets triangles be handled on their own, like sampling emitting ones,
+    /// without copying their vertices.
+    #[derive(Clone)]
+    pub struct MeshTriangle {
+        mesh: Rc<TriangleMesh>,
+        index: usize,
+    }
+
+    impl MeshTriangle {
+        /// Gets the mesh the triangle is part of.
+        pub fn mesh(&self) -> &Rc<TriangleMesh> {
+            &self.mesh
+        }
+
+        /// Gets the index of the triangle within its mesh.
+        pub fn index(&self) -> usize {
+            self.index
+        }
+    }
+
+    impl Hittable for MeshTriangle {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            match intersect(r, &self.mesh.vertices(self.index), ray_t) {
+                Some((t, b)) => {
+                    self.mesh.surface(r, t, self.index, b, rec);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            let [a, b, c] = self.mesh.vertices(self.index);
+            Some(Aabb::new(a, b).grow(c))
+        }
+
+        fn area(&self) -> f64 {
+            let [a, b, c] = self.mesh.vertices(self.index);
+            0.5 * (b - a).cross(c - a).length()
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            Some(self.mesh.sample_triangle(self.index))
+        }
+    }
+
+    impl fmt::Debug for MeshTriangle {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("MeshTriangle")
+                .field("mesh", &Rc::as_ptr(&self.mesh))
+                .field("index", &self.index)
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::mesh::*;
+        use crate::hittable::triangle::Triangle;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::sampler::random_range;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The unit cube from (0,0,0) to (1,1,1), two triangles per side.
+        fn cube() -> TriangleMesh {
+            let positions = (0..8)
+                .map(|i| {
+                    Point3D::with_values(
+                        f64::from(i & 1),
+                        f64::from((i >> 1) & 1),
+                        f64::from((i >> 2) & 1),
+                    )
+                })
+                .collect();
+            let indices = vec![
+                // z = 0
+                [0, 2, 1],
+                [1, 2, 3],
+                // z = 1
+                [4, 5, 6],
+                [5, 7, 6],
+                // y = 0
+                [0, 1, 4],
+                [1, 5, 4],
+                // y = 1
+                [2, 6, 3],
+                [3, 6, 7],
+                // x = 0
+                [0, 4, 2],
+                [2, 4, 6],
+                // x = 1
+                [1, 3, 5],
+                [3, 7, 5],
+            ];
+
+            TriangleMesh::new(positions, indices)
+        }
+
+        #[test]
+        fn mesh_new() {
+            let mesh = cube();
+
+            assert_eq!(mesh.len(), 12);
+            assert_eq!(mesh.positions().len(), 8, "Vertices not shared");
+            assert!(mesh.normals().is_empty() && mesh.uvs().is_empty());
+            assert!(mesh.colors().is_empty());
+            assert_eq!(mesh.area(), 6.0);
+            assert_eq!(
+                mesh.bounding_box().max(),
+                Point3D::with_values(1.0, 1.0, 1.0)
+            );
+            assert!(TriangleMesh::new(Vec::new(), Vec::new()).is_empty());
+        }
+
+        #[test]
+        #[should_panic]
+        fn mesh_invalid_index() {
+            TriangleMesh::new(vec![Point3D::new(); 3], vec![[0, 1, 3]]);
+        }
+
+        #[test]
+        fn mesh_hit() {
+            //           +-----+ (1,1)
+            //   o------>X     |        hitting the cube from the outside
+            // (-1,0.5)  |     |---->X  and from within
+            //           +-----+   (1,0.5)
+            //         (0,0)
+            let mesh = cube();
+            let outside = Ray::create(
+                Point3D::with_values(-1.0, 0.5, 0.25),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let inside = Ray::create(
+                Point3D::with_values(0.5, 0.5, 0.25),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                mesh.hit(&outside, Interval::new(0.0, 10.0), rec),
+                "Cube not hit"
+            );
+            assert_eq!(rec.t(), 1.0);
+            assert_eq!(rec.p(), Point3D::with_values(0.0, 0.5, 0.25));
+            assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert!(rec.front_face(), "Cube not hit from the outside");
+
+            assert!(mesh.hit(&inside, Interval::new(0.0, 10.0), rec));
+            assert_eq!(rec.t(), 0.5);
+            assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert!(!rec.front_face(), "Cube not hit from within");
+
+            assert!(!mesh.hit(&outside, Interval::new(0.0, 0.5), rec));
+            assert!(!mesh.hit(&outside, Interval::new(2.5, 10.0), rec));
+        }
+
+        #[test]
+        fn mesh_hit_deep() {
+            // Triangles doubling in size, splitting off only a few at every level,
+            // still within the traversal stack
+            let positions: Vec<Point3D> = (0..400)
+                .flat_map(|i| {
+                    let s = 2f64.powi(i);
+                    [
+                        Point3D::with_values(s, s, 0.0),
+                        Point3D::with_values(2.0 * s, s, 0.0),
+                        Point3D::with_values(s, 2.0 * s, 0.0),
+                    ]
+                })
+                .collect();
+            let indices: Vec<[u32; 3]> = (0..400).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+            let mesh = TriangleMesh::new(positions, indices);
+
+            let ray = Ray::create(
+                Point3D::with_values(1.1, 1.1, 1.0),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+            );
+            let mut rec = HitRecord::default();
+            assert!(mesh.hit(&ray, Interval::new(0.0, 10.0), &mut rec));
+            assert_eq!(rec.t(), 1.0);
+        }
+
+        #[test]
+        fn mesh_hit_like_triangles() {
+            // A soup of random triangles is hit exactly like a list of them
+            let positions: Vec<Point3D> = (0..600)
+                .map(|_| Vector3D::random_within(-5.0, 5.0))
+                .collect();
+            let indices: Vec<[u32; 3]> = (0..200).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+            let list: HittableList = indices
+                .iter()
+                .map(|&[a, b, c]| {
+                    Rc::new(Triangle::new(
+                        positions[a as usize],
+                        positions[b as usize],
+                        positions[c as usize],
+                    )) as Rc<dyn Hittable>
+                })
+                .collect();
+            let mesh = TriangleMesh::new(positions, indices);
+
+            for _ in 0..1000 {
+                let origin = Vector3D::random_within(-8.0, 8.0);
+                let target = Vector3D::random_within(-3.0, 3.0);
+                let ray = Ray::create(origin, target - origin);
+                let ray_t = Interval::new(0.001, random_range(0.5, 2.0));
+
+                let mut expected = HitRecord::default();
+                let mut actual = HitRecord::default();
+                assert_eq!(
+                    list.hit(&ray, ray_t, &mut expected),
+                    mesh.hit(&ray, ray_t, &mut actual),
+                    "Mesh and triangles disagree on hitting"
+                );
+                assert_eq!(expected.t(), actual.t(), "Mesh hit another triangle");
+                assert_eq!(expected.normal(), actual.normal());
+            }
+        }
+
+        #[test]
+        fn mesh_normals_uvs() {
+            // One quad, its vertex normals bending outwards towards its sides
+            let mesh = TriangleMesh::new(
+                vec![
+                    Point3D::with_values(0.0, 0.0, 0.0),
+                    Point3D::with_values(1.0, 0.0, 0.0),
+                    Point3D::with_values(0.0, 1.0, 0.0),
+                    Point3D::with_values(1.0, 1.0, 0.0),
+                ],
+                vec![[0, 1, 2], [1, 3, 2]],
+            )
+            .with_normals(vec![
+                Vector3D::with_values(-1.0, 0.0, 1.0),
+                Vector3D::with_values(1.0, 0.0, 1.0),
+                Vector3D::with_values(-1.0, 0.0, 1.0),
+                Vector3D::with_values(1.0, 0.0, 1.0),
+            ])
+            .with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)])
+            .with_colors(vec![
+                Color::new(),
+                Color::with_values(1.0, 0.0, 0.0),
+                Color::new(),
+                Color::with_values(1.0, 0.0, 0.0),
+            ]);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            // Both triangles interpolate the very same buffers
+            for (x, y) in [(0.5, 0.25), (0.5, 0.75), (0.25, 0.5)] {
+                let ray = Ray::create(
+                    Point3D::with_values(x, y, 1.0),
+                    Vector3D::with_values(0.0, 0.0, -1.0),
+                );
+                assert!(mesh.hit(&ray, Interval::new(0.0, 10.0), rec));
+                assert!((rec.u() - x).abs() < 1e-12 && (rec.v() - y).abs() < 1e-12);
+                assert!(rec.front_face());
+                assert!(
+                    (rec.color().x() - x).abs() < 1e-12,
+                    "Color not interpolated"
+                );
+                let expected = Vector3D::with_values(2.0 * x - 1.0, 0.0, 1.0).unit_vector();
+                assert!(
+                    (rec.normal() - expected).near_zero(),
+                    "Normal {} not interpolated at ({}, {})",
+                    rec.normal(),
+                    x,
+                    y
+                );
+            }
+        }
+
+        #[test]
+        fn mesh_triangles() {
+            let mesh = Rc::new(cube());
+            let triangles = mesh.triangles();
+
+            assert_eq!(triangles.len(), 12);
+            assert!(Rc::ptr_eq(triangles[3].mesh(), &mesh), "Vertices copied");
+            assert_eq!(triangles[3].index(), 3);
+            assert_eq!(triangles.iter().map(|t| t.area()).sum::<f64>(), mesh.area());
+
+            // The triangle at z = 0 facing -z
+            let ray = Ray::create(
+                Point3D::with_values(0.75, 0.75, -1.0),
+                Vector3D::with_values(0.0, 0.0, 1.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            assert!(!triangles[0].hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert!(triangles[1].hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert_eq!(rec.normal(), Vector3D::with_values(0.0, 0.0, -1.0));
+        }
+
+        #[test]
+        fn mesh_sample_surface() {
+            let mesh = cube();
+
+            for _ in 0..100 {
+                let rec = mesh.sample_surface().expect("Mesh not sampled");
+                let p = rec.p();
+                let on_face = [p.x(), p.y(), p.z()]
+                    .iter()
+                    .any(|&c| c.abs() < 1e-12 || (c - 1.0).abs() < 1e-12);
+                assert!(on_face, "Sample {} not on the surface", p);
+
+                // The outward normal points away from the center
+                let center = Point3D::with_values(0.5, 0.5, 0.5);
+                assert!(
+                    rec.normal().dot(p - center) > 0.0,
+                    "Normal pointing inwards"
+                );
+                assert!(rec.front_face(), "Sample not on the front face");
+            }
+            assert_eq!(
+                TriangleMesh::new(Vec::new(), Vec::new()).sample_surface(),
+                None
+            );
+        }
+    }
+}
+
+// QUAD
+pub mod quad {
+    //! Module for handling quads, parallelograms, in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a parallelogram in 3D space, spanned by two edges from a corner.
+    ///
+    /// The side the edges `u` and `v` are seen counterclockwise from is the outside.
+    pub struct Quad {
+        q: Point3D,
+        u: Vector3D,
+        v: Vector3D,
+        normal: Vector3D,
+        w: Vector3D,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Quad {
+        /// Creates a new quad with the specified corner and edges.
+        ///
+        /// # Arguments
+        ///
+        /// * `q` - The corner of the quad, at surface coordinates (0, 0).
+        /// * `u` - The edge from `q` to the corner at surface coordinates (1, 0).
+        /// * `v` - The edge from `q` to the corner at surface coordinates (0, 1).
+        pub fn new(q: Point3D, u: Vector3D, v: Vector3D) -> Self {
+            let n = u.cross(v);
+            Quad {
+                q,
+                u,
+                v,
+                normal: n.unit_vector(),
+                w: n / n.dot(n),
+                mat: None,
+            }
+        }
+
+        /// Creates a new quad with the specified corner, edges and material.
+        ///
+        /// # Arguments
+        ///
+        /// * `q`   - The corner of the quad, at surface coordinates (0, 0).
+        /// * `u`   - The edge from `q` to the corner at surface coordinates (1, 0).
+        /// * `v`   - The edge from `q` to the corner at surface coordinates (0, 1).
+        /// * `mat` - The material of the quad's surface.
+        pub fn with_material(q: Point3D, u: Vector3D, v: Vector3D, mat: Rc<dyn Material>) -> Self {
+            Quad {
+                mat: Some(mat),
+                ..Self::new(q, u, v)
+            }
+        }
+
+        /// Gets the corner of the quad the edges start at.
+        pub fn corner(&self) -> Point3D {
+            self.q
+        }
+
+        /// Gets the edge along the first surface coordinate.
+        pub fn u(&self) -> Vector3D {
+            self.u
+        }
+
+        /// Gets the edge along the second surface coordinate.
+        pub fn v(&self) -> Vector3D {
+            self.v
+        }
+
+        /// Gets the unit normal of the outside of the quad.
+        pub fn normal(&self) -> Vector3D {
+            self.normal
+        }
+
+        /// Gets the material of the quad's surface, if it has one.
+        pub fn material(&self) -> Option<&Rc<dyn Material>> {
+            self.mat.as_ref()
+        }
+    }
+
+    impl Hittable for Quad {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            // Rays parallel to the plane of the quad miss it
+            let denominator = self.normal.dot(r.direction());
+            if denominator.abs() < 1e-12 {
+                return false;
+            }
+
+            let t = self.normal.dot(self.q - r.origin()) / denominator;
+            if !ray_t.surrounds(t) {
+                return false;
+            }
+
+            // Express the point in the plane in the coordinates of the edges
+            let p = r.at(t);
+            let planar = p - self.q;
+            let alpha = self.w.dot(planar.cross(self.v));
+            let beta = self.w.dot(self.u.cross(planar));
+            if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+                return false;
+            }
+
+            rec.t = t;
+            rec.p = p;
+            rec.set_face_normal(*r, self.normal);
+            (rec.u, rec.v) = (alpha, beta);
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+
+            true
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            let opposite = self.q + self.u + self.v;
+            Some(
+                Aabb::new(self.q, opposite)
+                    .grow(self.q + self.u)
+                    .grow(self.q + self.v),
+            )
+        }
+
+        fn area(&self) -> f64 {
+            self.u.cross(self.v).length()
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            let (u, v) = (random_f64(), random_f64());
+
+            Some(HitRecord {
+                p: self.q + u * self.u + v * self.v,
+                normal: self.normal,
+                mat: self.mat.clone(),
+                u,
+                v,
+                front_face: true,
+                ..HitRecord::default()
+            })
+        }
+    }
+
+    impl PartialEq for Quad {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.q == other.q
+                && self.u == other.u
+                && self.v == other.v
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Quad {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Quad")
+                .field("q", &self.q)
+                .field("u", &self.u)
+                .field("v", &self.v)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::quad::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The quad from (2,-1,-1) spanning 2 along +z and 4 along +y, facing -x.
+        fn quad() -> Quad {
+            Quad::new(
+                Point3D::with_values(2.0, -1.0, -1.0),
+                Vector3D::with_values(0.0, 0.0, 2.0),
+                Vector3D::with_values(0.0, 4.0, 0.0),
+            )
+        }
+
+        #[test]
+        fn quad_new() {
+            let quad = quad();
+
+            assert_eq!(quad.corner(), Point3D::with_values(2.0, -1.0, -1.0));
+            assert_eq!(quad.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert_eq!(quad.area(), 8.0);
+            assert!(quad.material().is_none());
+        }
+
+        #[test]
+        fn quad_hit() {
+            //            (2,3)
+            //      t=2.0 --+
+            //              |       seen from above, the
+            //   o--------->X       quad is the line
+            // (0,0)  HIT   |       from (2,-1) to (2,3)
+            //      (2,0)   +
+            //            (2,-1)
+            let ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                quad().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Quad not hit"
+            );
+            assert_eq!(
+                *rec,
+                HitRecord::new(
+                    Point3D::with_values(2.0, 0.0, 0.0),
+                    Vector3D::with_values(-1.0, 0.0, 0.0),
+                    2.0,
+                    true
+                )
+                .with_uv(0.5, 0.25),
+                "Hit Record not as expected"
+            );
+
+            // From behind, past its edge, parallel to it or beyond the interval
+            let back = Ray::create(
+                Point3D::with_values(4.0, 2.0, 0.5),
+                Vector3D::with_values(-1.0, 0.0, 0.0),
+            );
+            assert!(quad().hit(&back, Interval::new(0.0, 10.0), rec));
+            assert!(!rec.front_face(), "Back face hit as front face");
+            assert_eq!((rec.u(), rec.v()), (0.75, 0.75));
+
+            let past = Ray::create(
+                Point3D::with_values(0.0, 3.5, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let parallel = Ray::create(
+                Point3D::with_values(2.0, 0.0, -5.0),
+                Vector3D::with_values(0.0, 0.0, 1.0),
+            );
+            assert!(!quad().hit(&past, Interval::new(0.0, 10.0), rec));
+            assert!(!quad().hit(&parallel, Interval::new(0.0, 10.0), rec));
+            assert!(!quad().hit(&ray, Interval::new(0.0, 1.5), rec));
+        }
+
+        #[test]
+        fn quad_sample_surface() {
+            let quad = quad();
+
+            for _ in 0..10 {
+                let rec = quad.sample_surface().expect("Quad not sampled");
+                let p = rec.p();
+                assert!(
+                    p.x() == 2.0 && (-1.0..=3.0).contains(&p.y()) && (-1.0..=1.0).contains(&p.z()),
+                    "Sample {} not on the quad",
+                    p
+                );
+                assert_eq!(rec.normal(), quad.normal());
+                assert_eq!(
+                    (rec.u(), rec.v()),
+                    ((p.z() + 1.0) / 2.0, (p.y() + 1.0) / 4.0)
+                );
+            }
+        }
+
+        #[test]
+        fn quad_pdf() {
+            //      +   area 8, seen 2 away head on,
+            //   o->|   or √8 away at 45° from (0,-1)
+            //    \ |   foreshortened by cos 45°
+            //     o+
+            let quad = quad();
+            let x = Vector3D::with_values(1.0, 0.0, 0.0);
+
+            assert!((quad.pdf(Point3D::new(), x) - 4.0 / 8.0).abs() < 1e-12);
+            let diagonal = quad.pdf(
+                Point3D::with_values(0.0, 0.0, -1.0),
+                Vector3D::with_values(1.0, 0.0, 1.0),
+            );
+            assert!((diagonal - 8.0 / (2f64.sqrt().recip() * 8.0)).abs() < 1e-12);
+            assert_eq!(quad.pdf(Point3D::new(), -x), 0.0, "Missed quad has density");
+        }
+    }
+}
+
+// DISK
+pub mod disk {
+    //! Module for handling disks in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a flat disk in 3D space, facing along its normal.
+    pub struct Disk {
+        center: Point3D,
+        normal: Vector3D,
+        radius: f64,
+        tangent: Vector3D,
+        bitangent: Vector3D,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Disk {
+        /// Creates a new disk with the specified center, normal and radius.
+        ///
+        /// # Arguments
+        ///
+        /// * `center` - The center of the disk.
+        /// * `normal` - The direction the outside of the disk faces.
+        /// * `radius` - The radius of the disk.
+        pub fn new(center: Point3D, normal: Vector3D, radius: f64) -> Self {
+            let normal = normal.unit_vector();
+            let (tangent, bitangent) = normal.orthonormal_basis();
+            Disk {
+                center,
+                normal,
+                radius,
+                tangent,
+                bitangent,
+                mat: None,
+            }
+        }
+
+        /// Creates a new disk with the specified center, normal, radius and material.
+        ///
+        /// # Arguments
+        ///
+        /// * `center` - The center of the disk.
+        /// * `normal` - The direction the outside of the disk faces.
+        /// * `radius` - The radius of the disk.
+        /// * `mat`    - The material of the disk's surface.
+        pub fn with_material(
+            center: Point3D,
+            normal: Vector3D,
+            radius: f64,
+            mat: Rc<dyn Material>,
+        ) -> Self {
+            Disk {
+                mat: Some(mat),
+                ..Self::new(center, normal, radius)
+            }
+        }
+
+        /// Gets the center of the disk.
+        pub fn center(&self) -> Point3D {
+            self.center
+        }
+
+        /// Gets the unit normal of the outside of the disk.
+        pub fn normal(&self) -> Vector3D {
+            self.normal
+        }
+
+        /// Gets the radius of the disk.
+        pub fn radius(&self) -> f64 {
+            self.radius
+        }
+
+        /// Computes the surface coordinates of a point on the disk.
+        ///
+        /// `u` is the angle around the normal, `v` the distance from the center
+        /// relative to the radius, both in [0, 1].
+        pub fn uv(&self, p: Point3D) -> (f64, f64) {
+            let d = p - self.center;
+            let phi = d.dot(self.bitangent).atan2(d.dot(self.tangent));
+
+            (
+                phi.rem_euclid(2.0 * PI) / (2.0 * PI),
+                d.length() / self.radius,
+            )
+        }
+    }
+
+    impl Hittable for Disk {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let denominator = self.normal.dot(r.direction());
+            if denominator.abs() < 1e-12 {
+                return false;
+            }
+
+            let t = self.normal.dot(self.center - r.origin()) / denominator;
+            if !ray_t.surrounds(t) {
+                return false;
+            }
+
+            let p = r.at(t);
+            if (p - self.center).length_squared() > self.radius * self.radius {
+                return false;
+            }
+
+            rec.t = t;
+            rec.p = p;
+            rec.set_face_normal(*r, self.normal);
+            (rec.u, rec.v) = self.uv(p);
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+
+            true
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            // Along every axis the rim reaches as far as the radius times the sine to the normal
+            let reach = |axis: f64| self.radius * (1.0 - axis * axis).max(0.0).sqrt();
+            let n = self.normal;
+            let radius = Vector3D::with_values(reach(n.x()), reach(n.y()), reach(n.z()));
+            Some(Aabb::new(self.center - radius, self.center + radius))
+        }
+
+        fn area(&self) -> f64 {
+            PI * self.radius * self.radius
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            // The square root spreads the samples evenly instead of crowding the center
+            let radius = self.radius * random_f64().sqrt();
+            let phi = 2.0 * PI * random_f64();
+            let p = self.center + radius * (phi.cos() * self.tangent + phi.sin() * self.bitangent);
+            let (u, v) = self.uv(p);
+
+            Some(HitRecord {
+                p,
+                normal: self.normal,
+                mat: self.mat.clone(),
+                u,
+                v,
+                front_face: true,
+                ..HitRecord::default()
+            })
+        }
+    }
+
+    impl PartialEq for Disk {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.center == other.center
+                && self.normal == other.normal
+                && self.radius == other.radius
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Disk {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Disk")
+                .field("center", &self.center)
+                .field("normal", &self.normal)
+                .field("radius", &self.radius)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::disk::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The disk of radius 2 around (2,0,0), facing -x.
+        fn disk() -> Disk {
+            Disk::new(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                Vector3D::with_values(-3.0, 0.0, 0.0),
+                2.0,
+            )
+        }
+
+        #[test]
+        fn disk_new() {
+            let disk = disk();
+
+            assert_eq!(disk.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert_eq!(disk.area(), 4.0 * PI);
+            assert_eq!(disk.uv(disk.center()).1, 0.0);
+        }
+
+        #[test]
+        fn disk_hit() {
+            //            (2,2)
+            //              +
+            //      t=2.0 --|       seen from above, the
+            //   o--------->X c     disk is the line
+            // (0,1)  HIT   |       from (2,-2) to (2,2)
+            //      (2,1)   +
+            //            (2,-2)
+            let ray = Ray::create(
+                Point3D::with_values(0.0, 1.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                disk().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Disk not hit"
+            );
+            assert_eq!(rec.t(), 2.0);
+            assert_eq!(rec.p(), Point3D::with_values(2.0, 1.0, 0.0));
+            assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert!(rec.front_face());
+            assert_eq!(rec.v(), 0.5);
+
+            // Within the square around it, but beyond its rim
+            let corner = Ray::create(
+                Point3D::with_values(0.0, 1.5, 1.5),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(!disk().hit(&corner, Interval::new(0.0, 10.0), rec));
+        }
+
+        #[test]
+        fn disk_uv() {
+            let disk = disk();
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            // Going around the rim once, u runs steadily through [0, 1) one way or the other
+            let mut us = Vec::new();
+            for i in 0..8 {
+                let phi = f64::from(i) * PI / 4.0;
+                let origin = Point3D::with_values(0.0, 2.0 * phi.cos(), 2.0 * phi.sin()) * 0.999;
+                let ray = Ray::create(origin, Vector3D::with_values(1.0, 0.0, 0.0));
+                assert!(disk.hit(&ray, Interval::new(0.0, 10.0), rec));
+                assert!((rec.v() - 0.999).abs() < 1e-12);
+                us.push(rec.u());
+            }
+            let steps: Vec<f64> = (0..8)
+                .map(|i| (us[(i + 1) % 8] - us[i]).rem_euclid(1.0))
+                .collect();
+            assert!(
+                steps.iter().all(|s| (s - steps[0]).abs() < 1e-9
+                    && (s - 0.125).abs().min((s - 0.875).abs()) < 1e-9),
+                "Angles {:?} not evenly spaced",
+                us
+            );
+        }
+
+        #[test]
+        fn disk_sample_surface() {
+            let disk = disk();
+
+            let mut inner = 0;
+            for _ in 0..1000 {
+                let rec = disk.sample_surface().expect("Disk not sampled");
+                let d = rec.p() - disk.center();
+                assert!(
+                    d.x().abs() < 1e-12 && d.length() <= 2.0,
+                    "Sample {} not on the disk",
+                    rec.p()
+                );
+                assert_eq!(rec.normal(), disk.normal());
+                if d.length() < 2f64.sqrt() {
+                    inner += 1;
+                }
+            }
+            assert!(
+                (400..600).contains(&inner),
+                "{} of 1000 samples in the inner half",
+                inner
+            );
+        }
+    }
+}
+
+// PLANE
+pub mod plane {
+    //! Module for handling infinite planes in the context of a raytracer.
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents an infinite plane in 3D space, facing along its normal.
+    ///
+    /// Planes have no finite area, so they can't be sampled as light sources.
+    pub struct Plane {
+        point: Point3D,
+        normal: Vector3D,
+        tangent: Vector3D,
+        bitangent: Vector3D,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Plane {
+        /// Creates a new plane through the specified point with the specified normal.
+        ///
+        /// # Arguments
+        ///
+        /// * `point`  - A point on the plane, at surface coordinates (0, 0).
+        /// * `normal` - The direction the outside of the plane faces.
+        pub fn new(point: Point3D, normal: Vector3D) -> Self {
+            let normal = normal.unit_vector();
+            let (tangent, bitangent) = normal.orthonormal_basis();
+            Plane {
+                point,
+                normal,
+                tangent,
+                bitangent,
+                mat: None,
+            }
+        }
+
+        /// Creates a new plane through the specified point with the specified normal and material.
+        ///
+        /// # Arguments
+        ///
+        /// * `point`  - A point on the plane, at surface coordinates (0, 0).
+        /// * `normal` - The direction the outside of the plane faces.
+        /// * `mat`    - The material of the plane's surface.
+        pub fn with_material(point: Point3D, normal: Vector3D, mat: Rc<dyn Material>) -> Self {
+            Plane {
+                mat: Some(mat),
+                ..Self::new(point, normal)
+            }
+        }
+
+        /// Gets the point on the plane at surface coordinates (0, 0).
+        pub fn point(&self) -> Point3D {
+            self.point
+        }
+
+        /// Gets the unit normal of the outside of the plane.
+        pub fn normal(&self) -> Vector3D {
+            self.normal
+        }
+
+        /// Computes the surface coordinates of a point on the plane.
+        ///
+        /// The coordinates are the distances from `point` along two perpendicular
+        /// directions in the plane, so textures repeat every unit.
+        pub fn uv(&self, p: Point3D) -> (f64, f64) {
+            let d = p - self.point;
+            (d.dot(self.tangent), d.dot(self.bitangent))
+        }
+    }
+
+    impl Hittable for Plane {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let denominator = self.normal.dot(r.direction());
+            if denominator.abs() < 1e-12 {
+                return false;
+            }
+
+            let t = self.normal.dot(self.point - r.origin()) / denominator;
+            if !ray_t.surrounds(t) {
+                return false;
+            }
+
+            rec.t = t;
+            rec.p = r.at(t);
+            rec.set_face_normal(*r, self.normal);
+            (rec.u, rec.v) = self.uv(rec.p);
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+
+            true
+        }
+    }
+
+    impl PartialEq for Plane {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.point == other.point
+                && self.normal == other.normal
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Plane {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Plane")
+                .field("point", &self.point)
+                .field("normal", &self.normal)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::plane::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        #[test]
+        fn plane_hit() {
+            //   o     o   rays from above
+            //    \    |   hit the ground
+            //     \   |   at y = -1,
+            // -----X--X-----  however far away
+            let ground = Plane::new(
+                Point3D::with_values(0.0, -1.0, 0.0),
+                Vector3D::with_values(0.0, 2.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            let down = Ray::create(Point3D::new(), Vector3D::with_values(0.0, -1.0, 0.0));
+            assert!(
+                ground.hit(&down, Interval::new(0.0, 10.0), rec),
+                "Plane not hit"
+            );
+            assert_eq!(rec.t(), 1.0);
+            assert_eq!(rec.normal(), Vector3D::with_values(0.0, 1.0, 0.0));
+            assert!(rec.front_face());
+            assert_eq!((rec.u(), rec.v()), (0.0, 0.0));
+
+            let far = Ray::create(Point3D::new(), Vector3D::with_values(1e6, -1.0, 0.0));
+            assert!(ground.hit(&far, Interval::new(0.0, 10.0), rec));
+            assert!((rec.p() - Point3D::with_values(1e6, -1.0, 0.0)).length() < 1e-6);
+            let (u, v) = ground.uv(rec.p());
+            assert!(
+                (u * u + v * v - 1e12).abs() < 1.0,
+                "Coordinates not distances"
+            );
+
+            let up = Ray::create(Point3D::new(), Vector3D::with_values(0.0, 1.0, 0.0));
+            let level = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            assert!(!ground.hit(&up, Interval::new(0.0, 10.0), rec));
+            assert!(!ground.hit(&level, Interval::new(0.0, 10.0), rec));
+
+            let below = Ray::create(Point3D::with_values(0.0, -2.0, 0.0), -down.direction());
+            assert!(ground.hit(&below, Interval::new(0.0, 10.0), rec));
+            assert!(!rec.front_face(), "Underside hit as front face");
+            assert_eq!(ground.area(), 0.0, "Infinite plane can be sampled");
+            assert!(ground.sample_surface().is_none());
+        }
+    }
+}
+
+// BOX
+pub mod cuboid {
+    //! Module for handling boxes, axis-aligned or oriented, in the context of a raytracer.
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::quad::Quad;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a rectangular box in 3D space, intersected directly as slabs.
+    ///
+    /// Every face has surface coordinates from (0, 0) to (1, 1), seen
+    /// the right way round from outside of the box.
+    pub struct Box {
+        center: Point3D,
+        half_size: Vector3D,
+        axes: [Vector3D; 3],
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Box {
+        /// Creates a new box aligned to the axes, spanned by two opposite corners.
+        ///
+        /// # Arguments
+        ///
+        /// * `a` - One corner of the box.
+        /// * `b` - The opposite corner of the box.
+        pub fn new(a: Point3D, b: Point3D) -> Self {
+            let d = b - a;
+            Box {
+                center: 0.5 * (a + b),
+                half_size: 0.5 * Vector3D::with_values(d.x().abs(), d.y().abs(), d.z().abs()),
+                axes: [
+                    Vector3D::with_values(1.0, 0.0, 0.0),
+                    Vector3D::with_values(0.0, 1.0, 0.0),
+                    Vector3D::with_values(0.0, 0.0, 1.0),
+                ],
+                mat: None,
+            }
+        }
+
+        /// Creates a new box aligned to the axes, spanned by two opposite corners, with a material.
+        ///
+        /// # Arguments
+        ///
+        /// * `a`   - One corner of the box.
+        /// * `b`   - The opposite corner of the box.
+        /// * `mat` - The material of the box's surface.
+        pub fn with_material(a: Point3D, b: Point3D, mat: Rc<dyn Material>) -> Self {
+            Box {
+                mat: Some(mat),
+                ..Self::new(a, b)
+            }
+        }
+
+        /// Returns the box rotated around its center.
+        ///
+        /// # Arguments
+        ///
+        /// * `axis`    - The axis to rotate around, counterclockwise when it points at the viewer.
+        /// * `degrees` - The angle to rotate by.
+        pub fn with_rotation(mut self, axis: Vector3D, degrees: f64) -> Self {
+            let k = axis.unit_vector();
+            let (sin, cos) = degrees.to_radians().sin_cos();
+
+            // Rodrigues' rotation formula
+            self.axes = self
+                .axes
+                .map(|v| (cos * v + sin * k.cross(v) + (1.0 - cos) * k.dot(v) * k).unit_vector());
+            self
+        }
+
+        /// Gets the center of the box.
+        pub fn center(&self) -> Point3D {
+            self.center
+        }
+
+        /// Gets the lengths of the edges along the axes of the box.
+        pub fn size(&self) -> Vector3D {
+            2.0 * self.half_size
+        }
+
+        /// Gets the unit axes of the box, the coordinate axes unless it's rotated.
+        pub fn axes(&self) -> [Vector3D; 3] {
+            self.axes
+        }
+
+        /// Computes the smallest box aligned to the coordinate axes containing the box.
+        pub fn bounding_box(&self) -> Aabb {
+            let extent = |axis: usize| {
+                (0..3)
+                    .map(|i| self.half_size.axis(i) * self.axes[i].axis(axis).abs())
+                    .sum::<f64>()
+            };
+            let extent = Vector3D::with_values(extent(0), extent(1), extent(2));
+
+            Aabb::new(self.center - extent, self.center + extent)
+        }
+
+        /// Builds the six faces of the box as quads, sharing its material.
+        ///
+        /// The quads face outwards and have the same surface coordinates as the box.
+        pub fn sides(&self) -> HittableList {
+            (0..6)
+                .map(|face| -> Rc<dyn Hittable> {
+                    let (q, u, v) = self.face(face);
+                    match &self.mat {
+                        Some(mat) => Rc::new(Quad::with_material(q, u, v, Rc::clone(mat))),
+                        None => Rc::new(Quad::new(q, u, v)),
+                    }
+                })
+                .collect()
+        }
+
+        /// Spans a face of the box from its corner at (0, 0) along its surface coordinates.
+        ///
+        /// Faces 0 to 2 face along the axes of the box, faces 3 to 5 against them.
+        fn face(&self, face: usize) -> (Point3D, Vector3D, Vector3D) {
+            let k = face % 3;
+            let (i, j) = ((k + 1) % 3, (k + 2) % 3);
+            let sign = if face < 3 { 1.0 } else { -1.0 };
+            let half = |axis: usize| self.half_size.axis(axis) * self.axes[axis];
+
+            // Turning u around on the back faces keeps them counterclockwise from outside
+            let u = sign * 2.0 * half(i);
+            let v = 2.0 * half(j);
+            (self.center + sign * half(k) - 0.5 * u - 0.5 * v, u, v)
+        }
+
+        /// Fills the hit record for a point on the face facing along `sign` times axis `k`.
+        fn surface(&self, r: &Ray, t: f64, k: usize, sign: f64, rec: &mut HitRecord) {
+            let p = r.at(t);
+            let (q, u, v) = self.face(if sign > 0.0 { k } else { k + 3 });
+
+            rec.t = t;
+            rec.p = p;
+            rec.set_face_normal(*r, sign * self.axes[k]);
+            rec.u = (p - q).dot(u) / u.length_squared();
+            rec.v = (p - q).dot(v) / v.length_squared();
+            rec.mat = self.mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+        }
+    }
+
+    impl Hittable for Box {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            // Intersect the slabs between opposite faces in the frame of the box
+            let offset = r.origin() - self.center;
+            let (mut t_near, mut t_far) = (f64::NEG_INFINITY, f64::INFINITY);
+            let (mut near, mut far) = ((0, 0.0), (0, 0.0));
+            for k in 0..3 {
+                let o = offset.dot(self.axes[k]);
+                let d = r.direction().dot(self.axes[k]);
+                let h = self.half_size.axis(k);
+                if d == 0.0 {
+                    if o.abs() > h {
+                        return false;
+                    }
+                    continue;
+                }
+
+                // The face the ray enters through faces against it
+                let sign = if d < 0.0 { 1.0 } else { -1.0 };
+                let t0 = (sign * h - o) / d;
+                let t1 = (-sign * h - o) / d;
+                if t0 > t_near {
+                    (t_near, near) = (t0, (k, sign));
+                }
+                if t1 < t_far {
+                    (t_far, far) = (t1, (k, -sign));
+                }
+            }
+            if t_near > t_far {
+                return false;
+            }
+
+            // Leave through the far face if the ray starts inside
+            let ((k, sign), t) = if ray_t.surrounds(t_near) {
+                (near, t_near)
+            } else if ray_t.surrounds(t_far) {
+                (far, t_far)
+            } else {
+                return false;
+            };
+            self.surface(r, t, k, sign, rec);
+
+            true
+        }
+
+        fn extent(&self) -> Option<Aabb> {
+            Some(self.bounding_box())
+        }
+
+        fn area(&self) -> f64 {
+            let [x, y, z] = [0, 1, 2].map(|i| self.half_size.axis(i));
+            8.0 * (x * y + y * z + z * x)
+        }
+
+        fn sample_surface(&self) -> Option<HitRecord> {
+            let area = self.area();
+            if area <= 0.0 {
+                return None;
+            }
+
+            // Choose a face by its area, then a point on it
+            let mut choice = random_f64() * area;
+            let mut face = 5;
+            for f in 0..6 {
+                let (_, u, v) = self.face(f);
+                choice -= u.cross(v).length();
+                if choice < 0.0 {
+                    face = f;
+                    break;
+                }
+            }
+            let (q, u, v) = self.face(face);
+            let (s, t) = (random_f64(), random_f64());
+
+            Some(HitRecord {
+                p: q + s * u + t * v,
+                normal: if face < 3 {
+                    self.axes[face]
+                } else {
+                    -self.axes[face - 3]
+                },
+                mat: self.mat.clone(),
+                u: s,
+                v: t,
+                front_face: true,
+                ..HitRecord::default()
+            })
+        }
+    }
+
+    impl PartialEq for Box {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.center == other.center
+                && self.half_size == other.half_size
+                && self.axes == other.axes
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Box {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Box")
+                .field("center", &self.center)
+                .field("half_size", &self.half_size)
+                .field("axes", &self.axes)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::cuboid::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::sampler::random_f64;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The box from (1,-1,-2) to (3,1,2).
+        fn cuboid() -> Box {
+            Box::new(
+                Point3D::with_values(3.0, 1.0, 2.0),
+                Point3D::with_values(1.0, -1.0, -2.0),
+            )
+        }
+
+        #[test]
+        fn box_new() {
+            let cuboid = cuboid();
+
+            assert_eq!(cuboid.center(), Point3D::with_values(2.0, 0.0, 0.0));
+            assert_eq!(cuboid.size(), Vector3D::with_values(2.0, 2.0, 4.0));
+            assert_eq!(cuboid.area(), 2.0 * (4.0 + 8.0 + 8.0));
+            assert_eq!(
+                cuboid.bounding_box(),
+                Aabb::new(
+                    Point3D::with_values(1.0, -1.0, -2.0),
+                    Point3D::with_values(3.0, 1.0, 2.0)
+                )
+            );
+            assert_eq!(cuboid.sides().len(), 6);
+        }
+
+        #[test]
+        fn box_hit() {
+            //              (3,1)
+            //      +---------+
+            //   o->X    c    X-->   entering at t=1 from outside,
+            // (0,0)|  (2,0)  |      leaving at t=3 from inside
+            //      +---------+
+            //   (1,-1)
+            let ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cuboid().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Box not hit"
+            );
+            assert_eq!(
+                *rec,
+                HitRecord::new(
+                    Point3D::with_values(1.0, 0.0, 0.0),
+                    Vector3D::with_values(-1.0, 0.0, 0.0),
+                    1.0,
+                    true
+                )
+                .with_uv(0.5, 0.5),
+                "Hit Record not as expected"
+            );
+
+            assert!(cuboid().hit(&ray, Interval::new(2.0, 10.0), rec));
+            assert_eq!(rec.t(), 3.0);
+            assert_eq!(rec.normal(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert!(!rec.front_face(), "Leaving the box hit the front face");
+
+            let above = Ray::create(
+                Point3D::with_values(0.0, 1.5, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(!cuboid().hit(&above, Interval::new(0.0, 10.0), rec));
+            assert!(!cuboid().hit(&ray, Interval::new(3.5, 10.0), rec));
+        }
+
+        #[test]
+        fn box_rotated() {
+            //        /\          the unit cube around (2,0,0), turned
+            //   o-->X  \  c      45° around y, seen from above, is hit
+            //        \  /        at its edge √½ before its center
+            //         \/
+            let cube = Box::new(
+                Point3D::with_values(1.5, -0.5, -0.5),
+                Point3D::with_values(2.5, 0.5, 0.5),
+            )
+            .with_rotation(Vector3D::with_values(0.0, 1.0, 0.0), 45.0);
+            let ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cube.hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Cube not hit"
+            );
+            assert!((rec.t() - (2.0 - 0.5f64.sqrt())).abs() < 1e-12);
+            let aabb = cube.bounding_box();
+            assert!((aabb.max().x() - (2.0 + 0.5f64.sqrt())).abs() < 1e-12);
+            assert!((aabb.max().y() - 0.5).abs() < 1e-12);
+
+            let grazing = Ray::create(
+                Point3D::with_values(0.0, 0.0, 0.75),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(
+                !cube.hit(&grazing, Interval::new(0.0, 10.0), rec),
+                "Corner cut off not missed"
+            );
+        }
+
+        #[test]
+        fn box_like_sides() {
+            let cube = cuboid().with_rotation(Vector3D::with_values(1.0, 2.0, 3.0), 30.0);
+            let sides = cube.sides();
+            let (rec, side_rec): (&mut HitRecord, &mut HitRecord) =
+                (&mut HitRecord::default(), &mut HitRecord::default());
+
+            for _ in 0..1000 {
+                let origin = 5.0 * Vector3D::random_within(-1.0, 1.0) + cube.center();
+                let target = 2.0 * Vector3D::random_within(-1.0, 1.0) + cube.center();
+                let ray = Ray::create(origin, target - origin);
+                let t_min = 2.0 * random_f64();
+                let ray_t = Interval::new(t_min, f64::INFINITY);
+
+                let hit = cube.hit(&ray, ray_t, rec);
+                assert_eq!(
+                    hit,
+                    sides.hit(&ray, ray_t, side_rec),
+                    "Quads differ on {:?}",
+                    ray
+                );
+                if hit {
+                    assert!((rec.t() - side_rec.t()).abs() < 1e-9);
+                    assert!((rec.normal() - side_rec.normal()).length() < 1e-9);
+                    assert_eq!(rec.front_face(), side_rec.front_face());
+                    assert!(
+                        (rec.u() - side_rec.u()).abs() < 1e-9,
+                        "u differs on {:?}",
+                        ray
+                    );
+                    assert!(
+                        (rec.v() - side_rec.v()).abs() < 1e-9,
+                        "v differs on {:?}",
+                        ray
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn box_sample_surface() {
+            let cube = cuboid().with_rotation(Vector3D::with_values(0.0, 0.0, 1.0), 90.0);
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            let mut ends = 0;
+            for _ in 0..1000 {
+                let sample = cube.sample_surface().expect("Box not sampled");
+
+                // Hitting the sample from just outside finds it again
+                let origin = sample.p() + 0.1 * sample.normal();
+                let ray = Ray::create(origin, -sample.normal());
+                assert!(cube.hit(&ray, Interval::new(0.0, 1.0), rec));
+                assert!(
+                    (rec.t() - 0.1).abs() < 1e-9,
+                    "Sample {} not on the box",
+                    sample.p()
+                );
+                assert!(rec.front_face(), "Normal {} not outwards", sample.normal());
+                assert!((rec.u() - sample.u()).abs() < 1e-9 && (rec.v() - sample.v()).abs() < 1e-9);
+                if sample.normal().z().abs() > 0.5 {
+                    ends += 1;
+                }
+            }
+
+            // The faces at the ends of z have a fifth of the area
+            assert!(
+                (150..250).contains(&ends),
+                "{} of 1000 samples on the ends",
+                ends
+            );
+        }
+    }
+}
+
+// CYLINDER
+pub mod cylinder {
+    //! Module for handling cylinders in the context of a raytracer.
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// A point a ray hits on a shape around an axis: the parameter along the ray,
+    /// the outward normal in the frame of the shape and the surface coordinates.
+    pub(crate) type Candidate = (f64, Vector3D, (f64, f64));
+
+    /// The frame of a shape around an axis, its z axis pointing along the axis.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) struct Frame {
+        origin: Point3D,
+        axes: [Vector3D; 3],
+    }
+
+    impl Frame {
+        /// Creates the frame at `origin` with its z axis along `axis`.
+        pub(crate) fn new(origin: Point3D, axis: Vector3D) -> Self {
+            let w = axis.unit_vector();
+            let (u, v) = w.orthonormal_basis();
+            Frame {
+                origin,
+                axes: [u, v, w],
+            }
+        }
+
+        /// Gets the origin of the frame.
+        pub(crate) fn origin(&self) -> Point3D {
+            self.origin
+        }
+
+        /// Gets the unit z axis of the frame.
+        pub(crate) fn axis(&self) -> Vector3D {
+            self.axes[2]
+        }
+
+        /// Expresses a ray in the frame, as origin and direction.
+        pub(crate) fn local(&self, r: &Ray) -> (Vector3D, Vector3D) {
+            let coords = |v: Vector3D| {
+                Vector3D::with_values(
+                    v.dot(self.axes[0]),
+                    v.dot(self.axes[1]),
+                    v.dot(self.axes[2]),
+                )
+            };
+            (coords(r.origin() - self.origin), coords(r.direction()))
+        }
+
+        /// Computes the angle around the z axis of a point in the frame, as fraction of a turn.
+        pub(crate) fn angle(p: Vector3D) -> f64 {
+            p.y().atan2(p.x()).rem_euclid(2.0 * PI) / (2.0 * PI)
+        }
+
+        /// Fills the hit record with the nearest candidate found, if any.
+        ///
+        /// # Returns
+        ///
+        /// Returns `true` if a candidate got found.
+        pub(crate) fn record(
+            &self,
+            r: &Ray,
+            nearest: Option<Candidate>,
+            mat: &Option<Rc<dyn Material>>,
+            rec: &mut HitRecord,
+        ) -> bool {
+            let Some((t, n, (u, v))) = nearest else {
+                return false;
+            };
+
+            let outward_normal = n.x() * self.axes[0] + n.y() * self.axes[1] + n.z() * self.axes[2];
+            rec.t = t;
+            rec.p = r.at(t);
+            rec.set_face_normal(*r, outward_normal.unit_vector());
+            (rec.u, rec.v) = (u, v);
+            rec.mat = mat.clone();
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+
+            true
+        }
+    }
+
+    /// Keeps the candidate if it lies within `ray_t` and nearer than the nearest one so far.
+    pub(crate) fn fold(nearest: &mut Option<Candidate>, ray_t: Interval, candidate: Candidate) {
+        if ray_t.surrounds(candidate.0) && !nearest.is_some_and(|n| n.0 <= candidate.0) {
+            *nearest = Some(candidate);
+        }
+    }
+
+    /// Finds where a ray in the frame of a shape hits the infinite cylinder of
+    /// radius `radius` around the z axis.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of hits and their parameters along the ray, in the
+    /// leading elements of the array.
+    pub(crate) fn side(o: Vector3D, d: Vector3D, radius: f64) -> (usize, [f64; 2]) {
+        let a = d.x() * d.x() + d.y() * d.y();
+        let half_b = o.x() * d.x() + o.y() * d.y();
+        let c = o.x() * o.x() + o.y() * o.y() - radius * radius;
+        let discriminant = half_b * half_b - a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return (0, [0.0; 2]);
+        }
+
+        let sqrtd = discriminant.sqrt();
+        (2, [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a])
+    }
+
+    /// Represents a cylinder in 3D space, closed by disks at both ends unless uncapped.
+    ///
+    /// Around the side `u` goes once around the axis and `v` from the base to the top,
+    /// on the caps `v` goes from the center to the rim.
+    pub struct Cylinder {
+        frame: Frame,
+        height: f64,
+        radius: f64,
+        capped: bool,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Cylinder {
+        /// Creates a new capped cylinder.
+        ///
+        /// # Arguments
+        ///
+        /// * `base`   - The center of the bottom of the cylinder.
+        /// * `axis`   - The vector from the center of the bottom to the center of the top.
+        /// * `radius` - The radius of the cylinder.
+        pub fn new(base: Point3D, axis: Vector3D, radius: f64) -> Self {
+            Cylinder {
+                frame: Frame::new(base, axis),
+                height: axis.length(),
+                radius,
+                capped: true,
+                mat: None,
+            }
+        }
+
+        /// Creates a new capped cylinder with a material.
+        ///
+        /// # Arguments
+        ///
+        /// * `base`   - The center of the bottom of the cylinder.
+        /// * `axis`   - The vector from the center of the bottom to the center of the top.
+        /// * `radius` - The radius of the cylinder.
+        /// * `mat`    - The material of the cylinder's surface.
+        pub fn with_material(
+            base: Point3D,
+            axis: Vector3D,
+            radius: f64,
+            mat: Rc<dyn Material>,
+        ) -> Self {
+            Cylinder {
+                mat: Some(mat),
+                ..Self::new(base, axis, radius)
+            }
+        }
+
+        /// Returns the cylinder with or without the disks closing its ends, like a pipe.
+        pub fn with_caps(mut self, capped: bool) -> Self {
+            self.capped = capped;
+            self
+        }
+
+        /// Gets the center of the bottom of the cylinder.
+        pub fn base(&self) -> Point3D {
+            self.frame.origin()
+        }
+
+        /// Gets the vector from the center of the bottom to the center of the top.
+        pub fn axis(&self) -> Vector3D {
+            self.height * self.frame.axis()
+        }
+
+        /// Gets the radius of the cylinder.
+        pub fn radius(&self) -> f64 {
+            self.radius
+        }
+
+        /// Checks if disks close the ends of the cylinder.
+        pub fn is_capped(&self) -> bool {
+            self.capped
+        }
+    }
+
+    impl Hittable for Cylinder {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let (o, d) = self.frame.local(r);
+            let (h, radius) = (self.height, self.radius);
+
+            let mut nearest: Option<Candidate> = None;
+            let (count, roots) = side(o, d, radius);
+            for &t in &roots[..count] {
+                let p = o + t * d;
+                if (0.0..=h).contains(&p.z()) {
+                    let normal = Vector3D::with_values(p.x(), p.y(), 0.0);
+                    fold(
+                        &mut nearest,
+                        ray_t,
+                        (t, normal, (Frame::angle(p), p.z() / h)),
+                    );
+                }
+            }
+            if self.capped && d.z() != 0.0 {
+                for (z, nz) in [(0.0, -1.0), (h, 1.0)] {
+                    let t = (z - o.z()) / d.z();
+                    let p = o + t * d;
+                    let rho = p.x().hypot(p.y());
+                    if rho <= radius {
+                        let normal = Vector3D::with_values(0.0, 0.0, nz);
+                        fold(
+                            &mut nearest,
+                            ray_t,
+                            (t, normal, (Frame::angle(p), rho / radius)),
+                        );
+                    }
+                }
+            }
+
+            self.frame.record(r, nearest, &self.mat, rec)
+        }
+    }
+
+    impl PartialEq for Cylinder {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.frame == other.frame
+                && self.height == other.height
+                && self.radius == other.radius
+                && self.capped == other.capped
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Cylinder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Cylinder")
+                .field("base", &self.base())
+                .field("axis", &self.axis())
+                .field("radius", &self.radius)
+                .field("capped", &self.capped)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::cylinder::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The cylinder of radius 1 from (2,0,0) up to (2,2,0).
+        fn cylinder() -> Cylinder {
+            Cylinder::new(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 2.0, 0.0),
+                1.0,
+            )
+        }
+
+        #[test]
+        fn cylinder_new() {
+            let cylinder = cylinder();
+
+            assert_eq!(cylinder.axis(), Vector3D::with_values(0.0, 2.0, 0.0));
+            assert!(cylinder.is_capped());
+            assert!(!cylinder.with_caps(false).is_capped());
+        }
+
+        #[test]
+        fn cylinder_hit_side() {
+            //       +---+ (3,2)
+            //   o-->X c |    hitting the side at t=1,
+            // (0,½) |   |    a quarter of the way up
+            //       +---+
+            //     (1,0)
+            let ray = Ray::create(
+                Point3D::with_values(0.0, 0.5, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cylinder().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Side not hit"
+            );
+            assert_eq!(rec.t(), 1.0);
+            assert!((rec.normal() - Vector3D::with_values(-1.0, 0.0, 0.0)).length() < 1e-12);
+            assert!(rec.front_face());
+            assert_eq!(rec.v(), 0.25);
+            assert!((0.0..1.0).contains(&rec.u()));
+
+            assert!(cylinder().hit(&ray, Interval::new(2.0, 10.0), rec));
+            assert_eq!(rec.t(), 3.0);
+            assert!(!rec.front_face(), "Leaving the cylinder hit the front face");
+
+            let above = Ray::create(
+                Point3D::with_values(0.0, 2.5, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(!cylinder().hit(&above, Interval::new(0.0, 10.0), rec));
+        }
+
+        #[test]
+        fn cylinder_hit_caps() {
+            //      o (2.5,5)
+            //      |          hitting the top cap at t=3,
+            //   +--X--+       or the inside of the open pipe
+            //   |  c  |       at t=2 after slanting in
+            //   +-----+
+            let down = Ray::create(
+                Point3D::with_values(2.5, 5.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cylinder().hit(&down, Interval::new(0.0, 10.0), rec),
+                "Cap not hit"
+            );
+            assert_eq!(rec.t(), 3.0);
+            assert_eq!(rec.normal(), Vector3D::with_values(0.0, 1.0, 0.0));
+            assert_eq!(rec.v(), 0.5);
+
+            let pipe = cylinder().with_caps(false);
+            assert!(
+                !pipe.hit(&down, Interval::new(0.0, 10.0), rec),
+                "Open pipe hit"
+            );
+            let slanted = Ray::create(
+                Point3D::with_values(2.0, 3.0, 0.0),
+                Vector3D::with_values(0.5, -1.0, 0.0),
+            );
+            assert!(pipe.hit(&slanted, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 2.0).abs() < 1e-12);
+            assert!(!rec.front_face(), "Inside of the pipe hit as outside");
+            assert!((rec.normal() - Vector3D::with_values(-1.0, 0.0, 0.0)).length() < 1e-12);
+        }
+    }
+}
+
+// CONE
+pub mod cone {
+    //! Module for handling cones in the context of a raytracer.
+    use crate::hittable::cylinder::{fold, Candidate, Frame};
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a cone in 3D space, closed by a disk at its base unless uncapped.
+    ///
+    /// Around the side `u` goes once around the axis and `v` from the base to the apex,
+    /// on the base `v` goes from the center to the rim.
+    pub struct Cone {
+        frame: Frame,
+        height: f64,
+        radius: f64,
+        capped: bool,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Cone {
+        /// Creates a new cone closed at its base.
+        ///
+        /// # Arguments
+        ///
+        /// * `base`   - The center of the base of the cone.
+        /// * `axis`   - The vector from the center of the base to the apex.
+        /// * `radius` - The radius of the base.
+        pub fn new(base: Point3D, axis: Vector3D, radius: f64) -> Self {
+            Cone {
+                frame: Frame::new(base, axis),
+                height: axis.length(),
+                radius,
+                capped: true,
+                mat: None,
+            }
+        }
+
+        /// Creates a new cone closed at its base with a material.
+        ///
+        /// # Arguments
+        ///
+        /// * `base`   - The center of the base of the cone.
+        /// * `axis`   - The vector from the center of the base to the apex.
+        /// * `radius` - The radius of the base.
+        /// * `mat`    - The material of the cone's surface.
+        pub fn with_material(
+            base: Point3D,
+            axis: Vector3D,
+            radius: f64,
+            mat: Rc<dyn Material>,
+        ) -> Self {
+            Cone {
+                mat: Some(mat),
+                ..Self::new(base, axis, radius)
+            }
+        }
+
+        /// Returns the cone with or without the disk closing its base.
+        pub fn with_cap(mut self, capped: bool) -> Self {
+            self.capped = capped;
+            self
+        }
+
+        /// Gets the center of the base of the cone.
+        pub fn base(&self) -> Point3D {
+            self.frame.origin()
+        }
+
+        /// Gets the vector from the center of the base to the apex.
+        pub fn axis(&self) -> Vector3D {
+            self.height * self.frame.axis()
+        }
+
+        /// Gets the radius of the base.
+        pub fn radius(&self) -> f64 {
+            self.radius
+        }
+
+        /// Checks if a disk closes the base of the cone.
+        pub fn is_capped(&self) -> bool {
+            self.capped
+        }
+    }
+
+    impl Hittable for Cone {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let (o, d) = self.frame.local(r);
+            let (h, radius) = (self.height, self.radius);
+
+            // The side is where x² + y² = k²(h - z)², its radius shrinking with the slope k
+            let k2 = (radius / h).powi(2);
+            let a = d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z();
+            let half_b = o.x() * d.x() + o.y() * d.y() + k2 * (h - o.z()) * d.z();
+            let c = o.x() * o.x() + o.y() * o.y() - k2 * (h - o.z()).powi(2);
+            let (count, roots) = if a.abs() < 1e-12 * d.length_squared() {
+                // Rays parallel to the slope hit the double cone once
+                if half_b == 0.0 {
+                    (0, [0.0; 2])
+                } else {
+                    (1, [-c / (2.0 * half_b), 0.0])
+                }
+            } else {
+                let discriminant = half_b * half_b - a * c;
+                if discriminant < 0.0 {
+                    (0, [0.0; 2])
+                } else {
+                    let sqrtd = discriminant.sqrt();
+                    (2, [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a])
+                }
+            };
+
+            let mut nearest: Option<Candidate> = None;
+            for &t in &roots[..count] {
+                let p = o + t * d;
+                if (0.0..=h).contains(&p.z()) {
+                    let mut normal = Vector3D::with_values(p.x(), p.y(), k2 * (h - p.z()));
+                    if normal.near_zero() {
+                        normal = Vector3D::with_values(0.0, 0.0, 1.0);
+                    }
+                    fold(
+                        &mut nearest,
+                        ray_t,
+                        (t, normal, (Frame::angle(p), p.z() / h)),
+                    );
+                }
+            }
+            if self.capped && d.z() != 0.0 {
+                let t = -o.z() / d.z();
+                let p = o + t * d;
+                let rho = p.x().hypot(p.y());
+                if rho <= radius {
+                    let normal = Vector3D::with_values(0.0, 0.0, -1.0);
+                    fold(
+                        &mut nearest,
+                        ray_t,
+                        (t, normal, (Frame::angle(p), rho / radius)),
+                    );
+                }
+            }
+
+            self.frame.record(r, nearest, &self.mat, rec)
+        }
+    }
+
+    impl PartialEq for Cone {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.frame == other.frame
+                && self.height == other.height
+                && self.radius == other.radius
+                && self.capped == other.capped
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Cone {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Cone")
+                .field("base", &self.base())
+                .field("axis", &self.axis())
+                .field("radius", &self.radius)
+                .field("capped", &self.capped)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::cone::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The cone of radius 1 at the origin with its apex at (0,2,0).
+        fn cone() -> Cone {
+            Cone::new(Point3D::new(), Vector3D::with_values(0.0, 2.0, 0.0), 1.0)
+        }
+
+        #[test]
+        fn cone_hit_side() {
+            //         + (0,2)
+            //   o--->X \      hitting the side halfway up,
+            // (-5,1) /  \     where the radius is ½
+            //       +----+
+            //    (-1,0) (1,0)
+            let ray = Ray::create(
+                Point3D::with_values(-5.0, 1.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cone().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Side not hit"
+            );
+            assert!((rec.t() - 4.5).abs() < 1e-12);
+            let expected = Vector3D::with_values(-0.5, 0.25, 0.0).unit_vector();
+            assert!(
+                (rec.normal() - expected).length() < 1e-12,
+                "Normal {}",
+                rec.normal()
+            );
+            assert!(rec.front_face());
+            assert!((rec.v() - 0.5).abs() < 1e-12);
+
+            // Only the upper half of the double cone counts
+            let high = Ray::create(
+                Point3D::with_values(-5.0, 3.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(
+                !cone().hit(&high, Interval::new(0.0, 10.0), rec),
+                "Mirrored cone hit"
+            );
+
+            // Parallel to the left slope, the ray only hits the right one
+            let parallel = Ray::create(
+                Point3D::with_values(-0.5, -1.0, 0.0),
+                Vector3D::with_values(1.0, 2.0, 0.0),
+            );
+            assert!(cone()
+                .with_cap(false)
+                .hit(&parallel, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 1.0).abs() < 1e-12, "t = {}", rec.t());
+            assert!(!rec.front_face());
+        }
+
+        #[test]
+        fn cone_hit_base() {
+            let ray = Ray::create(
+                Point3D::with_values(0.5, -5.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                cone().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Base not hit"
+            );
+            assert_eq!(rec.t(), 5.0);
+            assert_eq!(rec.normal(), Vector3D::with_values(0.0, -1.0, 0.0));
+            assert_eq!(rec.v(), 0.5);
+
+            // Without the base the ray passes in and leaves through the side
+            assert!(cone()
+                .with_cap(false)
+                .hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 6.0).abs() < 1e-12);
+            assert!(!rec.front_face(), "Inside of the open cone hit as outside");
+        }
+    }
+}
+
+// CAPSULE
+pub mod capsule {
+    //! Module for handling capsules, cylinders with hemispherical ends, in the context of a raytracer.
+    use crate::hittable::cylinder::{fold, side, Candidate, Frame};
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a capsule in 3D space, every point within a radius of a line segment.
+    ///
+    /// `u` goes once around the segment and `v` along the surface from the pole
+    /// beyond the first end to the pole beyond the second.
+    pub struct Capsule {
+        frame: Frame,
+        length: f64,
+        radius: f64,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Capsule {
+        /// Creates a new capsule around the segment from `a` to `b`.
+        ///
+        /// # Arguments
+        ///
+        /// * `a`      - The first end of the segment.
+        /// * `b`      - The second end of the segment.
+        /// * `radius` - The radius of the capsule.
+        pub fn new(a: Point3D, b: Point3D, radius: f64) -> Self {
+            // Segments of no length still need an axis for their surface coordinates
+            let axis = if (b - a).near_zero() {
+                Vector3D::with_values(0.0, 1.0, 0.0)
+            } else {
+                b - a
+            };
+            Capsule {
+                frame: Frame::new(a, axis),
+                length: (b - a).length(),
+                radius,
+                mat: None,
+            }
+        }
+
+        /// Creates a new capsule around the segment from `a` to `b` with a material.
+        ///
+        /// # Arguments
+        ///
+        /// * `a`      - The first end of the segment.
+        /// * `b`      - The second end of the segment.
+        /// * `radius` - The radius of the capsule.
+        /// * `mat`    - The material of the capsule's surface.
+        pub fn with_material(a: Point3D, b: Point3D, radius: f64, mat: Rc<dyn Material>) -> Self {
+            Capsule {
+                mat: Some(mat),
+                ..Self::new(a, b, radius)
+            }
+        }
+
+        /// Gets the ends of the segment.
+        pub fn ends(&self) -> (Point3D, Point3D) {
+            let a = self.frame.origin();
+            (a, a + self.length * self.frame.axis())
+        }
+
+        /// Gets the radius of the capsule.
+        pub fn radius(&self) -> f64 {
+            self.radius
+        }
+    }
+
+    impl Hittable for Capsule {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let (o, d) = self.frame.local(r);
+            let (h, radius) = (self.length, self.radius);
+
+            // The length of a meridian from pole to pole
+            let meridian = h + PI * radius;
+            let mut nearest: Option<Candidate> = None;
+            let (count, roots) = side(o, d, radius);
+            for &t in &roots[..count] {
+                let p = o + t * d;
+                if (0.0..=h).contains(&p.z()) {
+                    let normal = Vector3D::with_values(p.x(), p.y(), 0.0);
+                    let s = 0.5 * PI * radius + p.z();
+                    fold(
+                        &mut nearest,
+                        ray_t,
+                        (t, normal, (Frame::angle(p), s / meridian)),
+                    );
+                }
+            }
+
+            // Only the outer half of the sphere around either end counts
+            for (z, sign) in [(0.0, -1.0), (h, 1.0)] {
+                let oc = o - Vector3D::with_values(0.0, 0.0, z);
+                let a = d.length_squared();
+                let half_b = oc.dot(d);
+                let c = oc.length_squared() - radius * radius;
+                let discriminant = half_b * half_b - a * c;
+                if discriminant < 0.0 {
+                    continue;
+                }
+
+                let sqrtd = discriminant.sqrt();
+                for t in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
+                    let normal = (oc + t * d) / radius;
+                    if sign * normal.z() >= 0.0 {
+                        let latitude = normal.z().clamp(-1.0, 1.0).asin();
+                        let s = 0.5 * PI * radius + z + radius * latitude;
+                        let uv = (Frame::angle(normal), s / meridian);
+                        fold(&mut nearest, ray_t, (t, normal, uv));
+                    }
+                }
+            }
+
+            self.frame.record(r, nearest, &self.mat, rec)
+        }
+    }
+
+    impl PartialEq for Capsule {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.frame == other.frame
+                && self.length == other.length
+                && self.radius == other.radius
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Capsule {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Capsule")
+                .field("ends", &self.ends())
+                .field("radius", &self.radius)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::capsule::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The capsule of radius ½ around the segment from the origin to (0,2,0).
+        fn capsule() -> Capsule {
+            Capsule::new(Point3D::new(), Point3D::with_values(0.0, 2.0, 0.0), 0.5)
+        }
+
+        #[test]
+        fn capsule_hit() {
+            //        o (0,5)
+            //       _X_         hitting the top pole at t=2.5,
+            //      /   \        the side at t=4.5 halfway up
+            //  o-->X   |        and the bottom from below
+            //      \___/
+            //        X
+            //        o
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            let meridian = 2.0 + PI * 0.5;
+
+            let down = Ray::create(
+                Point3D::with_values(0.0, 5.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+            );
+            assert!(
+                capsule().hit(&down, Interval::new(0.0, 10.0), rec),
+                "Top not hit"
+            );
+            assert!((rec.t() - 2.5).abs() < 1e-12);
+            assert!((rec.normal() - Vector3D::with_values(0.0, 1.0, 0.0)).length() < 1e-12);
+            assert!((rec.v() - 1.0).abs() < 1e-12);
+
+            let side = Ray::create(
+                Point3D::with_values(-5.0, 1.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(
+                capsule().hit(&side, Interval::new(0.0, 10.0), rec),
+                "Side not hit"
+            );
+            assert!((rec.t() - 4.5).abs() < 1e-12);
+            assert!((rec.normal() - Vector3D::with_values(-1.0, 0.0, 0.0)).length() < 1e-12);
+            assert!((rec.v() - (PI * 0.25 + 1.0) / meridian).abs() < 1e-12);
+
+            let up = Ray::create(Point3D::with_values(0.0, -5.0, 0.0), -down.direction());
+            assert!(
+                capsule().hit(&up, Interval::new(0.0, 10.0), rec),
+                "Bottom not hit"
+            );
+            assert!((rec.t() - 4.5).abs() < 1e-12);
+            assert!(rec.v().abs() < 1e-12);
+
+            // From within, the ray leaves through the far end
+            let inside = Ray::create(Point3D::with_values(0.0, 1.0, 0.0), up.direction());
+            assert!(capsule().hit(&inside, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 1.5).abs() < 1e-12);
+            assert!(!rec.front_face());
+        }
+
+        #[test]
+        fn capsule_hit_ends() {
+            // Near the end, rays pass the sphere's inner half and hit its outer one
+            let ray = Ray::create(
+                Point3D::with_values(-5.0, 2.25, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(capsule().hit(&ray, Interval::new(0.0, 10.0), rec));
+            let x = (0.25f64 - 0.0625).sqrt();
+            assert!((rec.p() - Point3D::with_values(-x, 2.25, 0.0)).length() < 1e-12);
+            assert!(
+                ((rec.p() - Point3D::with_values(0.0, 2.0, 0.0)).unit_vector() - rec.normal())
+                    .length()
+                    < 1e-12
+            );
+
+            let beyond = Ray::create(
+                Point3D::with_values(-5.0, 2.6, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            assert!(!capsule().hit(&beyond, Interval::new(0.0, 10.0), rec));
+        }
+    }
+}
+
+// TORUS
+pub mod torus {
+    //! Module for handling tori, rings, in the context of a raytracer.
+    use crate::hittable::cylinder::{fold, Candidate, Frame};
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{same_material, Material};
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Tells coefficients close enough to 0 to take them as 0.
+    const EPSILON: f64 = 1e-9;
+
+    /// Finds the real roots of x² + px + q.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of roots and the roots in the leading elements of the array.
+    fn quadratic(p: f64, q: f64) -> (usize, [f64; 2]) {
+        let discriminant = p * p / 4.0 - q;
+        if discriminant.abs() < EPSILON {
+            (1, [-p / 2.0, 0.0])
+        } else if discriminant < 0.0 {
+            (0, [0.0; 2])
+        } else {
+            let sqrtd = discriminant.sqrt();
+            (2, [-p / 2.0 - sqrtd, -p / 2.0 + sqrtd])
+        }
+    }
+
+    /// Finds the real roots of x³ + ax² + bx + c with Cardano's formula.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of roots, at least 1, and the roots in the leading elements of the array.
+    fn cubic(a: f64, b: f64, c: f64) -> (usize, [f64; 3]) {
+        // Substitute x = y - a/3 to get rid of the quadratic term: y³ + 3py + 2q
+        let p = (b - a * a / 3.0) / 3.0;
+        let q = (2.0 * a * a * a / 27.0 - a * b / 3.0 + c) / 2.0;
+        let discriminant = q * q + p * p * p;
+
+        let (count, mut roots) = if discriminant.abs() < EPSILON {
+            if q.abs() < EPSILON {
+                (1, [0.0; 3])
+            } else {
+                let u = (-q).cbrt();
+                (2, [2.0 * u, -u, 0.0])
+            }
+        } else if discriminant < 0.0 {
+            // Three real roots, found with the trigonometric method
+            let phi = (-q / (-p * p * p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+            let t = 2.0 * (-p).sqrt();
+            (
+                3,
+                [
+                    t * phi.cos(),
+                    -t * (phi + PI / 3.0).cos(),
+                    -t * (phi - PI / 3.0).cos(),
+                ],
+            )
+        } else {
+            let sqrtd = discriminant.sqrt();
+            (1, [(sqrtd - q).cbrt() - (sqrtd + q).cbrt(), 0.0, 0.0])
+        };
+
+        for y in &mut roots[..count] {
+            *y -= a / 3.0;
+        }
+        (count, roots)
+    }
+
+    /// Finds the real roots of x⁴ + ax³ + bx² + cx + d with Ferrari's method.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of roots and the roots in the leading elements of the array.
+    pub(crate) fn quartic(a: f64, b: f64, c: f64, d: f64) -> (usize, [f64; 4]) {
+        // Substitute x = y - a/4 to get rid of the cubic term: y⁴ + py² + qy + r
+        let a2 = a * a;
+        let p = -3.0 / 8.0 * a2 + b;
+        let q = a2 * a / 8.0 - a * b / 2.0 + c;
+        let r = -3.0 / 256.0 * a2 * a2 + a2 * b / 16.0 - a * c / 4.0 + d;
+
+        let mut roots = [0.0; 4];
+        let count = if r.abs() < EPSILON {
+            let (count, cubic_roots) = cubic(0.0, p, q);
+            // y(y³ + py + q) has the root 0 besides those of the cubic, already in place
+            roots[..count].copy_from_slice(&cubic_roots[..count]);
+            count + 1
+        } else {
+            // Split into two quadratics with a root of the resolvent cubic
+            let z = cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0).1[0];
+            let u = z * z - r;
+            let v = 2.0 * z - p;
+            let u = if u.abs() < EPSILON {
+                0.0
+            } else if u > 0.0 {
+                u.sqrt()
+            } else {
+                return (0, roots);
+            };
+            let v = if v.abs() < EPSILON {
+                0.0
+            } else if v > 0.0 {
+                v.sqrt()
+            } else {
+                return (0, roots);
+            };
+            let v = if q < 0.0 { -v } else { v };
+
+            let (first, first_roots) = quadratic(v, z - u);
+            let (second, second_roots) = quadratic(-v, z + u);
+            roots[..first].copy_from_slice(&first_roots[..first]);
+            roots[first..first + second].copy_from_slice(&second_roots[..second]);
+            first + second
+        };
+
+        for y in &mut roots[..count] {
+            *y -= a / 4.0;
+        }
+        (count, roots)
+    }
+
+    /// Represents a torus in 3D space, a tube bent into a ring around an axis.
+    ///
+    /// `u` goes once around the axis and `v` once around the tube, starting at its outer rim.
+    pub struct Torus {
+        frame: Frame,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Option<Rc<dyn Material>>,
+    }
+
+    impl Torus {
+        /// Creates a new torus.
+        ///
+        /// # Arguments
+        ///
+        /// * `center`       - The center of the ring.
+        /// * `axis`         - The direction of the axis through the hole of the ring.
+        /// * `major_radius` - The radius of the ring, from its center to the center of the tube.
+        /// * `minor_radius` - The radius of the tube.
+        pub fn new(center: Point3D, axis: Vector3D, major_radius: f64, minor_radius: f64) -> Self {
+            Torus {
+                frame: Frame::new(center, axis),
+                major_radius,
+                minor_radius,
+                mat: None,
+            }
+        }
+
+        /// Creates a new torus with a material.
+        ///
+        /// # Arguments
+        ///
+        /// * `center`       - The center of the ring.
+        /// * `axis`         - The direction of the axis through the hole of the ring.
+        /// * `major_radius` - The radius of the ring, from its center to the center of the tube.
+        /// * `minor_radius` - The radius of the tube.
+        /// * `mat`          - The material of the torus' surface.
+        pub fn with_material(
+            center: Point3D,
+            axis: Vector3D,
+            major_radius: f64,
+            minor_radius: f64,
+            mat: Rc<dyn Material>,
+        ) -> Self {
+            Torus {
+                mat: Some(mat),
+                ..Self::new(center, axis, major_radius, minor_radius)
+            }
+        }
+
+        /// Gets the center of the ring.
+        pub fn center(&self) -> Point3D {
+            self.frame.origin()
+        }
+
+        /// Gets the unit axis through the hole of the ring.
+        pub fn axis(&self) -> Vector3D {
+            self.frame.axis()
+        }
+
+        /// Gets the radius of the ring, from its center to the center of the tube.
+        pub fn major_radius(&self) -> f64 {
+            self.major_radius
+        }
+
+        /// Gets the radius of the tube.
+        pub fn minor_radius(&self) -> f64 {
+            self.minor_radius
+        }
+    }
+
+    impl Hittable for Torus {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let (o, d) = self.frame.local(r);
+            let length = d.length();
+            if length == 0.0 || self.major_radius <= 0.0 {
+                return false;
+            }
+
+            // Solve in units of the ring's radius along the unit direction, keeping
+            // the coefficients of (|p|² - 1 - r²)² = 4(r² - z²) well-conditioned
+            let o = o / self.major_radius;
+            let d = d / length;
+            let minor = self.minor_radius / self.major_radius;
+            let f = o.dot(d);
+            let e = o.length_squared() - 1.0 - minor * minor;
+            let [a, b, c, k] = [
+                4.0 * f,
+                2.0 * e + 4.0 * f * f + 4.0 * d.z() * d.z(),
+                4.0 * f * e + 8.0 * o.z() * d.z(),
+                e * e - 4.0 * (minor * minor - o.z() * o.z()),
+            ];
+
+            let mut nearest: Option<Candidate> = None;
+            let (count, roots) = quartic(a, b, c, k);
+            for mut s in roots.into_iter().take(count) {
+                // Polish the root, the closed form loses digits on the way
+                for _ in 0..2 {
+                    let value = (((s + a) * s + b) * s + c) * s + k;
+                    let slope = ((4.0 * s + 3.0 * a) * s + 2.0 * b) * s + c;
+                    if slope != 0.0 {
+                        s -= value / slope;
+                    }
+                }
+
+                let p = o + s * d;
+                let rho = p.x().hypot(p.y());
+                if rho == 0.0 {
+                    continue;
+                }
+                let ring = Vector3D::with_values(p.x() / rho, p.y() / rho, 0.0);
+                let normal = p - ring;
+                let v = p.z().atan2(rho - 1.0).rem_euclid(2.0 * PI) / (2.0 * PI);
+                let t = s * self.major_radius / length;
+                fold(&mut nearest, ray_t, (t, normal, (Frame::angle(p), v)));
+            }
+
+            self.frame.record(r, nearest, &self.mat, rec)
+        }
+    }
+
+    impl PartialEq for Torus {
+        // Materials are equal if they are the very same material
+        fn eq(&self, other: &Self) -> bool {
+            self.frame == other.frame
+                && self.major_radius == other.major_radius
+                && self.minor_radius == other.minor_radius
+                && same_material(&self.mat, &other.mat)
+        }
+    }
+
+    impl fmt::Debug for Torus {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Torus")
+                .field("center", &self.center())
+                .field("axis", &self.axis())
+                .field("major_radius", &self.major_radius)
+                .field("minor_radius", &self.minor_radius)
+                .field("mat", &self.mat.as_ref().map(Rc::as_ptr))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::torus::*;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The ring of radius 2 around the y axis with a tube of radius ½.
+        fn torus() -> Torus {
+            Torus::new(
+                Point3D::new(),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+                2.0,
+                0.5,
+            )
+        }
+
+        #[test]
+        fn torus_quartic() {
+            // (x - 1)(x - 2)(x + 3)(x + 0.5) = x⁴ + 0.5x³ - 7x² + 2.5x + 3
+            let (count, mut roots) = quartic(0.5, -7.0, 2.5, 3.0);
+            roots.sort_by(f64::total_cmp);
+            assert_eq!(count, 4);
+            for (root, expected) in roots.iter().zip([-3.0, -0.5, 1.0, 2.0]) {
+                assert!((root - expected).abs() < 1e-9, "Roots {:?}", roots);
+            }
+
+            assert_eq!(quartic(0.0, 0.0, 0.0, 1.0).0, 0, "x⁴ + 1 has real roots");
+        }
+
+        #[test]
+        fn torus_hit() {
+            //        .-----.          seen from the side, the ring
+            //   o-->X  X X  X         is hit at its outer rim at t=2.5,
+            // (-5,0) '-----'          and from the hole at t=1.5
+            //     (-2.5)  (2.5)
+            let ray = Ray::create(
+                Point3D::with_values(-5.0, 0.0, 0.0),
+                Vector3D::with_values(2.0, 0.0, 0.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(
+                torus().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Torus not hit"
+            );
+            assert!((rec.t() - 1.25).abs() < 1e-12, "t = {}", rec.t());
+            assert!((rec.normal() - Vector3D::with_values(-1.0, 0.0, 0.0)).length() < 1e-12);
+            assert!(rec.front_face());
+            assert!(
+                rec.v().abs() < 1e-12 || (rec.v() - 1.0).abs() < 1e-12,
+                "Not the outer rim"
+            );
+
+            let hole = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            assert!(torus().hit(&hole, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 1.5).abs() < 1e-12);
+            assert!((rec.v() - 0.5).abs() < 1e-12, "Not the inner rim");
+
+            let along_axis = Ray::create(
+                Point3D::with_values(0.0, 5.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+            );
+            assert!(
+                !torus().hit(&along_axis, Interval::new(0.0, 10.0), rec),
+                "Hole hit"
+            );
+
+            let top = Ray::create(
+                Point3D::with_values(2.0, 5.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+            );
+            assert!(torus().hit(&top, Interval::new(0.0, 10.0), rec));
+            assert!((rec.t() - 4.5).abs() < 1e-12);
+            assert!(
+                (rec.v() - 0.25).abs() < 1e-12,
+                "Top not a quarter around the tube"
+            );
+        }
+
+        #[test]
+        fn torus_like_marching() {
+            // Marching along random rays finds the same first hit as the quartic
+            let torus = Torus::new(
+                Point3D::with_values(1.0, 2.0, 3.0),
+                Vector3D::with_values(1.0, 1.0, 0.0),
+                2.0,
+                0.5,
+            );
+            let inside = |p: Point3D| {
+                let q = p - torus.center();
+                let z = q.dot(torus.axis());
+                let rho = (q.length_squared() - z * z).max(0.0).sqrt();
+                (rho - 2.0).powi(2) + z * z < 0.25
+            };
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            for _ in 0..200 {
+                let origin = torus.center() + 4.0 * Vector3D::random_unit_vector();
+                let target = torus.center() + 2.5 * Vector3D::random_within(-1.0, 1.0);
+                let ray = Ray::create(origin, (target - origin).unit_vector());
+
+                let step = 1e-3;
+                let start = inside(origin);
+                let marched = (1..10000)
+                    .map(|i| f64::from(i) * step)
+                    .find(|&t| inside(ray.at(t)) != start);
+
+                let hit = torus.hit(&ray, Interval::new(1e-6, 10.0), rec);
+                match marched {
+                    Some(t) => {
+                        assert!(hit, "Torus missed along {:?}", ray);
+                        // Grazing the tube between two steps, marching may cross it later
+                        assert!(rec.t() <= t + 1e-9, "{} after {}", rec.t(), t);
+                        let q = rec.p() - torus.center();
+                        let z = q.dot(torus.axis());
+                        let rho = (q.length_squared() - z * z).sqrt();
+                        assert!(
+                            ((rho - 2.0).powi(2) + z * z - 0.25).abs() < 1e-9,
+                            "Hit off the torus"
+                        );
+                    }
+                    None => assert!(!hit || rec.t() > 10.0 - step, "Torus hit along {:?}", ray),
+                }
+            }
+        }
+    }
+}
+
+// --- PARTICIPATING MEDIA -----------------------------------------------------
+
+// CONSTANT MEDIUM
+pub mod constant_medium {
+    //! Module for handling volumes of constant density, like fog or smoke, in the context of a raytracer.
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::{Isotropic, Material};
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::Vector3D;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// Represents a medium of constant density filling a boundary, scattering
+    /// rays at random distances within it.
+    ///
+    /// The boundary has to be convex, rays entering it once and leaving it once.
+    pub struct ConstantMedium {
+        boundary: Rc<dyn Hittable>,
+        density: f64,
+        phase_function: Rc<dyn Material>,
+    }
+
+    impl ConstantMedium {
+        /// Creates a new medium scattering light equally into every direction.
+        ///
+        /// # Arguments
+        ///
+        /// * `boundary` - The shape the medium fills.
+        /// * `density`  - The chance of scattering per unit distance.
+        /// * `albedo`   - The fraction of light scattered instead of absorbed.
+        pub fn new(boundary: Rc<dyn Hittable>, density: f64, albedo: Color) -> Self {
+            Self::with_material(boundary, density, Rc::new(Isotropic::new(albedo)))
+        }
+
+        /// Creates a new medium scattering light by the given phase function.
+        ///
+        /// # Arguments
+        ///
+        /// * `boundary`       - The shape the medium fills.
+        /// * `density`        - The chance of scattering per unit distance.
+        /// * `phase_function` - The volumetric material scattering light within the medium.
+        pub fn with_material(
+            boundary: Rc<dyn Hittable>,
+            density: f64,
+            phase_function: Rc<dyn Material>,
+        ) -> Self {
+            ConstantMedium {
+                boundary,
+                density,
+                phase_function,
+            }
+        }
+
+        /// Gets the chance of scattering per unit distance.
+        pub fn density(&self) -> f64 {
+            self.density
+        }
+
+        /// Gets the shape the medium fills.
+        pub fn boundary(&self) -> &dyn Hittable {
+            self.boundary.as_ref()
+        }
+    }
+
+    impl ConstantMedium {
+        /// Finds the part of `ray_t` the ray spends within the boundary.
+        fn overlap(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+            // Where the whole line enters and leaves the boundary
+            let mut rec1 = HitRecord::default();
+            let mut rec2 = HitRecord::default();
+            if !self.boundary.hit(
+                r,
+                Interval::new(f64::NEG_INFINITY, f64::INFINITY),
+                &mut rec1,
+            ) {
+                return None;
+            }
+            if !self
+                .boundary
+                .hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2)
+            {
+                return None;
+            }
+
+            let t_enter = rec1.t.max(ray_t.min).max(0.0);
+            let t_exit = rec2.t.min(ray_t.max);
+            (t_enter < t_exit).then_some((t_enter, t_exit))
+        }
+    }
+
+    impl Hittable for ConstantMedium {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            if self.density <= 0.0 {
+                return false;
+            }
+            let Some((t_enter, t_exit)) = self.overlap(r, ray_t) else {
+                return false;
+            };
+
+            let ray_length = r.direction().length();
+            let distance_inside = (t_exit - t_enter) * ray_length;
+            let hit_distance = -(1.0 - random_f64()).ln() / self.density;
+            if hit_distance > distance_inside {
+                return false;
+            }
+
+            // Normal and face are arbitrary, the phase function doesn't depend on them
+            rec.t = t_enter + hit_distance / ray_length;
+            rec.p = r.at(rec.t);
+            rec.normal = Vector3D::with_values(1.0, 0.0, 0.0);
+            rec.front_face = true;
+            rec.u = 0.0;
+            rec.v = 0.0;
+            rec.mat = Some(self.phase_function.clone());
+            rec.color = Color::with_values(1.0, 1.0, 1.0);
+            rec.primitive_id = 0;
+
+            true
+        }
+
+        fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+            match self.overlap(r, ray_t) {
+                Some((t_enter, t_exit)) if self.density > 0.0 => {
+                    (-self.density * (t_exit - t_enter) * r.direction().length()).exp()
+                }
+                _ => 1.0,
+            }
+        }
+    }
+
+    impl fmt::Debug for ConstantMedium {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("ConstantMedium")
+                .field("boundary", &Rc::as_ptr(&self.boundary))
+                .field("density", &self.density)
+                .field("phase_function", &Rc::as_ptr(&self.phase_function))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::constant_medium::*;
+        use crate::hittable::sphere::Sphere;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        fn fog(density: f64) -> ConstantMedium {
+            let boundary = Rc::new(Sphere::new(Point3D::with_values(2.0, 0.0, 0.0), 1.0));
+            ConstantMedium::new(boundary, density, Color::with_values(1.0, 1.0, 1.0))
+        }
+
+        #[test]
+        fn medium_hit_dense() {
+            //             .-*****-.
+            //            *:::::::::*
+            //   o------->X:::c:::::::*   Scattered right where it enters
+            // (0,0)  (1,0)*:(2,0):::*    a very dense medium
+            //              ''*****''
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let rec: &mut HitRecord = &mut HitRecord::default();
+
+            assert!(fog(1e9).hit(&ray, Interval::new(0.001, f64::INFINITY), rec));
+            assert!((rec.t() - 1.0).abs() < 1e-6, "Dense fog hit at {}", rec.t());
+            assert!(
+                rec.material().is_volumetric(),
+                "Fog not scattering like a volume"
+            );
+
+            // Starting inside, the medium scatters right away
+            let inside: Ray = Ray::create(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 2.0, 0.0),
+            );
+            assert!(fog(1e9).hit(&inside, Interval::new(0.001, f64::INFINITY), rec));
+            assert!(rec.t() < 0.01, "Fog around the origin of the ray skipped");
+        }
+
+        #[test]
+        fn medium_not_hit() {
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            let away: Ray = Ray::create(Point3D::new(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert!(!fog(1e9).hit(&away, Interval::new(0.001, f64::INFINITY), rec));
+
+            // Ending before the medium
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            assert!(!fog(1e9).hit(&ray, Interval::new(0.001, 0.5), rec));
+            assert!(!fog(0.0).hit(&ray, Interval::new(0.001, f64::INFINITY), rec));
+            assert_eq!(*rec, HitRecord::default(), "Hit Record not as expected");
+        }
+
+        #[test]
+        fn medium_transmittance() {
+            // Rays through the center cross 2 units of medium, passing with a chance of e^(-2σ)
+            let density = 0.5;
+            let medium = fog(density);
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(2.0, 0.0, 0.0));
+
+            let samples = 10000;
+            let mut passed = 0;
+            for _ in 0..samples {
+                let rec: &mut HitRecord = &mut HitRecord::default();
+                if !medium.hit(&ray, Interval::new(0.001, f64::INFINITY), rec) {
+                    passed += 1;
+                } else {
+                    assert!(
+                        rec.p().x() >= 1.0 - 1e-9 && rec.p().x() <= 3.0 + 1e-9,
+                        "Scattered at {} outside the medium",
+                        rec.p()
+                    );
+                }
+            }
+
+            let transmittance = f64::from(passed) / f64::from(samples);
+            assert!(
+                (transmittance - (-2.0 * density).exp()).abs() < 0.02,
+                "Transmittance {} not e^-1",
+                transmittance
+            );
+            assert!(
+                (medium.transmittance(&ray, Interval::new(0.001, f64::INFINITY))
+                    - (-2.0 * density).exp())
+                .abs()
+                    < 1e-9,
+                "Exact transmittance not e^-1"
+            );
+
+            // Halfway through the medium, only half of it dims the light
+            let half = medium.transmittance(&ray, Interval::new(0.001, 1.0));
+            assert!(
+                (half - (-density).exp()).abs() < 1e-9,
+                "Transmittance {} not e^-½",
+                half
+            );
+        }
+    }
+}
+
+// GRID MEDIUM
+pub mod grid_medium {
+    //! Module for handling volumes of varying density, like clouds or simulated smoke,
+    //! in the context of a raytracer.
+    use crate::color::Color;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::interval::*;
+    use crate::material::Material;
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fmt;
+    use std::fs::File;
+    use std::io::{self, BufReader, Read};
+    use std::path::Path;
+    use std::rc::Rc;
+
+    /// Represents densities sampled on a regular 3D grid over the unit cube,
+    /// interpolated trilinearly between the centers of the voxels.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DensityGrid {
+        nx: usize,
+        ny: usize,
+        nz: usize,
+        values: Vec<f64>,
+        max: f64,
+    }
+
+    impl DensityGrid {
+        /// Creates a new grid.
+        ///
+        /// # Arguments
+        ///
+        /// * `nx`     - How many voxels there are along x.
+        /// * `ny`     - How many voxels there are along y.
+        /// * `nz`     - How many voxels there are along z.
+        /// * `values` - The non-negative density of every voxel, x varying fastest, then y, then z.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the grid is empty, the number of values does not match it or
+        /// any of them is not finite.
+        pub fn new(nx: usize, ny: usize, nz: usize, values: Vec<f64>) -> Self {
+            assert!(nx > 0 && ny > 0 && nz > 0, "Empty density grid");
+            assert_eq!(
+                Some(values.len()),
+                nx.checked_mul(ny).and_then(|n| n.checked_mul(nz)),
+                "Densities not matching the grid"
+            );
+            assert!(
+                values.iter().all(|v| v.is_finite()),
+                "Density grid not finite"
+            );
+
+            let values: Vec<f64> = values.into_iter().map(|v| v.max(0.0)).collect();
+            let max = values.iter().copied().fold(0.0, f64::max);
+            DensityGrid {
+                nx,
+                ny,
+                nz,
+                values,
+                max,
+            }
+        }
+
+        /// Loads a grid from a file in the format of `read_grid`.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the file.
+        pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            read_grid(&mut BufReader::new(File::open(path)?))
+        }
+
+        /// Loads a grid from a headerless file of little-endian 32-bit floats,
+        /// as exported by many simulations.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the file.
+        /// * `nx`   - How many voxels there are along x.
+        /// * `ny`   - How many voxels there are along y.
+        /// * `nz`   - How many voxels there are along z.
+        pub fn load_raw<P: AsRef<Path>>(
+            path: P,
+            nx: usize,
+            ny: usize,
+            nz: usize,
+        ) -> io::Result<Self> {
+            read_raw(&mut BufReader::new(File::open(path)?), nx, ny, nz)
+        }
+
+        /// Gets how many voxels there are along x, y and z.
+        pub fn resolution(&self) -> (usize, usize, usize) {
+            (self.nx, self.ny, self.nz)
+        }
+
+        /// Gets the highest density of the grid.
+        pub fn max(&self) -> f64 {
+            self.max
+        }
+
+        /// Looks up the density of a voxel, 0 outside the grid.
+        fn voxel(&self, x: isize, y: isize, z: isize) -> f64 {
+            if x < 0 || y < 0 || z < 0 {
+                return 0.0;
+            }
+            let (x, y, z) = (x as usize, y as usize, z as usize);
+            if x >= self.nx || y >= self.ny || z >= self.nz {
+                return 0.0;
+            }
+
+            self.values[(z * self.ny + y) * self.nx + x]
+        }
+
+        /// Computes the density at a point of the unit cube, interpolated trilinearly.
+        ///
+        /// # Arguments
+        ///
+        /// * `p` - The point, each coordinate within [0, 1].
+        pub fn density(&self, p: Point3D) -> f64 {
+            // Relative to the voxel centers
+            let x = p.x() * self.nx as f64 - 0.5;
+            let y = p.y() * self.ny as f64 - 0.5;
+            let z = p.z() * self.nz as f64 - 0.5;
+            let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+            let (dx, dy, dz) = (x - x0, y - y0, z - z0);
+            let (x0, y0, z0) = (x0 as isize, y0 as isize, z0 as isize);
+
+            // Voxels beyond the edge take the density of the edge
+            let at = |i: isize, j: isize, k: isize| {
+                self.voxel(
+                    i.clamp(0, self.nx as isize - 1),
+                    j.clamp(0, self.ny as isize - 1),
+                    k.clamp(0, self.nz as isize - 1),
+                )
+            };
+            let lerp = |a: f64, b: f64, t: f64| a + t * (b - a);
+
+            let face = |k: isize| {
+                lerp(
+                    lerp(at(x0, y0, k), at(x0 + 1, y0, k), dx),
+                    lerp(at(x0, y0 + 1, k), at(x0 + 1, y0 + 1, k), dx),
+                    dy,
+                )
+            };
+            lerp(face(z0), face(z0 + 1), dz)
+        }
+    }
+
+    /// Reads a density grid from a simple binary format.
+    ///
+    /// The file starts with the text line `GRID <nx> <ny> <nz>`, followed by
+    /// nx·ny·nz little-endian 32-bit floats, x varying fastest, then y, then z.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader the file is read from.
+    pub fn read_grid<R: Read>(reader: &mut R) -> io::Result<DensityGrid> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // Header line byte by byte, the densities follow right after it
+        let mut header = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            reader.read_exact(&mut byte)?;
+            if byte[0] == b'\n' {
+                break;
+            }
+            header.push(byte[0]);
+            if header.len() > 256 {
+                return Err(invalid("Header line too long"));
+            }
+        }
+
+        let header = String::from_utf8(header).map_err(|_| invalid("Invalid header"))?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let (nx, ny, nz) = match fields.as_slice() {
+            ["GRID", nx, ny, nz] => {
+                let parse = |n: &str| n.parse::<usize>().map_err(|_| invalid("Invalid grid size"));
+                (parse(nx)?, parse(ny)?, parse(nz)?)
+            }
+            _ => return Err(invalid("Not a density grid")),
+        };
+
+        read_raw(reader, nx, ny, nz)
+    }
+
+    /// Reads nx·ny·nz little-endian 32-bit floats as a density grid,
+    /// x varying fastest, then y, then z.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The reader the densities are read from.
+    /// * `nx`     - How many voxels there are along x.
+    /// * `ny`     - How many voxels there are along y.
+    /// * `nz`     - How many voxels there are along z.
+    pub fn read_raw<R: Read>(
+        reader: &mut R,
+        nx: usize,
+        ny: usize,
+        nz: usize,
+    ) -> io::Result<DensityGrid> {
+        let length = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .filter(|&n| n > 0)
+            .and_then(|n| n.checked_mul(4))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid grid size"))?;
+
+        // Grow the densities as they arrive, so crafted sizes can't allocate more than the file holds
+        let mut bytes = Vec::new();
+        reader.take(length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Fewer densities than the grid has voxels",
+            ));
+        }
+        let values: Vec<f64> = bytes
+            .chunks_exact(4)
+            .map(|b| f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+            .collect();
+        if !values.iter().all(|v| v.is_finite()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Density not finite",
+            ));
+        }
+
+        Ok(DensityGrid::new(nx, ny, nz, values))
+    }
+
+    /// Represents a medium of varying density filling an axis-aligned box,
+    /// scattering rays by delta tracking and dimming shadow rays by ratio tracking.
+    pub struct GridMedium {
+        min: Point3D,
+        max: Point3D,
+        grid: Rc<DensityGrid>,
+        density: f64,
+        phase_function: Rc<dyn Material>,
+    }
+
+    impl GridMedium {
+        /// Creates a new medium.
+        ///
+        /// # Arguments
+        ///
+        /// * `min`            - The corner of the box with the smallest coordinates.
+        /// * `max`            - The corner of the box with the largest coordinates.
+        /// * `grid`           - The densities stretched over the box.
+        /// * `density`        - The chance of scattering per unit distance at a density of 1.
+        /// * `phase_function` - The volumetric material scattering light within the medium.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the density is not finite.
+        pub fn new(
+            min: Point3D,
+            max: Point3D,
+            grid: Rc<DensityGrid>,
+            density: f64,
+            phase_function: Rc<dyn Material>,
+        ) -> Self {
+            assert!(density.is_finite(), "Medium density not finite");
+            GridMedium {
+                min,
+                max,
+                grid,
+                density,
+                phase_function,
+            }
+        }
+
+        /// Gets the densities of the medium.
+        pub fn grid(&self) -> &DensityGrid {
+            self.grid.as_ref()
+        }
+
+        /// Gets the chance of scattering per unit distance at a density of 1.
+        pub fn density(&self) -> f64 {
+            self.density
+        }
+
+        /// Computes the chance of scattering per unit distance at a point within the box.
+        pub fn sigma_t(&self, p: Point3D) -> f64 {
+            let extent = self.max - self.min;
+            let local = p - self.min;
+            let local = Point3D::with_values(
+                local.x() / extent.x(),
+                local.y() / extent.y(),
+                local.z() / extent.z(),
+            );
+
+            self.density * self.grid.density(local)
+        }
+
+        /// Finds the part of `ray_t` the ray spends within the box by the slab method.
+        fn overlap(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+            let (mut t0, mut t1) = (ray_t.min.max(0.0), ray_t.max);
+            let axes = [
+                (
+                    r.origin().x(),
+                    r.direction().x(),
+                    self.min.x(),
+                    self.max.x(),
+                ),
+                (
+                    r.origin().y(),
+                    r.direction().y(),
+                    self.min.y(),
+                    self.max.y(),
+                ),
+                (
+                    r.origin().z(),
+                    r.direction().z(),
+                    self.min.z(),
+                    self.max.z(),
+                ),
+            ];
+
+            for (origin, direction, min, max) in axes {
+                let inv = 1.0 / direction;
+                let (mut near, mut far) = ((min - origin) * inv, (max - origin) * inv);
+                if near > far {
+                    std::mem::swap(&mut near, &mut far);
+                }
+                // NaN for rays within a slab plane keeps the previous bounds
+                t0 = if near > t0 { near } else { t0 };
+                t1 = if far < t1 { far } else { t1 };
+                if t0 >= t1 {
+                    return None;
+                }
+            }
+
+            Some((t0, t1))
+        }
+
+        /// Finds the next tentative collision with the majorant density after `t`.
+        fn step(&self, t: f64, majorant: f64) -> f64 {
+            t - (1.0 - random_f64()).ln() / majorant
+        }
+    }
+
+    impl Hittable for GridMedium {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let Some((t0, t1)) = self.overlap(r, ray_t) else {
+                return false;
+            };
+
+            // Delta tracking: collide with the majorant, keep only the real collisions
+            let majorant = self.density * self.grid.max() * r.direction().length();
+            if majorant <= 0.0 {
+                return false;
+            }
+
+            let mut t = t0;
+            loop {
+                let next = self.step(t, majorant);
+                if next >= t1 {
+                    return false;
+                }
+                // Densities too high to step through at this precision collide right away
+                let stuck = next <= t;
+                t = next;
+
+                let p = r.at(t);
+                if stuck || random_f64() * self.density * self.grid.max() < self.sigma_t(p) {
+                    // Normal and face are arbitrary, the phase function doesn't depend on them
+                    rec.t = t;
+                    rec.p = p;
+                    rec.normal = Vector3D::with_values(1.0, 0.0, 0.0);
+                    rec.front_face = true;
+                    rec.u = 0.0;
+                    rec.v = 0.0;
+                    rec.mat = Some(self.phase_function.clone());
+                    rec.color = Color::with_values(1.0, 1.0, 1.0);
+                    rec.primitive_id = 0;
+                    return true;
+                }
+            }
+        }
+
+        fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+            let Some((t0, t1)) = self.overlap(r, ray_t) else {
+                return 1.0;
+            };
+            let sigma_max = self.density * self.grid.max();
+            let majorant = sigma_max * r.direction().length();
+            if majorant <= 0.0 {
+                return 1.0;
+            }
+
+            // Ratio tracking: weigh by the chance of every tentative collision being fictitious
+            let mut transmittance = 1.0;
+            let mut t = t0;
+            loop {
+                let next = self.step(t, majorant);
+                if next >= t1 {
+                    return transmittance;
+                }
+                // Densities too high to step through at this precision let no light pass
+                if next <= t {
+                    return 0.0;
+                }
+                t = next;
+
+                transmittance *= 1.0 - self.sigma_t(r.at(t)) / sigma_max;
+
+                // Russian roulette for light mostly absorbed already
+                if transmittance < 0.1 {
+                    if random_f64() < 0.5 {
+                        return 0.0;
+                    }
+                    transmittance *= 2.0;
+                }
+            }
+        }
+    }
+
+    impl fmt::Debug for GridMedium {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("GridMedium")
+                .field("min", &self.min)
+                .field("max", &self.max)
+                .field("grid", &self.grid.resolution())
+                .field("density", &self.density)
+                .field("phase_function", &Rc::as_ptr(&self.phase_function))
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::grid_medium::*;
+        use crate::material::HenyeyGreenstein;
+        use std::io::Cursor;
+
+        /// A unit cube of smoke getting denser along +x, from 0 to 2 at its far side.
+        fn smoke() -> GridMedium {
+            let values = (0..4)
+                .flat_map(|_| [0.25, 0.75, 1.25, 1.75])
+                .collect::<Vec<f64>>();
+            let grid = DensityGrid::new(4, 2, 2, values);
+            GridMedium::new(
+                Point3D::new(),
+                Point3D::with_values(1.0, 1.0, 1.0),
+                Rc::new(grid),
+                1.0,
+                Rc::new(HenyeyGreenstein::new(
+                    Color::with_values(1.0, 1.0, 1.0),
+                    0.5,
+                )),
+            )
+        }
+
+        #[test]
+        fn density_grid_lookup() {
+            //  density
+            //  1.75 |           .-*    Interpolated linearly between voxel
+            //       |       .-*        centers, constant beyond the outer ones
+            //  0.25 *--*-'
+            //       0 1/8  ...   7/8 1  x
+            let medium = smoke();
+            let grid = medium.grid();
+
+            assert_eq!(grid.resolution(), (4, 2, 2));
+            assert_eq!(grid.max(), 1.75);
+            for (x, expected) in [
+                (0.0, 0.25),
+                (0.125, 0.25),
+                (0.5, 1.0),
+                (0.875, 1.75),
+                (1.0, 1.75),
+            ] {
+                let density = grid.density(Point3D::with_values(x, 0.3, 0.6));
+                assert!(
+                    (density - expected).abs() < 1e-12,
+                    "Density {} at x={} not {}",
+                    density,
+                    x,
+                    expected
+                );
+            }
+            assert!((medium.sigma_t(Point3D::with_values(0.25, 0.5, 0.5)) - 0.5).abs() < 1e-12);
+        }
+
+        #[test]
+        fn density_grid_read() {
+            let mut data = b"GRID 2 1 1\n".to_vec();
+            data.extend(0.5f32.to_le_bytes());
+            data.extend(2.0f32.to_le_bytes());
+
+            let grid = read_grid(&mut Cursor::new(data.clone())).unwrap();
+            assert_eq!(grid.resolution(), (2, 1, 1));
+            assert_eq!(grid.max(), 2.0);
+
+            let raw = read_raw(&mut Cursor::new(data[11..].to_vec()), 2, 1, 1).unwrap();
+            assert_eq!(raw, grid, "Raw densities not read like the grid");
+
+            assert!(read_grid(&mut Cursor::new(b"VOXELS 2 1 1\n".to_vec())).is_err());
+            assert!(
+                read_grid(&mut Cursor::new(data[..14].to_vec())).is_err(),
+                "Truncated grid read"
+            );
+            assert!(
+                read_raw(&mut Cursor::new(Vec::new()), 0, 1, 1).is_err(),
+                "Empty grid read"
+            );
+
+            let mut infinite = f32::INFINITY.to_le_bytes().to_vec();
+            infinite.extend(0.5f32.to_le_bytes());
+            assert!(
+                read_raw(&mut Cursor::new(infinite), 2, 1, 1).is_err(),
+                "Infinite density read"
+            );
+
+            // Crafted sizes fail instead of overflowing or allocating terabytes
+            for header in ["GRID 4611686018427387904 1 1\n", "GRID 100000 100000 100\n"] {
+                let mut crafted = header.as_bytes().to_vec();
+                crafted.extend(&data[11..]);
+                assert!(
+                    read_grid(&mut Cursor::new(crafted)).is_err(),
+                    "Read {}",
+                    header
+                );
+            }
+        }
+
+        #[test]
+        fn grid_medium_tracking() {
+            //       +-------+
+            //  o--->|:::::::|---->   Optical depth ∫σ dx = 1 along the x axis,
+            //       |:::::::|        transmittance e^-1 by both trackers
+            //       +-------+
+            //     (0,0)   (1,0)
+            let medium = smoke();
+            let ray = Ray::create(
+                Point3D::with_values(-1.0, 0.5, 0.5),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+            let ray_t = Interval::new(0.001, f64::INFINITY);
+            let expected = (-1.0f64).exp();
+
+            let samples = 20000;
+            let (mut passed, mut ratio) = (0, 0.0);
+            for _ in 0..samples {
+                let mut rec = HitRecord::default();
+                if medium.hit(&ray, ray_t, &mut rec) {
+                    assert!(
+                        rec.p().x() >= 0.0 && rec.p().x() <= 1.0,
+                        "Hit {} outside",
+                        rec.p()
+                    );
+                    assert!(rec.material().is_volumetric());
+                } else {
+                    passed += 1;
+                }
+                ratio += medium.transmittance(&ray, ray_t) / f64::from(samples);
+            }
+
+            let delta = f64::from(passed) / f64::from(samples);
+            assert!(
+                (delta - expected).abs() < 0.015,
+                "Delta tracking passed {}",
+                delta
+            );
+            assert!(
+                (ratio - expected).abs() < 0.015,
+                "Ratio tracking passed {}",
+                ratio
+            );
+
+            let away = Ray::create(
+                Point3D::with_values(-1.0, 0.5, 0.5),
+                Vector3D::with_values(-1.0, 0.0, 0.0),
+            );
+            assert_eq!(medium.transmittance(&away, ray_t), 1.0);
+            assert!(!medium.hit(&away, ray_t, &mut HitRecord::default()));
+
+            // Too dense to step through, yet tracking ends
+            let smoke = smoke();
+            let dense = GridMedium::new(
+                Point3D::new(),
+                Point3D::with_values(1.0, 1.0, 1.0),
+                Rc::new(smoke.grid().clone()),
+                1e300,
+                smoke.phase_function.clone(),
+            );
+            assert_eq!(dense.transmittance(&ray, ray_t), 0.0);
+            let mut rec = HitRecord::default();
+            assert!(dense.hit(&ray, ray_t, &mut rec));
+            assert!((rec.t() - 1.0).abs() < 1e-9, "Hit at {}", rec.t());
+        }
+
+        #[test]
+        #[should_panic]
+        fn density_grid_infinite() {
+            DensityGrid::new(2, 1, 1, vec![f64::INFINITY, 0.5]);
+        }
+    }
+}
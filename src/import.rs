@@ -0,0 +1,2745 @@
+//! Module for importing meshes and scenes from common file formats in the context of a raytracer.
+
+// --- WAVEFRONT OBJ -----------------------------------------------------------
+
+pub mod obj {
+    //! Module for Wavefront OBJ meshes and their MTL material libraries.
+
+    use crate::color::Color;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::mesh::TriangleMesh;
+    use crate::hittable::Hittable;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal, Phong};
+    use crate::texture::image::ImageTexture;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader};
+    use std::path::Path;
+    use std::rc::Rc;
+
+    /// Creates the error of invalid data on the given line.
+    fn invalid(line: usize, message: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Line {}: {}", line, message),
+        )
+    }
+
+    /// Parses the numbers following a statement, at least `min` and at most `max` of them.
+    fn numbers(line: usize, tokens: &[&str], min: usize, max: usize) -> io::Result<Vec<f64>> {
+        if tokens.len() < min {
+            return Err(invalid(line, "Missing numbers"));
+        }
+        tokens
+            .iter()
+            .take(max)
+            .map(|t| {
+                t.parse::<f64>()
+                    .map_err(|_| invalid(line, "Invalid number"))
+            })
+            .collect()
+    }
+
+    /// Loads an OBJ file together with its material libraries, ready to render.
+    ///
+    /// Material libraries are looked up next to the OBJ file, texture maps next to their library.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the .obj file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a triangle mesh for every group and material of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<HittableList> {
+        let obj = Obj::load(&path)?;
+        let directory = path.as_ref().parent().unwrap_or(Path::new(""));
+
+        let mut materials = HashMap::new();
+        for library in obj.material_libraries() {
+            let library = directory.join(library);
+            let textures = library.parent().unwrap_or(Path::new(""));
+            for material in read_mtl(&mut BufReader::new(File::open(&library)?))? {
+                materials.insert(material.name().to_string(), material.to_material(textures)?);
+            }
+        }
+
+        Ok(obj.into_hittables(&materials))
+    }
+
+    /// Represents the faces of one group of an OBJ file sharing one material.
+    #[derive(Debug)]
+    pub struct ObjMesh {
+        name: String,
+        material: Option<String>,
+        mesh: TriangleMesh,
+    }
+
+    impl ObjMesh {
+        /// Gets the name of the group or object the faces belong to, empty outside of any.
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Gets the name of the material of the faces, if they have one.
+        pub fn material(&self) -> Option<&str> {
+            self.material.as_deref()
+        }
+
+        /// Gets the triangles of the faces.
+        pub fn mesh(&self) -> &TriangleMesh {
+            &self.mesh
+        }
+    }
+
+    /// The vertices of one mesh while reading, every combination of indices once.
+    #[derive(Default)]
+    struct MeshBuilder {
+        positions: Vec<Point3D>,
+        normals: Vec<Option<Vector3D>>,
+        uvs: Vec<Option<(f64, f64)>>,
+        vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+        indices: Vec<[u32; 3]>,
+    }
+
+    impl MeshBuilder {
+        /// Builds the mesh, with normals only if every vertex has one.
+        fn build(self) -> TriangleMesh {
+            let mut mesh = TriangleMesh::new(self.positions, self.indices);
+            if self.normals.iter().all(Option::is_some) {
+                mesh = mesh.with_normals(self.normals.into_iter().flatten().collect());
+            }
+            if self.uvs.iter().any(Option::is_some) {
+                mesh = mesh.with_uvs(
+                    self.uvs
+                        .into_iter()
+                        .map(|uv| uv.unwrap_or((0.0, 0.0)))
+                        .collect(),
+                );
+            }
+
+            mesh
+        }
+    }
+
+    /// Represents the contents of an OBJ file.
+    #[derive(Debug)]
+    pub struct Obj {
+        meshes: Vec<ObjMesh>,
+        material_libraries: Vec<String>,
+    }
+
+    impl Obj {
+        /// Loads an OBJ file, without its material libraries.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the .obj file.
+        pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            Self::read(&mut BufReader::new(File::open(path)?))
+        }
+
+        /// Reads the positions, texture coordinates, normals, faces, groups and
+        /// materials of an OBJ file.
+        ///
+        /// Faces with more than three vertices are split into a fan of triangles,
+        /// assuming they are convex. Lines, points and smoothing groups are skipped.
+        ///
+        /// # Arguments
+        ///
+        /// * `reader` - The contents of an .obj file.
+        pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Self> {
+            let mut positions: Vec<Point3D> = Vec::new();
+            let mut normals: Vec<Vector3D> = Vec::new();
+            let mut uvs: Vec<(f64, f64)> = Vec::new();
+            let mut material_libraries = Vec::new();
+
+            let mut group = String::new();
+            let mut material: Option<String> = None;
+            let mut builders: Vec<(String, Option<String>, MeshBuilder)> = Vec::new();
+            let mut current: Option<usize> = None;
+
+            for (number, line) in reader.lines().enumerate() {
+                let number = number + 1;
+                let line = line?;
+                let content = line.split('#').next().unwrap_or("").trim();
+                let tokens: Vec<&str> = content.split_whitespace().collect();
+                let rest = |keyword: &str| content[keyword.len()..].trim().to_string();
+
+                match tokens.first().copied() {
+                    Some("v") => {
+                        let v = numbers(number, &tokens[1..], 3, 3)?;
+                        positions.push(Point3D::with_values(v[0], v[1], v[2]));
+                    }
+                    Some("vn") => {
+                        let n = numbers(number, &tokens[1..], 3, 3)?;
+                        normals.push(Vector3D::with_values(n[0], n[1], n[2]));
+                    }
+                    Some("vt") => {
+                        let t = numbers(number, &tokens[1..], 1, 2)?;
+                        uvs.push((t[0], t.get(1).copied().unwrap_or(0.0)));
+                    }
+                    Some("g") | Some("o") => {
+                        group = rest(tokens[0]);
+                        current = None;
+                    }
+                    Some("usemtl") => {
+                        material = Some(rest("usemtl"));
+                        current = None;
+                    }
+                    Some("mtllib") => material_libraries.push(rest("mtllib")),
+                    Some("f") => {
+                        if tokens.len() < 4 {
+                            return Err(invalid(number, "Face with less than three vertices"));
+                        }
+
+                        // Faces go into the mesh of the current group and material
+                        let index = match current {
+                            Some(index) => index,
+                            None => {
+                                let found = builders
+                                    .iter()
+                                    .position(|(g, m, _)| *g == group && *m == material);
+                                let index = found.unwrap_or_else(|| {
+                                    builders.push((
+                                        group.clone(),
+                                        material.clone(),
+                                        MeshBuilder::default(),
+                                    ));
+                                    builders.len() - 1
+                                });
+                                current = Some(index);
+                                index
+                            }
+                        };
+                        let builder = &mut builders[index].2;
+
+                        let mut face = Vec::with_capacity(tokens.len() - 1);
+                        for token in &tokens[1..] {
+                            let mut parts = token.split('/');
+                            let resolve =
+                                |part: Option<&str>, count: usize| -> io::Result<Option<usize>> {
+                                    match part {
+                                        None | Some("") => Ok(None),
+                                        Some(part) => {
+                                            let i: i64 = part
+                                                .parse()
+                                                .map_err(|_| invalid(number, "Invalid index"))?;
+                                            // Indices start at 1, negative ones count back from the last
+                                            let i = if i < 0 { count as i64 + i } else { i - 1 };
+                                            if i < 0 || i >= count as i64 {
+                                                return Err(invalid(number, "Index out of range"));
+                                            }
+                                            Ok(Some(i as usize))
+                                        }
+                                    }
+                                };
+                            let v = resolve(parts.next(), positions.len())?
+                                .ok_or_else(|| invalid(number, "Missing vertex index"))?;
+                            let vt = resolve(parts.next(), uvs.len())?;
+                            let vn = resolve(parts.next(), normals.len())?;
+
+                            let vertex =
+                                *builder.vertices.entry((v, vt, vn)).or_insert_with(|| {
+                                    builder.positions.push(positions[v]);
+                                    builder.uvs.push(vt.map(|i| uvs[i]));
+                                    builder.normals.push(vn.map(|i| normals[i]));
+                                    (builder.positions.len() - 1) as u32
+                                });
+                            face.push(vertex);
+                        }
+
+                        for i in 1..face.len() - 1 {
+                            builder.indices.push([face[0], face[i], face[i + 1]]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let meshes = builders
+                .into_iter()
+                .map(|(name, material, builder)| ObjMesh {
+                    name,
+                    material,
+                    mesh: builder.build(),
+                })
+                .collect();
+
+            Ok(Obj {
+                meshes,
+                material_libraries,
+            })
+        }
+
+        /// Gets a mesh for every group and material of the file, in the order they first appear.
+        pub fn meshes(&self) -> &[ObjMesh] {
+            &self.meshes
+        }
+
+        /// Gets the file names of the material libraries the file refers to.
+        pub fn material_libraries(&self) -> &[String] {
+            &self.material_libraries
+        }
+
+        /// Turns the meshes into objects to render, giving them their materials.
+        ///
+        /// # Arguments
+        ///
+        /// * `materials` - The materials by their names, meshes with other materials get none.
+        pub fn into_hittables(self, materials: &HashMap<String, Rc<dyn Material>>) -> HittableList {
+            self.meshes
+                .into_iter()
+                .map(|obj_mesh| -> Rc<dyn Hittable> {
+                    let mat = obj_mesh.material.as_ref().and_then(|m| materials.get(m));
+                    match mat {
+                        Some(mat) => Rc::new(obj_mesh.mesh.with_material(Rc::clone(mat))),
+                        None => Rc::new(obj_mesh.mesh),
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// Represents a material of an MTL material library.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct MtlMaterial {
+        name: String,
+        diffuse: Color,
+        specular: Color,
+        emission: Color,
+        shininess: f64,
+        refractive_index: f64,
+        dissolve: f64,
+        illumination: u32,
+        metallic: f64,
+        roughness: f64,
+        diffuse_map: Option<String>,
+    }
+
+    impl MtlMaterial {
+        /// Creates a new material with the values of statements missing from a library.
+        fn new(name: String) -> Self {
+            MtlMaterial {
+                name,
+                diffuse: Lambertian::DEFAULT.albedo(),
+                specular: Color::new(),
+                emission: Color::new(),
+                shininess: 1.0,
+                refractive_index: 1.5,
+                dissolve: 1.0,
+                illumination: 2,
+                metallic: 0.0,
+                roughness: 0.0,
+                diffuse_map: None,
+            }
+        }
+
+        /// Gets the name of the material.
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Gets the diffuse color (Kd).
+        pub fn diffuse(&self) -> Color {
+            self.diffuse
+        }
+
+        /// Gets the specular color (Ks).
+        pub fn specular(&self) -> Color {
+            self.specular
+        }
+
+        /// Gets the emitted radiance (Ke).
+        pub fn emission(&self) -> Color {
+            self.emission
+        }
+
+        /// Gets the specular exponent (Ns).
+        pub fn shininess(&self) -> f64 {
+            self.shininess
+        }
+
+        /// Gets the index of refraction (Ni), 1.5 if not given.
+        pub fn refractive_index(&self) -> f64 {
+            self.refractive_index
+        }
+
+        /// Gets the opacity (d, or 1 - Tr).
+        pub fn dissolve(&self) -> f64 {
+            self.dissolve
+        }
+
+        /// Gets the illumination model (illum).
+        pub fn illumination(&self) -> u32 {
+            self.illumination
+        }
+
+        /// Gets the file name of the diffuse texture map (map_Kd), if there is one.
+        pub fn diffuse_map(&self) -> Option<&str> {
+            self.diffuse_map.as_deref()
+        }
+
+        /// Turns the material into the closest material of the crate.
+        ///
+        /// Emitting materials become diffuse lights, transparent or refracting ones
+        /// (illum 4, 6, 7 or 9) dielectrics, metallic ones (Pm) metals fuzzed by their
+        /// roughness (Pr), ones with a highlight Phong and all others Lambertian.
+        ///
+        /// # Arguments
+        ///
+        /// * `directory` - The directory texture maps are looked up in.
+        ///
+        /// # Returns
+        ///
+        /// Returns the material, or the error of loading its diffuse texture map
+        /// if that is missing or in a format `ImageTexture::load` can't read.
+        pub fn to_material(&self, directory: &Path) -> io::Result<Rc<dyn Material>> {
+            if !self.emission.near_zero() {
+                return Ok(Rc::new(DiffuseLight::new(self.emission)));
+            }
+            if self.dissolve < 1.0 || matches!(self.illumination, 4 | 6 | 7 | 9) {
+                return Ok(Rc::new(Dielectric::new(self.refractive_index)));
+            }
+            if self.metallic >= 0.5 {
+                return Ok(Rc::new(Metal::new(self.diffuse, self.roughness)));
+            }
+
+            let texture = match &self.diffuse_map {
+                Some(map) => Some(ImageTexture::load(directory.join(map)).map_err(|e| {
+                    io::Error::new(e.kind(), format!("Texture map {}: {}", map, e))
+                })?),
+                None => None,
+            };
+            Ok(if !self.specular.near_zero() && self.illumination > 1 {
+                let phong = Phong::new(self.diffuse, self.specular, self.shininess);
+                match texture {
+                    Some(texture) => Rc::new(phong.with_texture(Rc::new(texture))),
+                    None => Rc::new(phong),
+                }
+            } else {
+                let lambertian = Lambertian::new(self.diffuse);
+                match texture {
+                    Some(texture) => Rc::new(lambertian.with_texture(Rc::new(texture))),
+                    None => Rc::new(lambertian),
+                }
+            })
+        }
+    }
+
+    /// Reads the materials of an MTL material library.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The contents of an .mtl file.
+    pub fn read_mtl<R: BufRead>(reader: &mut R) -> io::Result<Vec<MtlMaterial>> {
+        let mut materials: Vec<MtlMaterial> = Vec::new();
+
+        for (number, line) in reader.lines().enumerate() {
+            let number = number + 1;
+            let line = line?;
+            let content = line.split('#').next().unwrap_or("").trim();
+            let tokens: Vec<&str> = content.split_whitespace().collect();
+            let Some(&keyword) = tokens.first() else {
+                continue;
+            };
+
+            if keyword == "newmtl" {
+                materials.push(MtlMaterial::new(content[6..].trim().to_string()));
+                continue;
+            }
+            let Some(material) = materials.last_mut() else {
+                return Err(invalid(number, "Statement before the first material"));
+            };
+
+            // Colors given by a single value are gray
+            let color = || -> io::Result<Color> {
+                let c = numbers(number, &tokens[1..], 1, 3)?;
+                Ok(match c[..] {
+                    [r, g, b] => Color::with_values(r, g, b),
+                    _ => Color::with_values(c[0], c[0], c[0]),
+                })
+            };
+            let value = || numbers(number, &tokens[1..], 1, 1).map(|v| v[0]);
+
+            match keyword {
+                "Kd" => material.diffuse = color()?,
+                "Ks" => material.specular = color()?,
+                "Ke" => material.emission = color()?,
+                "Ns" => material.shininess = value()?,
+                "Ni" => material.refractive_index = value()?,
+                "d" => material.dissolve = value()?,
+                "Tr" => material.dissolve = 1.0 - value()?,
+                "Pm" => material.metallic = value()?,
+                "Pr" => material.roughness = value()?,
+                "illum" => material.illumination = value()? as u32,
+                "map_Kd" => {
+                    // Skip options like `-s 1 1 1` or `-clamp on` before the file name
+                    let mut i = 1;
+                    while i < tokens.len() && tokens[i].starts_with('-') {
+                        i += 1;
+                        while i < tokens.len()
+                            && (tokens[i].parse::<f64>().is_ok()
+                                || tokens[i] == "on"
+                                || tokens[i] == "off")
+                        {
+                            i += 1;
+                        }
+                    }
+                    if i >= tokens.len() {
+                        return Err(invalid(number, "Missing texture map file name"));
+                    }
+                    material.diffuse_map = Some(tokens[i..].join(" "));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(materials)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::import::obj::{read_mtl, MtlMaterial, Obj};
+        use crate::interval::Interval;
+        use crate::material::{Lambertian, Material};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::collections::HashMap;
+        use std::path::Path;
+        use std::rc::Rc;
+
+        const QUADS: &str = "# Two unit quads stacked along z
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g front
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g back
+usemtl blue
+v 0 0 -1
+v 1 0 -1
+v 1 1 -1
+v 0 1 -1
+f -4//1 -3//1 -2//1 -1//1
+";
+
+        #[test]
+        fn obj_read() {
+            let obj = Obj::read(&mut QUADS.as_bytes()).unwrap();
+
+            assert_eq!(obj.material_libraries(), ["scene.mtl"]);
+            assert_eq!(obj.meshes().len(), 2, "Not a mesh per group and material");
+            let front = &obj.meshes()[0];
+            assert_eq!((front.name(), front.material()), ("front", Some("red")));
+            assert_eq!(front.mesh().len(), 2, "Quad not split into two triangles");
+            assert_eq!(front.mesh().positions().len(), 4, "Vertices not shared");
+            assert_eq!(front.mesh().uvs()[2], (1.0, 1.0));
+            assert_eq!(front.mesh().normals().len(), 4);
+
+            let back = &obj.meshes()[1];
+            assert_eq!(back.material(), Some("blue"));
+            assert!(back.mesh().uvs().is_empty(), "Missing uvs made up");
+            assert_eq!(
+                back.mesh().positions()[0],
+                Point3D::with_values(0.0, 0.0, -1.0),
+                "Negative indices not relative"
+            );
+        }
+
+        #[test]
+        fn obj_read_invalid() {
+            for text in [
+                "v 0 0 0\nv 1 0 0\nf 1 2\n",
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0\n",
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n",
+                "v 0 zero 0\n",
+            ] {
+                assert!(
+                    Obj::read(&mut text.as_bytes()).is_err(),
+                    "Read invalid {:?}",
+                    text
+                );
+            }
+        }
+
+        #[test]
+        fn obj_into_hittables() {
+            //     +-----+ z=0 red quad, hit first
+            //  o->|     |
+            //     +-----+ z=-1 blue quad
+            let obj = Obj::read(&mut QUADS.as_bytes()).unwrap();
+            let red: Rc<dyn Material> = Rc::new(Lambertian::new(Color::with_values(1.0, 0.0, 0.0)));
+            let materials = HashMap::from([("red".to_string(), Rc::clone(&red))]);
+            let hittables = obj.into_hittables(&materials);
+            assert_eq!(hittables.len(), 2);
+
+            let ray = Ray::create(
+                Point3D::with_values(0.5, 0.25, 1.0),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            assert!(
+                hittables.hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Quad not hit"
+            );
+            assert_eq!(rec.t(), 1.0);
+            assert_eq!((rec.u(), rec.v()), (0.5, 0.25));
+            let up = Vector3D::with_values(0.0, 0.0, 1.0);
+            assert_eq!(rec.material().eval(rec, up, up), red.eval(rec, up, up));
+
+            assert!(hittables[1].hit(&ray, Interval::new(0.0, 10.0), rec));
+            assert_eq!(rec.t(), 2.0);
+            assert_eq!(
+                rec.material().eval(rec, up, up),
+                Lambertian::DEFAULT.eval(rec, up, up),
+                "Unknown material not the default"
+            );
+        }
+
+        #[test]
+        fn mtl_read() {
+            let text = "newmtl glass
+Kd 1 1 1
+Ni 1.45
+d 0.5
+
+newmtl plastic
+Kd 0.8 0.1 0.1
+Ks 0.5
+Ns 250
+illum 2
+map_Kd -s 2 2 1 -clamp on wood grain.ppm
+
+newmtl lamp
+Ke 4 4 4
+";
+            let materials = read_mtl(&mut text.as_bytes()).unwrap();
+            assert_eq!(materials.len(), 3);
+
+            let glass = &materials[0];
+            assert_eq!((glass.refractive_index(), glass.dissolve()), (1.45, 0.5));
+            let plastic = &materials[1];
+            assert_eq!(plastic.diffuse(), Color::with_values(0.8, 0.1, 0.1));
+            assert_eq!(plastic.specular(), Color::with_values(0.5, 0.5, 0.5));
+            assert_eq!((plastic.shininess(), plastic.illumination()), (250.0, 2));
+            assert_eq!(plastic.diffuse_map(), Some("wood grain.ppm"));
+            assert_eq!(materials[2].emission(), Color::with_values(4.0, 4.0, 4.0));
+
+            // Mapped to a dielectric, a highlight and a light
+            let rec = HitRecord::new(
+                Point3D::new(),
+                Vector3D::with_values(0.0, 0.0, 1.0),
+                1.0,
+                true,
+            );
+            let wo = Vector3D::with_values(0.0, 0.6, 0.8);
+            let wi = Vector3D::with_values(0.0, -0.6, 0.8);
+            let directory = Path::new("missing");
+            assert!(
+                glass.to_material(directory).unwrap().is_specular(),
+                "Glass not refracting"
+            );
+            assert!(
+                plastic.to_material(directory).is_err(),
+                "Missing texture map left out"
+            );
+            let untextured = MtlMaterial {
+                diffuse_map: None,
+                ..plastic.clone()
+            };
+            let untextured = untextured.to_material(directory).unwrap();
+            let highlight = untextured.eval(&rec, wo, wi);
+            let diffuse = untextured.eval(&rec, wo, -wo);
+            assert!(highlight.x() > diffuse.x(), "Plastic without highlight");
+            assert_eq!(
+                materials[2].to_material(directory).unwrap().emitted(&rec),
+                Color::with_values(4.0, 4.0, 4.0)
+            );
+
+            assert!(read_mtl(&mut "Kd 1 1 1\n".as_bytes()).is_err());
+            assert!(read_mtl(&mut "newmtl a\nmap_Kd -clamp on\n".as_bytes()).is_err());
+        }
+    }
+}
+
+// --- PLY ---------------------------------------------------------------------
+
+pub mod ply {
+    //! Module for meshes in the Stanford polygon file format (PLY).
+
+    use crate::color::Color;
+    use crate::hittable::mesh::TriangleMesh;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader};
+    use std::path::Path;
+
+    /// Creates the error of invalid data.
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+
+    /// Converts a value read as a length or vertex index into an integer.
+    fn whole(value: f64) -> io::Result<u32> {
+        if value >= 0.0 && value <= f64::from(u32::MAX) && value.fract() == 0.0 {
+            Ok(value as u32)
+        } else {
+            Err(invalid("Invalid list length or index"))
+        }
+    }
+
+    /// Represents the type of a property.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Scalar {
+        Int8,
+        UInt8,
+        Int16,
+        UInt16,
+        Int32,
+        UInt32,
+        Float32,
+        Float64,
+    }
+
+    impl Scalar {
+        /// Parses the name of a type, in the old or the sized form.
+        fn parse(name: &str) -> io::Result<Self> {
+            Ok(match name {
+                "char" | "int8" => Scalar::Int8,
+                "uchar" | "uint8" => Scalar::UInt8,
+                "short" | "int16" => Scalar::Int16,
+                "ushort" | "uint16" => Scalar::UInt16,
+                "int" | "int32" => Scalar::Int32,
+                "uint" | "uint32" => Scalar::UInt32,
+                "float" | "float32" => Scalar::Float32,
+                "double" | "float64" => Scalar::Float64,
+                _ => return Err(invalid("Unknown property type")),
+            })
+        }
+
+        /// Gets the largest value of integer types, `None` for floating point ones.
+        fn max(self) -> Option<f64> {
+            match self {
+                Scalar::Int8 => Some(127.0),
+                Scalar::UInt8 => Some(255.0),
+                Scalar::Int16 => Some(32767.0),
+                Scalar::UInt16 => Some(65535.0),
+                Scalar::Int32 => Some(2147483647.0),
+                Scalar::UInt32 => Some(4294967295.0),
+                Scalar::Float32 | Scalar::Float64 => None,
+            }
+        }
+    }
+
+    /// Represents a property of an element, a list if it has the type of its length.
+    #[derive(Debug)]
+    struct Property {
+        name: String,
+        kind: Scalar,
+        count: Option<Scalar>,
+    }
+
+    /// Represents the declaration of an element, like vertex or face.
+    #[derive(Debug)]
+    struct Element {
+        name: String,
+        count: usize,
+        properties: Vec<Property>,
+    }
+
+    /// The values after the header, as text or in binary of either byte order.
+    enum Body<'a> {
+        Ascii(std::str::SplitAsciiWhitespace<'a>),
+        Binary { bytes: &'a [u8], big_endian: bool },
+    }
+
+    impl Body<'_> {
+        /// Reads the next value of the given type.
+        fn read(&mut self, kind: Scalar) -> io::Result<f64> {
+            match self {
+                Body::Ascii(tokens) => tokens
+                    .next()
+                    .ok_or_else(|| invalid("Missing values"))?
+                    .parse::<f64>()
+                    .map_err(|_| invalid("Invalid value")),
+                Body::Binary { bytes, big_endian } => {
+                    let size = match kind {
+                        Scalar::Int8 | Scalar::UInt8 => 1,
+                        Scalar::Int16 | Scalar::UInt16 => 2,
+                        Scalar::Int32 | Scalar::UInt32 | Scalar::Float32 => 4,
+                        Scalar::Float64 => 8,
+                    };
+                    if bytes.len() < size {
+                        return Err(invalid("Missing values"));
+                    }
+                    let mut value = [0u8; 8];
+                    value[..size].copy_from_slice(&bytes[..size]);
+                    if *big_endian {
+                        value[..size].reverse();
+                    }
+                    *bytes = &bytes[size..];
+
+                    Ok(match kind {
+                        Scalar::Int8 => f64::from(value[0] as i8),
+                        Scalar::UInt8 => f64::from(value[0]),
+                        Scalar::Int16 => f64::from(i16::from_le_bytes([value[0], value[1]])),
+                        Scalar::UInt16 => f64::from(u16::from_le_bytes([value[0], value[1]])),
+                        Scalar::Int32 => {
+                            f64::from(i32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                        }
+                        Scalar::UInt32 => {
+                            f64::from(u32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                        }
+                        Scalar::Float32 => {
+                            f64::from(f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                        }
+                        Scalar::Float64 => f64::from_le_bytes(value),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Loads a mesh from a PLY file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the .ply file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<TriangleMesh> {
+        read_ply(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a mesh from a PLY file, in ASCII or binary of either byte order.
+    ///
+    /// Vertices may come with normals (nx, ny, nz), surface coordinates (u, v or s, t)
+    /// and colors (red, green, blue). Colors of integer types are linearized with the
+    /// inverse of the gamma images get written with, floating point ones taken as linear.
+    /// Faces with more than three vertices are split into a fan of triangles,
+    /// other elements are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The contents of a .ply file.
+    pub fn read_ply<R: BufRead>(reader: &mut R) -> io::Result<TriangleMesh> {
+        // Header lines up to end_header
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim_end() != "ply" {
+            return Err(invalid("Not a PLY file"));
+        }
+
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("Missing end of header"));
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens[..] {
+                ["end_header"] => break,
+                ["format", name, _] => format = Some(name.to_string()),
+                ["element", name, count] => elements.push(Element {
+                    name: name.to_string(),
+                    count: count
+                        .parse()
+                        .map_err(|_| invalid("Invalid element count"))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count, kind, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("Property before the first element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::parse(kind)?,
+                        count: Some(Scalar::parse(count)?),
+                    }),
+                ["property", kind, name] => elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("Property before the first element"))?
+                    .properties
+                    .push(Property {
+                        name: name.to_string(),
+                        kind: Scalar::parse(kind)?,
+                        count: None,
+                    }),
+                _ => {}
+            }
+        }
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut body = match format.as_deref() {
+            Some("ascii") => Body::Ascii(
+                std::str::from_utf8(&bytes)
+                    .map_err(|_| invalid("Invalid ASCII values"))?
+                    .split_ascii_whitespace(),
+            ),
+            Some("binary_little_endian") => Body::Binary {
+                bytes: &bytes,
+                big_endian: false,
+            },
+            Some("binary_big_endian") => Body::Binary {
+                bytes: &bytes,
+                big_endian: true,
+            },
+            _ => return Err(invalid("Unknown format")),
+        };
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut colors = Vec::new();
+        let mut indices: Vec<[u32; 3]> = Vec::new();
+
+        for element in &elements {
+            let find = |names: &[&str]| {
+                element
+                    .properties
+                    .iter()
+                    .position(|p| names.contains(&p.name.as_str()))
+            };
+
+            let vertex = element.name == "vertex";
+            let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+            let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+            let uv = [
+                find(&["u", "s", "texture_u", "texture_s"]),
+                find(&["v", "t", "texture_v", "texture_t"]),
+            ];
+            let color = [find(&["red"]), find(&["green"]), find(&["blue"])];
+            let face_indices = find(&["vertex_indices", "vertex_index"]);
+            if vertex && position.iter().any(Option::is_none) {
+                return Err(invalid("Vertices without positions"));
+            }
+
+            // Nothing to read, however many of them there are
+            if element.properties.is_empty() {
+                continue;
+            }
+
+            let mut values = vec![0.0; element.properties.len()];
+            let mut face = Vec::new();
+            for _ in 0..element.count {
+                for (i, property) in element.properties.iter().enumerate() {
+                    match property.count {
+                        None => values[i] = body.read(property.kind)?,
+                        Some(count) => {
+                            let length = whole(body.read(count)?)?;
+                            let list = (0..length)
+                                .map(|_| body.read(property.kind))
+                                .collect::<io::Result<Vec<f64>>>()?;
+                            if element.name == "face" && Some(i) == face_indices {
+                                face = list;
+                            }
+                        }
+                    }
+                }
+
+                let get = |index: Option<usize>| index.map(|i| values[i]);
+                if vertex {
+                    let [x, y, z] = position.map(|p| get(p).unwrap_or(0.0));
+                    positions.push(Point3D::with_values(x, y, z));
+                    if let [Some(x), Some(y), Some(z)] = normal.map(get) {
+                        normals.push(Vector3D::with_values(x, y, z));
+                    }
+                    if let [Some(u), Some(v)] = uv.map(get) {
+                        uvs.push((u, v));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color.map(get) {
+                        let linear = |i: Option<usize>, value: f64| match i
+                            .and_then(|i| element.properties[i].kind.max())
+                        {
+                            Some(max) => (value / max) * (value / max),
+                            None => value,
+                        };
+                        colors.push(Color::with_values(
+                            linear(color[0], r),
+                            linear(color[1], g),
+                            linear(color[2], b),
+                        ));
+                    }
+                } else if element.name == "face" && face.len() >= 3 {
+                    let face = face
+                        .iter()
+                        .map(|&i| whole(i))
+                        .collect::<io::Result<Vec<u32>>>()?;
+                    for i in 1..face.len() - 1 {
+                        indices.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+            }
+        }
+
+        if indices
+            .iter()
+            .flatten()
+            .any(|&i| i as usize >= positions.len())
+        {
+            return Err(invalid("Face refers to a vertex that doesn't exist"));
+        }
+
+        let count = positions.len();
+        let mut mesh = TriangleMesh::new(positions, indices);
+        if count > 0 && normals.len() == count {
+            mesh = mesh.with_normals(normals);
+        }
+        if count > 0 && uvs.len() == count {
+            mesh = mesh.with_uvs(uvs);
+        }
+        if count > 0 && colors.len() == count {
+            mesh = mesh.with_colors(colors);
+        }
+
+        Ok(mesh)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::Hittable;
+        use crate::import::ply::read_ply;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        /// The header of a unit quad with colors, normals and an extra element to skip.
+        fn header(format: &str) -> String {
+            format!(
+                "ply
+format {} 1.0
+comment A unit quad facing +z
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element material 1
+property uchar index
+element face 1
+property list uchar int vertex_indices
+end_header
+",
+                format
+            )
+        }
+
+        const VERTICES: [[f32; 6]; 4] = [
+            [0.0, 0.0, 0.0, 0.0, 0.0, 2.0],
+            [1.0, 0.0, 0.0, 0.0, 0.0, 2.0],
+            [1.0, 1.0, 0.0, 0.0, 0.0, 2.0],
+            [0.0, 1.0, 0.0, 0.0, 0.0, 2.0],
+        ];
+
+        fn binary(big_endian: bool) -> Vec<u8> {
+            let format = if big_endian {
+                "binary_big_endian"
+            } else {
+                "binary_little_endian"
+            };
+            let mut bytes = header(format).into_bytes();
+            for (i, vertex) in VERTICES.iter().enumerate() {
+                for value in vertex {
+                    if big_endian {
+                        bytes.extend(value.to_be_bytes());
+                    } else {
+                        bytes.extend(value.to_le_bytes());
+                    }
+                }
+                bytes.extend([255, if i == 0 { 0 } else { 255 }, 51]);
+            }
+            bytes.push(7);
+            bytes.push(4);
+            for index in [0i32, 1, 2, 3] {
+                if big_endian {
+                    bytes.extend(index.to_be_bytes());
+                } else {
+                    bytes.extend(index.to_le_bytes());
+                }
+            }
+            bytes
+        }
+
+        #[test]
+        fn ply_read_ascii() {
+            let text = header("ascii")
+                + "0 0 0 0 0 2 255 0 51\n1 0 0 0 0 2 255 255 51\n"
+                + "1 1 0 0 0 2 255 255 51\n0 1 0 0 0 2 255 255 51\n"
+                + "7\n"
+                + "4 0 1 2 3\n";
+            let mesh = read_ply(&mut text.as_bytes()).unwrap();
+
+            assert_eq!(mesh.positions().len(), 4);
+            assert_eq!(mesh.indices(), [[0, 1, 2], [0, 2, 3]], "Quad not split");
+            assert_eq!(mesh.positions()[2], Point3D::with_values(1.0, 1.0, 0.0));
+            assert_eq!(mesh.normals()[0], Vector3D::with_values(0.0, 0.0, 1.0));
+            assert_eq!(mesh.colors()[0].x(), 1.0);
+            assert!(
+                (mesh.colors()[0].z() - 0.04).abs() < 1e-12,
+                "Not linearized"
+            );
+            assert!(mesh.uvs().is_empty());
+            assert_eq!(mesh.area(), 1.0);
+        }
+
+        #[test]
+        fn ply_read_binary() {
+            let ascii = header("ascii")
+                + "0 0 0 0 0 2 255 0 51\n1 0 0 0 0 2 255 255 51\n"
+                + "1 1 0 0 0 2 255 255 51\n0 1 0 0 0 2 255 255 51\n"
+                + "7\n4 0 1 2 3\n";
+            let expected = read_ply(&mut ascii.as_bytes()).unwrap();
+
+            for big_endian in [false, true] {
+                let mesh = read_ply(&mut binary(big_endian).as_slice()).unwrap();
+                assert_eq!(mesh.positions(), expected.positions());
+                assert_eq!(mesh.normals(), expected.normals());
+                assert_eq!(mesh.colors(), expected.colors());
+                assert_eq!(mesh.indices(), expected.indices());
+                assert_eq!(mesh.colors()[0].y(), 0.0);
+                assert!((mesh.colors()[1] - Color::with_values(1.0, 1.0, 0.04)).length() < 1e-12);
+            }
+        }
+
+        #[test]
+        fn ply_read_invalid() {
+            let vertex = "element vertex 1\nproperty float x\nproperty float y\nproperty float z\n";
+            let face = "element face 1\nproperty list uchar int vertex_indices\n";
+            for text in [
+                "obj\n".to_string(),
+                "ply\nformat ascii 1.0\n".to_string(),
+                format!("ply\nformat ascii 1.0\n{}end_header\n0 0\n", vertex),
+                format!(
+                    "ply\nformat ascii 1.0\n{}{}end_header\n0 0 0\n3 0 0 1\n",
+                    vertex, face
+                ),
+                format!(
+                    "ply\nformat ascii 1.0\n{}{}end_header\n0 0 0\n3 0 -1 0\n",
+                    vertex, face
+                ),
+                format!(
+                    "ply\nformat ascii 1.0\n{}{}end_header\n0 0 0\n3 0 0.5 0\n",
+                    vertex, face
+                ),
+                format!(
+                    "ply\nformat ascii 1.0\n{}{}end_header\n0 0 0\n2.5 0 0 0\n",
+                    vertex, face
+                ),
+                format!(
+                    "ply\nformat binary_middle_endian 1.0\n{}end_header\n",
+                    vertex
+                ),
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty float y\nend_header\n0\n"
+                    .to_string(),
+            ] {
+                assert!(
+                    read_ply(&mut text.as_bytes()).is_err(),
+                    "Read invalid {:?}",
+                    text
+                );
+            }
+
+            // Elements without properties are skipped at once, however many there are
+            let empty = format!(
+                "ply\nformat ascii 1.0\n{}element nothing {}\nend_header\n0 0 0\n",
+                vertex,
+                usize::MAX
+            );
+            assert_eq!(
+                read_ply(&mut empty.as_bytes()).unwrap().positions().len(),
+                1
+            );
+        }
+    }
+}
+
+// --- STL ---------------------------------------------------------------------
+
+pub mod stl {
+    //! Module for meshes in the stereolithography format (STL).
+
+    use crate::hittable::mesh::TriangleMesh;
+    use crate::vector3d::Point3D;
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{self, BufReader, Read};
+    use std::path::Path;
+
+    /// Creates the error of invalid data.
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+
+    /// Loads a mesh from an STL file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the .stl file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<TriangleMesh> {
+        read_stl(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a mesh from an STL file, in ASCII or binary.
+    ///
+    /// Vertices at the very same position get shared by their triangles.
+    /// The normals of the facets are skipped, the order of their vertices
+    /// telling the outside already.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The contents of an .stl file.
+    pub fn read_stl<R: Read>(reader: &mut R) -> io::Result<TriangleMesh> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        // Binary files may start with "solid" too, but always match their triangle count
+        let binary = bytes.len() >= 84 && {
+            let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]);
+            bytes.len() == 84 + 50 * count as usize
+        };
+
+        let corners: Vec<Point3D> = if binary {
+            let float = |at: usize| {
+                f64::from(f32::from_le_bytes([
+                    bytes[at],
+                    bytes[at + 1],
+                    bytes[at + 2],
+                    bytes[at + 3],
+                ]))
+            };
+            // Every facet has its normal first, its attribute byte count last
+            (84..bytes.len())
+                .step_by(50)
+                .flat_map(|facet| (1..4).map(move |i| facet + 12 * i))
+                .map(|at| Point3D::with_values(float(at), float(at + 4), float(at + 8)))
+                .collect()
+        } else {
+            let text = std::str::from_utf8(&bytes).map_err(|_| invalid("Not an STL file"))?;
+            if !text.trim_start().starts_with("solid") {
+                return Err(invalid("Not an STL file"));
+            }
+
+            let mut tokens = text.split_ascii_whitespace();
+            let mut corners = Vec::new();
+            while let Some(token) = tokens.next() {
+                if token == "vertex" {
+                    let mut coordinate = || {
+                        tokens
+                            .next()
+                            .and_then(|t| t.parse::<f64>().ok())
+                            .ok_or_else(|| invalid("Invalid vertex"))
+                    };
+                    corners.push(Point3D::with_values(
+                        coordinate()?,
+                        coordinate()?,
+                        coordinate()?,
+                    ));
+                }
+            }
+            if corners.len() % 3 != 0 {
+                return Err(invalid("Facet without three vertices"));
+            }
+            corners
+        };
+
+        // Share vertices, telling -0 and 0 apart no longer
+        let mut positions = Vec::new();
+        let mut shared: HashMap<[u64; 3], u32> = HashMap::new();
+        let vertices: Vec<u32> = corners
+            .into_iter()
+            .map(|p| {
+                let key = [p.x(), p.y(), p.z()].map(|c| (c + 0.0).to_bits());
+                *shared.entry(key).or_insert_with(|| {
+                    positions.push(p);
+                    (positions.len() - 1) as u32
+                })
+            })
+            .collect();
+        let indices = vertices.chunks(3).map(|c| [c[0], c[1], c[2]]).collect();
+
+        Ok(TriangleMesh::new(positions, indices))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::hittable::Hittable;
+        use crate::import::stl::read_stl;
+        use crate::vector3d::Point3D;
+
+        const SQUARE: [[f32; 9]; 2] = [
+            [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, -0.0],
+        ];
+
+        #[test]
+        fn stl_read_ascii() {
+            let mut text = "solid square\n".to_string();
+            for facet in SQUARE {
+                text += "  facet normal 0 0 1\n    outer loop\n";
+                for v in facet.chunks(3) {
+                    text += &format!("      vertex {} {} {}\n", v[0], v[1], v[2]);
+                }
+                text += "    endloop\n  endfacet\n";
+            }
+            text += "endsolid square\n";
+            let mesh = read_stl(&mut text.as_bytes()).unwrap();
+
+            assert_eq!(mesh.len(), 2);
+            assert_eq!(mesh.positions().len(), 4, "Vertices not shared");
+            assert_eq!(mesh.indices(), [[0, 1, 2], [0, 2, 3]]);
+            assert_eq!(mesh.positions()[3], Point3D::with_values(0.0, 1.0, 0.0));
+            assert_eq!(mesh.area(), 1.0);
+        }
+
+        #[test]
+        fn stl_read_binary() {
+            // Binary files starting with "solid" are binary still
+            let mut bytes = b"solid but binary".to_vec();
+            bytes.resize(80, 0);
+            bytes.extend(2u32.to_le_bytes());
+            for facet in SQUARE {
+                bytes.extend([0.0f32, 0.0, 1.0].iter().flat_map(|f| f.to_le_bytes()));
+                bytes.extend(facet.iter().flat_map(|f| f.to_le_bytes()));
+                bytes.extend([0, 0]);
+            }
+            let mesh = read_stl(&mut bytes.as_slice()).unwrap();
+
+            assert_eq!(mesh.len(), 2);
+            assert_eq!(mesh.positions().len(), 4, "Vertices not shared");
+            assert_eq!(mesh.positions()[2], Point3D::with_values(1.0, 1.0, 0.0));
+            assert_eq!(mesh.area(), 1.0);
+        }
+
+        #[test]
+        fn stl_read_invalid() {
+            assert!(read_stl(&mut "mesh\n".as_bytes()).is_err());
+            assert!(read_stl(&mut "solid a\nvertex 0 0\n".as_bytes()).is_err());
+            assert!(read_stl(&mut "solid a\nvertex 0 0 0\nendsolid\n".as_bytes()).is_err());
+        }
+    }
+}
+
+// --- GLTF --------------------------------------------------------------------
+
+pub mod gltf {
+    //! Module for scenes in the GL Transmission Format (glTF 2.0), as .gltf with
+    //! separate or embedded buffers or as binary .glb.
+
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::mesh::TriangleMesh;
+    use crate::light::directional::DirectionalLight;
+    use crate::light::point::PointLight;
+    use crate::light::spot::SpotLight;
+    use crate::light::LightList;
+    use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+    use crate::texture::image::{read_jpeg, read_png, ImageTexture};
+    use crate::vector3d::{Point3D, Vector3D};
+    use nalgebra::{Matrix3, Matrix4, Quaternion, UnitQuaternion, Vector3};
+    use std::fs::File;
+    use std::io::{self, BufReader, Read};
+    use std::path::Path;
+    use std::rc::Rc;
+
+    /// Creates the error of invalid data.
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+
+    /// Represents a JSON value, the objects keeping the order of their members.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    /// The value of missing members and elements.
+    static NULL: Json = Json::Null;
+
+    impl Json {
+        /// Parses a JSON document.
+        fn parse(text: &str) -> io::Result<Json> {
+            let mut parser = Parser {
+                bytes: text.as_bytes(),
+                position: 0,
+                depth: 0,
+            };
+            let value = parser.value()?;
+            parser.whitespace();
+            if parser.position < parser.bytes.len() {
+                return Err(invalid("Trailing characters after JSON"));
+            }
+
+            Ok(value)
+        }
+
+        /// Gets the member `key` of an object, null if there is none.
+        fn get(&self, key: &str) -> &Json {
+            match self {
+                Json::Object(members) => members
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map_or(&NULL, |(_, v)| v),
+                _ => &NULL,
+            }
+        }
+
+        /// Gets the element `index` of an array, null if there is none.
+        fn at(&self, index: usize) -> &Json {
+            self.array().get(index).unwrap_or(&NULL)
+        }
+
+        /// Checks if the value is null, or missing.
+        fn is_null(&self) -> bool {
+            *self == Json::Null
+        }
+
+        /// Gets the value as number.
+        fn f64(&self) -> Option<f64> {
+            match self {
+                Json::Number(n) => Some(*n),
+                _ => None,
+            }
+        }
+
+        /// Gets the value as index or count.
+        fn usize(&self) -> Option<usize> {
+            self.f64()
+                .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                .map(|n| n as usize)
+        }
+
+        /// Gets the value as string.
+        fn str(&self) -> Option<&str> {
+            match self {
+                Json::String(s) => Some(s),
+                _ => None,
+            }
+        }
+
+        /// Gets the elements of an array, none for other values.
+        fn array(&self) -> &[Json] {
+            match self {
+                Json::Array(elements) => elements,
+                _ => &[],
+            }
+        }
+
+        /// Gets an array of `N` numbers, `default` if it's missing or not one.
+        fn numbers<const N: usize>(&self, default: [f64; N]) -> [f64; N] {
+            let mut numbers = default;
+            if self.array().len() != N {
+                return default;
+            }
+            for (number, element) in numbers.iter_mut().zip(self.array()) {
+                match element.f64() {
+                    Some(n) => *number = n,
+                    None => return default,
+                }
+            }
+
+            numbers
+        }
+    }
+
+    /// How deeply JSON values may nest, keeping crafted files from overflowing the stack.
+    const MAX_DEPTH: usize = 128;
+
+    /// Reads JSON values from the bytes of a text.
+    struct Parser<'a> {
+        bytes: &'a [u8],
+        position: usize,
+        depth: usize,
+    }
+
+    impl Parser<'_> {
+        /// Skips any whitespace.
+        fn whitespace(&mut self) {
+            while self
+                .bytes
+                .get(self.position)
+                .is_some_and(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+            {
+                self.position += 1;
+            }
+        }
+
+        /// Skips whitespace and the expected byte.
+        fn expect(&mut self, expected: u8) -> io::Result<()> {
+            self.whitespace();
+            if self.bytes.get(self.position) != Some(&expected) {
+                return Err(invalid("Unexpected character in JSON"));
+            }
+            self.position += 1;
+            Ok(())
+        }
+
+        /// Reads the next value.
+        fn value(&mut self) -> io::Result<Json> {
+            if self.depth == MAX_DEPTH {
+                return Err(invalid("JSON nested too deeply"));
+            }
+            self.depth += 1;
+            let value = self.nested_value();
+            self.depth -= 1;
+            value
+        }
+
+        /// Reads the next value, the caller keeping track of the depth.
+        fn nested_value(&mut self) -> io::Result<Json> {
+            self.whitespace();
+            let rest = &self.bytes[self.position..];
+            match rest.first() {
+                Some(b'{') => {
+                    self.position += 1;
+                    let mut members = Vec::new();
+                    self.whitespace();
+                    if self.bytes.get(self.position) == Some(&b'}') {
+                        self.position += 1;
+                        return Ok(Json::Object(members));
+                    }
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        members.push((key, self.value()?));
+                        self.whitespace();
+                        match self.bytes.get(self.position) {
+                            Some(b',') => self.position += 1,
+                            Some(b'}') => {
+                                self.position += 1;
+                                return Ok(Json::Object(members));
+                            }
+                            _ => return Err(invalid("Unterminated JSON object")),
+                        }
+                    }
+                }
+                Some(b'[') => {
+                    self.position += 1;
+                    let mut elements = Vec::new();
+                    self.whitespace();
+                    if self.bytes.get(self.position) == Some(&b']') {
+                        self.position += 1;
+                        return Ok(Json::Array(elements));
+                    }
+                    loop {
+                        elements.push(self.value()?);
+                        self.whitespace();
+                        match self.bytes.get(self.position) {
+                            Some(b',') => self.position += 1,
+                            Some(b']') => {
+                                self.position += 1;
+                                return Ok(Json::Array(elements));
+                            }
+                            _ => return Err(invalid("Unterminated JSON array")),
+                        }
+                    }
+                }
+                Some(b'"') => Ok(Json::String(self.string()?)),
+                _ if rest.starts_with(b"true") => {
+                    self.position += 4;
+                    Ok(Json::Bool(true))
+                }
+                _ if rest.starts_with(b"false") => {
+                    self.position += 5;
+                    Ok(Json::Bool(false))
+                }
+                _ if rest.starts_with(b"null") => {
+                    self.position += 4;
+                    Ok(Json::Null)
+                }
+                _ => {
+                    let length = rest
+                        .iter()
+                        .take_while(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                        .count();
+                    let number = std::str::from_utf8(&rest[..length])
+                        .ok()
+                        .filter(|n| !n.is_empty())
+                        .and_then(|n| n.parse::<f64>().ok())
+                        .ok_or_else(|| invalid("Invalid JSON value"))?;
+                    self.position += length;
+                    Ok(Json::Number(number))
+                }
+            }
+        }
+
+        /// Reads the next string, resolving escapes.
+        fn string(&mut self) -> io::Result<String> {
+            if self.bytes.get(self.position) != Some(&b'"') {
+                return Err(invalid("Expected a JSON string"));
+            }
+            self.position += 1;
+
+            let mut bytes = Vec::new();
+            loop {
+                let Some(&byte) = self.bytes.get(self.position) else {
+                    return Err(invalid("Unterminated JSON string"));
+                };
+                self.position += 1;
+                match byte {
+                    b'"' => break,
+                    b'\\' => {
+                        let Some(&escape) = self.bytes.get(self.position) else {
+                            return Err(invalid("Unterminated JSON string"));
+                        };
+                        self.position += 1;
+                        let c = match escape {
+                            b'"' => '"',
+                            b'\\' => '\\',
+                            b'/' => '/',
+                            b'b' => '\u{8}',
+                            b'f' => '\u{c}',
+                            b'n' => '\n',
+                            b'r' => '\r',
+                            b't' => '\t',
+                            b'u' => {
+                                let mut code = self.hex()?;
+                                // Characters beyond the basic plane come as surrogate pairs
+                                if (0xd800..0xdc00).contains(&code)
+                                    && self.bytes[self.position..].starts_with(b"\\u")
+                                {
+                                    self.position += 2;
+                                    let low = self.hex()?;
+                                    code = 0x10000
+                                        + ((code - 0xd800) << 10)
+                                        + (low.wrapping_sub(0xdc00) & 0x3ff);
+                                }
+                                char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                            }
+                            _ => return Err(invalid("Invalid escape in JSON string")),
+                        };
+                        bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                    _ => bytes.push(byte),
+                }
+            }
+
+            // Only whole characters of the valid text got copied
+            String::from_utf8(bytes).map_err(|_| invalid("Invalid JSON string"))
+        }
+
+        /// Reads the four hexadecimal digits of an escaped character.
+        fn hex(&mut self) -> io::Result<u32> {
+            let digits = self
+                .bytes
+                .get(self.position..self.position + 4)
+                .and_then(|d| std::str::from_utf8(d).ok())
+                .and_then(|d| u32::from_str_radix(d, 16).ok())
+                .ok_or_else(|| invalid("Invalid escape in JSON string"))?;
+            self.position += 4;
+            Ok(digits)
+        }
+    }
+
+    /// Decodes base64, as embedded in data URIs.
+    fn base64(text: &str) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+        let (mut bits, mut count) = (0u32, 0);
+        for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+            let value = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' | b'-' => 62,
+                b'/' | b'_' => 63,
+                b'=' => break,
+                _ => return Err(invalid("Invalid base64")),
+            };
+            bits = bits << 6 | u32::from(value);
+            count += 6;
+            if count >= 8 {
+                count -= 8;
+                bytes.push((bits >> count) as u8);
+                bits &= (1 << count) - 1;
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Decodes a PNG or JPEG image, telling them apart by their media type.
+    fn decode_image(bytes: &[u8], mime_type: Option<&str>) -> io::Result<ImageTexture> {
+        let (width, height, pixels) = match mime_type {
+            Some("image/png") => read_png(&mut &bytes[..])?,
+            Some("image/jpeg") => read_jpeg(&mut &bytes[..])?,
+            _ => return Err(invalid("Unsupported image type")),
+        };
+
+        Ok(ImageTexture::new(width, height, pixels))
+    }
+
+    /// Decodes the escaped bytes (%XX) of a relative URI.
+    fn decode_uri(uri: &str) -> String {
+        let bytes = uri.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let escaped = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            match (bytes[i], escaped) {
+                (b'%', Some(byte)) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                (byte, _) => {
+                    decoded.push(byte);
+                    i += 1;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    /// Represents a perspective camera of a glTF scene, placed by its node.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct GltfCamera {
+        center: Point3D,
+        direction: Vector3D,
+        up: Vector3D,
+        vertical_fov: f64,
+        aspect_ratio: Option<f64>,
+    }
+
+    impl GltfCamera {
+        /// Gets the position of the camera.
+        pub fn center(&self) -> Point3D {
+            self.center
+        }
+
+        /// Gets the unit direction the camera looks in.
+        pub fn direction(&self) -> Vector3D {
+            self.direction
+        }
+
+        /// Gets the unit direction pointing up in the image.
+        pub fn up(&self) -> Vector3D {
+            self.up
+        }
+
+        /// Gets the angle between the top and the bottom edge of the image in degrees.
+        pub fn vertical_fov(&self) -> f64 {
+            self.vertical_fov
+        }
+
+        /// Gets the ratio of the width to the height of the image, if the scene sets one.
+        pub fn aspect_ratio(&self) -> Option<f64> {
+            self.aspect_ratio
+        }
+
+        /// Creates a camera rendering the view of the scene.
+        ///
+        /// # Arguments
+        ///
+        /// * `image_width`       - The width of the image in pixels.
+        /// * `samples_per_pixel` - How many samples there should be for one pixel.
+        /// * `max_depth`         - How many rays should scatter.
+        ///
+        /// # Returns
+        ///
+        /// Returns the camera, with an aspect ratio of 16:9 if the scene sets none.
+        pub fn to_camera(
+            &self,
+            image_width: u16,
+            samples_per_pixel: u16,
+            max_depth: u16,
+        ) -> Camera {
+            Camera::new(
+                self.aspect_ratio.unwrap_or(16.0 / 9.0),
+                image_width,
+                samples_per_pixel,
+                max_depth,
+            )
+            .with_vertical_fov(self.vertical_fov)
+            .with_view(self.center, self.direction, self.up)
+        }
+    }
+
+    /// Represents a glTF scene, ready to render.
+    pub struct GltfScene {
+        world: HittableList,
+        lights: LightList,
+        cameras: Vec<GltfCamera>,
+    }
+
+    impl GltfScene {
+        /// Gets a triangle mesh for every primitive of every mesh in the scene.
+        pub fn world(&self) -> &HittableList {
+            &self.world
+        }
+
+        /// Gets the punctual lights of the scene.
+        pub fn lights(&self) -> &LightList {
+            &self.lights
+        }
+
+        /// Gets the perspective cameras of the scene, in the order of their nodes.
+        pub fn cameras(&self) -> &[GltfCamera] {
+            &self.cameras
+        }
+    }
+
+    /// Loads a glTF scene from a .gltf or .glb file.
+    ///
+    /// Buffers and images are looked up next to the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the .gltf or .glb file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<GltfScene> {
+        let directory = path.as_ref().parent().unwrap_or(Path::new(""));
+        read_gltf(&mut BufReader::new(File::open(&path)?), directory)
+    }
+
+    /// Reads a glTF scene, as JSON or binary .glb.
+    ///
+    /// The nodes of the default scene get flattened, meshes and cameras moved by the
+    /// transformations of their nodes. Materials become the closest material of the
+    /// crate: emissive ones diffuse lights, transmissive ones (KHR_materials_transmission)
+    /// dielectrics, metallic ones metals fuzzed by their roughness and all others
+    /// Lambertian with their base color texture, read from PNG or JPEG images in files,
+    /// data URIs or buffer views. Orthographic cameras are left out. Punctual lights
+    /// (KHR_lights_punctual) keep their intensity in candela or lux as it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader`    - The contents of a .gltf or .glb file.
+    /// * `directory` - The directory buffers and images are looked up in.
+    pub fn read_gltf<R: Read>(reader: &mut R, directory: &Path) -> io::Result<GltfScene> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        // Binary files have a header and chunks of JSON and binary data
+        let (text, binary) = if bytes.starts_with(b"glTF") {
+            let word = |at: usize| {
+                bytes
+                    .get(at..at + 4)
+                    .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]) as usize)
+                    .ok_or_else(|| invalid("Truncated GLB chunk"))
+            };
+            if word(4)? != 2 {
+                return Err(invalid("Unsupported GLB version"));
+            }
+
+            let (mut text, mut binary) = (None, None);
+            let mut at = 12;
+            while at + 8 <= bytes.len().min(word(8)?) {
+                let (length, kind) = (word(at)?, word(at + 4)?);
+                let chunk = bytes
+                    .get(at + 8..at + 8 + length)
+                    .ok_or_else(|| invalid("Truncated GLB chunk"))?;
+                match kind {
+                    0x4e4f534a if text.is_none() => text = Some(chunk),
+                    0x004e4942 if binary.is_none() => binary = Some(chunk.to_vec()),
+                    _ => {}
+                }
+                at += 8 + length;
+            }
+            (text.ok_or_else(|| invalid("GLB without JSON"))?, binary)
+        } else {
+            (bytes.as_slice(), None)
+        };
+
+        let text = std::str::from_utf8(text).map_err(|_| invalid("glTF not in UTF-8"))?;
+        let json = Json::parse(text.trim_start_matches('\u{feff}'))?;
+        if !json
+            .get("asset")
+            .get("version")
+            .str()
+            .is_some_and(|v| v.starts_with("2."))
+        {
+            return Err(invalid("Not a glTF 2.0 file"));
+        }
+
+        let mut binary = binary;
+        let mut buffers = Vec::new();
+        for buffer in json.get("buffers").array() {
+            let data = match buffer.get("uri").str() {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, data) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| invalid("Data URI not in base64"))?;
+                    base64(data)?
+                }
+                Some(uri) => {
+                    let mut data = Vec::new();
+                    File::open(directory.join(decode_uri(uri)))?.read_to_end(&mut data)?;
+                    data
+                }
+                None => binary
+                    .take()
+                    .ok_or_else(|| invalid("Buffer without data"))?,
+            };
+            if data.len() < buffer.get("byteLength").usize().unwrap_or(0) {
+                return Err(invalid("Buffer shorter than its length"));
+            }
+            buffers.push(data);
+        }
+
+        let mut document = Document {
+            json: &json,
+            buffers,
+            directory,
+            materials: Vec::new(),
+            scene: GltfScene {
+                world: HittableList::new(),
+                lights: LightList::new(),
+                cameras: Vec::new(),
+            },
+        };
+        document.materials = json
+            .get("materials")
+            .array()
+            .iter()
+            .map(|m| document.material(m))
+            .collect::<io::Result<_>>()?;
+
+        // Without scenes every node without a parent is in the scene
+        let scene = json
+            .get("scenes")
+            .at(json.get("scene").usize().unwrap_or(0));
+        let roots: Vec<usize> = if json.get("scenes").is_null() {
+            let nodes = json.get("nodes").array();
+            let children: Vec<usize> = nodes
+                .iter()
+                .flat_map(|n| n.get("children").array())
+                .filter_map(Json::usize)
+                .collect();
+            (0..nodes.len()).filter(|i| !children.contains(i)).collect()
+        } else {
+            scene
+                .get("nodes")
+                .array()
+                .iter()
+                .filter_map(Json::usize)
+                .collect()
+        };
+        for root in roots {
+            document.node(root, Matrix4::identity(), 0)?;
+        }
+
+        Ok(document.scene)
+    }
+
+    /// The parts of a glTF file needed while turning its nodes into a scene.
+    struct Document<'a> {
+        json: &'a Json,
+        buffers: Vec<Vec<u8>>,
+        directory: &'a Path,
+        materials: Vec<Rc<dyn Material>>,
+        scene: GltfScene,
+    }
+
+    impl Document<'_> {
+        /// Adds a node and its children to the scene.
+        ///
+        /// # Arguments
+        ///
+        /// * `index`  - The index of the node.
+        /// * `parent` - The transformation of the parent node to the scene.
+        /// * `depth`  - How many ancestors the node has, to tell cycles.
+        fn node(&mut self, index: usize, parent: Matrix4<f64>, depth: usize) -> io::Result<()> {
+            let nodes = self.json.get("nodes").array();
+            let node = nodes
+                .get(index)
+                .ok_or_else(|| invalid("Node doesn't exist"))?;
+            if depth > nodes.len() {
+                return Err(invalid("Nodes contain themselves"));
+            }
+
+            let local = if node.get("matrix").is_null() {
+                let [x, y, z] = node.get("translation").numbers([0.0; 3]);
+                let [i, j, k, w] = node.get("rotation").numbers([0.0, 0.0, 0.0, 1.0]);
+                let [sx, sy, sz] = node.get("scale").numbers([1.0; 3]);
+                Matrix4::new_translation(&Vector3::new(x, y, z))
+                    * UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k)).to_homogeneous()
+                    * Matrix4::new_nonuniform_scaling(&Vector3::new(sx, sy, sz))
+            } else {
+                Matrix4::from_column_slice(&node.get("matrix").numbers([
+                    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+                ]))
+            };
+            let transform = parent * local;
+
+            let point = |x: f64, y: f64, z: f64| {
+                let p = transform.transform_point(&nalgebra::Point3::new(x, y, z));
+                Point3D::with_values(p.x, p.y, p.z)
+            };
+            let vector = |x: f64, y: f64, z: f64| {
+                let v = transform.transform_vector(&Vector3::new(x, y, z));
+                Vector3D::with_values(v.x, v.y, v.z).unit_vector()
+            };
+
+            if let Some(mesh) = node.get("mesh").usize() {
+                let mesh = self.json.get("meshes").at(mesh);
+                if mesh.is_null() {
+                    return Err(invalid("Mesh doesn't exist"));
+                }
+                for primitive in mesh.get("primitives").array() {
+                    if let Some(mesh) = self.primitive(primitive, &transform)? {
+                        self.scene.world.push(Rc::new(mesh));
+                    }
+                }
+            }
+
+            if let Some(camera) = node.get("camera").usize() {
+                let camera = self.json.get("cameras").at(camera);
+                let perspective = camera.get("perspective");
+                if camera.get("type").str() == Some("perspective") {
+                    let yfov = perspective
+                        .get("yfov")
+                        .f64()
+                        .ok_or_else(|| invalid("Perspective camera without field of view"))?;
+                    self.scene.cameras.push(GltfCamera {
+                        center: point(0.0, 0.0, 0.0),
+                        direction: vector(0.0, 0.0, -1.0),
+                        up: vector(0.0, 1.0, 0.0),
+                        vertical_fov: yfov.to_degrees(),
+                        aspect_ratio: perspective.get("aspectRatio").f64(),
+                    });
+                }
+            }
+
+            let light = node
+                .get("extensions")
+                .get("KHR_lights_punctual")
+                .get("light");
+            if let Some(light) = light.usize() {
+                let light = self
+                    .json
+                    .get("extensions")
+                    .get("KHR_lights_punctual")
+                    .get("lights")
+                    .at(light);
+                let [r, g, b] = light.get("color").numbers([1.0; 3]);
+                let intensity = light.get("intensity").f64().unwrap_or(1.0);
+                let color = intensity * Color::with_values(r, g, b);
+                let spot = light.get("spot");
+                match light.get("type").str() {
+                    Some("point") => self
+                        .scene
+                        .lights
+                        .push(Rc::new(PointLight::new(point(0.0, 0.0, 0.0), color))),
+                    Some("spot") => self.scene.lights.push(Rc::new(SpotLight::new(
+                        point(0.0, 0.0, 0.0),
+                        vector(0.0, 0.0, -1.0),
+                        color,
+                        spot.get("innerConeAngle").f64().unwrap_or(0.0).to_degrees(),
+                        spot.get("outerConeAngle")
+                            .f64()
+                            .unwrap_or(std::f64::consts::FRAC_PI_4)
+                            .to_degrees(),
+                    ))),
+                    Some("directional") => self.scene.lights.push(Rc::new(DirectionalLight::new(
+                        vector(0.0, 0.0, -1.0),
+                        color,
+                    ))),
+                    _ => return Err(invalid("Unknown type of light")),
+                }
+            }
+
+            for child in node.get("children").array() {
+                let child = child.usize().ok_or_else(|| invalid("Invalid child node"))?;
+                self.node(child, transform, depth + 1)?;
+            }
+
+            Ok(())
+        }
+
+        /// Turns a primitive of a mesh into a triangle mesh in the scene.
+        ///
+        /// # Returns
+        ///
+        /// Returns the triangle mesh, or `None` for primitives of points or lines.
+        fn primitive(
+            &self,
+            primitive: &Json,
+            transform: &Matrix4<f64>,
+        ) -> io::Result<Option<TriangleMesh>> {
+            let mode = primitive.get("mode").usize().unwrap_or(4);
+            if !(4..=6).contains(&mode) {
+                return Ok(None);
+            }
+
+            let attributes = primitive.get("attributes");
+            let position = attributes
+                .get("POSITION")
+                .usize()
+                .ok_or_else(|| invalid("Primitive without positions"))?;
+            let (positions, components) = self.accessor(position)?;
+            if components != 3 {
+                return Err(invalid("Positions not in three dimensions"));
+            }
+            let count = positions.len() / 3;
+
+            let vertices: Vec<u32> = match primitive.get("indices").usize() {
+                Some(indices) => {
+                    let (indices, _) = self.accessor(indices)?;
+                    if indices.iter().any(|&i| i as usize >= count) {
+                        return Err(invalid("Index of a vertex that doesn't exist"));
+                    }
+                    indices.into_iter().map(|i| i as u32).collect()
+                }
+                None => (0..count as u32).collect(),
+            };
+
+            // Mirroring transformations turn the front faces around
+            let linear: Matrix3<f64> = transform.fixed_view::<3, 3>(0, 0).into();
+            let mirrored = linear.determinant() < 0.0;
+            let mut indices: Vec<[u32; 3]> = match mode {
+                4 => vertices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                5 => (2..vertices.len())
+                    .map(|i| match i % 2 {
+                        0 => [vertices[i - 2], vertices[i - 1], vertices[i]],
+                        _ => [vertices[i - 1], vertices[i - 2], vertices[i]],
+                    })
+                    .collect(),
+                _ => (2..vertices.len())
+                    .map(|i| [vertices[0], vertices[i - 1], vertices[i]])
+                    .collect(),
+            };
+            if mirrored {
+                indices.iter_mut().for_each(|t| t.swap(1, 2));
+            }
+            if indices.is_empty() {
+                return Ok(None);
+            }
+
+            let positions = positions
+                .chunks_exact(3)
+                .map(|p| {
+                    let p = transform.transform_point(&nalgebra::Point3::new(p[0], p[1], p[2]));
+                    Point3D::with_values(p.x, p.y, p.z)
+                })
+                .collect();
+            let mut mesh = TriangleMesh::new(positions, indices);
+
+            if let Some(normals) = attributes.get("NORMAL").usize() {
+                let (normals, components) = self.accessor(normals)?;
+                let normal_matrix = linear
+                    .try_inverse()
+                    .ok_or_else(|| invalid("Transformation flattening a mesh"))?
+                    .transpose();
+                if components == 3 && normals.len() == 3 * count {
+                    mesh = mesh.with_normals(
+                        normals
+                            .chunks_exact(3)
+                            .map(|n| {
+                                let n = normal_matrix * Vector3::new(n[0], n[1], n[2]);
+                                Vector3D::with_values(n.x, n.y, n.z)
+                            })
+                            .collect(),
+                    );
+                }
+            }
+
+            // Images start at the top in glTF, but at the bottom for textures
+            if let Some(uvs) = attributes.get("TEXCOORD_0").usize() {
+                let (uvs, components) = self.accessor(uvs)?;
+                if components == 2 && uvs.len() == 2 * count {
+                    mesh = mesh.with_uvs(uvs.chunks_exact(2).map(|t| (t[0], 1.0 - t[1])).collect());
+                }
+            }
+
+            if let Some(colors) = attributes.get("COLOR_0").usize() {
+                let (colors, components) = self.accessor(colors)?;
+                if (components == 3 || components == 4) && colors.len() == components * count {
+                    mesh = mesh.with_colors(
+                        colors
+                            .chunks_exact(components)
+                            .map(|c| Color::with_values(c[0], c[1], c[2]))
+                            .collect(),
+                    );
+                }
+            }
+
+            if let Some(material) = primitive.get("material").usize() {
+                let material = self
+                    .materials
+                    .get(material)
+                    .ok_or_else(|| invalid("Material doesn't exist"))?;
+                mesh = mesh.with_material(Rc::clone(material));
+            }
+
+            Ok(Some(mesh))
+        }
+
+        /// Reads the elements of an accessor.
+        ///
+        /// # Returns
+        ///
+        /// Returns the components of all elements in a row, with normalized integers
+        /// mapped to [0, 1] or [-1, 1], and the number of components of every element.
+        fn accessor(&self, index: usize) -> io::Result<(Vec<f64>, usize)> {
+            let accessor = self.json.get("accessors").at(index);
+            let count = accessor
+                .get("count")
+                .usize()
+                .ok_or_else(|| invalid("Accessor without count"))?;
+            let components = match accessor.get("type").str() {
+                Some("SCALAR") => 1,
+                Some("VEC2") => 2,
+                Some("VEC3") => 3,
+                Some("VEC4") | Some("MAT2") => 4,
+                Some("MAT3") => 9,
+                Some("MAT4") => 16,
+                _ => return Err(invalid("Unknown accessor type")),
+            };
+            let kind = accessor.get("componentType").usize().unwrap_or(0);
+            let normalized = *accessor.get("normalized") == Json::Bool(true);
+            let offset = accessor.get("byteOffset").usize().unwrap_or(0);
+
+            let mut values = match accessor.get("bufferView").usize() {
+                Some(view) => self.elements(view, offset, count, components, kind, normalized)?,
+                None => {
+                    // Zeros take no space in the file, so bound them by the size of its buffers
+                    let length = count
+                        .checked_mul(components)
+                        .filter(|&l| l <= self.buffers.iter().map(Vec::len).sum())
+                        .ok_or_else(|| invalid("Accessor larger than the buffers"))?;
+                    vec![0.0; length]
+                }
+            };
+
+            // Sparse accessors replace some of the elements
+            let sparse = accessor.get("sparse");
+            if let Some(replaced) = sparse.get("count").usize() {
+                let (indices, changes) = (sparse.get("indices"), sparse.get("values"));
+                let view = |json: &Json| {
+                    json.get("bufferView")
+                        .usize()
+                        .ok_or_else(|| invalid("Sparse accessor without buffer view"))
+                };
+                let indices = self.elements(
+                    view(indices)?,
+                    indices.get("byteOffset").usize().unwrap_or(0),
+                    replaced,
+                    1,
+                    indices.get("componentType").usize().unwrap_or(0),
+                    false,
+                )?;
+                let changes = self.elements(
+                    view(changes)?,
+                    changes.get("byteOffset").usize().unwrap_or(0),
+                    replaced,
+                    components,
+                    kind,
+                    normalized,
+                )?;
+                for (i, &at) in indices.iter().enumerate() {
+                    let at = at as usize;
+                    if at >= count {
+                        return Err(invalid("Sparse index out of range"));
+                    }
+                    values[at * components..(at + 1) * components]
+                        .copy_from_slice(&changes[i * components..(i + 1) * components]);
+                }
+            }
+
+            Ok((values, components))
+        }
+
+        /// Reads elements from a buffer view.
+        ///
+        /// # Arguments
+        ///
+        /// * `view`       - The index of the buffer view.
+        /// * `offset`     - The offset of the first element within the view in bytes.
+        /// * `count`      - The number of elements.
+        /// * `components` - The number of components of every element.
+        /// * `kind`       - The type of the components, as glTF component type.
+        /// * `normalized` - Whether integer components get mapped to [0, 1] or [-1, 1].
+        fn elements(
+            &self,
+            view: usize,
+            offset: usize,
+            count: usize,
+            components: usize,
+            kind: usize,
+            normalized: bool,
+        ) -> io::Result<Vec<f64>> {
+            let view = self.json.get("bufferViews").at(view);
+            let buffer = view
+                .get("buffer")
+                .usize()
+                .and_then(|b| self.buffers.get(b))
+                .ok_or_else(|| invalid("Buffer doesn't exist"))?;
+            let size = match kind {
+                5120 | 5121 => 1,
+                5122 | 5123 => 2,
+                5125 | 5126 => 4,
+                _ => return Err(invalid("Unknown component type")),
+            };
+            let element = size * components;
+            let stride = view.get("byteStride").usize().unwrap_or(element);
+            if stride < element {
+                return Err(invalid("Buffer view stride shorter than its elements"));
+            }
+
+            // Crafted offsets and counts mustn't wrap around
+            let start = view.get("byteOffset").usize().unwrap_or(0);
+            let end = start.checked_add(view.get("byteLength").usize().unwrap_or(0));
+            let first = start.checked_add(offset);
+            let last = count.checked_sub(1).map_or(first, |n| {
+                stride
+                    .checked_mul(n)
+                    .and_then(|s| s.checked_add(first?))
+                    .and_then(|s| s.checked_add(element))
+            });
+            let (Some(end), Some(first), Some(last)) = (end, first, last) else {
+                return Err(invalid("Accessor beyond its buffer view"));
+            };
+            if end > buffer.len() || last > end {
+                return Err(invalid("Accessor beyond its buffer view"));
+            }
+
+            let mut values = Vec::with_capacity(count * components);
+            for i in 0..count {
+                for c in 0..components {
+                    let at = first + i * stride + c * size;
+                    let b = &buffer[at..at + size];
+                    let (value, max) = match kind {
+                        5120 => (f64::from(b[0] as i8), 127.0),
+                        5121 => (f64::from(b[0]), 255.0),
+                        5122 => (f64::from(i16::from_le_bytes([b[0], b[1]])), 32767.0),
+                        5123 => (f64::from(u16::from_le_bytes([b[0], b[1]])), 65535.0),
+                        5125 => (f64::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])), 1.0),
+                        _ => (f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])), 1.0),
+                    };
+                    values.push(if normalized {
+                        (value / max).max(-1.0)
+                    } else {
+                        value
+                    });
+                }
+            }
+
+            Ok(values)
+        }
+
+        /// Turns a material of the file into the closest material of the crate.
+        fn material(&self, material: &Json) -> io::Result<Rc<dyn Material>> {
+            let extensions = material.get("extensions");
+            let [r, g, b] = material.get("emissiveFactor").numbers([0.0; 3]);
+            let strength = extensions
+                .get("KHR_materials_emissive_strength")
+                .get("emissiveStrength")
+                .f64()
+                .unwrap_or(1.0);
+            let emission = strength * Color::with_values(r, g, b);
+            if !emission.near_zero() {
+                return Ok(Rc::new(DiffuseLight::new(emission)));
+            }
+
+            let transmission = extensions
+                .get("KHR_materials_transmission")
+                .get("transmissionFactor")
+                .f64()
+                .unwrap_or(0.0);
+            if transmission >= 0.5 {
+                let ior = extensions.get("KHR_materials_ior").get("ior").f64();
+                return Ok(Rc::new(Dielectric::new(ior.unwrap_or(1.5))));
+            }
+
+            let pbr = material.get("pbrMetallicRoughness");
+            let [r, g, b, _] = pbr.get("baseColorFactor").numbers([1.0; 4]);
+            let base_color = Color::with_values(r, g, b);
+            let metallic = pbr.get("metallicFactor").f64().unwrap_or(1.0);
+            let roughness = pbr.get("roughnessFactor").f64().unwrap_or(1.0);
+            if metallic >= 0.5 {
+                return Ok(Rc::new(Metal::new(base_color, roughness)));
+            }
+
+            let lambertian = Lambertian::new(base_color);
+            Ok(match self.texture(pbr.get("baseColorTexture"))? {
+                Some(texture) => Rc::new(lambertian.with_texture(Rc::new(texture))),
+                None => Rc::new(lambertian),
+            })
+        }
+
+        /// Loads the image of a texture reference from a file, a data URI or a buffer view.
+        ///
+        /// # Returns
+        ///
+        /// Returns `None` without a reference or for textures without a source image.
+        fn texture(&self, reference: &Json) -> io::Result<Option<ImageTexture>> {
+            let Some(index) = reference.get("index").usize() else {
+                return Ok(None);
+            };
+            let texture = self.json.get("textures").at(index);
+            if texture.is_null() {
+                return Err(invalid("Texture doesn't exist"));
+            }
+            let Some(source) = texture.get("source").usize() else {
+                return Ok(None);
+            };
+            let image = self.json.get("images").at(source);
+            if image.is_null() {
+                return Err(invalid("Image doesn't exist"));
+            }
+
+            let mime_type = image.get("mimeType").str();
+            let texture = match (image.get("uri").str(), image.get("bufferView").usize()) {
+                (Some(uri), _) if uri.starts_with("data:") => {
+                    let (header, data) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| invalid("Data URI not in base64"))?;
+                    let uri_type = &header["data:".len()..];
+                    let mime_type = Some(uri_type).filter(|t| !t.is_empty()).or(mime_type);
+                    decode_image(&base64(data)?, mime_type)?
+                }
+                (Some(uri), _) => ImageTexture::load(self.directory.join(decode_uri(uri)))?,
+                (None, Some(view)) => decode_image(self.view(view)?, mime_type)?,
+                (None, None) => return Err(invalid("Image without data")),
+            };
+
+            Ok(Some(texture))
+        }
+
+        /// Gets the bytes of a buffer view.
+        fn view(&self, index: usize) -> io::Result<&[u8]> {
+            let view = self.json.get("bufferViews").at(index);
+            let buffer = view
+                .get("buffer")
+                .usize()
+                .and_then(|b| self.buffers.get(b))
+                .ok_or_else(|| invalid("Buffer doesn't exist"))?;
+            let start = view.get("byteOffset").usize().unwrap_or(0);
+            let length = view.get("byteLength").usize().unwrap_or(0);
+            start
+                .checked_add(length)
+                .and_then(|end| buffer.get(start..end))
+                .ok_or_else(|| invalid("Buffer view beyond its buffer"))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::import::gltf::{base64, decode_uri, read_gltf, GltfScene, Json};
+        use crate::interval::Interval;
+        use crate::material::{Lambertian, Material};
+        use crate::ray::Ray;
+        use crate::texture::image::read_png;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::path::Path;
+
+        /// Encodes bytes as base64, the inverse of `base64`.
+        fn encode(bytes: &[u8]) -> String {
+            const DIGITS: &[u8] =
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+            let mut text = String::new();
+            for chunk in bytes.chunks(3) {
+                let bits = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+                for i in 0..4 {
+                    if i <= chunk.len() {
+                        text.push(char::from(DIGITS[(bits >> (18 - 6 * i) & 63) as usize]));
+                    } else {
+                        text.push('=');
+                    }
+                }
+            }
+            text
+        }
+
+        /// The buffer of a triangle facing +z, with its positions and indices.
+        fn buffer() -> Vec<u8> {
+            let mut bytes: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+                .iter()
+                .flat_map(|f| f.to_le_bytes())
+                .collect();
+            bytes.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes()));
+            bytes
+        }
+
+        /// A scene of the triangle, moved by a hierarchy of nodes, seen by a camera
+        /// and lit by a spot light pointing down.
+        fn scene(uri: &str) -> String {
+            format!(
+                r#"{{
+  "asset": {{"version": "2.0", "generator": "By \"hand\" \u00e9"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0, 2, 3]}}],
+  "nodes": [
+    {{"scale": [2, 2, 2], "children": [1]}},
+    {{"translation": [0, 0, -1], "mesh": 0}},
+    {{"translation": [0, 0, 5], "camera": 0}},
+    {{"translation": [0, 4, 0], "rotation": [-0.7071067811865476, 0, 0, 0.7071067811865476],
+     "extensions": {{"KHR_lights_punctual": {{"light": 0}}}}}},
+    {{"mesh": 0}}
+  ],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "indices": 1, "material": 0}}]}}],
+  "materials": [{{"pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0}}}}],
+  "cameras": [{{"type": "perspective", "perspective": {{"yfov": 0.5, "aspectRatio": 1.5, "znear": 0.1}}}}],
+  "extensions": {{"KHR_lights_punctual": {{"lights": [
+    {{"type": "spot", "color": [1, 1, 0.5], "intensity": 10, "spot": {{"outerConeAngle": 0.5}}}}
+  ]}}}},
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+  ],
+  "bufferViews": [{{"buffer": 0, "byteLength": 36}}, {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}],
+  "buffers": [{{"byteLength": 42{}}}]
+}}"#,
+                uri
+            )
+        }
+
+        /// Checks the scene of `scene` got read.
+        fn check(scene: &GltfScene) {
+            //       y
+            //       ^ light
+            //  (0,2)+  pointing down
+            //       |\
+            //       | \        the triangle at z = -2,
+            //       +--+-> x   seen from z = 5
+            //          (2,0)
+            assert_eq!(scene.world().len(), 1, "Node outside the scene read");
+            let ray = Ray::create(
+                Point3D::with_values(0.5, 0.5, 5.0),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+            );
+            let rec: &mut HitRecord = &mut HitRecord::default();
+            assert!(
+                scene.world().hit(&ray, Interval::new(0.0, 10.0), rec),
+                "Triangle not hit"
+            );
+            assert!((rec.t() - 7.0).abs() < 1e-12, "Nodes not transformed");
+            assert!(rec.front_face());
+            let z = Vector3D::with_values(0.0, 0.0, 1.0);
+            let red = Lambertian::new(Color::with_values(1.0, 0.0, 0.0));
+            assert_eq!(rec.material().eval(rec, z, z), red.eval(rec, z, z));
+            let miss = Ray::create(Point3D::with_values(1.5, 1.5, 5.0), -z);
+            assert!(!scene.world().hit(&miss, Interval::new(0.0, 10.0), rec));
+
+            let camera = scene.cameras()[0];
+            assert_eq!(camera.center(), Point3D::with_values(0.0, 0.0, 5.0));
+            assert_eq!(camera.direction(), -z);
+            assert_eq!(camera.aspect_ratio(), Some(1.5));
+            assert!((camera.vertical_fov() - 0.5f64.to_degrees()).abs() < 1e-12);
+            let cam = camera.to_camera(300, 1, 1);
+            assert_eq!(cam.image_height(), 200);
+            assert_eq!(cam.center(), camera.center());
+
+            assert_eq!(scene.lights().len(), 1);
+            let below = scene.lights()[0].sample(Point3D::with_values(0.0, 2.0, 0.0));
+            assert!((below.direction - Vector3D::with_values(0.0, 1.0, 0.0)).length() < 1e-12);
+            assert!((below.illumination - Color::with_values(2.5, 2.5, 1.25)).length() < 1e-9);
+            let aside = scene.lights()[0].sample(Point3D::with_values(4.0, 2.0, 0.0));
+            assert_eq!(aside.illumination, Color::new(), "Spot not pointing down");
+        }
+
+        #[test]
+        fn json_parse() {
+            let json = Json::parse(
+                r#" {"a": [1, -2.5e2, true, false, null], "b": {"c": "\"\\\/\n\u00e9\ud83d\ude00"}, "d": []} "#,
+            )
+            .unwrap();
+
+            assert_eq!(json.get("a").at(1).f64(), Some(-250.0));
+            assert_eq!(json.get("a").at(2), &Json::Bool(true));
+            assert!(json.get("a").at(4).is_null());
+            assert!(json.get("a").at(5).is_null(), "Missing element not null");
+            assert_eq!(json.get("b").get("c").str(), Some("\"\\/\né😀"));
+            assert!(json.get("d").array().is_empty());
+            assert!(json.get("e").is_null(), "Missing member not null");
+
+            for text in ["", "{", "[1,]", "{\"a\" 1}", "\"a", "1 2", "tru", "\"\\x\""] {
+                assert!(Json::parse(text).is_err(), "Parsed invalid {:?}", text);
+            }
+
+            // Deep nesting fails instead of overflowing the stack
+            assert!(Json::parse(&"[".repeat(200000)).is_err());
+            let nested = format!("{}{}", "[".repeat(100), "]".repeat(100));
+            assert!(Json::parse(&nested).is_ok());
+        }
+
+        #[test]
+        fn uri_decode() {
+            assert_eq!(base64(&encode(b"glTF 2.0")).unwrap(), b"glTF 2.0");
+            assert_eq!(base64("Z2x=").unwrap(), b"gl");
+            assert!(base64("Z2*").is_err());
+            assert_eq!(decode_uri("my%20mesh.bin"), "my mesh.bin");
+            assert_eq!(decode_uri("100%"), "100%");
+        }
+
+        #[test]
+        fn gltf_read() {
+            let uri = format!(
+                r#", "uri": "data:application/octet-stream;base64,{}""#,
+                encode(&buffer())
+            );
+            let text = scene(&uri);
+            check(&read_gltf(&mut text.as_bytes(), Path::new("")).unwrap());
+        }
+
+        #[test]
+        fn gltf_read_binary() {
+            let mut json = scene("").into_bytes();
+            json.resize(json.len().next_multiple_of(4), b' ');
+            let mut binary = buffer();
+            binary.resize(binary.len().next_multiple_of(4), 0);
+
+            let mut glb = b"glTF".to_vec();
+            glb.extend(2u32.to_le_bytes());
+            glb.extend((12 + 8 + json.len() as u32 + 8 + binary.len() as u32).to_le_bytes());
+            glb.extend((json.len() as u32).to_le_bytes());
+            glb.extend(b"JSON");
+            glb.extend(json);
+            glb.extend((binary.len() as u32).to_le_bytes());
+            glb.extend(b"BIN\0");
+            glb.extend(binary);
+
+            check(&read_gltf(&mut glb.as_slice(), Path::new("")).unwrap());
+        }
+
+        #[test]
+        fn gltf_read_textures() {
+            // A single gray pixel, multiplied with the red base color
+            let mut png = Vec::new();
+            let mut encoder = png::Encoder::new(&mut png, 1, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[128]).unwrap();
+            writer.finish().unwrap();
+            let gray = read_png(&mut png.as_slice()).unwrap().2[0];
+
+            let mut bytes = buffer();
+            bytes.extend(&png);
+            let uri = format!(
+                r#", "uri": "data:application/octet-stream;base64,{}""#,
+                encode(&bytes)
+            );
+            let textured = |image: &str| {
+                scene(&uri)
+                    .replace(
+                        "\"metallicFactor\": 0}}]",
+                        &format!(
+                            r#""metallicFactor": 0, "baseColorTexture": {{"index": 0}}}}}}],
+  "textures": [{{"source": 0}}],
+  "images": [{}]"#,
+                            image
+                        ),
+                    )
+                    .replace(
+                        "\"byteLength\": 6}]",
+                        &format!(
+                            r#""byteLength": 6}}, {{"buffer": 0, "byteOffset": 42, "byteLength": {}}}]"#,
+                            png.len()
+                        ),
+                    )
+                    .replace("\"byteLength\": 42", &format!("\"byteLength\": {}", bytes.len()))
+            };
+
+            let z = Vector3D::with_values(0.0, 0.0, 1.0);
+            let ray = Ray::create(Point3D::with_values(0.5, 0.5, 5.0), -z);
+            let expected = Lambertian::new(Color::with_values(gray.x(), 0.0, 0.0));
+            for image in [
+                r#"{"bufferView": 2, "mimeType": "image/png"}"#.to_string(),
+                format!(r#"{{"uri": "data:image/png;base64,{}"}}"#, encode(&png)),
+            ] {
+                let text = textured(&image);
+                let scene = read_gltf(&mut text.as_bytes(), Path::new("")).unwrap();
+                let rec: &mut HitRecord = &mut HitRecord::default();
+                assert!(scene.world().hit(&ray, Interval::new(0.0, 10.0), rec));
+                assert_eq!(
+                    rec.material().eval(rec, z, z),
+                    expected.eval(rec, z, z),
+                    "Texture of {} not applied",
+                    image
+                );
+            }
+
+            for image in [
+                r#"{"bufferView": 2, "mimeType": "image/gif"}"#,
+                r#"{"bufferView": 1, "mimeType": "image/png"}"#,
+                r#"{"uri": "missing.png"}"#,
+                r#"{}"#,
+            ] {
+                let text = textured(image);
+                assert!(
+                    read_gltf(&mut text.as_bytes(), Path::new("")).is_err(),
+                    "Read unreadable image {}",
+                    image
+                );
+            }
+        }
+
+        #[test]
+        fn gltf_read_invalid() {
+            let uri = format!(
+                r#", "uri": "data:application/octet-stream;base64,{}""#,
+                encode(&buffer())
+            );
+            let valid = scene(&uri);
+            for text in [
+                "{}".to_string(),
+                valid.replace("\"2.0\"", "\"1.0\""),
+                valid.replace(
+                    "\"count\": 3, \"type\": \"VEC3\"",
+                    "\"count\": 4, \"type\": \"VEC3\"",
+                ),
+                valid.replace("\"byteLength\": 42", "\"byteLength\": 43"),
+                valid.replace("\"children\": [1]", "\"children\": [0]"),
+                valid.replace("\"material\": 0", "\"material\": 1"),
+                valid.replace(
+                    "\"bufferView\": 0, \"componentType\": 5126, \"count\": 3",
+                    "\"componentType\": 5126, \"count\": 1000000000000000",
+                ),
+                valid.replace(
+                    "\"bufferView\": 0,",
+                    "\"bufferView\": 0, \"byteOffset\": 18446744073709551000,",
+                ),
+                valid.replace(
+                    "\"byteLength\": 36}",
+                    "\"byteLength\": 36, \"byteStride\": 4}",
+                ),
+                scene(r#", "uri": "missing.bin""#),
+                scene(""),
+            ] {
+                assert!(
+                    read_gltf(&mut text.as_bytes(), Path::new("")).is_err(),
+                    "Read invalid {:?}",
+                    text
+                );
+            }
+
+            let mut glb = b"glTF".to_vec();
+            glb.extend(1u32.to_le_bytes());
+            glb.extend(12u32.to_le_bytes());
+            assert!(read_gltf(&mut glb.as_slice(), Path::new("")).is_err());
+        }
+    }
+}
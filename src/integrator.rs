@@ -0,0 +1,3425 @@
+//! Module for the integrators turning camera rays into colors in the context of a raytracer.
+
+use crate::atmosphere::Atmosphere;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hittable::hittables::HittableList;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::light::tree::LightTree;
+use crate::light::{Light, LightList};
+use crate::ray::Ray;
+use crate::sampler::random_f64;
+use crate::vector3d::Vector3D;
+use std::f64::consts::PI;
+
+pub trait Integrator {
+    /// Computes the color seen along a camera ray.
+    ///
+    /// # Arguments
+    ///
+    /// * `r`     - The camera ray.
+    /// * `depth` - How many more times the ray may bounce.
+    /// * `world` - The geometry of the scene.
+    ///
+    /// # Returns
+    ///
+    /// Returns the (unnormalized) color sample for the ray.
+    fn ray_color(&self, r: &Ray, depth: u16, world: &HittableList) -> Color;
+}
+
+pub trait ImageIntegrator {
+    /// Computes the color of every pixel of the camera's image at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `cam`   - The camera generating the rays of every pixel.
+    /// * `world` - The geometry of the scene.
+    ///
+    /// # Returns
+    ///
+    /// Returns the final color of every pixel, row by row from the top left.
+    fn render_image(&self, cam: &Camera, world: &HittableList) -> Vec<Color>;
+}
+
+/// Represents light leaving a point on a light source, as sampled by `sample_emission`.
+pub(crate) struct Emission {
+    /// The sampled point on the light with its outward normal.
+    pub rec: HitRecord,
+    /// The ray the light leaves the point with.
+    pub ray: Ray,
+    /// The radiance emitted along `ray`.
+    pub emitted: Color,
+    /// The area density of choosing the point among all lights.
+    pub pdf_pos: f64,
+    /// The solid angle density of choosing the direction of `ray`.
+    pub pdf_dir: f64,
+}
+
+/// Samples light leaving a uniformly chosen point on a uniformly chosen light
+/// in a cosine distributed direction.
+///
+/// Returns `None` if the chosen light can't be sampled.
+pub(crate) fn sample_emission(lights: &HittableList) -> Option<Emission> {
+    if lights.is_empty() {
+        return None;
+    }
+    let index = ((random_f64() * lights.len() as f64) as usize).min(lights.len() - 1);
+    let light = &lights[index];
+    if light.area() <= 0.0 {
+        return None;
+    }
+    let rec = light.sample_surface()?;
+
+    let direction = Vector3D::random_cosine_direction(rec.normal());
+    let pdf_dir = rec.normal().dot(direction) / PI;
+    if pdf_dir <= 0.0 {
+        return None;
+    }
+
+    Some(Emission {
+        emitted: rec.material().emitted(&rec),
+        ray: Ray::create(rec.p(), direction),
+        pdf_pos: 1.0 / (lights.len() as f64 * light.area()),
+        pdf_dir,
+        rec,
+    })
+}
+
+/// Sums up the light of every light source visible from the hit, reflected towards `r`.
+///
+/// # Arguments
+///
+/// * `lights`     - The light sources of the scene.
+/// * `r`          - The ray that hit the surface.
+/// * `rec`        - The hit record of the surface.
+/// * `world`      - The geometry of the scene, casting shadows.
+/// * `atmosphere` - The medium filling the scene, dimming the light, if any.
+/// * `mis`        - Whether the light of area lights gets weighted against scattering onto them.
+pub(crate) fn direct_light(
+    lights: &LightList,
+    r: &Ray,
+    rec: &HitRecord,
+    world: &HittableList,
+    atmosphere: Option<&Atmosphere>,
+    mis: bool,
+) -> Color {
+    let wo = -r.direction().unit_vector();
+    let pmf = mis.then_some(1.0);
+    lights
+        .iter()
+        .map(|light| light_contribution(light.as_ref(), wo, rec, world, atmosphere, pmf))
+        .fold(Color::new(), |sum, color| sum + color)
+}
+
+/// Computes the light of point, spot and directional lights arriving at a hit,
+/// choosing a single light of the tree per hit and every light infinitely far away.
+///
+/// # Arguments
+///
+/// * `tree`       - The light sources of the scene.
+/// * `r`          - The ray that hit the surface.
+/// * `rec`        - The hit record of the surface.
+/// * `world`      - The geometry of the scene, casting shadows.
+/// * `atmosphere` - The medium filling the scene, dimming the light, if any.
+/// * `mis`        - Whether the light of area lights gets weighted against scattering onto them.
+pub(crate) fn sampled_direct_light(
+    tree: &LightTree,
+    r: &Ray,
+    rec: &HitRecord,
+    world: &HittableList,
+    atmosphere: Option<&Atmosphere>,
+    mis: bool,
+) -> Color {
+    let mut color = direct_light(tree.unbounded(), r, rec, world, atmosphere, mis);
+
+    // Light reaches points within volumes from every side
+    if let Some((index, pmf)) = tree.sample(rec.p(), tree_normal(rec), random_f64()) {
+        let wo = -r.direction().unit_vector();
+        let light = tree.bounded()[index].as_ref();
+        let mis_pmf = mis.then_some(pmf);
+        color += light_contribution(light, wo, rec, world, atmosphere, mis_pmf) / pmf;
+    }
+
+    color
+}
+
+/// Gets the normal a light tree chooses lights for a hit with, zero within volumes,
+/// where light arrives from every side.
+pub(crate) fn tree_normal(rec: &HitRecord) -> Vector3D {
+    if rec.material().is_volumetric() {
+        Vector3D::new()
+    } else {
+        rec.normal()
+    }
+}
+
+/// Computes the light of a single light reflected at a hit towards `wo`, nothing if shadowed.
+///
+/// Given the probability `mis_pmf` of having chosen the light, light of lights rays can
+/// hit gets weighted against scattering onto them with the power heuristic.
+fn light_contribution(
+    light: &dyn Light,
+    wo: Vector3D,
+    rec: &HitRecord,
+    world: &HittableList,
+    atmosphere: Option<&Atmosphere>,
+    mis_pmf: Option<f64>,
+) -> Color {
+    let sample = light.sample(rec.p());
+    let cos_theta = foreshortening(rec, sample.direction);
+    if cos_theta <= 0.0 {
+        return Color::new();
+    }
+
+    let weight = match mis_pmf {
+        Some(pmf) => {
+            let light_pdf = pmf * light.pdf(rec.p(), sample.direction);
+            if light_pdf > 0.0 {
+                let bsdf_pdf = rec.material().pdf(rec, wo, sample.direction);
+                light_pdf * light_pdf / (light_pdf * light_pdf + bsdf_pdf * bsdf_pdf)
+            } else {
+                1.0
+            }
+        }
+        None => 1.0,
+    };
+    if weight <= 0.0 {
+        return Color::new();
+    }
+
+    // Surfaces block the light, media in between dim it
+    let shadow_ray = Ray::create(rec.p(), sample.direction);
+    let transmittance = world.transmittance(&shadow_ray, Interval::new(0.001, sample.distance));
+    if transmittance <= 0.0 {
+        return Color::new();
+    }
+
+    let illumination = match atmosphere {
+        Some(atmosphere) => {
+            sample.illumination * atmosphere.transmittance(&shadow_ray, sample.distance)
+        }
+        None => sample.illumination,
+    };
+
+    rec.material().eval(rec, wo, sample.direction)
+        * illumination
+        * (cos_theta * transmittance * weight)
+}
+
+/// Computes the cosine of the light arriving at a hit from the unit direction `wi`,
+/// 1 for hits within volumes, which don't foreshorten light.
+pub(crate) fn foreshortening(rec: &HitRecord, wi: Vector3D) -> f64 {
+    if rec.material().is_volumetric() {
+        1.0
+    } else {
+        rec.normal().dot(wi)
+    }
+}
+
+// --- PATH TRACER -------------------------------------------------------------
+
+pub mod path_tracer {
+    //! Module for the stochastic path tracer the camera renders with by default.
+
+    use crate::atmosphere::Atmosphere;
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::{
+        direct_light, foreshortening, sampled_direct_light, tree_normal, Integrator,
+    };
+    use crate::interval::Interval;
+    use crate::light::area::AreaLight;
+    use crate::light::tree::LightTree;
+    use crate::light::LightList;
+    use crate::ray::Ray;
+    use crate::vector3d::Vector3D;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    /// The stochastic path tracer implemented by `Camera::ray_color`, adding the
+    /// light of point, spot and directional lights at every hit by next-event estimation.
+    ///
+    /// Such lights can't be hit by rays, so their light never gets counted twice.
+    /// Emitting objects registered as area lights, see `with_emitters`, and
+    /// environments which can be importance sampled get sampled at every hit as
+    /// well, weighted against finding them by scattering with multiple importance
+    /// sampling.
+    ///
+    /// Scenes with many lights should choose a single light per hit from a light
+    /// tree instead of adding up all of them, see `with_light_tree`. Scenes filled
+    /// with air or fog scatter rays between the hits, see `with_atmosphere`.
+    #[derive(Clone)]
+    pub struct PathTracer {
+        lights: LightList,
+        environment: Rc<dyn Environment>,
+        light_tree: Option<Rc<LightTree>>,
+        atmosphere: Option<Rc<Atmosphere>>,
+        /// The index within `lights` of the area light of every emitting object of the world.
+        emitters: HashMap<usize, usize>,
+    }
+
+    impl Default for PathTracer {
+        /// Creates a path tracer without lights under the white to light blue sky.
+        fn default() -> Self {
+            Self::new(Vec::new())
+        }
+    }
+
+    impl PathTracer {
+        /// Creates a new path tracer lighting the scene with the given lights
+        /// in addition to emitting geometry and the sky.
+        pub fn new(lights: LightList) -> Self {
+            PathTracer {
+                lights,
+                environment: Rc::new(Gradient::default()),
+                light_tree: None,
+                atmosphere: None,
+                emitters: HashMap::new(),
+            }
+        }
+
+        /// Samples a single light per hit from a tree over the lights, proportional to
+        /// how much light it may cast there, instead of adding up the light of all of them.
+        pub fn with_light_tree(mut self) -> Self {
+            self.light_tree = Some(Rc::new(LightTree::new(&self.lights)));
+            self
+        }
+
+        /// Samples the emitting objects of the world as area lights at every hit.
+        ///
+        /// The world must be the list the path tracer renders, hits are matched to
+        /// their lights by the index of the object within it.
+        pub fn with_emitters(mut self, world: &HittableList) -> Self {
+            for (id, object) in world.iter().enumerate() {
+                if let Some(light) = AreaLight::new(object.clone()) {
+                    self.emitters.insert(id, self.lights.len());
+                    self.lights.push(Rc::new(light));
+                }
+            }
+            if self.light_tree.is_some() {
+                self = self.with_light_tree();
+            }
+            self
+        }
+
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Fills the space between the hits with the given atmosphere.
+        pub fn with_atmosphere(mut self, atmosphere: Rc<Atmosphere>) -> Self {
+            self.atmosphere = Some(atmosphere);
+            self
+        }
+
+        /// Gets the lights of the scene.
+        pub fn lights(&self) -> &LightList {
+            &self.lights
+        }
+
+        /// Gets the environment surrounding the scene.
+        pub fn environment(&self) -> &dyn Environment {
+            self.environment.as_ref()
+        }
+
+        /// Gets the tree lights are chosen from, if any.
+        pub fn light_tree(&self) -> Option<&LightTree> {
+            self.light_tree.as_deref()
+        }
+
+        /// Gets the atmosphere filling the scene, if any.
+        pub fn atmosphere(&self) -> Option<&Atmosphere> {
+            self.atmosphere.as_deref()
+        }
+
+        /// Samples the light of the environment arriving at the hit, reflected towards `r`.
+        fn direct_environment(&self, r: &Ray, rec: &HitRecord, world: &HittableList) -> Color {
+            let Some((wi, env_pdf)) = self.environment.sample() else {
+                return Color::new();
+            };
+            let cos_theta = foreshortening(rec, wi);
+            if cos_theta <= 0.0 || env_pdf <= 0.0 {
+                return Color::new();
+            }
+
+            let shadow_ray = Ray::create(rec.p(), wi);
+            let transmittance =
+                world.transmittance(&shadow_ray, Interval::new(0.001, f64::INFINITY));
+            if transmittance <= 0.0 {
+                return Color::new();
+            }
+
+            let wo = -r.direction().unit_vector();
+            let bsdf_pdf = rec.material().pdf(rec, wo, wi);
+            let weight = env_pdf * env_pdf / (env_pdf * env_pdf + bsdf_pdf * bsdf_pdf);
+            let radiance = match &self.atmosphere {
+                Some(atmosphere) => {
+                    self.environment.radiance(wi)
+                        * atmosphere.transmittance(&shadow_ray, f64::INFINITY)
+                }
+                None => self.environment.radiance(wi),
+            };
+
+            rec.material().eval(rec, wo, wi) * radiance * cos_theta / env_pdf
+                * (weight * transmittance)
+        }
+
+        /// Computes the weight of the light of a hit emitter found by scattering with
+        /// density `bsdf_pdf` from a point with normal `n`, against sampling its area light.
+        fn emission_weight(&self, r: &Ray, rec: &HitRecord, bsdf_pdf: f64, n: Vector3D) -> f64 {
+            let Some(&index) = self.emitters.get(&rec.object_id()) else {
+                return 1.0;
+            };
+            let pmf = match &self.light_tree {
+                Some(tree) => tree
+                    .bounded_index(index)
+                    .map_or(0.0, |bounded| tree.pmf(r.origin(), n, bounded)),
+                None => 1.0,
+            };
+
+            let light_pdf = pmf * self.lights[index].pdf(r.origin(), r.direction());
+            if light_pdf <= 0.0 {
+                return 1.0;
+            }
+            bsdf_pdf * bsdf_pdf / (bsdf_pdf * bsdf_pdf + light_pdf * light_pdf)
+        }
+
+        /// Computes the light arriving along `r`.
+        ///
+        /// # Arguments
+        ///
+        /// * `r`       - The ray.
+        /// * `depth`   - How many more times the ray may bounce.
+        /// * `world`   - The geometry of the scene.
+        /// * `scatter` - The density of scattering into `r` and the normal lights got
+        ///   chosen for at its origin, `None` if not scattered diffusely.
+        fn trace(
+            &self,
+            r: &Ray,
+            depth: u16,
+            world: &HittableList,
+            scatter: Option<(f64, Vector3D)>,
+        ) -> Color {
+            // If we've exceeded the ray bounce limit, no more light is gathered.
+            if depth == 0 {
+                return Color::new();
+            }
+
+            let mut rec = HitRecord::default();
+            let hit = world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec);
+
+            // The atmosphere may scatter the ray before it reaches the hit
+            let mut weight = Color::with_values(1.0, 1.0, 1.0);
+            if let Some(atmosphere) = &self.atmosphere {
+                let t_max = if hit { rec.t() } else { f64::INFINITY };
+                let (t, sample_weight) = atmosphere.sample(r, t_max);
+                weight = sample_weight;
+
+                if let Some(t) = t {
+                    // Normal and face are arbitrary, the phase function doesn't depend on them
+                    let p = r.at(t);
+                    let scattering =
+                        HitRecord::new(p, Vector3D::with_values(1.0, 0.0, 0.0), t, true)
+                            .with_material(atmosphere.scattering_at(p));
+                    return weight * self.shade(r, depth, world, &scattering, 1.0);
+                }
+            }
+
+            if !hit {
+                let radiance = self.environment.radiance(r.direction());
+                return weight
+                    * match scatter {
+                        Some((bsdf_pdf, _)) => {
+                            let env_pdf = self.environment.pdf(r.direction().unit_vector());
+                            radiance
+                                * (bsdf_pdf * bsdf_pdf / (bsdf_pdf * bsdf_pdf + env_pdf * env_pdf))
+                        }
+                        None => radiance,
+                    };
+            }
+
+            let emission_weight = match scatter {
+                Some((bsdf_pdf, n)) => self.emission_weight(r, &rec, bsdf_pdf, n),
+                None => 1.0,
+            };
+            weight * self.shade(r, depth, world, &rec, emission_weight)
+        }
+
+        /// Computes the light leaving a hit along `r` back towards its origin.
+        ///
+        /// # Arguments
+        ///
+        /// * `r`     - The ray that hit the surface.
+        /// * `depth` - How many more times the ray may bounce.
+        /// * `world` - The geometry of the scene.
+        /// * `rec`   - The hit record of the surface, or of a point within a medium.
+        /// * `emission_weight` - The weight of the light emitted at the hit.
+        fn shade(
+            &self,
+            r: &Ray,
+            depth: u16,
+            world: &HittableList,
+            rec: &HitRecord,
+            emission_weight: f64,
+        ) -> Color {
+            let atmosphere = self.atmosphere.as_deref();
+            let mat = rec.material();
+
+            // Only scattered rays may find area lights again
+            let mis = depth > 1 && !mat.is_specular();
+            let mut color = mat.emitted(rec) * emission_weight
+                + match &self.light_tree {
+                    Some(tree) => sampled_direct_light(tree, r, rec, world, atmosphere, mis),
+                    None => direct_light(&self.lights, r, rec, world, atmosphere, mis),
+                };
+
+            // Like scattered rays, sampled environment light needs a bounce left
+            if depth > 1 && !mat.is_specular() {
+                color += self.direct_environment(r, rec, world);
+            }
+
+            let (scattered, attenuation, scattered_ray) = mat.scatter(r, rec);
+            if scattered {
+                let next = if mat.is_specular() {
+                    None
+                } else {
+                    let wo = -r.direction().unit_vector();
+                    let bsdf_pdf = mat.pdf(rec, wo, scattered_ray.direction().unit_vector());
+                    Some((bsdf_pdf, tree_normal(rec)))
+                };
+                color += attenuation * self.trace(&scattered_ray, depth - 1, world, next);
+            }
+
+            color
+        }
+    }
+
+    impl Integrator for PathTracer {
+        fn ray_color(&self, r: &Ray, depth: u16, world: &HittableList) -> Color {
+            self.trace(r, depth, world, None)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::atmosphere::Atmosphere;
+        use crate::color::Color;
+        use crate::environment::map::EnvironmentMap;
+        use crate::environment::uniform::Uniform;
+        use crate::environment::Environment;
+        use crate::hittable::constant_medium::ConstantMedium;
+        use crate::hittable::disk::Disk;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::plane::Plane;
+        use crate::hittable::sphere::Sphere;
+        use crate::integrator::path_tracer::PathTracer;
+        use crate::integrator::Integrator;
+        use crate::light::point::PointLight;
+        use crate::light::{Light, LightList};
+        use crate::material::{DiffuseLight, Lambertian, Subsurface};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::f64::consts::PI;
+        use std::rc::Rc;
+
+        #[test]
+        fn path_tracer_furnace() {
+            //        .-*****-.       A diffuse sphere (R=2, albedo 0.5)
+            //      *   .***.   *     around an emitting sphere (r=1, L=1):
+            //     *   * c   *  o-->X
+            //      *   '***'   *     L = a·L·(r/R)² / (1 - a·(1 - (r/R)²)) = 0.2
+            //        ''*****''
+
+            let world: HittableList = vec![
+                Rc::new(Sphere::with_material(
+                    Point3D::new(),
+                    1.0,
+                    Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+                )),
+                Rc::new(Sphere::with_material(
+                    Point3D::new(),
+                    2.0,
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+            let ray: Ray = Ray::create(
+                Point3D::with_values(1.5, 0.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+            );
+
+            let samples = 20000;
+            let mut sum = Color::new();
+            for _ in 0..samples {
+                sum += PathTracer::default().ray_color(&ray, 50, &world);
+            }
+            let mean = sum / f64::from(samples);
+
+            assert!(
+                (mean.x() - 0.2).abs() < 0.01,
+                "Furnace radiance {} not 0.2",
+                mean
+            );
+        }
+
+        #[test]
+        fn path_tracer_next_event_estimation() {
+            //   *     o----------->X     c
+            // light (0,0)        (1,0) (2,0)
+            // (-1,0)
+
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+            ))];
+            let light = PointLight::new(
+                Point3D::with_values(-1.0, 0.0, 0.0),
+                Color::with_values(4.0 * PI, 4.0 * PI, 4.0 * PI),
+            );
+            let path_tracer = PathTracer::new(vec![Rc::new(light)]);
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            // Only the direct light arrives without bouncing any further
+            let color = path_tracer.ray_color(&ray, 1, &world);
+            assert!(
+                (color - Color::with_values(0.5, 0.5, 0.5)).near_zero(),
+                "Lit diffuse surface {} not its albedo",
+                color
+            );
+            assert_eq!(path_tracer.ray_color(&ray, 0, &world), Color::new());
+        }
+
+        #[test]
+        fn path_tracer_subsurface() {
+            //    .-*****-.     Light walking through a white wax sphere under a
+            //   *  ~  ~   *    uniform white sky always finds its way out again,
+            //  *  ~ c  ~   *<----o  while a darker wax swallows some of it.
+            //   *   ~  ~  *
+            //    ''*****''
+            let sky = Rc::new(Uniform::new(Color::with_values(1.0, 1.0, 1.0)));
+            let path_tracer = PathTracer::default().with_environment(sky);
+            let ray: Ray = Ray::create(
+                Point3D::with_values(3.0, 0.0, 0.0),
+                Vector3D::with_values(-1.0, 0.0, 0.0),
+            );
+            let wax = |albedo: f64| -> HittableList {
+                vec![Rc::new(Sphere::with_material(
+                    Point3D::new(),
+                    1.0,
+                    Rc::new(Subsurface::new(
+                        Color::with_values(albedo, albedo, albedo),
+                        0.1,
+                    )),
+                ))]
+            };
+
+            let samples = 2000;
+            let mean = |world: &HittableList| {
+                (0..samples)
+                    .map(|_| path_tracer.ray_color(&ray, 500, world).y())
+                    .sum::<f64>()
+                    / f64::from(samples)
+            };
+
+            let white = mean(&wax(1.0));
+            assert!((white - 1.0).abs() < 0.01, "White wax turned {}", white);
+            let grey = mean(&wax(0.5));
+            assert!(grey > 0.45 && grey < 0.7, "Grey wax turned {}", grey);
+        }
+
+        #[test]
+        fn path_tracer_constant_medium() {
+            //    .-*****-.     A white fog under a uniform white sky only ever
+            //   *:::::::::*    scatters light around, so everything stays white.
+            //  *:::: c ::::*<----o
+            //   *:::::::::*
+            //    ''*****''
+
+            let boundary = Rc::new(Sphere::new(Point3D::new(), 1.0));
+            let world: HittableList = vec![Rc::new(ConstantMedium::new(
+                boundary,
+                2.0,
+                Color::with_values(1.0, 1.0, 1.0),
+            ))];
+            let path_tracer = PathTracer::default()
+                .with_environment(Rc::new(Uniform::new(Color::with_values(1.0, 1.0, 1.0))));
+            let ray: Ray = Ray::create(
+                Point3D::with_values(3.0, 0.0, 0.0),
+                Vector3D::with_values(-1.0, 0.0, 0.0),
+            );
+
+            for _ in 0..1000 {
+                let color = path_tracer.ray_color(&ray, 50, &world);
+                assert!(
+                    (color - Color::with_values(1.0, 1.0, 1.0)).near_zero(),
+                    "White fog turned {}",
+                    color
+                );
+            }
+
+            // A point light lights the fog from every side, without foreshortening,
+            // even along the arbitrary normal of the scattering point at (1,0)
+            let light = PointLight::new(
+                Point3D::with_values(1.0, 0.0, 5.0),
+                Color::with_values(1.0, 1.0, 1.0),
+            );
+            let lit = PathTracer::new(vec![Rc::new(light)])
+                .with_environment(Rc::new(Uniform::new(Color::new())));
+            let dense: HittableList = vec![Rc::new(ConstantMedium::new(
+                Rc::new(Sphere::new(Point3D::new(), 1.0)),
+                1e9,
+                Color::with_values(1.0, 1.0, 1.0),
+            ))];
+            let color = lit.ray_color(&ray, 1, &dense);
+            assert!(color.x() > 0.0, "Fog not lit from the side");
+        }
+
+        #[test]
+        fn path_tracer_atmosphere_furnace() {
+            //    \  ~ ~ ~ ~ /     Air scattering without absorbing over a white
+            //     o  ~ ~ ~ ~      ground under a uniform white sky only ever
+            //  ~ ~ ~ ~ ~ ~ ~ ~    moves light around, so everything stays white.
+            // =================
+
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(0.0, -1e4, 0.0),
+                1e4,
+                Rc::new(Lambertian::new(Color::with_values(1.0, 1.0, 1.0))),
+            ))];
+            let air = Atmosphere::new().with_rayleigh(Color::with_values(0.2, 0.2, 0.2), 2.0);
+            let path_tracer = PathTracer::default()
+                .with_environment(Rc::new(Uniform::new(Color::with_values(1.0, 1.0, 1.0))))
+                .with_atmosphere(Rc::new(air));
+            assert!(path_tracer.atmosphere().is_some());
+
+            let samples = 2000;
+            let mut sum = Color::new();
+            for i in 0..samples {
+                let direction = Vector3D::with_values(f64::from(i % 7) - 3.0, -0.5, -1.0);
+                let ray = Ray::create(Point3D::with_values(0.0, 1.0, 0.0), direction);
+                let color = path_tracer.ray_color(&ray, 50, &world);
+                assert!(
+                    color.x() <= 1.0 + 1e-9,
+                    "Air brightened the sky to {}",
+                    color
+                );
+                sum += color;
+            }
+            let mean = sum / f64::from(samples);
+            assert!(mean.x() > 0.99, "Air darkened the sky to {}", mean);
+        }
+
+        #[test]
+        fn path_tracer_atmosphere_haze() {
+            //   c <- - - - - - - - - - o   An emitter 9 units behind absorbing
+            // (0,1,-10)             (0,1,0) fog fades to e^(-0.1·9)
+
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(0.0, 1.0, -10.0),
+                1.0,
+                Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+            ))];
+            let fog = Atmosphere::new().with_height_fog(0.1, 1e12, Color::new());
+            let path_tracer = PathTracer::default()
+                .with_environment(Rc::new(Uniform::new(Color::new())))
+                .with_atmosphere(Rc::new(fog));
+            let ray = Ray::create(
+                Point3D::with_values(0.0, 1.0, 0.0),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+            );
+
+            let samples = 20000;
+            let mut sum = Color::new();
+            for _ in 0..samples {
+                sum += path_tracer.ray_color(&ray, 50, &world);
+            }
+            let mean = sum / f64::from(samples);
+
+            let expected = (-0.9f64).exp();
+            assert!(
+                (mean.x() - expected).abs() < 0.015,
+                "Hazy emitter {} not {}",
+                mean,
+                expected
+            );
+        }
+
+        #[test]
+        fn path_tracer_light_tree() {
+            //   * * * * *   o----------->X     c
+            //    lights   (0,0)        (1,0) (2,0)
+
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+            ))];
+            let lights: LightList = (1..=16)
+                .map(|i| {
+                    let offset = f64::from(i);
+                    Rc::new(PointLight::new(
+                        Point3D::with_values(-offset, offset.sin(), offset.cos()),
+                        Color::with_values(offset, offset, offset),
+                    )) as Rc<dyn Light>
+                })
+                .collect();
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            // Choosing a single light per hit converges to the light of all of them
+            let expected = PathTracer::new(lights.clone()).ray_color(&ray, 1, &world);
+            let path_tracer = PathTracer::new(lights).with_light_tree();
+            assert!(path_tracer.light_tree().is_some());
+
+            let samples = 20000;
+            let mut sum = Color::new();
+            for _ in 0..samples {
+                sum += path_tracer.ray_color(&ray, 1, &world);
+            }
+            let mean = sum / f64::from(samples);
+
+            assert!(
+                (mean.x() - expected.x()).abs() < 0.02 * expected.x(),
+                "Light tree estimate {} not {}",
+                mean,
+                expected
+            );
+        }
+
+        #[test]
+        fn path_tracer_emitters() {
+            //        ===         An emitting disk (r=0.1, L=100) facing down onto a
+            //         |          diffuse floor (albedo 0.5), lit with
+            //      o  |          E = π·L·r² / (1 + r²) ≈ 3.11, seen as
+            //        '-._        L = 0.5 / π · E ≈ 0.495
+            //   ---------X----
+
+            let world: HittableList = vec![
+                Rc::new(Plane::with_material(
+                    Point3D::new(),
+                    Vector3D::with_values(0.0, 1.0, 0.0),
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+                Rc::new(Disk::with_material(
+                    Point3D::with_values(0.0, 1.0, 0.0),
+                    Vector3D::with_values(0.0, -1.0, 0.0),
+                    0.1,
+                    Rc::new(DiffuseLight::new(Color::with_values(100.0, 100.0, 100.0))),
+                )),
+            ];
+            let ray: Ray = Ray::create(
+                Point3D::with_values(1.0, 0.5, 0.0),
+                Vector3D::with_values(-1.0, -0.5, 0.0),
+            );
+            let expected = 0.5 / PI * (PI * 100.0 * 0.01 / 1.01);
+
+            let dark = Rc::new(Uniform::new(Color::new()));
+            let scattering = PathTracer::default().with_environment(dark.clone());
+            let sampling = PathTracer::default()
+                .with_environment(dark.clone())
+                .with_emitters(&world);
+            let sampling_tree = PathTracer::default()
+                .with_environment(dark)
+                .with_light_tree()
+                .with_emitters(&world);
+            assert_eq!(sampling.lights().len(), 1, "Floor or disk not told apart");
+            assert!(sampling_tree.light_tree().is_some());
+
+            // Sampling the disk finds it far more often than scattering onto it
+            let estimate = |path_tracer: &PathTracer| {
+                let samples = 4000;
+                let values: Vec<f64> = (0..samples)
+                    .map(|_| path_tracer.ray_color(&ray, 2, &world).x())
+                    .collect();
+                let mean = values.iter().sum::<f64>() / f64::from(samples);
+                let variance =
+                    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / f64::from(samples);
+                (mean, variance)
+            };
+            let (_, scattering_variance) = estimate(&scattering);
+            for path_tracer in [&sampling, &sampling_tree] {
+                let (mean, variance) = estimate(path_tracer);
+                assert!(
+                    (mean - expected).abs() < 0.02 * expected,
+                    "Emitter estimate {} not {}",
+                    mean,
+                    expected
+                );
+                assert!(
+                    variance < 0.01 * scattering_variance,
+                    "Variance {} not below {} of scattering alone",
+                    variance,
+                    scattering_variance
+                );
+            }
+        }
+
+        #[test]
+        fn path_tracer_environment_map() {
+            //   \ | /      An environment map lighting a diffuse sphere,
+            //    .-.       seen head-on. Scattered rays can only escape.
+            //   ( c )<----o
+            //    '-'
+
+            let (width, height) = (8, 4);
+            let pixels: Vec<Color> = (0..width * height)
+                .map(|i| {
+                    let v = (1 + i % 3 + 2 * (i / width)) as f64;
+                    Color::with_values(v, v, v)
+                })
+                .collect();
+            let map = Rc::new(EnvironmentMap::new(width, height, pixels));
+            let albedo = 0.5;
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::new(),
+                1.0,
+                Rc::new(Lambertian::new(Color::with_values(albedo, albedo, albedo))),
+            ))];
+
+            // Reference: albedo / π · ∫ L(ω) cos(θ) dω over the hemisphere facing +z
+            let n = 400;
+            let mut irradiance = 0.0;
+            for j in 0..n {
+                let theta = PI * (f64::from(j) + 0.5) / f64::from(n);
+                for i in 0..n {
+                    let phi = 2.0 * PI * (f64::from(i) + 0.5) / f64::from(n);
+                    let direction = Vector3D::with_values(
+                        theta.sin() * phi.cos(),
+                        theta.cos(),
+                        theta.sin() * phi.sin(),
+                    );
+                    irradiance +=
+                        map.radiance(direction).x() * direction.z().max(0.0) * theta.sin();
+                }
+            }
+            irradiance *= 2.0 * PI * PI / f64::from(n * n);
+            let expected = albedo / PI * irradiance;
+
+            let path_tracer = PathTracer::default().with_environment(map);
+            let ray: Ray = Ray::create(
+                Point3D::with_values(0.0, 0.0, 3.0),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+            );
+            let samples = 20000;
+            let mut sum = Color::new();
+            for _ in 0..samples {
+                sum += path_tracer.ray_color(&ray, 2, &world);
+            }
+            let mean = sum / f64::from(samples);
+
+            assert!(
+                (mean.x() - expected).abs() < 0.02 * expected,
+                "Radiance {} not {}",
+                mean,
+                expected
+            );
+        }
+    }
+}
+
+// --- AMBIENT OCCLUSION -------------------------------------------------------
+
+pub mod ambient_occlusion {
+    //! Module for rendering ambient occlusion passes.
+
+    use crate::color::Color;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::Integrator;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vector3d::Vector3D;
+
+    /// Renders how much of the hemisphere above the first hit is not blocked by nearby geometry.
+    ///
+    /// Rays that miss the scene are considered unoccluded and thus white.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct AmbientOcclusion {
+        samples: u16,
+        max_distance: f64,
+    }
+
+    impl Default for AmbientOcclusion {
+        /// Creates an ambient occlusion integrator with 16 occlusion rays reaching infinitely far.
+        fn default() -> Self {
+            Self::new(16, f64::INFINITY)
+        }
+    }
+
+    impl AmbientOcclusion {
+        /// Creates a new ambient occlusion integrator.
+        ///
+        /// # Arguments
+        ///
+        /// * `samples`      - How many occlusion rays are shot from every first hit.
+        /// * `max_distance` - How far away geometry may be to still occlude the hit.
+        pub fn new(samples: u16, max_distance: f64) -> Self {
+            AmbientOcclusion {
+                samples: samples.max(1),
+                max_distance,
+            }
+        }
+
+        /// Gets the number of occlusion rays per first hit.
+        pub fn samples(self) -> u16 {
+            self.samples
+        }
+
+        /// Gets the maximum distance of occluding geometry.
+        pub fn max_distance(self) -> f64 {
+            self.max_distance
+        }
+    }
+
+    impl Integrator for AmbientOcclusion {
+        fn ray_color(&self, r: &Ray, _depth: u16, world: &HittableList) -> Color {
+            let mut rec = HitRecord::default();
+
+            if !world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                return Color::with_values(1.0, 1.0, 1.0);
+            }
+
+            let mut unoccluded: u16 = 0;
+            for _ in 0..self.samples {
+                let direction = Vector3D::random_cosine_direction(rec.normal());
+                let occlusion_ray = Ray::create(rec.p(), direction);
+                let mut occluder = HitRecord::default();
+
+                if !world.hit(
+                    &occlusion_ray,
+                    Interval::new(0.001, self.max_distance),
+                    &mut occluder,
+                ) {
+                    unoccluded += 1;
+                }
+            }
+
+            let visibility = f64::from(unoccluded) / f64::from(self.samples);
+            Color::with_values(visibility, visibility, visibility)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::sphere::Sphere;
+        use crate::integrator::ambient_occlusion::AmbientOcclusion;
+        use crate::integrator::Integrator;
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::rc::Rc;
+
+        #[test]
+        fn ao_miss() {
+            let world: HittableList = HittableList::new();
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            assert_eq!(
+                AmbientOcclusion::default().ray_color(&ray, 1, &world),
+                Color::with_values(1.0, 1.0, 1.0),
+                "Missing ray not white"
+            );
+        }
+
+        #[test]
+        fn ao_unoccluded() {
+            //   o----------->X     c
+            // (0,0)        (1,0) (2,0)
+            //
+            // Nothing but the convex sphere itself is around the hit.
+
+            let world: HittableList = vec![Rc::new(Sphere::new(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+            ))];
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            assert_eq!(
+                AmbientOcclusion::default().ray_color(&ray, 1, &world),
+                Color::with_values(1.0, 1.0, 1.0),
+                "Hit on a lone sphere occluded"
+            );
+        }
+
+        #[test]
+        fn ao_occluded() {
+            //         .-*****-.
+            //       *           *
+            //      *      o----->X  Every occlusion ray hits
+            //      *    (0,0)   *   the sphere from inside
+            //       *           *
+            //         ''*****''
+
+            let world: HittableList = vec![Rc::new(Sphere::new(Point3D::new(), 1.0))];
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+
+            assert_eq!(
+                AmbientOcclusion::default().ray_color(&ray, 1, &world),
+                Color::new(),
+                "Hit inside a sphere not occluded"
+            );
+        }
+    }
+}
+
+// --- DEBUG VISUALIZATION -----------------------------------------------------
+
+pub mod visualize {
+    //! Module for rendering debug views of the scene geometry.
+
+    use crate::color::Color;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::Integrator;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+
+    /// The quantity of the first hit a `Visualize` integrator renders.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Visualization {
+        /// The shading normal mapped from [-1, 1] to [0, 1] per component.
+        Normal,
+        /// The linear depth `t` of the hit as gray, white at the camera and black at `max_depth`.
+        Depth { max_depth: f64 },
+        /// Front faces in green and back faces in red.
+        FrontFace,
+        /// The surface coordinates `u` and `v` in the red and green channel.
+        Uv,
+        /// A false color per object and primitive within it, like the triangles of a mesh.
+        PrimitiveId,
+        /// How many objects and bounding boxes the camera ray got tested against, on a heat
+        /// ramp from blue for none over green to red for `max_tests` and more. Shown for
+        /// rays missing the scene too.
+        Complexity { max_tests: usize },
+    }
+
+    /// Renders a quantity of the first hit along every camera ray, rays missing the scene are black.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Visualize {
+        mode: Visualization,
+    }
+
+    impl Default for Visualize {
+        /// Creates an integrator visualizing the shading normals.
+        fn default() -> Self {
+            Self::new(Visualization::Normal)
+        }
+    }
+
+    impl Visualize {
+        /// Creates a new integrator rendering the given visualization.
+        pub fn new(mode: Visualization) -> Self {
+            Visualize { mode }
+        }
+
+        /// Gets the rendered visualization.
+        pub fn mode(self) -> Visualization {
+            self.mode
+        }
+
+        /// Maps a pair of ids onto a well distinguishable, stable color.
+        fn false_color(object_id: usize, primitive_id: usize) -> Color {
+            // Scramble the bits of the ids, so neighbouring ids get unrelated hues
+            let mut h: u64 = (object_id as u64)
+                .wrapping_add(1)
+                .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (primitive_id as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+            h ^= h >> 29;
+            h = h.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            h ^= h >> 32;
+
+            let channel = |shift: u32| 0.2 + 0.8 * f64::from(((h >> shift) & 0xFF) as u8) / 255.0;
+            Color::with_values(channel(0), channel(8), channel(16))
+        }
+
+        /// Maps a value within [0, 1] onto a ramp from blue over green to red.
+        fn heat(x: f64) -> Color {
+            let x = Interval::new(0.0, 1.0).clamp(x);
+            Color::with_values(
+                (2.0 * x - 1.0).max(0.0),
+                1.0 - (2.0 * x - 1.0).abs(),
+                (1.0 - 2.0 * x).max(0.0),
+            )
+        }
+    }
+
+    impl Integrator for Visualize {
+        fn ray_color(&self, r: &Ray, _depth: u16, world: &HittableList) -> Color {
+            let mut rec = HitRecord::default();
+            let ray_t = Interval::new(0.001, f64::INFINITY);
+
+            let hit = world.hit(r, ray_t, &mut rec);
+            if !hit && !matches!(self.mode, Visualization::Complexity { .. }) {
+                return Color::new();
+            }
+
+            match self.mode {
+                Visualization::Normal => 0.5 * (rec.normal() + Color::with_values(1.0, 1.0, 1.0)),
+                Visualization::Depth { max_depth } => {
+                    let gray = Interval::new(0.0, 1.0).clamp(1.0 - rec.t() / max_depth);
+                    Color::with_values(gray, gray, gray)
+                }
+                Visualization::FrontFace => {
+                    if rec.front_face() {
+                        Color::with_values(0.0, 1.0, 0.0)
+                    } else {
+                        Color::with_values(1.0, 0.0, 0.0)
+                    }
+                }
+                Visualization::Uv => Color::with_values(rec.u(), rec.v(), 0.0),
+                Visualization::PrimitiveId => {
+                    Self::false_color(rec.object_id(), rec.primitive_id())
+                }
+                Visualization::Complexity { max_tests } => {
+                    let tests = world.intersection_tests(r, ray_t);
+                    Self::heat(tests as f64 / max_tests.max(1) as f64)
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::mesh::TriangleMesh;
+        use crate::hittable::sphere::Sphere;
+        use crate::integrator::visualize::{Visualization, Visualize};
+        use crate::integrator::Integrator;
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::rc::Rc;
+
+        //   o----------->X     c0          c1
+        // (0,0)        (1,0) (2,0)       (5,0)
+        fn world() -> HittableList {
+            vec![
+                Rc::new(Sphere::new(Point3D::with_values(5.0, 0.0, 0.0), 1.0)),
+                Rc::new(Sphere::new(Point3D::with_values(2.0, 0.0, 0.0), 1.0)),
+            ]
+        }
+
+        fn color(mode: Visualization, origin: Point3D) -> Color {
+            let ray: Ray = Ray::create(origin, Vector3D::with_values(1.0, 0.0, 0.0));
+            Visualize::new(mode).ray_color(&ray, 1, &world())
+        }
+
+        #[test]
+        fn visualize_miss() {
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(-1.0, 0.0, 0.0));
+
+            assert_eq!(
+                Visualize::default().ray_color(&ray, 1, &world()),
+                Color::new(),
+                "Missing ray not black"
+            );
+        }
+
+        #[test]
+        fn visualize_normal() {
+            assert_eq!(
+                color(Visualization::Normal, Point3D::new()),
+                Color::with_values(0.0, 0.5, 0.5)
+            );
+        }
+
+        #[test]
+        fn visualize_depth() {
+            assert_eq!(
+                color(Visualization::Depth { max_depth: 2.0 }, Point3D::new()),
+                Color::with_values(0.5, 0.5, 0.5)
+            );
+            assert_eq!(
+                color(Visualization::Depth { max_depth: 0.5 }, Point3D::new()),
+                Color::new()
+            );
+        }
+
+        #[test]
+        fn visualize_front_face() {
+            assert_eq!(
+                color(Visualization::FrontFace, Point3D::new()),
+                Color::with_values(0.0, 1.0, 0.0),
+                "Front face not green"
+            );
+            assert_eq!(
+                color(
+                    Visualization::FrontFace,
+                    Point3D::with_values(2.0, 0.0, 0.0)
+                ),
+                Color::with_values(1.0, 0.0, 0.0),
+                "Back face not red"
+            );
+        }
+
+        #[test]
+        fn visualize_uv() {
+            assert_eq!(
+                color(Visualization::Uv, Point3D::new()),
+                Color::with_values(0.0, 0.5, 0.0)
+            );
+        }
+
+        #[test]
+        fn visualize_complexity() {
+            // Both spheres of the list get tested, hit or not
+            let complexity = |max_tests: usize, direction: f64| {
+                let ray = Ray::create(Point3D::new(), Vector3D::with_values(direction, 0.0, 0.0));
+                Visualize::new(Visualization::Complexity { max_tests }).ray_color(&ray, 1, &world())
+            };
+
+            assert_eq!(complexity(4, 1.0), Color::with_values(0.0, 1.0, 0.0));
+            assert_eq!(complexity(2, 1.0), Color::with_values(1.0, 0.0, 0.0));
+            assert_eq!(complexity(2, -1.0), complexity(2, 1.0), "Missing ray free");
+
+            // A mesh adds the boxes and triangles of its hierarchy
+            let mesh = TriangleMesh::new(
+                vec![
+                    Point3D::with_values(1.0, -1.0, -1.0),
+                    Point3D::with_values(1.0, 1.0, -1.0),
+                    Point3D::with_values(1.0, 0.0, 1.0),
+                ],
+                vec![[0, 1, 2]],
+            );
+            let world: HittableList = vec![Rc::new(mesh)];
+            let ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let color = Visualize::new(Visualization::Complexity { max_tests: 3 })
+                .ray_color(&ray, 1, &world);
+            assert_eq!(color, Color::with_values(1.0, 0.0, 0.0));
+        }
+
+        #[test]
+        fn visualize_primitive_id() {
+            let first = color(Visualization::PrimitiveId, Point3D::new());
+            let second = color(
+                Visualization::PrimitiveId,
+                Point3D::with_values(3.5, 0.0, 0.0),
+            );
+
+            assert_ne!(first, second, "Different primitives share a color");
+            assert_eq!(
+                first,
+                color(
+                    Visualization::PrimitiveId,
+                    Point3D::with_values(0.5, 0.0, 0.0)
+                ),
+                "Same primitive changes its color"
+            );
+
+            // Every triangle of a mesh gets a color of its own
+            let mesh = TriangleMesh::new(
+                vec![
+                    Point3D::with_values(1.0, -1.0, 0.0),
+                    Point3D::with_values(1.0, 1.0, 0.0),
+                    Point3D::with_values(1.0, 0.0, 2.0),
+                    Point3D::with_values(1.0, 0.0, -2.0),
+                ],
+                vec![[0, 1, 2], [1, 0, 3]],
+            );
+            let world: HittableList = vec![Rc::new(mesh)];
+            let triangle = |z: f64| {
+                let ray = Ray::create(
+                    Point3D::with_values(0.0, 0.0, z),
+                    Vector3D::with_values(1.0, 0.0, 0.0),
+                );
+                Visualize::new(Visualization::PrimitiveId).ray_color(&ray, 1, &world)
+            };
+            assert_ne!(
+                triangle(0.5),
+                triangle(-0.5),
+                "Triangles of a mesh share a color"
+            );
+        }
+    }
+}
+
+// --- WHITTED -----------------------------------------------------------------
+
+pub mod whitted {
+    //! Module for classic recursive Whitted-style ray tracing.
+
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::{direct_light, Integrator};
+    use crate::interval::Interval;
+    use crate::light::LightList;
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use std::rc::Rc;
+
+    /// The throughput below which reflections and refractions no longer get followed.
+    const MIN_THROUGHPUT: f64 = 1e-3;
+
+    /// How many times a ray may split into several lobes, beyond which only one
+    /// of them gets followed, keeping the cost from growing exponentially with depth.
+    const MAX_SPLITS: u16 = 4;
+
+    /// Renders noise-free previews by directly lighting every hit with hard shadows
+    /// and following only the perfectly specular reflections and refractions.
+    ///
+    /// Glossy and fuzzy surfaces are treated as perfect mirrors, light bouncing
+    /// off diffuse surfaces is ignored.
+    pub struct Whitted {
+        lights: LightList,
+        environment: Rc<dyn Environment>,
+    }
+
+    impl Whitted {
+        /// Creates a new Whitted integrator lighting the scene with the given lights.
+        pub fn new(lights: LightList) -> Self {
+            Whitted {
+                lights,
+                environment: Rc::new(Gradient::default()),
+            }
+        }
+
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Gets the lights of the scene.
+        pub fn lights(&self) -> &LightList {
+            &self.lights
+        }
+
+        /// Computes the color of a ray, following reflections and refractions
+        /// as long as they still contribute.
+        ///
+        /// # Arguments
+        ///
+        /// * `r`          - The ray.
+        /// * `depth`      - How many more times the ray may bounce.
+        /// * `world`      - The geometry of the scene.
+        /// * `throughput` - The fraction of the ray's color reaching the camera.
+        /// * `splits`     - How many times the path to the ray split into several lobes.
+        fn trace(
+            &self,
+            r: &Ray,
+            depth: u16,
+            world: &HittableList,
+            throughput: Color,
+            splits: u16,
+        ) -> Color {
+            // If we've exceeded the ray bounce limit, no more light is gathered.
+            if depth == 0 {
+                return Color::new();
+            }
+
+            let mut rec = HitRecord::default();
+            if !world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                return self.environment.radiance(r.direction());
+            }
+
+            let mut color = rec.material().emitted(&rec)
+                + direct_light(&self.lights, r, &rec, world, None, false);
+            let mut lobes = rec.material().specular(r, &rec);
+            let mut splits = splits;
+            if lobes.len() > 1 {
+                if splits == MAX_SPLITS {
+                    // Follow a single lobe picked by its weight, scaled up to make up for the others
+                    let total: f64 = lobes.iter().map(|(w, _)| w.luminance()).sum();
+                    let mut pick = random_f64() * total;
+                    let chosen = lobes
+                        .iter()
+                        .position(|(w, _)| {
+                            pick -= w.luminance();
+                            pick < 0.0
+                        })
+                        .unwrap_or(lobes.len() - 1);
+                    let (weight, ray) = lobes.swap_remove(chosen);
+                    let probability = weight.luminance() / total;
+                    lobes = if probability > 0.0 {
+                        vec![(weight / probability, ray)]
+                    } else {
+                        Vec::new()
+                    };
+                } else {
+                    splits += 1;
+                }
+            }
+
+            for (weight, specular_ray) in lobes {
+                let throughput = throughput * weight;
+                if throughput.luminance() < MIN_THROUGHPUT {
+                    continue;
+                }
+                color += weight * self.trace(&specular_ray, depth - 1, world, throughput, splits);
+            }
+
+            color
+        }
+    }
+
+    impl Integrator for Whitted {
+        fn ray_color(&self, r: &Ray, depth: u16, world: &HittableList) -> Color {
+            self.trace(r, depth, world, Color::with_values(1.0, 1.0, 1.0), 0)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::environment::gradient::Gradient;
+        use crate::environment::Environment;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::sphere::Sphere;
+        use crate::hittable::{HitRecord, Hittable};
+        use crate::integrator::whitted::Whitted;
+        use crate::integrator::Integrator;
+        use crate::interval::Interval;
+        use crate::light::point::PointLight;
+        use crate::material::{Dielectric, DiffuseLight, Lambertian, Metal};
+        use crate::ray::Ray;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::cell::Cell;
+        use std::f64::consts::PI;
+        use std::rc::Rc;
+
+        //   *     o----------->X     c
+        // light (0,0)        (1,0) (2,0)
+        // (-1,0)
+        fn lit(mut world: HittableList) -> Color {
+            world.push(Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+            )));
+            let whitted = Whitted::new(vec![Rc::new(PointLight::new(
+                Point3D::with_values(-1.0, 0.0, 0.0),
+                Color::with_values(4.0 * PI, 4.0 * PI, 4.0 * PI),
+            ))]);
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            whitted.ray_color(&ray, 10, &world)
+        }
+
+        #[test]
+        fn whitted_direct_light() {
+            let color = lit(HittableList::new());
+            assert!(
+                (color - Color::with_values(0.5, 0.5, 0.5)).near_zero(),
+                "Lit diffuse surface {} not its albedo",
+                color
+            );
+        }
+
+        #[test]
+        fn whitted_hard_shadow() {
+            let blocker = Sphere::new(Point3D::with_values(-0.5, 0.0, 0.0), 0.25);
+            assert_eq!(
+                lit(vec![Rc::new(blocker)]),
+                Color::new(),
+                "Shadowed surface lit"
+            );
+        }
+
+        #[test]
+        fn whitted_miss() {
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            assert_eq!(
+                Whitted::new(Vec::new()).ray_color(&ray, 10, &HittableList::new()),
+                Gradient::default().radiance(ray.direction())
+            );
+            assert_eq!(
+                Whitted::new(Vec::new()).ray_color(&ray, 0, &HittableList::new()),
+                Color::new(),
+                "Light gathered beyond the bounce limit"
+            );
+        }
+
+        #[test]
+        fn whitted_mirror() {
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(Metal::new(Color::with_values(1.0, 1.0, 1.0), 0.5)),
+            ))];
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let reflected = Ray::create(Point3D::new(), Vector3D::with_values(-1.0, 0.0, 0.0));
+            assert_eq!(
+                Whitted::new(Vec::new()).ray_color(&ray, 10, &world),
+                Gradient::default().radiance(reflected.direction()),
+                "Mirror not reflecting the sky"
+            );
+        }
+
+        #[test]
+        fn whitted_emitter() {
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(DiffuseLight::new(Color::with_values(4.0, 2.0, 1.0))),
+            ))];
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            assert_eq!(
+                Whitted::new(Vec::new()).ray_color(&ray, 10, &world),
+                Color::with_values(4.0, 2.0, 1.0),
+                "Emitter not rendered with its radiance"
+            );
+        }
+
+        /// A sphere counting how often rays get tested against it.
+        struct Counted(Sphere, Rc<Cell<usize>>);
+
+        impl Hittable for Counted {
+            fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+                self.1.set(self.1.get() + 1);
+                self.0.hit(r, ray_t, rec)
+            }
+        }
+
+        #[test]
+        fn whitted_glass_depth() {
+            //  o---->( )( )( )( )( )( )   every surface along the axis splits
+            // (0,0)  (2,0) ...   (12,0)   the ray into a reflection and a refraction
+            let tests = Rc::new(Cell::new(0));
+            let world: HittableList = (1..=6)
+                .map(|i| {
+                    let sphere = Sphere::with_material(
+                        Point3D::with_values(2.0 * f64::from(i), 0.0, 0.0),
+                        1.0,
+                        Rc::new(Dielectric::new(1.5)),
+                    );
+                    Rc::new(Counted(sphere, Rc::clone(&tests))) as Rc<dyn Hittable>
+                })
+                .collect();
+
+            let ray: Ray = Ray::create(Point3D::new(), Vector3D::with_values(1.0, 0.0, 0.0));
+            let color = Whitted::new(Vec::new()).ray_color(&ray, 50, &world);
+
+            assert!(color.x().is_finite());
+            assert!(
+                tests.get() < 100000,
+                "{} tests, splitting exponentially",
+                tests.get()
+            );
+        }
+    }
+}
+
+// --- BIDIRECTIONAL PATH TRACER -----------------------------------------------
+
+pub mod bdpt {
+    //! Module for bidirectional path tracing.
+
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::{foreshortening, sample_emission, ImageIntegrator};
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vector3d::Point3D;
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /// What kind of path end or bounce a vertex represents.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum VertexKind {
+        Camera,
+        Light,
+        Surface,
+    }
+
+    /// Represents a vertex of a camera or light subpath.
+    #[derive(Debug, Clone)]
+    struct Vertex {
+        kind: VertexKind,
+        rec: HitRecord,
+        /// The throughput of the subpath up to (and including) this vertex.
+        beta: Color,
+        /// The area density of sampling this vertex from its predecessor.
+        pdf_fwd: f64,
+        /// The area density of sampling this vertex coming from the other end of the path.
+        pdf_rev: f64,
+        /// Whether the vertex scattered specularly.
+        delta: bool,
+    }
+
+    impl Vertex {
+        fn p(&self) -> Point3D {
+            self.rec.p()
+        }
+
+        /// Checks whether a subpath may be connected to another one at this vertex.
+        fn connectible(&self) -> bool {
+            match self.kind {
+                VertexKind::Surface => !self.rec.material().is_specular(),
+                _ => true,
+            }
+        }
+
+        /// Converts the solid angle density of sampling `next` from this vertex into an area density.
+        fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+            let w = next.p() - self.p();
+            let distance_squared = w.length_squared();
+            if distance_squared == 0.0 {
+                return 0.0;
+            }
+
+            let pdf = pdf / distance_squared;
+            match next.kind {
+                VertexKind::Camera => pdf,
+                _ => pdf * foreshortening(&next.rec, w / distance_squared.sqrt()).abs(),
+            }
+        }
+
+        /// Computes the area density of sampling `next` from this vertex,
+        /// having arrived at it from `prev`.
+        fn pdf(&self, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+            let wi = (next.p() - self.p()).unit_vector();
+
+            let pdf = match (self.kind, prev) {
+                (VertexKind::Light, _) => foreshortening(&self.rec, wi).max(0.0) / PI,
+                (VertexKind::Surface, Some(prev)) => {
+                    let wo = (prev.p() - self.p()).unit_vector();
+                    self.rec.material().pdf(&self.rec, wo, wi)
+                }
+                _ => 0.0,
+            };
+
+            self.convert_density(pdf, next)
+        }
+
+        /// Evaluates the BSDF at this vertex for light scattered from `next` towards `prev`,
+        /// or the light emitted towards `next` for light vertices.
+        fn f(&self, prev: Option<&Vertex>, next: &Vertex) -> Color {
+            let wi = (next.p() - self.p()).unit_vector();
+
+            match (self.kind, prev) {
+                (VertexKind::Light, _) if foreshortening(&self.rec, wi) > 0.0 => {
+                    Color::with_values(1.0, 1.0, 1.0)
+                }
+                (VertexKind::Surface, Some(prev)) => {
+                    let wo = (prev.p() - self.p()).unit_vector();
+                    self.rec.material().eval(&self.rec, wo, wi)
+                }
+                _ => Color::new(),
+            }
+        }
+    }
+
+    /// Treats densities of 0 (from specular vertices) as 1 for the ratios of the MIS weights.
+    fn remap0(pdf: f64) -> f64 {
+        if pdf == 0.0 {
+            1.0
+        } else {
+            pdf
+        }
+    }
+
+    /// Renders by connecting every camera subpath vertex with every light subpath vertex,
+    /// weighting the resulting paths with multiple importance sampling (power heuristic β = 1).
+    ///
+    /// Light subpaths start on the surface of the given lights, which have to be
+    /// part of the world as well. Every light subpath vertex the camera sees gets
+    /// connected to the camera itself and splatted onto the pixel it's seen through,
+    /// finding caustics no camera subpath can connect to.
+    pub struct BidirectionalPathTracer {
+        lights: HittableList,
+        environment: Rc<dyn Environment>,
+    }
+
+    impl BidirectionalPathTracer {
+        /// Creates a new bidirectional path tracer starting light subpaths on the given emitters.
+        pub fn new(lights: HittableList) -> Self {
+            BidirectionalPathTracer {
+                lights,
+                environment: Rc::new(Gradient::default()),
+            }
+        }
+
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Gets the emitters light subpaths start on.
+        pub fn lights(&self) -> &HittableList {
+            &self.lights
+        }
+
+        /// Extends a subpath by randomly scattering through the world.
+        ///
+        /// # Arguments
+        ///
+        /// * `world`        - The geometry of the scene.
+        /// * `ray`          - The ray leaving the last vertex of the path.
+        /// * `beta`         - The throughput of the path including the sampling of `ray`.
+        /// * `pdf_dir`      - The solid angle density `ray` got sampled with.
+        /// * `max_vertices` - How many vertices the path may have.
+        /// * `path`         - The path to extend.
+        ///
+        /// # Returns
+        ///
+        /// Returns the ray escaping the scene and its throughput, if the path left it.
+        fn random_walk(
+            world: &HittableList,
+            mut ray: Ray,
+            mut beta: Color,
+            mut pdf_dir: f64,
+            max_vertices: usize,
+            path: &mut Vec<Vertex>,
+        ) -> Option<(Ray, Color)> {
+            while path.len() < max_vertices {
+                let mut rec = HitRecord::default();
+                if !world.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                    return Some((ray, beta));
+                }
+
+                let prev = path.len() - 1;
+                let mut vertex = Vertex {
+                    kind: VertexKind::Surface,
+                    rec,
+                    beta,
+                    pdf_fwd: 0.0,
+                    pdf_rev: 0.0,
+                    delta: false,
+                };
+                vertex.pdf_fwd = path[prev].convert_density(pdf_dir, &vertex);
+                path.push(vertex);
+
+                let current = &path[prev + 1];
+                let mat = current.rec.material();
+                let (scattered, attenuation, scattered_ray) = mat.scatter(&ray, &current.rec);
+                if !scattered || path.len() == max_vertices {
+                    break;
+                }
+
+                let wo = -ray.direction().unit_vector();
+                let wi = scattered_ray.direction().unit_vector();
+                let (pdf_fwd, pdf_rev) = if mat.is_specular() {
+                    (0.0, 0.0)
+                } else {
+                    (mat.pdf(&current.rec, wo, wi), mat.pdf(&current.rec, wi, wo))
+                };
+
+                path[prev + 1].delta = mat.is_specular();
+                path[prev].pdf_rev = path[prev + 1].convert_density(pdf_rev, &path[prev]);
+                beta = beta * attenuation;
+                pdf_dir = pdf_fwd;
+                ray = Ray::create(scattered_ray.origin(), wi);
+            }
+
+            None
+        }
+
+        /// Samples a subpath starting at a uniformly chosen point on a uniformly chosen light.
+        fn light_path(&self, world: &HittableList, max_vertices: usize) -> Vec<Vertex> {
+            let mut path = Vec::new();
+            let emission = match sample_emission(&self.lights) {
+                Some(emission) => emission,
+                None => return path,
+            };
+
+            path.push(Vertex {
+                kind: VertexKind::Light,
+                rec: emission.rec,
+                beta: emission.emitted / emission.pdf_pos,
+                pdf_fwd: emission.pdf_pos,
+                pdf_rev: 0.0,
+                delta: false,
+            });
+
+            // cos(θ) / pdf_dir = π for cosine distributed directions
+            let beta = PI * emission.emitted / emission.pdf_pos;
+            Self::random_walk(
+                world,
+                emission.ray,
+                beta,
+                emission.pdf_dir,
+                max_vertices,
+                &mut path,
+            );
+            path
+        }
+
+        /// Computes the area density of choosing the light emitting at `vertex` as start
+        /// of a light subpath, 0 if it is none of the lights.
+        fn light_origin_pdf(&self, prev: &Vertex, vertex: &Vertex) -> f64 {
+            let to_vertex = vertex.p() - prev.p();
+            let distance = to_vertex.length();
+            let ray = Ray::create(prev.p(), to_vertex / distance);
+            let around = Interval::new(distance * (1.0 - 1e-6), distance * (1.0 + 1e-6));
+
+            for light in &self.lights {
+                let mut rec = HitRecord::default();
+                if light.area() > 0.0 && light.hit(&ray, around, &mut rec) {
+                    return 1.0 / (self.lights.len() as f64 * light.area());
+                }
+            }
+
+            0.0
+        }
+
+        /// Checks whether nothing blocks the line of sight between two vertices.
+        fn visible(world: &HittableList, a: &Vertex, b: &Vertex) -> bool {
+            let to_b = b.p() - a.p();
+            let distance = to_b.length();
+            let shadow_ray = Ray::create(a.p(), to_b / distance);
+            let mut occluder = HitRecord::default();
+
+            !world.hit(
+                &shadow_ray,
+                Interval::new(0.001, distance - 0.001),
+                &mut occluder,
+            )
+        }
+
+        /// Computes the light carried by the path made of the first `s` light
+        /// and the first `t` camera subpath vertices, weighted for MIS.
+        fn connect(
+            &self,
+            cam: &Camera,
+            world: &HittableList,
+            light_path: &[Vertex],
+            camera_path: &[Vertex],
+            s: usize,
+            t: usize,
+        ) -> Color {
+            let pt = &camera_path[t - 1];
+            let pt_minus = &camera_path[t - 2];
+
+            let contribution = if s == 0 {
+                // The camera subpath hit a light on its own
+                pt.beta * pt.rec.material().emitted(&pt.rec)
+            } else {
+                let qs = &light_path[s - 1];
+                let qs_minus = if s > 1 {
+                    Some(&light_path[s - 2])
+                } else {
+                    None
+                };
+                if !pt.connectible() || !qs.connectible() {
+                    return Color::new();
+                }
+
+                let unweighted = pt.beta
+                    * pt.f(Some(pt_minus), qs)
+                    * qs.f(qs_minus, pt)
+                    * qs.beta
+                    * Self::geometry(pt, qs);
+                if unweighted.near_zero() || !Self::visible(world, pt, qs) {
+                    return Color::new();
+                }
+                unweighted
+            };
+
+            if contribution.near_zero() {
+                return Color::new();
+            }
+
+            contribution * self.mis_weight(cam, light_path, camera_path, s, t)
+        }
+
+        /// Computes the light carried by the path made of the first `s` light subpath
+        /// vertices and the camera, weighted for MIS.
+        ///
+        /// # Returns
+        ///
+        /// Returns the index of the pixel the last light subpath vertex is seen through
+        /// and the light arriving there, `None` if the camera can't see the vertex.
+        fn splat(
+            &self,
+            cam: &Camera,
+            world: &HittableList,
+            light_path: &[Vertex],
+            camera_path: &[Vertex],
+            s: usize,
+        ) -> Option<(usize, Color)> {
+            let camera = &camera_path[0];
+            let qs = &light_path[s - 1];
+            let qs_minus = if s > 1 {
+                Some(&light_path[s - 2])
+            } else {
+                None
+            };
+            if !qs.connectible() {
+                return None;
+            }
+            let (i, j) = cam.pixel_at(qs.p())?;
+
+            let to_camera = camera.p() - qs.p();
+            let distance_squared = to_camera.length_squared();
+            let wi = to_camera / distance_squared.sqrt();
+            let we = cam.importance(-wi) * cam.direction().dot(-wi) / distance_squared;
+            let contribution =
+                qs.beta * qs.f(qs_minus, camera) * (foreshortening(&qs.rec, wi).abs() * we);
+            if contribution.near_zero() || !Self::visible(world, qs, camera) {
+                return None;
+            }
+
+            let pixel = usize::from(j) * usize::from(cam.image_width()) + usize::from(i);
+            Some((
+                pixel,
+                contribution * self.mis_weight(cam, light_path, camera_path, s, 1),
+            ))
+        }
+
+        /// The geometry term between two vertices (without visibility),
+        /// vertices within volumes not foreshortening the light.
+        fn geometry(a: &Vertex, b: &Vertex) -> f64 {
+            let d = b.p() - a.p();
+            let distance_squared = d.length_squared();
+            let d = d / distance_squared.sqrt();
+
+            foreshortening(&a.rec, d).abs() * foreshortening(&b.rec, d).abs() / distance_squared
+        }
+
+        /// Computes the multiple importance sampling weight of connecting the
+        /// first `s` light and the first `t` camera subpath vertices.
+        fn mis_weight(
+            &self,
+            cam: &Camera,
+            light_path: &[Vertex],
+            camera_path: &[Vertex],
+            s: usize,
+            t: usize,
+        ) -> f64 {
+            let pt = &camera_path[t - 1];
+            let pt_minus = if t > 1 {
+                Some(&camera_path[t - 2])
+            } else {
+                None
+            };
+            let qs = if s > 0 {
+                Some(&light_path[s - 1])
+            } else {
+                None
+            };
+            let qs_minus = if s > 1 {
+                Some(&light_path[s - 2])
+            } else {
+                None
+            };
+
+            // (pdf_fwd, pdf_rev, delta) of every vertex, as if the path was sampled by this strategy
+            let mut camera: Vec<(f64, f64, bool)> = camera_path[..t]
+                .iter()
+                .map(|v| (v.pdf_fwd, v.pdf_rev, v.delta))
+                .collect();
+            let mut light: Vec<(f64, f64, bool)> = light_path[..s]
+                .iter()
+                .map(|v| (v.pdf_fwd, v.pdf_rev, v.delta))
+                .collect();
+
+            camera[t - 1].2 = false;
+            camera[t - 1].1 = match qs {
+                Some(qs) => qs.pdf(qs_minus, pt),
+                None => self.light_origin_pdf(pt_minus.expect("pt_minus exists for s = 0"), pt),
+            };
+            if s == 0 && camera[t - 1].1 == 0.0 {
+                // Only the camera subpath can find lights light subpaths don't start on
+                return 1.0;
+            }
+            if let Some(pt_minus) = pt_minus {
+                camera[t - 2].1 = match qs {
+                    Some(qs) => pt.pdf(Some(qs), pt_minus),
+                    None => {
+                        let light = Vertex {
+                            kind: VertexKind::Light,
+                            ..pt.clone()
+                        };
+                        light.pdf(None, pt_minus)
+                    }
+                };
+            }
+            if let Some(qs) = qs {
+                light[s - 1].2 = false;
+                light[s - 1].1 = match pt_minus {
+                    Some(pt_minus) => pt.pdf(Some(pt_minus), qs),
+                    // The camera samples directions through its image
+                    None => pt.convert_density(cam.pdf(qs.p() - pt.p()), qs),
+                };
+            }
+            if let Some(qs_minus) = qs_minus {
+                light[s - 2].1 = qs.expect("qs exists for s > 1").pdf(Some(pt), qs_minus);
+            }
+
+            let mut sum_ri = 0.0;
+
+            // Strategies with fewer camera vertices, down to the camera alone
+            let mut ri = 1.0;
+            for i in (1..t).rev() {
+                ri *= remap0(camera[i].1) / remap0(camera[i].0);
+                if !camera[i].2 && !camera[i - 1].2 {
+                    sum_ri += ri;
+                }
+            }
+
+            // Strategies with fewer light vertices
+            let mut ri = 1.0;
+            for i in (0..s).rev() {
+                ri *= remap0(light[i].1) / remap0(light[i].0);
+                let delta_light_vertex = i > 0 && light[i - 1].2;
+                if !light[i].2 && !delta_light_vertex {
+                    sum_ri += ri;
+                }
+            }
+
+            1.0 / (1.0 + sum_ri)
+        }
+
+        /// Traces a camera and a light subpath and connects them in every way.
+        ///
+        /// # Arguments
+        ///
+        /// * `cam`    - The camera the ray leaves.
+        /// * `r`      - The camera ray.
+        /// * `world`  - The geometry of the scene.
+        /// * `splats` - The light light subpaths add to every pixel, row by row from the top left.
+        ///
+        /// # Returns
+        ///
+        /// Returns the light arriving along the camera ray.
+        fn sample(
+            &self,
+            cam: &Camera,
+            r: &Ray,
+            world: &HittableList,
+            splats: &mut [Color],
+        ) -> Color {
+            if cam.max_depth() == 0 {
+                return Color::new();
+            }
+            let max_depth = usize::from(cam.max_depth());
+
+            let mut camera_path = vec![Vertex {
+                kind: VertexKind::Camera,
+                rec: HitRecord::new(r.origin(), r.direction().unit_vector(), 0.0, true),
+                beta: Color::with_values(1.0, 1.0, 1.0),
+                pdf_fwd: 1.0,
+                pdf_rev: 0.0,
+                delta: false,
+            }];
+            let escaped = Self::random_walk(
+                world,
+                *r,
+                Color::with_values(1.0, 1.0, 1.0),
+                cam.pdf(r.direction()),
+                max_depth + 2,
+                &mut camera_path,
+            );
+            let light_path = self.light_path(world, max_depth + 1);
+
+            // The sky can only be found by the camera subpath
+            let mut color = match escaped {
+                Some((ray, beta)) => beta * self.environment.radiance(ray.direction()),
+                None => Color::new(),
+            };
+
+            for s in 1..=light_path.len() {
+                if let Some((pixel, splat)) = self.splat(cam, world, &light_path, &camera_path, s) {
+                    splats[pixel] += splat;
+                }
+            }
+            for t in 2..=camera_path.len() {
+                for s in 0..=light_path.len() {
+                    if s + t - 2 > max_depth {
+                        break;
+                    }
+                    color += self.connect(cam, world, &light_path, &camera_path, s, t);
+                }
+            }
+
+            color
+        }
+    }
+
+    impl ImageIntegrator for BidirectionalPathTracer {
+        fn render_image(&self, cam: &Camera, world: &HittableList) -> Vec<Color> {
+            let width = usize::from(cam.image_width());
+            let samples = cam.samples_per_pixel().max(1);
+            let mut pixels = vec![Color::new(); width * usize::from(cam.image_height())];
+            let mut splats = pixels.clone();
+
+            for j in 0..cam.image_height() {
+                eprintln!("Scanlines remaining: {}", cam.image_height() - j);
+                eprint!("\x1B[2J\x1B[1;1H"); // Clear output
+
+                for i in 0..cam.image_width() {
+                    let pixel = usize::from(j) * width + usize::from(i);
+                    for _ in 0..samples {
+                        pixels[pixel] += self.sample(cam, &cam.ray(i, j), world, &mut splats);
+                    }
+                }
+            }
+
+            // Every camera sample traced one light subpath, splatting onto any pixel
+            pixels
+                .iter()
+                .zip(&splats)
+                .map(|(&color, &splat)| (color + splat) / f64::from(samples))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::camera::Camera;
+        use crate::color::Color;
+        use crate::environment::uniform::Uniform;
+        use crate::hittable::disk::Disk;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::plane::Plane;
+        use crate::hittable::sphere::Sphere;
+        use crate::hittable::HitRecord;
+        use crate::hittable::Hittable;
+        use crate::integrator::bdpt::{BidirectionalPathTracer, Vertex, VertexKind};
+        use crate::integrator::ImageIntegrator;
+        use crate::interval::Interval;
+        use crate::material::{DiffuseLight, Isotropic, Lambertian, Metal};
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::f64::consts::PI;
+        use std::rc::Rc;
+
+        #[test]
+        fn bdpt_direct_light() {
+            let emit = Color::with_values(4.0, 2.0, 1.0);
+            let light: Rc<dyn Hittable> = Rc::new(Sphere::with_material(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                1.0,
+                Rc::new(DiffuseLight::new(emit)),
+            ));
+            let world: HittableList = vec![light.clone()];
+            let bdpt = BidirectionalPathTracer::new(vec![light]);
+
+            // Seeing the light from the camera is by far likelier than hitting the camera
+            let cam = Camera::new(1.0, 2, 100, 10)
+                .with_vertical_fov(10.0)
+                .with_view(
+                    Point3D::new(),
+                    Vector3D::with_values(1.0, 0.0, 0.0),
+                    Vector3D::with_values(0.0, 1.0, 0.0),
+                );
+            for pixel in bdpt.render_image(&cam, &world) {
+                assert!(
+                    (pixel - emit).length() < 0.01 * emit.length(),
+                    "Light {} not seen directly",
+                    pixel
+                );
+            }
+        }
+
+        #[test]
+        fn bdpt_furnace() {
+            //        .-*****-.       A diffuse sphere (R=2, albedo 0.5)
+            //      *   .***.   *     around an emitting sphere (r=1, L=1):
+            //     *   * c   *  o-->X
+            //      *   '***'   *     L = a·L·(r/R)² / (1 - a·(1 - (r/R)²)) = 0.2
+            //        ''*****''
+
+            let light: Rc<dyn Hittable> = Rc::new(Sphere::with_material(
+                Point3D::new(),
+                1.0,
+                Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+            ));
+            let world: HittableList = vec![
+                light.clone(),
+                Rc::new(Sphere::with_material(
+                    Point3D::new(),
+                    2.0,
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+            let bdpt = BidirectionalPathTracer::new(vec![light]);
+            let cam = Camera::new(1.0, 4, 250, 10).with_view(
+                Point3D::with_values(1.5, 0.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+            );
+
+            let pixels = bdpt.render_image(&cam, &world);
+            let mean =
+                pixels.iter().fold(Color::new(), |sum, &pixel| sum + pixel) / pixels.len() as f64;
+
+            assert!(
+                (mean.x() - 0.2).abs() < 0.01,
+                "Furnace radiance {} not 0.2",
+                mean
+            );
+        }
+
+        #[test]
+        fn bdpt_caustic() {
+            //   ==================   A mirror 2 up reflects a small disk (r=0.05, L=400)
+            //          *-*   o       facing up onto a diffuse floor (albedo 0.5), lit
+            //                 \      as if by the disk's image 3 up facing down
+            //   ------------------   with E = L·πr²·9 / d⁴
+            //
+            // The floor can't be connected to the disk through the mirror, only light
+            // subpaths splatted onto the image find the caustic.
+
+            let light: Rc<dyn Hittable> = Rc::new(Disk::with_material(
+                Point3D::with_values(0.0, 1.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+                0.05,
+                Rc::new(DiffuseLight::new(Color::with_values(400.0, 400.0, 400.0))),
+            ));
+            let floor: Rc<dyn Hittable> = Rc::new(Plane::with_material(
+                Point3D::new(),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+                Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+            ));
+            let world: HittableList = vec![
+                light.clone(),
+                floor.clone(),
+                Rc::new(Plane::with_material(
+                    Point3D::with_values(0.0, 2.0, 0.0),
+                    Vector3D::with_values(0.0, -1.0, 0.0),
+                    Rc::new(Metal::new(Color::with_values(1.0, 1.0, 1.0), 0.0)),
+                )),
+            ];
+            let bdpt = BidirectionalPathTracer::new(vec![light])
+                .with_environment(Rc::new(Uniform::new(Color::new())));
+
+            // Only light reflected once by the mirror and once by the floor
+            let cam = Camera::new(4.0 / 3.0, 4, 2000, 2)
+                .with_vertical_fov(30.0)
+                .with_view(
+                    Point3D::with_values(1.0, 1.2, 2.0),
+                    Vector3D::with_values(0.0, -1.2, -2.0),
+                    Vector3D::with_values(0.0, 1.0, 0.0),
+                );
+            let pixels = bdpt.render_image(&cam, &world);
+
+            // The radiance of the floor, averaged over the pixels
+            let radiance = |p: Point3D| {
+                let d_squared = p.x() * p.x() + 9.0 + p.z() * p.z();
+                0.5 / PI * 400.0 * PI * 0.05 * 0.05 * 9.0 / (d_squared * d_squared)
+            };
+            let expected: Vec<f64> = (0..cam.image_height())
+                .flat_map(|j| (0..cam.image_width()).map(move |i| (i, j)))
+                .map(|(i, j)| {
+                    let samples = 256;
+                    let sum: f64 = (0..samples)
+                        .map(|_| {
+                            let mut rec = HitRecord::default();
+                            let hit = floor.hit(
+                                &cam.ray(i, j),
+                                Interval::new(0.001, f64::INFINITY),
+                                &mut rec,
+                            );
+                            assert!(hit, "Floor not seen");
+                            radiance(rec.p())
+                        })
+                        .sum();
+                    sum / f64::from(samples)
+                })
+                .collect();
+
+            let total: f64 = pixels.iter().map(|pixel| pixel.x()).sum();
+            let expected_total: f64 = expected.iter().sum();
+            assert!(
+                (total - expected_total).abs() < 0.1 * expected_total,
+                "Caustic {} not {}",
+                total,
+                expected_total
+            );
+            for (pixel, expected) in pixels.iter().zip(&expected) {
+                assert!(
+                    (pixel.x() - expected).abs() < 0.35 * expected,
+                    "Caustic pixel {} not {}",
+                    pixel,
+                    expected
+                );
+            }
+        }
+
+        #[test]
+        fn bdpt_volume_geometry() {
+            //   medium    surface
+            //     m-------->|       2 apart, the connection along the (arbitrary)
+            //   (0,0)     (2,0)     normal of the medium vertex, perpendicular to it
+            //                       or not, gets foreshortened by the surface alone
+            let vertex = |p: Point3D, normal: Vector3D, volumetric: bool| {
+                let rec = HitRecord::new(p, normal, 1.0, true);
+                Vertex {
+                    kind: VertexKind::Surface,
+                    rec: if volumetric {
+                        rec.with_material(Rc::new(Isotropic::new(Color::with_values(
+                            1.0, 1.0, 1.0,
+                        ))))
+                    } else {
+                        rec
+                    },
+                    beta: Color::with_values(1.0, 1.0, 1.0),
+                    pdf_fwd: 0.0,
+                    pdf_rev: 0.0,
+                    delta: false,
+                }
+            };
+            let surface = vertex(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                Vector3D::with_values(-0.6, 0.8, 0.0),
+                false,
+            );
+
+            for normal in [
+                Vector3D::with_values(1.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+            ] {
+                let medium = vertex(Point3D::new(), normal, true);
+                let g = BidirectionalPathTracer::geometry(&medium, &surface);
+                assert!(
+                    (g - 0.6 / 4.0).abs() < 1e-12,
+                    "Geometry {} of the medium",
+                    g
+                );
+                assert!((medium.convert_density(1.0, &surface) - 0.6 / 4.0).abs() < 1e-12);
+                assert!((surface.convert_density(1.0, &medium) - 1.0 / 4.0).abs() < 1e-12);
+            }
+        }
+    }
+}
+
+// --- STOCHASTIC PROGRESSIVE PHOTON MAPPING -----------------------------------
+
+pub mod sppm {
+    //! Module for stochastic progressive photon mapping.
+
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::{sample_emission, ImageIntegrator};
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::collections::HashMap;
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /// The fraction of new photons kept when shrinking the radius of a pixel.
+    const ALPHA: f64 = 2.0 / 3.0;
+
+    /// Represents the first non-specular surface seen through a pixel.
+    struct VisiblePoint {
+        rec: HitRecord,
+        wo: Vector3D,
+        beta: Color,
+    }
+
+    /// Represents the progressive estimate of a single pixel.
+    struct SppmPixel {
+        radius: f64,
+        /// Light found directly by the camera paths, summed over all iterations.
+        ld: Color,
+        /// The flux gathered within the radius, scaled to the current radius.
+        tau: Color,
+        /// The number of photons accounted for.
+        n: f64,
+        vp: Option<VisiblePoint>,
+        /// The flux gathered in the current iteration.
+        phi: Color,
+        /// The number of photons gathered in the current iteration.
+        m: u64,
+    }
+
+    /// Renders by gathering photons shot from the lights at the points seen through every pixel,
+    /// shrinking the gathering radius with every iteration.
+    ///
+    /// Every one of the camera's samples per pixel is one iteration. Photons
+    /// start on the surface of the given lights, which have to be part of
+    /// the world as well.
+    pub struct StochasticProgressivePhotonMapper {
+        lights: HittableList,
+        photons_per_iteration: usize,
+        initial_radius: f64,
+        environment: Rc<dyn Environment>,
+    }
+
+    impl StochasticProgressivePhotonMapper {
+        /// Creates a new photon mapper.
+        ///
+        /// # Arguments
+        ///
+        /// * `lights`                - The emitters photons start on.
+        /// * `photons_per_iteration` - How many photons are shot in every iteration.
+        /// * `initial_radius`        - The radius photons are gathered in at first.
+        pub fn new(
+            lights: HittableList,
+            photons_per_iteration: usize,
+            initial_radius: f64,
+        ) -> Self {
+            StochasticProgressivePhotonMapper {
+                lights,
+                photons_per_iteration: photons_per_iteration.max(1),
+                initial_radius,
+                environment: Rc::new(Gradient::default()),
+            }
+        }
+
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Follows a camera ray through specular bounces up to the first other surface,
+        /// collecting the light found on the way into the pixel.
+        fn trace_camera_ray(
+            &self,
+            mut r: Ray,
+            max_depth: u16,
+            world: &HittableList,
+            pixel: &mut SppmPixel,
+        ) {
+            let mut beta = Color::with_values(1.0, 1.0, 1.0);
+
+            for _ in 0..max_depth {
+                let mut rec = HitRecord::default();
+                if !world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                    pixel.ld += beta * self.environment.radiance(r.direction());
+                    return;
+                }
+
+                let mat = rec.material();
+                pixel.ld += beta * mat.emitted(&rec);
+
+                let (scattered, attenuation, scattered_ray) = mat.scatter(&r, &rec);
+                if !mat.is_specular() {
+                    if scattered {
+                        let wo = -r.direction().unit_vector();
+                        pixel.vp = Some(VisiblePoint { rec, wo, beta });
+                    }
+                    return;
+                }
+                if !scattered {
+                    return;
+                }
+
+                beta = beta * attenuation;
+                r = scattered_ray;
+            }
+        }
+
+        /// Gets the grid cell containing a point.
+        fn cell(p: Point3D, cell_size: f64) -> (i64, i64, i64) {
+            (
+                (p.x() / cell_size).floor() as i64,
+                (p.y() / cell_size).floor() as i64,
+                (p.z() / cell_size).floor() as i64,
+            )
+        }
+
+        /// Hashes the index of every pixel with a visible point into all grid cells its radius overlaps.
+        fn build_grid(
+            pixels: &[SppmPixel],
+            cell_size: f64,
+        ) -> HashMap<(i64, i64, i64), Vec<usize>> {
+            let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+
+            for (index, pixel) in pixels.iter().enumerate() {
+                let vp = match &pixel.vp {
+                    Some(vp) if !vp.beta.near_zero() => vp,
+                    _ => continue,
+                };
+
+                let extent = Vector3D::with_values(pixel.radius, pixel.radius, pixel.radius);
+                let min = Self::cell(vp.rec.p() - extent, cell_size);
+                let max = Self::cell(vp.rec.p() + extent, cell_size);
+                for x in min.0..=max.0 {
+                    for y in min.1..=max.1 {
+                        for z in min.2..=max.2 {
+                            grid.entry((x, y, z)).or_default().push(index);
+                        }
+                    }
+                }
+            }
+
+            grid
+        }
+
+        /// Shoots a photon from the lights and lets every visible point close to its
+        /// hits on non-specular surfaces gather it.
+        fn trace_photon(
+            &self,
+            max_depth: u16,
+            world: &HittableList,
+            grid: &HashMap<(i64, i64, i64), Vec<usize>>,
+            cell_size: f64,
+            pixels: &mut [SppmPixel],
+        ) {
+            let emission = match sample_emission(&self.lights) {
+                Some(emission) => emission,
+                None => return,
+            };
+
+            // cos(θ) / pdf_dir = π for cosine distributed directions
+            let mut beta = PI * emission.emitted / emission.pdf_pos;
+            let mut r = emission.ray;
+
+            for _ in 0..max_depth {
+                let mut rec = HitRecord::default();
+                if !world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                    return;
+                }
+
+                let mat = rec.material();
+                if !mat.is_specular() {
+                    let wi = -r.direction().unit_vector();
+
+                    for &index in grid
+                        .get(&Self::cell(rec.p(), cell_size))
+                        .into_iter()
+                        .flatten()
+                    {
+                        let pixel = &mut pixels[index];
+                        let vp = pixel
+                            .vp
+                            .as_ref()
+                            .expect("Only visible points are in the grid");
+                        if (vp.rec.p() - rec.p()).length_squared() > pixel.radius * pixel.radius {
+                            continue;
+                        }
+
+                        let phi = beta * vp.rec.material().eval(&vp.rec, vp.wo, wi);
+                        pixel.phi += phi;
+                        pixel.m += 1;
+                    }
+                }
+
+                let (scattered, attenuation, scattered_ray) = mat.scatter(&r, &rec);
+                if !scattered {
+                    return;
+                }
+                beta = beta * attenuation;
+                r = scattered_ray;
+            }
+        }
+
+        /// Shrinks the radius of every pixel which gathered photons, keeping a
+        /// fraction of `ALPHA` of the new ones.
+        fn update(pixels: &mut [SppmPixel]) {
+            for pixel in pixels.iter_mut() {
+                if pixel.m > 0 {
+                    let beta = pixel.vp.as_ref().map_or(Color::new(), |vp| vp.beta);
+                    let m = pixel.m as f64;
+                    let n_new = pixel.n + ALPHA * m;
+                    let radius_new = pixel.radius * (n_new / (pixel.n + m)).sqrt();
+
+                    pixel.tau = (pixel.tau + beta * pixel.phi)
+                        * (radius_new * radius_new / (pixel.radius * pixel.radius));
+                    pixel.n = n_new;
+                    pixel.radius = radius_new;
+                    pixel.phi = Color::new();
+                    pixel.m = 0;
+                }
+                pixel.vp = None;
+            }
+        }
+    }
+
+    impl ImageIntegrator for StochasticProgressivePhotonMapper {
+        fn render_image(&self, cam: &Camera, world: &HittableList) -> Vec<Color> {
+            let width = cam.image_width();
+            let height = cam.image_height();
+            let iterations = cam.samples_per_pixel().max(1);
+
+            let mut pixels: Vec<SppmPixel> = (0..usize::from(width) * usize::from(height))
+                .map(|_| SppmPixel {
+                    radius: self.initial_radius,
+                    ld: Color::new(),
+                    tau: Color::new(),
+                    n: 0.0,
+                    vp: None,
+                    phi: Color::new(),
+                    m: 0,
+                })
+                .collect();
+
+            for iteration in 0..iterations {
+                eprintln!("Iterations remaining: {}", iterations - iteration);
+                eprint!("\x1B[2J\x1B[1;1H"); // Clear output
+
+                for j in 0..height {
+                    for i in 0..width {
+                        let pixel =
+                            &mut pixels[usize::from(j) * usize::from(width) + usize::from(i)];
+                        self.trace_camera_ray(cam.ray(i, j), cam.max_depth(), world, pixel);
+                    }
+                }
+
+                // Cells as large as the largest radius make every visible point overlap at most 8 cells
+                let cell_size = 2.0
+                    * pixels
+                        .iter()
+                        .map(|p| p.radius)
+                        .fold(f64::MIN_POSITIVE, f64::max);
+                let grid = Self::build_grid(&pixels, cell_size);
+
+                for _ in 0..self.photons_per_iteration {
+                    self.trace_photon(cam.max_depth(), world, &grid, cell_size, &mut pixels);
+                }
+
+                Self::update(&mut pixels);
+            }
+
+            let photons = (usize::from(iterations) * self.photons_per_iteration) as f64;
+            pixels
+                .iter()
+                .map(|pixel| {
+                    pixel.ld / f64::from(iterations)
+                        + pixel.tau / (photons * PI * pixel.radius * pixel.radius)
+                })
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::camera::Camera;
+        use crate::color::Color;
+        use crate::environment::uniform::Uniform;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::plane::Plane;
+        use crate::hittable::sphere::Sphere;
+        use crate::hittable::Hittable;
+        use crate::integrator::path_tracer::PathTracer;
+        use crate::integrator::sppm::StochasticProgressivePhotonMapper;
+        use crate::integrator::{ImageIntegrator, Integrator};
+        use crate::light::point::PointLight;
+        use crate::material::{Dielectric, DiffuseLight, Lambertian};
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::f64::consts::PI;
+        use std::rc::Rc;
+
+        #[test]
+        fn sppm_furnace() {
+            //        .-*****-.       A diffuse sphere (R=2, albedo 0.5)
+            //      *   .***.   *     around an emitting sphere (r=1, L=1)
+            //  X<--o--* c   *  *     behind the camera:
+            //      *   '***'   *     L = a·L·(r/R)² / (1 - a·(1 - (r/R)²)) = 0.2
+            //        ''*****''
+
+            let center = Point3D::with_values(0.0, 0.0, 1.5);
+            let light: Rc<dyn Hittable> = Rc::new(Sphere::with_material(
+                center,
+                1.0,
+                Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+            ));
+            let world: HittableList = vec![
+                light.clone(),
+                Rc::new(Sphere::with_material(
+                    center,
+                    2.0,
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+
+            // Photons within the radius lie on a spherical cap of area π·r², so
+            // even a large radius doesn't blur the uniformly lit enclosure
+            let cam = Camera::new(1.0, 2, 32, 10);
+            let sppm = StochasticProgressivePhotonMapper::new(vec![light], 2000, 1.0);
+            let pixels = sppm.render_image(&cam, &world);
+
+            assert_eq!(pixels.len(), 4, "Not one color per pixel");
+            for pixel in pixels {
+                assert!(
+                    (pixel.x() - 0.2).abs() < 0.02,
+                    "Furnace radiance {} not 0.2",
+                    pixel
+                );
+            }
+        }
+
+        #[test]
+        fn sppm_caustic() {
+            //        *           A small light (r=0.1, L=100) 2 above a glass ball (r=0.5)
+            //        o           focuses its light onto a diffuse floor (albedo 0.5) 1 below
+            //       .-.          its center. The caustic X is seen directly from beside it
+            //      ( c )         and through the ball from above it, and compared to a point
+            //       '-'    o     of the floor the light reaches directly, 2 further along z.
+            //   -----X---------
+
+            let light: Rc<dyn Hittable> = Rc::new(Sphere::with_material(
+                Point3D::with_values(0.0, 3.0, 0.0),
+                0.1,
+                Rc::new(DiffuseLight::new(Color::with_values(100.0, 100.0, 100.0))),
+            ));
+            let world: HittableList = vec![
+                light.clone(),
+                Rc::new(Sphere::with_material(
+                    Point3D::with_values(0.0, 1.0, 0.0),
+                    0.5,
+                    Rc::new(Dielectric::new(1.5)),
+                )),
+                Rc::new(Plane::with_material(
+                    Point3D::new(),
+                    Vector3D::with_values(0.0, 1.0, 0.0),
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+            let dark = Rc::new(Uniform::new(Color::new()));
+            let view = |center: Point3D, target: Point3D| {
+                Camera::new(1.0, 1, 8, 10).with_vertical_fov(2.0).with_view(
+                    center,
+                    target - center,
+                    Vector3D::with_values(1.0, 0.0, 0.0),
+                )
+            };
+            let caustic = view(Point3D::with_values(1.0, 0.4, 0.0), Point3D::new());
+            let through_ball = view(
+                Point3D::with_values(0.15, 2.4, 0.0),
+                Point3D::with_values(0.15, 0.0, 0.0),
+            );
+            let lit = view(
+                Point3D::with_values(1.0, 0.4, 2.0),
+                Point3D::with_values(0.0, 0.0, 2.0),
+            );
+
+            let sppm = StochasticProgressivePhotonMapper::new(vec![light], 10000, 0.1)
+                .with_environment(dark.clone());
+            let render = |cam: &Camera| sppm.render_image(cam, &world)[0].x();
+            let reference = render(&lit);
+            assert!(reference > 0.0, "Floor not lit");
+            for (cam, name) in [
+                (&caustic, "seen directly"),
+                (&through_ball, "through the ball"),
+            ] {
+                let radiance = render(cam);
+                assert!(
+                    radiance > 3.0 * reference,
+                    "Caustic {} {} not brighter than the lit floor {}",
+                    name,
+                    radiance,
+                    reference
+                );
+            }
+
+            // Shadow rays towards a point light as bright as the small one can't pass the ball
+            let point_light = PointLight::new(
+                Point3D::with_values(0.0, 3.0, 0.0),
+                Color::with_values(100.0, 100.0, 100.0) * (PI * 0.01),
+            );
+            let path_tracer = PathTracer::new(vec![Rc::new(point_light)]).with_environment(dark);
+            let unlit = world[1..].to_vec();
+            let samples = 2000;
+            let mean = |cam: &Camera| {
+                (0..samples)
+                    .map(|_| path_tracer.ray_color(&cam.ray(0, 0), 10, &unlit).x())
+                    .sum::<f64>()
+                    / f64::from(samples)
+            };
+            let (traced_caustic, traced_lit) = (mean(&caustic), mean(&lit));
+
+            // L = a/π · I·cos(θ)/d², 3 below and 2 beside the light
+            let expected = 0.5 / PI * (PI * 3.0 / 13.0_f64.sqrt() / 13.0);
+            assert!(
+                (traced_lit - expected).abs() < 0.05 * expected,
+                "Lit floor {} not {}",
+                traced_lit,
+                expected
+            );
+            assert!(
+                traced_caustic < 0.1 * traced_lit,
+                "Path tracer found the caustic {}",
+                traced_caustic
+            );
+        }
+    }
+}
+
+// --- METROPOLIS LIGHT TRANSPORT ----------------------------------------------
+
+pub mod mlt {
+    //! Module for primary sample space Metropolis light transport.
+
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::integrator::ImageIntegrator;
+    use crate::sampler::{random_f64, with_sampler, Sampler};
+    use rand::distributions::WeightedIndex;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::cell::RefCell;
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /// Represents one random number of the primary sample space.
+    #[derive(Debug, Clone, Copy, Default)]
+    struct PrimarySample {
+        value: f64,
+        last_modification_iteration: u64,
+        value_backup: f64,
+        modify_backup: u64,
+    }
+
+    impl PrimarySample {
+        fn backup(&mut self) {
+            self.value_backup = self.value;
+            self.modify_backup = self.last_modification_iteration;
+        }
+
+        fn restore(&mut self) {
+            self.value = self.value_backup;
+            self.last_modification_iteration = self.modify_backup;
+        }
+    }
+
+    /// Provides the random numbers of a Markov chain over the primary sample space,
+    /// lazily mutating the numbers when they are drawn.
+    struct MltSampler {
+        rng: StdRng,
+        sigma: f64,
+        large_step_probability: f64,
+        samples: Vec<PrimarySample>,
+        current_iteration: u64,
+        large_step: bool,
+        last_large_step_iteration: u64,
+        sample_index: usize,
+    }
+
+    impl MltSampler {
+        /// Creates a new sampler, whose first iteration is a large step determined by `seed`.
+        fn new(seed: u64, sigma: f64, large_step_probability: f64) -> Self {
+            MltSampler {
+                rng: StdRng::seed_from_u64(seed),
+                sigma,
+                large_step_probability,
+                samples: Vec::new(),
+                current_iteration: 0,
+                large_step: true,
+                last_large_step_iteration: 0,
+                sample_index: 0,
+            }
+        }
+
+        /// Starts proposing a new state, either independent of the current one or close to it.
+        fn start_iteration(&mut self) {
+            self.current_iteration += 1;
+            self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+            self.sample_index = 0;
+        }
+
+        /// Makes the proposed state the current one.
+        fn accept(&mut self) {
+            if self.large_step {
+                self.last_large_step_iteration = self.current_iteration;
+            }
+        }
+
+        /// Returns to the state before the proposal.
+        fn reject(&mut self) {
+            for sample in self.samples.iter_mut() {
+                if sample.last_modification_iteration == self.current_iteration {
+                    sample.restore();
+                }
+            }
+            self.current_iteration -= 1;
+        }
+
+        /// Draws a standard normally distributed number (Box-Muller transform).
+        fn normal(&mut self) -> f64 {
+            let u1: f64 = 1.0 - self.rng.gen::<f64>();
+            let u2: f64 = self.rng.gen::<f64>();
+            (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+        }
+
+        /// Brings the sample at `index` up to date with the current iteration.
+        fn ensure_ready(&mut self, index: usize) {
+            // Numbers drawn for the first time start out uniformly distributed
+            while index >= self.samples.len() {
+                let value = self.rng.gen::<f64>();
+                self.samples.push(PrimarySample {
+                    value,
+                    last_modification_iteration: self.current_iteration,
+                    ..PrimarySample::default()
+                });
+            }
+
+            // Catch up on a large step that happened since the sample got last used
+            if self.samples[index].last_modification_iteration < self.last_large_step_iteration {
+                self.samples[index].value = self.rng.gen::<f64>();
+                self.samples[index].last_modification_iteration = self.last_large_step_iteration;
+            }
+            self.samples[index].backup();
+
+            if self.large_step {
+                self.samples[index].value = self.rng.gen::<f64>();
+            } else {
+                // Apply all small steps missed since the sample got last used at once
+                let small_steps =
+                    self.current_iteration - self.samples[index].last_modification_iteration;
+                let sigma = self.sigma * (small_steps as f64).sqrt();
+                let value = self.samples[index].value + self.normal() * sigma;
+                // Tiny negative values round up to 1 when wrapped, which is 0 again
+                let wrapped = value - value.floor();
+                self.samples[index].value = if wrapped < 1.0 { wrapped } else { 0.0 };
+            }
+            self.samples[index].last_modification_iteration = self.current_iteration;
+        }
+    }
+
+    impl Sampler for MltSampler {
+        fn next_f64(&mut self) -> f64 {
+            let index = self.sample_index;
+            self.sample_index += 1;
+            self.ensure_ready(index);
+            self.samples[index].value
+        }
+    }
+
+    /// Renders by running Markov chains, which mutate the random numbers driving the
+    /// path tracer of the camera, so bright paths get explored more thoroughly.
+    ///
+    /// The camera's samples per pixel set the number of mutations per pixel.
+    pub struct MetropolisLightTransport {
+        bootstrap_samples: u64,
+        chains: u64,
+        sigma: f64,
+        large_step_probability: f64,
+        environment: Rc<dyn Environment>,
+    }
+
+    impl Default for MetropolisLightTransport {
+        /// Creates an integrator with 100000 bootstrap samples, 1000 chains, small steps
+        /// with a standard deviation of 0.01 and large steps with a probability of 30%.
+        fn default() -> Self {
+            Self::new(100000, 1000, 0.01, 0.3)
+        }
+    }
+
+    impl MetropolisLightTransport {
+        /// Creates a new Metropolis light transport integrator.
+        ///
+        /// # Arguments
+        ///
+        /// * `bootstrap_samples`      - How many independent paths estimate the brightness of the image.
+        /// * `chains`                 - How many Markov chains are run.
+        /// * `sigma`                  - The standard deviation of small mutations of a random number.
+        /// * `large_step_probability` - The probability of proposing an independent path.
+        pub fn new(
+            bootstrap_samples: u64,
+            chains: u64,
+            sigma: f64,
+            large_step_probability: f64,
+        ) -> Self {
+            MetropolisLightTransport {
+                bootstrap_samples: bootstrap_samples.max(1),
+                chains: chains.max(1),
+                sigma,
+                large_step_probability,
+                environment: Rc::new(Gradient::default()),
+            }
+        }
+
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Traces the path determined by the sampler's random numbers.
+        ///
+        /// # Returns
+        ///
+        /// Returns the index of the pixel and the color of the path.
+        fn evaluate(
+            &self,
+            sampler: &Rc<RefCell<MltSampler>>,
+            cam: &Camera,
+            world: &HittableList,
+        ) -> (usize, Color) {
+            with_sampler(sampler.clone(), || {
+                let width = cam.image_width();
+                let height = cam.image_height();
+                let i = ((random_f64() * f64::from(width)) as u16).min(width - 1);
+                let j = ((random_f64() * f64::from(height)) as u16).min(height - 1);
+
+                let r = cam.ray(i, j);
+                let color =
+                    Camera::ray_color(&r, cam.max_depth(), world, self.environment.as_ref());
+                (usize::from(j) * usize::from(width) + usize::from(i), color)
+            })
+        }
+
+        /// The brightness the chains are distributed by, 0 for invalid colors.
+        fn contribution(color: Color) -> f64 {
+            let y = color.luminance();
+            if y.is_finite() && y > 0.0 {
+                y
+            } else {
+                0.0
+            }
+        }
+    }
+
+    impl ImageIntegrator for MetropolisLightTransport {
+        fn render_image(&self, cam: &Camera, world: &HittableList) -> Vec<Color> {
+            let pixel_count = usize::from(cam.image_width()) * usize::from(cam.image_height());
+            let mut pixels = vec![Color::new(); pixel_count];
+
+            // Estimate the overall brightness with independent paths
+            let weights: Vec<f64> = (0..self.bootstrap_samples)
+                .map(|seed| {
+                    let sampler = Rc::new(RefCell::new(MltSampler::new(
+                        seed,
+                        self.sigma,
+                        self.large_step_probability,
+                    )));
+                    Self::contribution(self.evaluate(&sampler, cam, world).1)
+                })
+                .collect();
+            let b = weights.iter().sum::<f64>() / self.bootstrap_samples as f64;
+            let distribution = match WeightedIndex::new(&weights) {
+                Ok(distribution) => distribution,
+                Err(_) => return pixels,
+            };
+
+            let total_mutations = u64::from(cam.samples_per_pixel()) * pixel_count as u64;
+            let mutations_per_chain = (total_mutations / self.chains).max(1);
+            let mut rng = thread_rng();
+
+            for chain in 0..self.chains {
+                eprintln!("Chains remaining: {}", self.chains - chain);
+                eprint!("\x1B[2J\x1B[1;1H"); // Clear output
+
+                // Start from a bootstrap path, chosen proportionally to its brightness
+                let seed = distribution.sample(&mut rng) as u64;
+                let sampler = Rc::new(RefCell::new(MltSampler::new(
+                    seed,
+                    self.sigma,
+                    self.large_step_probability,
+                )));
+                let (mut current_pixel, mut current) = self.evaluate(&sampler, cam, world);
+
+                for _ in 0..mutations_per_chain {
+                    sampler.borrow_mut().start_iteration();
+                    let (proposed_pixel, proposed) = self.evaluate(&sampler, cam, world);
+
+                    let y_current = Self::contribution(current);
+                    let y_proposed = Self::contribution(proposed);
+                    let accept = if y_current > 0.0 {
+                        (y_proposed / y_current).min(1.0)
+                    } else {
+                        1.0
+                    };
+
+                    // Splat both states weighted by their expected acceptance
+                    if y_proposed > 0.0 {
+                        pixels[proposed_pixel] += proposed * (accept / y_proposed);
+                    }
+                    if y_current > 0.0 {
+                        pixels[current_pixel] += current * ((1.0 - accept) / y_current);
+                    }
+
+                    if rng.gen::<f64>() < accept {
+                        current_pixel = proposed_pixel;
+                        current = proposed;
+                        sampler.borrow_mut().accept();
+                    } else {
+                        sampler.borrow_mut().reject();
+                    }
+                }
+            }
+
+            let mutations_per_pixel =
+                (mutations_per_chain * self.chains) as f64 / pixel_count as f64;
+            pixels
+                .into_iter()
+                .map(|pixel| pixel * (b / mutations_per_pixel))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::camera::Camera;
+        use crate::color::Color;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::sphere::Sphere;
+        use crate::integrator::mlt::{MetropolisLightTransport, MltSampler};
+        use crate::integrator::ImageIntegrator;
+        use crate::material::{DiffuseLight, Lambertian};
+        use crate::sampler::Sampler;
+        use crate::vector3d::Point3D;
+        use std::rc::Rc;
+
+        #[test]
+        fn mlt_sampler_reject() {
+            let mut sampler = MltSampler::new(7, 0.01, 0.3);
+            let first: Vec<f64> = (0..4).map(|_| sampler.next_f64()).collect();
+
+            for _ in 0..10 {
+                sampler.start_iteration();
+                let proposed: Vec<f64> = (0..4).map(|_| sampler.next_f64()).collect();
+                assert_ne!(proposed, first, "Proposal not mutated");
+                assert!(proposed.iter().all(|x| (0.0..1.0).contains(x)));
+                sampler.reject();
+            }
+
+            sampler.start_iteration();
+            sampler.reject();
+            assert_eq!(
+                sampler
+                    .samples
+                    .iter()
+                    .map(|s| s.value)
+                    .collect::<Vec<f64>>(),
+                first,
+                "Rejected proposals not undone"
+            );
+        }
+
+        #[test]
+        fn mlt_sampler_replay() {
+            let mut a = MltSampler::new(3, 0.01, 0.3);
+            let mut b = MltSampler::new(3, 0.01, 0.3);
+
+            for _ in 0..5 {
+                assert_eq!(a.next_f64(), b.next_f64(), "Same seed, different path");
+            }
+        }
+
+        #[test]
+        fn mlt_furnace() {
+            //        .-*****-.       A diffuse sphere (R=2, albedo 0.5)
+            //      *   .***.   *     around an emitting sphere (r=1, L=1)
+            //  X<--o--* c   *  *     behind the camera:
+            //      *   '***'   *     L = a·L·(r/R)² / (1 - a·(1 - (r/R)²)) = 0.2
+            //        ''*****''
+
+            let center = Point3D::with_values(0.0, 0.0, 1.5);
+            let world: HittableList = vec![
+                Rc::new(Sphere::with_material(
+                    center,
+                    1.0,
+                    Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+                )),
+                Rc::new(Sphere::with_material(
+                    center,
+                    2.0,
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+
+            let cam = Camera::new(1.0, 2, 10000, 50);
+            let mlt = MetropolisLightTransport::new(20000, 200, 0.01, 0.3);
+            let pixels = mlt.render_image(&cam, &world);
+
+            assert_eq!(pixels.len(), 4, "Not one color per pixel");
+            for pixel in pixels {
+                assert!(
+                    (pixel.x() - 0.2).abs() < 0.03,
+                    "Furnace radiance {} not 0.2",
+                    pixel
+                );
+            }
+        }
+    }
+}
+
+// --- SPECTRAL PATH TRACER ----------------------------------------------------
+
+pub mod spectral {
+    //! Module for path tracing light at sampled wavelengths instead of RGB.
+
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::environment::gradient::Gradient;
+    use crate::environment::Environment;
+    use crate::hittable::hittables::HittableList;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::integrator::ImageIntegrator;
+    use crate::interval::Interval;
+    use crate::ray::Ray;
+    use crate::sampler::random_f64;
+    use crate::spectrum::{xyz_to_rgb, RgbSpectrum, SampledSpectrum, SampledWavelengths};
+    use crate::vector3d::Vector3D;
+    use std::rc::Rc;
+
+    /// The stochastic path tracer of `Camera::ray_color`, tracing a few wavelengths per
+    /// path with hero wavelength sampling and accumulating the pixels in CIE XYZ.
+    ///
+    /// Colors of materials and the sky get upsampled to smooth spectra.
+    #[derive(Clone)]
+    pub struct SpectralPathTracer {
+        environment: Rc<dyn Environment>,
+    }
+
+    impl Default for SpectralPathTracer {
+        /// Creates a spectral path tracer under the white to light blue sky.
+        fn default() -> Self {
+            SpectralPathTracer {
+                environment: Rc::new(Gradient::default()),
+            }
+        }
+    }
+
+    impl SpectralPathTracer {
+        /// Lights the scene with the given environment instead of the default sky.
+        pub fn with_environment(mut self, environment: Rc<dyn Environment>) -> Self {
+            self.environment = environment;
+            self
+        }
+
+        /// Computes the radiance arriving along a ray at the sampled wavelengths.
+        ///
+        /// # Arguments
+        ///
+        /// * `r`      - The ray, tagged with the hero wavelength.
+        /// * `lambda` - The wavelengths traced along the path, reduced to the hero on dispersion.
+        /// * `depth`  - How many times the path may scatter.
+        /// * `world`  - The geometry of the scene.
+        pub fn radiance(
+            &self,
+            r: &Ray,
+            lambda: &mut SampledWavelengths,
+            depth: u16,
+            world: &HittableList,
+        ) -> SampledSpectrum {
+            let mut radiance = SampledSpectrum::new(0.0);
+            let mut throughput = SampledSpectrum::new(1.0);
+            let mut ray = *r;
+
+            for _ in 0..depth {
+                let mut rec = HitRecord::default();
+                if !world.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                    let background =
+                        RgbSpectrum::new(self.environment.radiance(ray.direction())).sample(lambda);
+                    radiance += throughput * background;
+                    break;
+                }
+
+                let emitted = rec.material().emitted(&rec);
+                radiance += throughput * RgbSpectrum::new(emitted).sample(lambda);
+
+                let (scattered, attenuation, scattered_ray) = rec.material().scatter(&ray, &rec);
+                if !scattered {
+                    break;
+                }
+                if rec.material().is_dispersive() {
+                    lambda.terminate_secondary();
+                }
+                throughput *= RgbSpectrum::new(attenuation).sample(lambda);
+                if throughput.is_black() {
+                    break;
+                }
+                ray = scattered_ray.with_wavelength(lambda.hero());
+            }
+
+            radiance
+        }
+    }
+
+    impl ImageIntegrator for SpectralPathTracer {
+        fn render_image(&self, cam: &Camera, world: &HittableList) -> Vec<Color> {
+            let mut pixels = Vec::new();
+            let samples = f64::from(cam.samples_per_pixel());
+
+            for j in 0..cam.image_height() {
+                eprintln!("Scanlines remaining: {}", cam.image_height() - j);
+                eprint!("\x1B[2J\x1B[1;1H"); // Clear output
+
+                for i in 0..cam.image_width() {
+                    let mut xyz = Vector3D::new();
+
+                    for _ in 0..cam.samples_per_pixel() {
+                        let mut lambda = SampledWavelengths::sample_uniform(random_f64());
+                        let r = cam.ray(i, j).with_wavelength(lambda.hero());
+                        let radiance = self.radiance(&r, &mut lambda, cam.max_depth(), world);
+                        xyz += lambda.to_xyz(&radiance);
+                    }
+
+                    pixels.push(xyz_to_rgb(xyz / samples));
+                }
+            }
+
+            pixels
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::camera::Camera;
+        use crate::color::Color;
+        use crate::hittable::hittables::HittableList;
+        use crate::hittable::sphere::Sphere;
+        use crate::integrator::spectral::SpectralPathTracer;
+        use crate::integrator::ImageIntegrator;
+        use crate::material::{DiffuseLight, Lambertian};
+        use crate::vector3d::Point3D;
+        use std::rc::Rc;
+
+        #[test]
+        fn spectral_emitter_color() {
+            //     o-->   ( c )       The camera seeing nothing but an emitting
+            //                        sphere, in its color after the round trip
+            //                        RGB -> spectrum -> XYZ -> RGB
+
+            let emit = Color::with_values(0.8, 0.4, 0.1);
+            let world: HittableList = vec![Rc::new(Sphere::with_material(
+                Point3D::with_values(0.0, 0.0, -3.0),
+                2.5,
+                Rc::new(DiffuseLight::new(emit)),
+            ))];
+
+            let cam = Camera::new(1.0, 2, 10000, 10);
+            for pixel in SpectralPathTracer::default().render_image(&cam, &world) {
+                assert!(
+                    (pixel - emit).length() < 0.03,
+                    "Emitter color {} not {}",
+                    pixel,
+                    emit
+                );
+            }
+        }
+
+        #[test]
+        fn spectral_furnace() {
+            //        .-*****-.       A diffuse sphere (R=2, albedo 0.5)
+            //      *   .***.   *     around an emitting sphere (r=1, L=1)
+            //  X<--o--* c   *  *     behind the camera:
+            //      *   '***'   *     L = a·L·(r/R)² / (1 - a·(1 - (r/R)²)) = 0.2
+            //        ''*****''
+
+            let center = Point3D::with_values(0.0, 0.0, 1.5);
+            let world: HittableList = vec![
+                Rc::new(Sphere::with_material(
+                    center,
+                    1.0,
+                    Rc::new(DiffuseLight::new(Color::with_values(1.0, 1.0, 1.0))),
+                )),
+                Rc::new(Sphere::with_material(
+                    center,
+                    2.0,
+                    Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+                )),
+            ];
+
+            let cam = Camera::new(1.0, 1, 20000, 50);
+            let pixel = SpectralPathTracer::default().render_image(&cam, &world)[0];
+
+            for channel in [pixel.x(), pixel.y(), pixel.z()] {
+                assert!(
+                    (channel - 0.2).abs() < 0.015,
+                    "Furnace radiance {} not 0.2",
+                    pixel
+                );
+            }
+        }
+    }
+}
@@ -1,12 +1,17 @@
+pub mod aabb;
+pub mod atmosphere;
 pub mod camera;
 pub mod color;
+pub mod distribution;
+pub mod environment;
 pub mod hittable;
+pub mod import;
+pub mod integrator;
 pub mod interval;
+pub mod light;
+pub mod material;
 pub mod ray;
+pub mod sampler;
+pub mod spectrum;
+pub mod texture;
 pub mod vector3d;
-
-use camera::Camera;
-use hittable::hittables::*;
-use hittable::sphere::*;
-use std::rc::Rc;
-use vector3d::*;
@@ -0,0 +1,1717 @@
+//! Module for representing light sources in the context of a raytracer.
+
+use crate::color::Color;
+use crate::vector3d::{Point3D, Vector3D};
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+/// Represents the light a light source casts onto a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// The unit direction from the point towards the light.
+    pub direction: Vector3D,
+    /// The distance from the point to the light, infinite for lights infinitely far away.
+    pub distance: f64,
+    /// The light arriving at the point perpendicular to `direction`, including the falloff.
+    pub illumination: Color,
+}
+
+pub trait Light {
+    /// Samples the light cast onto a point.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The illuminated point.
+    ///
+    /// # Returns
+    ///
+    /// Returns where the light arrives from and how much of it arrives.
+    fn sample(&self, p: Point3D) -> LightSample;
+
+    /// Computes the density per solid angle of `sample` choosing `direction` from `p`.
+    ///
+    /// Returns 0 for lights rays can't hit, like point lights, whose light must not
+    /// be weighted against finding them by scattering.
+    fn pdf(&self, _p: Point3D, _direction: Vector3D) -> f64 {
+        0.0
+    }
+
+    /// Bounds where the light is and which way it shines, for choosing among many lights.
+    ///
+    /// Returns `None` for lights infinitely far away, which light every point.
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+}
+
+/// Represents a conservative estimate of the light some lights cast, used to
+/// choose the ones most likely to matter at a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightBounds {
+    /// The corner of the box around the lights with the smallest coordinates.
+    pub min: Point3D,
+    /// The corner of the box around the lights with the largest coordinates.
+    pub max: Point3D,
+    /// The total power of the lights, as luminance.
+    pub power: f64,
+    /// The unit axis of the cone around every direction the lights face in.
+    pub direction: Vector3D,
+    /// The cosine of the half-angle of the cone of facing directions.
+    pub cos_theta_o: f64,
+    /// The cosine of how much further than the facing directions light is emitted.
+    pub cos_theta_e: f64,
+}
+
+impl LightBounds {
+    /// Creates the bounds of a light at a single point.
+    ///
+    /// # Arguments
+    ///
+    /// * `p`           - The position of the light.
+    /// * `power`       - The power of the light, as luminance.
+    /// * `direction`   - The unit direction the light faces in.
+    /// * `cos_theta_o` - The cosine of the half-angle of the cone of facing directions.
+    /// * `cos_theta_e` - The cosine of how much further than the facing directions light is emitted.
+    pub fn at_point(
+        p: Point3D,
+        power: f64,
+        direction: Vector3D,
+        cos_theta_o: f64,
+        cos_theta_e: f64,
+    ) -> Self {
+        LightBounds {
+            min: p,
+            max: p,
+            power,
+            direction,
+            cos_theta_o,
+            cos_theta_e,
+        }
+    }
+
+    /// Gets the center of the box around the lights.
+    pub fn centroid(&self) -> Point3D {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Combines two bounds into bounds around both.
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        let (direction, cos_theta_o) = union_cones(
+            (self.direction, self.cos_theta_o),
+            (other.direction, other.cos_theta_o),
+        );
+
+        LightBounds {
+            min: Point3D::with_values(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            max: Point3D::with_values(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+            power: self.power + other.power,
+            direction,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+        }
+    }
+
+    /// Estimates how much light the lights can cast onto a point, never less than they do.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The illuminated point.
+    /// * `n` - The surface normal at the point, light arriving from either side counts. Zero within volumes.
+    pub fn importance(&self, p: Point3D, n: Vector3D) -> f64 {
+        // Clamped to keep points within the bounds from getting infinitely important
+        let pc = self.centroid();
+        let diagonal = (self.max - self.min).length();
+        let distance_squared = (p - pc).length_squared().max((diagonal / 2.0).powi(2));
+
+        // Cosine of the angle between the cone and the point, lessened by the angles
+        // of the cone and the box seen from the point
+        let wi = (p - pc).unit_vector();
+        let cos_theta_w = self.direction.dot(wi);
+        let cos_theta_b = bound_subtended(self.min, self.max, p);
+
+        let cos_theta_x = cos_sub_clamped(cos_theta_w, self.cos_theta_o);
+        let cos_theta_p = cos_sub_clamped(cos_theta_x, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.power * cos_theta_p / distance_squared;
+        if !n.near_zero() {
+            importance *= cos_sub_clamped(wi.dot(n).abs(), cos_theta_b);
+        }
+        importance.max(0.0)
+    }
+}
+
+/// Computes the cosine of the angle of `cos_a` less the one of `cos_b`, 1 if negative.
+fn cos_sub_clamped(cos_a: f64, cos_b: f64) -> f64 {
+    if cos_a > cos_b {
+        return 1.0;
+    }
+
+    let sin_a = (1.0 - cos_a * cos_a).max(0.0).sqrt();
+    let sin_b = (1.0 - cos_b * cos_b).max(0.0).sqrt();
+    cos_a * cos_b + sin_a * sin_b
+}
+
+/// Computes the cosine of the half-angle of the cone a box is seen in from `p`, -1 inside.
+fn bound_subtended(min: Point3D, max: Point3D, p: Point3D) -> f64 {
+    let center = (min + max) / 2.0;
+    let radius_squared = (max - center).length_squared();
+    let distance_squared = (p - center).length_squared();
+    if distance_squared <= radius_squared {
+        return -1.0;
+    }
+
+    (1.0 - radius_squared / distance_squared).max(0.0).sqrt()
+}
+
+/// Computes the smallest cone around two cones, each an axis and the cosine of its half-angle.
+fn union_cones(a: (Vector3D, f64), b: (Vector3D, f64)) -> (Vector3D, f64) {
+    let theta_a = a.1.clamp(-1.0, 1.0).acos();
+    let theta_b = b.1.clamp(-1.0, 1.0).acos();
+    let theta_d = a.0.dot(b.0).clamp(-1.0, 1.0).acos();
+
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return a;
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return b;
+    }
+
+    let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+    let axis = a.0.cross(b.0);
+    if theta_o >= PI || axis.near_zero() {
+        return (a.0, -1.0);
+    }
+
+    // Rotate the axis of `a` towards the one of `b` (Rodrigues)
+    let k = axis.unit_vector();
+    let theta_r = theta_o - theta_a;
+    let direction = a.0 * theta_r.cos()
+        + k.cross(a.0) * theta_r.sin()
+        + k * (k.dot(a.0) * (1.0 - theta_r.cos()));
+
+    (direction.unit_vector(), theta_o.cos())
+}
+
+/// Alias for a list of light sources.
+pub type LightList = Vec<Rc<dyn Light>>;
+
+// --- POINT LIGHT -------------------------------------------------------------
+
+pub mod point {
+    //! Module for handling point lights in the context of a raytracer.
+
+    use crate::color::Color;
+    use crate::light::ies::IesProfile;
+    use crate::light::{Light, LightBounds, LightSample};
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /// Represents a light emitting uniformly in every direction from a single point.
+    ///
+    /// With a photometric profile the light is distributed like a real luminaire
+    /// hanging from the ceiling instead.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PointLight {
+        position: Point3D,
+        intensity: Color,
+        profile: Option<Rc<IesProfile>>,
+    }
+
+    impl PointLight {
+        /// Creates a new point light.
+        ///
+        /// # Arguments
+        ///
+        /// * `position`  - The position of the light.
+        /// * `intensity` - The emitted intensity, arriving one unit away from the light.
+        pub fn new(position: Point3D, intensity: Color) -> Self {
+            PointLight {
+                position,
+                intensity,
+                profile: None,
+            }
+        }
+
+        /// Distributes the light by a photometric profile, its nadir pointing down
+        /// along -y and its horizontal angle of 0° along +x.
+        ///
+        /// The intensity becomes the peak intensity of the profile.
+        pub fn with_profile(mut self, profile: Rc<IesProfile>) -> Self {
+            self.profile = Some(profile);
+            self
+        }
+
+        /// Gets the position of the light.
+        pub fn position(&self) -> Point3D {
+            self.position
+        }
+
+        /// Gets the emitted intensity of the light.
+        pub fn intensity(&self) -> Color {
+            self.intensity
+        }
+
+        /// Gets the photometric profile of the light, if any.
+        pub fn profile(&self) -> Option<&IesProfile> {
+            self.profile.as_deref()
+        }
+    }
+
+    impl Light for PointLight {
+        fn sample(&self, p: Point3D) -> LightSample {
+            let to_light = self.position - p;
+            let distance_squared = to_light.length_squared();
+            let direction = to_light.unit_vector();
+
+            let intensity = match &self.profile {
+                Some(profile) => {
+                    self.intensity
+                        * profile.relative_intensity(
+                            -direction,
+                            Vector3D::with_values(0.0, -1.0, 0.0),
+                            Vector3D::with_values(1.0, 0.0, 0.0),
+                        )
+                }
+                None => self.intensity,
+            };
+
+            LightSample {
+                direction,
+                distance: distance_squared.sqrt(),
+                illumination: intensity / distance_squared,
+            }
+        }
+
+        fn bounds(&self) -> Option<LightBounds> {
+            Some(LightBounds::at_point(
+                self.position,
+                4.0 * PI * self.intensity.luminance(),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+                -1.0,
+                0.0,
+            ))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::light::ies::IesProfile;
+        use crate::light::point::PointLight;
+        use crate::light::{Light, LightSample};
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::rc::Rc;
+
+        #[test]
+        fn point_light_sample() {
+            //   p - - - - - - - - - * light
+            // (0,0)               (2,0)
+
+            let light = PointLight::new(
+                Point3D::with_values(2.0, 0.0, 0.0),
+                Color::with_values(4.0, 4.0, 4.0),
+            );
+
+            assert_eq!(
+                light.sample(Point3D::new()),
+                LightSample {
+                    direction: Vector3D::with_values(1.0, 0.0, 0.0),
+                    distance: 2.0,
+                    illumination: Color::with_values(1.0, 1.0, 1.0),
+                },
+                "Light not falling off with the squared distance"
+            );
+        }
+
+        #[test]
+        fn point_light_profile() {
+            //        * light (0,1)
+            //        |\
+            //        | \        Full intensity straight down,
+            //  ------p--p---    half of it at 45°
+
+            let profile = IesProfile::new(vec![0.0, 45.0, 90.0], vec![0.0], vec![100.0, 50.0, 0.0]);
+            let light = PointLight::new(
+                Point3D::with_values(0.0, 1.0, 0.0),
+                Color::with_values(2.0, 2.0, 2.0),
+            )
+            .with_profile(Rc::new(profile));
+
+            assert!(light.profile().is_some());
+            assert_eq!(
+                light.sample(Point3D::new()).illumination,
+                Color::with_values(2.0, 2.0, 2.0)
+            );
+            let diagonal = light
+                .sample(Point3D::with_values(1.0, 0.0, 0.0))
+                .illumination;
+            assert!(
+                (diagonal - Color::with_values(0.5, 0.5, 0.5)).near_zero(),
+                "Profile not applied, got {}",
+                diagonal
+            );
+            let above = light
+                .sample(Point3D::with_values(0.0, 2.0, 0.0))
+                .illumination;
+            assert_eq!(above, Color::new(), "Lit above the luminaire");
+        }
+    }
+}
+
+// --- SPOT LIGHT --------------------------------------------------------------
+
+pub mod spot {
+    //! Module for handling spot lights in the context of a raytracer.
+
+    use crate::color::Color;
+    use crate::light::ies::IesProfile;
+    use crate::light::{Light, LightBounds, LightSample};
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    /// Represents a point light shining only into a cone, fading out towards its edge.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SpotLight {
+        position: Point3D,
+        direction: Vector3D,
+        intensity: Color,
+        cos_falloff_start: f64,
+        cos_total_width: f64,
+        profile: Option<Rc<IesProfile>>,
+    }
+
+    impl SpotLight {
+        /// Creates a new spot light.
+        ///
+        /// # Arguments
+        ///
+        /// * `position`      - The position of the light.
+        /// * `direction`     - The direction the cone of light points in.
+        /// * `intensity`     - The emitted intensity along `direction`, arriving one unit away.
+        /// * `falloff_start` - The angle in degrees from `direction` where the light starts to fade.
+        /// * `total_width`   - The angle in degrees from `direction` where no light is left.
+        pub fn new(
+            position: Point3D,
+            direction: Vector3D,
+            intensity: Color,
+            falloff_start: f64,
+            total_width: f64,
+        ) -> Self {
+            let total_width = total_width.clamp(0.0, 180.0);
+            let falloff_start = falloff_start.clamp(0.0, total_width);
+
+            SpotLight {
+                position,
+                direction: direction.unit_vector(),
+                intensity,
+                cos_falloff_start: falloff_start.to_radians().cos(),
+                cos_total_width: total_width.to_radians().cos(),
+                profile: None,
+            }
+        }
+
+        /// Distributes the light within the cone by a photometric profile, its nadir
+        /// pointing along the direction of the light.
+        ///
+        /// The intensity becomes the peak intensity of the profile.
+        pub fn with_profile(mut self, profile: Rc<IesProfile>) -> Self {
+            self.profile = Some(profile);
+            self
+        }
+
+        /// Gets the position of the light.
+        pub fn position(&self) -> Point3D {
+            self.position
+        }
+
+        /// Gets the unit direction the cone of light points in.
+        pub fn direction(&self) -> Vector3D {
+            self.direction
+        }
+
+        /// Gets the emitted intensity along the direction of the light.
+        pub fn intensity(&self) -> Color {
+            self.intensity
+        }
+
+        /// Gets the photometric profile of the light, if any.
+        pub fn profile(&self) -> Option<&IesProfile> {
+            self.profile.as_deref()
+        }
+
+        /// Computes the fraction of the intensity emitted at the angle with the cosine
+        /// `cos_theta` from the direction of the light, smoothly fading out.
+        pub fn falloff(&self, cos_theta: f64) -> f64 {
+            if cos_theta >= self.cos_falloff_start {
+                return 1.0;
+            }
+            if cos_theta <= self.cos_total_width {
+                return 0.0;
+            }
+
+            let t = (cos_theta - self.cos_total_width)
+                / (self.cos_falloff_start - self.cos_total_width);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    impl Light for SpotLight {
+        fn sample(&self, p: Point3D) -> LightSample {
+            let to_light = self.position - p;
+            let distance_squared = to_light.length_squared();
+            let direction = to_light.unit_vector();
+            let mut falloff = self.falloff((-direction).dot(self.direction));
+            if let Some(profile) = &self.profile {
+                let (reference, _) = self.direction.orthonormal_basis();
+                falloff *= profile.relative_intensity(-direction, self.direction, reference);
+            }
+
+            LightSample {
+                direction,
+                distance: distance_squared.sqrt(),
+                illumination: self.intensity * (falloff / distance_squared),
+            }
+        }
+
+        fn bounds(&self) -> Option<LightBounds> {
+            // Full intensity within the falloff start, fading out until the total width
+            let theta_e = self.cos_total_width.acos() - self.cos_falloff_start.acos();
+
+            Some(LightBounds::at_point(
+                self.position,
+                4.0 * PI * self.intensity.luminance(),
+                self.direction,
+                self.cos_falloff_start,
+                theta_e.cos(),
+            ))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::light::ies::IesProfile;
+        use crate::light::spot::SpotLight;
+        use crate::light::Light;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::rc::Rc;
+
+        #[test]
+        fn spot_light_sample() {
+            //        * light (0,2)
+            //       /|\
+            //      / | \
+            //  ---p--p--p---  lit in the center, fading out and dark further out
+
+            let intensity = Color::with_values(4.0, 4.0, 4.0);
+            let light = SpotLight::new(
+                Point3D::with_values(0.0, 2.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+                intensity,
+                20.0,
+                40.0,
+            );
+
+            let center = light.sample(Point3D::new());
+            assert_eq!(center.direction, Vector3D::with_values(0.0, 1.0, 0.0));
+            assert_eq!(center.distance, 2.0);
+            assert_eq!(center.illumination, intensity / 4.0, "Center not fully lit");
+
+            // 30° off the axis, within the falloff
+            let p = Point3D::with_values(2.0 * 30f64.to_radians().tan(), 0.0, 0.0);
+            let fading = light.sample(p).illumination.x() * (light.position - p).length_squared();
+            assert!(
+                fading > 0.0 && fading < 4.0,
+                "Intensity {} not fading",
+                fading
+            );
+
+            let outside = light.sample(Point3D::with_values(5.0, 0.0, 0.0));
+            assert_eq!(outside.illumination, Color::new(), "Outside the cone lit");
+        }
+
+        #[test]
+        fn spot_light_falloff() {
+            let light = SpotLight::new(
+                Point3D::new(),
+                Vector3D::with_values(0.0, 0.0, -1.0),
+                Color::with_values(1.0, 1.0, 1.0),
+                10.0,
+                30.0,
+            );
+
+            let mut previous = 1.0;
+            for degrees in 0..=40 {
+                let falloff = light.falloff(f64::from(degrees).to_radians().cos());
+                assert!(
+                    falloff <= previous,
+                    "Falloff not decreasing at {}°",
+                    degrees
+                );
+                previous = falloff;
+            }
+            assert_eq!(light.falloff(1.0), 1.0);
+            assert_eq!(light.falloff(0.0), 0.0);
+        }
+
+        #[test]
+        fn spot_light_profile() {
+            // A profile dimming linearly to nothing at 20°, within the full cone
+            let profile = IesProfile::new(vec![0.0, 20.0], vec![0.0], vec![10.0, 0.0]);
+            let light = SpotLight::new(
+                Point3D::new(),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+                Color::with_values(1.0, 1.0, 1.0),
+                30.0,
+                30.0,
+            )
+            .with_profile(Rc::new(profile));
+
+            let along = light.sample(Point3D::with_values(1.0, 0.0, 0.0));
+            assert_eq!(along.illumination, Color::with_values(1.0, 1.0, 1.0));
+
+            let p = Point3D::with_values(1.0, 10f64.to_radians().tan(), 0.0);
+            let off_axis = light.sample(p).illumination.x() * p.length_squared();
+            assert!(
+                (off_axis - 0.5).abs() < 1e-9,
+                "Profile not aimed along the spot"
+            );
+        }
+    }
+}
+
+// --- DIRECTIONAL LIGHT -------------------------------------------------------
+
+pub mod directional {
+    //! Module for handling directional lights in the context of a raytracer.
+
+    use crate::color::Color;
+    use crate::light::{Light, LightSample};
+    use crate::vector3d::{Point3D, Vector3D};
+
+    /// Represents a light infinitely far away, shining parallel light onto the scene.
+    ///
+    /// Lights with an angular diameter, like the sun, shine from a disk of directions
+    /// instead, casting soft shadows.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct DirectionalLight {
+        direction: Vector3D,
+        irradiance: Color,
+        cos_half_angle: f64,
+    }
+
+    impl DirectionalLight {
+        /// Creates a new directional light.
+        ///
+        /// # Arguments
+        ///
+        /// * `direction`  - The direction the light travels in.
+        /// * `irradiance` - The light arriving on a surface facing the light.
+        pub fn new(direction: Vector3D, irradiance: Color) -> Self {
+            DirectionalLight {
+                direction: direction.unit_vector(),
+                irradiance,
+                cos_half_angle: 1.0,
+            }
+        }
+
+        /// Spreads the light over a disk of directions.
+        ///
+        /// # Arguments
+        ///
+        /// * `degrees` - The apparent diameter of the light, about 0.53° for the sun.
+        pub fn with_angular_diameter(mut self, degrees: f64) -> Self {
+            self.cos_half_angle = (degrees.clamp(0.0, 180.0) / 2.0).to_radians().cos();
+            self
+        }
+
+        /// Gets the unit direction the light travels in.
+        pub fn direction(&self) -> Vector3D {
+            self.direction
+        }
+
+        /// Gets the light arriving on a surface facing the light.
+        pub fn irradiance(&self) -> Color {
+            self.irradiance
+        }
+
+        /// Gets the apparent diameter of the light in degrees.
+        pub fn angular_diameter(&self) -> f64 {
+            2.0 * self.cos_half_angle.acos().to_degrees()
+        }
+    }
+
+    impl Light for DirectionalLight {
+        fn sample(&self, _p: Point3D) -> LightSample {
+            let direction = if self.cos_half_angle < 1.0 {
+                Vector3D::random_in_cone(-self.direction, self.cos_half_angle)
+            } else {
+                -self.direction
+            };
+
+            LightSample {
+                direction,
+                distance: f64::INFINITY,
+                illumination: self.irradiance,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::light::directional::DirectionalLight;
+        use crate::light::Light;
+        use crate::vector3d::{Point3D, Vector3D};
+
+        #[test]
+        fn directional_light_sample() {
+            let irradiance = Color::with_values(1.0, 0.9, 0.8);
+            let light = DirectionalLight::new(Vector3D::with_values(0.0, -2.0, 0.0), irradiance);
+
+            for p in [Point3D::new(), Point3D::with_values(100.0, -5.0, 3.0)] {
+                let sample = light.sample(p);
+                assert_eq!(sample.direction, Vector3D::with_values(0.0, 1.0, 0.0));
+                assert_eq!(sample.distance, f64::INFINITY, "Sun not infinitely far");
+                assert_eq!(sample.illumination, irradiance, "Sun falling off");
+            }
+        }
+
+        #[test]
+        fn directional_light_angular_diameter() {
+            let light = DirectionalLight::new(
+                Vector3D::with_values(0.0, -1.0, 0.0),
+                Color::with_values(1.0, 1.0, 1.0),
+            )
+            .with_angular_diameter(10.0);
+            assert!((light.angular_diameter() - 10.0).abs() < 1e-9);
+
+            let cos_half_angle = 5f64.to_radians().cos();
+            let mut spread = false;
+            for _ in 0..100 {
+                let direction = light.sample(Point3D::new()).direction;
+                assert!(
+                    direction.y() >= cos_half_angle - 1e-12,
+                    "{} not within the sun disk",
+                    direction
+                );
+                spread |= direction.y() < 1.0 - 1e-9;
+            }
+            assert!(spread, "Sun disk a single point");
+        }
+    }
+}
+
+// --- AREA LIGHT --------------------------------------------------------------
+
+pub mod area {
+    //! Module for handling emitting objects as light sources in the context of a raytracer.
+
+    use crate::aabb::Aabb;
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::light::{Light, LightBounds, LightSample};
+    use crate::vector3d::{Point3D, Vector3D};
+    use std::f64::consts::PI;
+    use std::fmt;
+    use std::rc::Rc;
+
+    /// How far before the light shadow rays stop, so they don't hit the light itself.
+    const SHADOW_EPSILON: f64 = 0.001;
+
+    /// Represents an object with an emitting material, like `DiffuseLight`, as a
+    /// light source lighting points from uniformly sampled points on its surface.
+    ///
+    /// The object stays part of the scene, rays hitting it still see its light.
+    #[derive(Clone)]
+    pub struct AreaLight {
+        object: Rc<dyn Hittable>,
+        radiance: Color,
+        extent: Aabb,
+    }
+
+    impl AreaLight {
+        /// Creates a new light of an emitting object.
+        ///
+        /// The radiance is taken at a single point, emitting materials are expected
+        /// to emit the same light everywhere on their front faces.
+        ///
+        /// # Returns
+        ///
+        /// Returns `None` for objects that can't be sampled, have no extent or don't emit.
+        pub fn new(object: Rc<dyn Hittable>) -> Option<Self> {
+            let extent = object.extent()?;
+            if object.area() <= 0.0 {
+                return None;
+            }
+            let rec = object.sample_surface()?;
+            let radiance = rec.material().emitted(&rec);
+            if radiance.near_zero() {
+                return None;
+            }
+
+            Some(AreaLight {
+                object,
+                radiance,
+                extent,
+            })
+        }
+
+        /// Gets the emitting object.
+        pub fn object(&self) -> &Rc<dyn Hittable> {
+            &self.object
+        }
+
+        /// Gets the radiance emitted from the front faces of the object.
+        pub fn radiance(&self) -> Color {
+            self.radiance
+        }
+    }
+
+    impl Light for AreaLight {
+        fn sample(&self, p: Point3D) -> LightSample {
+            let dark = LightSample {
+                direction: Vector3D::with_values(0.0, 1.0, 0.0),
+                distance: 0.0,
+                illumination: Color::new(),
+            };
+            let Some(rec) = self.object.sample_surface() else {
+                return dark;
+            };
+
+            // Only the outside of the surface emits
+            let to_light = rec.p() - p;
+            let distance = to_light.length();
+            let direction = to_light / distance;
+            let cos_light = -rec.normal().dot(direction);
+            if distance <= SHADOW_EPSILON || cos_light <= 0.0 {
+                return dark;
+            }
+
+            LightSample {
+                direction,
+                distance: distance - SHADOW_EPSILON,
+                illumination: rec.material().emitted(&rec) * cos_light * self.object.area()
+                    / (distance * distance),
+            }
+        }
+
+        fn pdf(&self, p: Point3D, direction: Vector3D) -> f64 {
+            self.object.pdf(p, direction)
+        }
+
+        fn bounds(&self) -> Option<LightBounds> {
+            // Curved objects face every way, so the cone spans the whole sphere
+            Some(LightBounds {
+                min: self.extent.min(),
+                max: self.extent.max(),
+                power: PI * self.object.area() * self.radiance.luminance(),
+                direction: Vector3D::with_values(0.0, 0.0, 1.0),
+                cos_theta_o: -1.0,
+                cos_theta_e: 0.0,
+            })
+        }
+    }
+
+    impl fmt::Debug for AreaLight {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("AreaLight")
+                .field("object", &Rc::as_ptr(&self.object))
+                .field("radiance", &self.radiance)
+                .field("extent", &self.extent)
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::hittable::disk::Disk;
+        use crate::hittable::sphere::Sphere;
+        use crate::light::area::AreaLight;
+        use crate::light::Light;
+        use crate::material::{DiffuseLight, Lambertian};
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::f64::consts::PI;
+        use std::rc::Rc;
+
+        /// A disk of radius 0.1 at y = 1 facing down, emitting `radiance`.
+        fn disk(radiance: f64) -> Disk {
+            Disk::with_material(
+                Point3D::with_values(0.0, 1.0, 0.0),
+                Vector3D::with_values(0.0, -1.0, 0.0),
+                0.1,
+                Rc::new(DiffuseLight::new(Color::with_values(
+                    radiance, radiance, radiance,
+                ))),
+            )
+        }
+
+        #[test]
+        fn area_light_sample() {
+            //   ===    disk at y = 1 facing down
+            //
+            // ---p---  the irradiance below is πLr² / (h² + r²)
+            let light = AreaLight::new(Rc::new(disk(100.0))).unwrap();
+            let samples = 10000;
+            let mut irradiance = 0.0;
+            for _ in 0..samples {
+                let sample = light.sample(Point3D::new());
+                assert!(sample.distance < 1.01 && sample.distance > 0.99);
+                irradiance += sample.illumination.x() * sample.direction.y();
+            }
+            irradiance /= f64::from(samples);
+            let expected = PI * 100.0 * 0.01 / 1.01;
+            assert!(
+                (irradiance - expected).abs() < 0.01 * expected,
+                "Irradiance {} not {}",
+                irradiance,
+                expected
+            );
+
+            let above = light.sample(Point3D::with_values(0.0, 2.0, 0.0));
+            assert_eq!(above.illumination, Color::new(), "Back of the disk lit");
+
+            let up = Vector3D::with_values(0.0, 1.0, 0.0);
+            assert!((light.pdf(Point3D::new(), up) - 1.0 / (PI * 0.01)).abs() < 1e-9);
+            assert_eq!(light.pdf(Point3D::new(), -up), 0.0, "Disk behind the point");
+
+            let bounds = light.bounds().unwrap();
+            assert!((bounds.power - PI * PI * 0.01 * 100.0).abs() < 1e-9);
+            assert!(bounds.min.y() <= 1.0 && bounds.max.y() >= 1.0);
+            assert!((bounds.max.x() - 0.1).abs() < 1e-12, "Extent not the disk");
+        }
+
+        #[test]
+        fn area_light_non_emitting() {
+            let lambertian = Sphere::with_material(
+                Point3D::new(),
+                1.0,
+                Rc::new(Lambertian::new(Color::with_values(0.5, 0.5, 0.5))),
+            );
+            assert!(AreaLight::new(Rc::new(lambertian)).is_none());
+            assert!(AreaLight::new(Rc::new(disk(0.0))).is_none());
+        }
+    }
+}
+
+// --- IES PROFILE -------------------------------------------------------------
+
+pub mod ies {
+    //! Module for reading photometric profiles of luminaires from IES LM-63 files.
+
+    use crate::vector3d::Vector3D;
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    /// Represents how the intensity of a luminaire varies with the direction it is
+    /// measured in, as type C photometry.
+    ///
+    /// Vertical angles are measured from the nadir, the direction straight down out
+    /// of the luminaire, horizontal angles counterclockwise around it seen from above.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct IesProfile {
+        vertical_angles: Vec<f64>,
+        horizontal_angles: Vec<f64>,
+        candela: Vec<f64>,
+        max_candela: f64,
+    }
+
+    impl IesProfile {
+        /// Creates a new profile.
+        ///
+        /// # Arguments
+        ///
+        /// * `vertical_angles`   - The increasing vertical angles in degrees, within [0, 180].
+        /// * `horizontal_angles` - The increasing horizontal angles in degrees, ending at 0, 90, 180 or 360 for symmetric luminaires.
+        /// * `candela`           - The intensities, all vertical angles for one horizontal angle after another.
+        ///
+        /// # Panics
+        ///
+        /// Panics if there are no angles or the number of intensities does not match them.
+        pub fn new(
+            vertical_angles: Vec<f64>,
+            horizontal_angles: Vec<f64>,
+            candela: Vec<f64>,
+        ) -> Self {
+            assert!(
+                !vertical_angles.is_empty() && !horizontal_angles.is_empty(),
+                "Profile without angles"
+            );
+            assert_eq!(
+                candela.len(),
+                vertical_angles.len() * horizontal_angles.len(),
+                "Intensities not matching the angles"
+            );
+
+            let max_candela = candela.iter().copied().fold(0.0, f64::max);
+            IesProfile {
+                vertical_angles,
+                horizontal_angles,
+                candela,
+                max_candela,
+            }
+        }
+
+        /// Loads a profile from an IES LM-63 (.ies) file.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the file.
+        pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            Self::parse(&fs::read_to_string(path)?)
+        }
+
+        /// Parses a profile in the IES LM-63 format of 1986 to 2002.
+        ///
+        /// The intensities are scaled by the candela multiplier and ballast factors.
+        /// Only type C photometry, the one used for building luminaires, is supported.
+        /// Tilt factors only matter for lamps mounted at an angle and are ignored,
+        /// included ones get skipped and `TILT=<file>` ones are not read.
+        ///
+        /// # Arguments
+        ///
+        /// * `text` - The contents of an .ies file.
+        pub fn parse(text: &str) -> io::Result<Self> {
+            let invalid =
+                |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+            // Header and keyword lines up to the tilt
+            let mut lines = text.lines();
+            let tilt = loop {
+                match lines.next() {
+                    Some(line) => {
+                        if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+                            break tilt.trim().to_string();
+                        }
+                    }
+                    None => return Err(invalid("Missing TILT line")),
+                }
+            };
+
+            let rest: Vec<&str> = lines.collect();
+            let mut numbers = rest
+                .iter()
+                .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+                .filter(|token| !token.is_empty())
+                .map(|token| token.parse::<f64>().map_err(|_| invalid("Invalid number")));
+            let mut next = || {
+                numbers
+                    .next()
+                    .unwrap_or(Err(invalid("Unexpected end of file")))
+            };
+            let count = |n: f64| {
+                if n.is_finite() && n >= 0.0 && n.fract() == 0.0 && n <= u32::MAX as f64 {
+                    Ok(n as usize)
+                } else {
+                    Err(invalid("Invalid count"))
+                }
+            };
+
+            // Tilt angles and factors only matter for lamps mounted at an angle
+            if tilt == "INCLUDE" {
+                next()?;
+                let pairs = count(next()?)?;
+                let values = pairs
+                    .checked_mul(2)
+                    .ok_or_else(|| invalid("Invalid count"))?;
+                for _ in 0..values {
+                    next()?;
+                }
+            }
+
+            let _lamps = next()?;
+            let _lumens = next()?;
+            let multiplier = next()?;
+            let n_vertical = next()?;
+            let n_horizontal = next()?;
+            let photometric_type = next()?;
+            for _ in 0..4 {
+                next()?; // units and luminous opening
+            }
+            let ballast_factor = next()?;
+            let ballast_lamp_factor = next()?;
+            let _watts = next()?;
+
+            if photometric_type != 1.0 {
+                return Err(invalid("Unsupported photometric type"));
+            }
+            let (n_vertical, n_horizontal) = (count(n_vertical)?, count(n_horizontal)?);
+            if n_vertical < 1 || n_horizontal < 1 {
+                return Err(invalid("Profile without angles"));
+            }
+            let n_candela = n_vertical
+                .checked_mul(n_horizontal)
+                .ok_or_else(|| invalid("Invalid count"))?;
+
+            let vertical_angles = (0..n_vertical)
+                .map(|_| next())
+                .collect::<io::Result<Vec<_>>>()?;
+            let horizontal_angles = (0..n_horizontal)
+                .map(|_| next())
+                .collect::<io::Result<Vec<_>>>()?;
+            let scale = multiplier * ballast_factor * ballast_lamp_factor;
+            let candela = (0..n_candela)
+                .map(|_| next().map(|c| c * scale))
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let increasing = |angles: &[f64]| angles.windows(2).all(|w| w[0] < w[1]);
+            if !increasing(&vertical_angles) || !increasing(&horizontal_angles) {
+                return Err(invalid("Angles not increasing"));
+            }
+
+            Ok(Self::new(vertical_angles, horizontal_angles, candela))
+        }
+
+        /// Gets the vertical angles in degrees.
+        pub fn vertical_angles(&self) -> &[f64] {
+            &self.vertical_angles
+        }
+
+        /// Gets the horizontal angles in degrees.
+        pub fn horizontal_angles(&self) -> &[f64] {
+            &self.horizontal_angles
+        }
+
+        /// Gets the highest intensity of the luminaire in candela.
+        pub fn max_candela(&self) -> f64 {
+            self.max_candela
+        }
+
+        /// Computes the intensity in candela, interpolated between the measured angles.
+        ///
+        /// # Arguments
+        ///
+        /// * `vertical`   - The angle from the nadir in degrees.
+        /// * `horizontal` - The angle around the nadir in degrees.
+        pub fn candela(&self, vertical: f64, horizontal: f64) -> f64 {
+            let (v, v_t) = match Self::locate(&self.vertical_angles, vertical) {
+                Some(located) => located,
+                None => return 0.0,
+            };
+            let (h, h_t) =
+                Self::locate(&self.horizontal_angles, self.fold(horizontal)).unwrap_or((0, 0.0));
+
+            let n_v = self.vertical_angles.len();
+            let at = |h: usize, v: usize| {
+                let h = h.min(self.horizontal_angles.len() - 1);
+                self.candela[h * n_v + v.min(n_v - 1)]
+            };
+
+            let lerp = |a: f64, b: f64, t: f64| a + t * (b - a);
+            lerp(
+                lerp(at(h, v), at(h, v + 1), v_t),
+                lerp(at(h + 1, v), at(h + 1, v + 1), v_t),
+                h_t,
+            )
+        }
+
+        /// Computes the intensity towards `direction` relative to the highest one.
+        ///
+        /// # Arguments
+        ///
+        /// * `direction` - The unit direction out of the luminaire.
+        /// * `nadir`     - The unit direction of the vertical angle 0°.
+        /// * `reference` - The unit direction of the horizontal angle 0°, perpendicular to `nadir`.
+        pub fn relative_intensity(
+            &self,
+            direction: Vector3D,
+            nadir: Vector3D,
+            reference: Vector3D,
+        ) -> f64 {
+            if self.max_candela <= 0.0 {
+                return 0.0;
+            }
+
+            // Counterclockwise seen from above, looking along the nadir
+            let side = reference.cross(nadir);
+            let vertical = direction.dot(nadir).clamp(-1.0, 1.0).acos().to_degrees();
+            let horizontal = direction
+                .dot(side)
+                .atan2(direction.dot(reference))
+                .to_degrees();
+
+            self.candela(vertical, horizontal) / self.max_candela
+        }
+
+        /// Maps a horizontal angle into the measured range by the symmetry of the luminaire.
+        ///
+        /// The range gets folded relative to its first angle, so profiles from 90° to
+        /// 270° mirror across the plane through 90° and 270°.
+        fn fold(&self, horizontal: f64) -> f64 {
+            let first = self.horizontal_angles[0];
+            let span = self.horizontal_angles[self.horizontal_angles.len() - 1] - first;
+            let mut h = (horizontal - first).rem_euclid(360.0);
+
+            if span <= 0.0 {
+                return first;
+            }
+            if span <= 180.0 && h > 180.0 {
+                h = 360.0 - h;
+            }
+            if span <= 90.0 && h > 90.0 {
+                h = 180.0 - h;
+            }
+            first + h
+        }
+
+        /// Finds the interval of `angles` containing `angle` and where within it lies.
+        ///
+        /// # Returns
+        ///
+        /// Returns the index of the interval start and the fraction within, or `None` outside.
+        fn locate(angles: &[f64], angle: f64) -> Option<(usize, f64)> {
+            let first = angles[0];
+            let last = angles[angles.len() - 1];
+            if angle < first - 1e-9 || angle > last + 1e-9 {
+                return None;
+            }
+            if angles.len() == 1 {
+                return Some((0, 0.0));
+            }
+
+            let i = angles
+                .partition_point(|&a| a <= angle)
+                .clamp(1, angles.len() - 1)
+                - 1;
+            let t = ((angle - angles[i]) / (angles[i + 1] - angles[i])).clamp(0.0, 1.0);
+            Some((i, t))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::light::ies::IesProfile;
+        use crate::vector3d::Vector3D;
+
+        const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] Downlight
+[MANUFAC] Nobody
+TILT=NONE
+1 1000 2 3 1 1 2 0.1 0.1 0.0
+1.0 1.0 10
+0 45 90
+0
+100 50 0
+";
+
+        #[test]
+        fn ies_parse() {
+            //      luminaire
+            //     ----*----
+            //        /|\        100 cd straight down, 50 cd at 45°,
+            //       / | \       nothing sideways, doubled by the multiplier
+            let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+
+            assert_eq!(profile.vertical_angles(), &[0.0, 45.0, 90.0]);
+            assert_eq!(profile.max_candela(), 200.0, "Multiplier not applied");
+            assert_eq!(profile.candela(0.0, 0.0), 200.0);
+            assert!(
+                (profile.candela(22.5, 123.0) - 150.0).abs() < 1e-9,
+                "Not interpolated"
+            );
+            assert_eq!(
+                profile.candela(45.0, -40.0),
+                100.0,
+                "Not rotationally symmetric"
+            );
+            assert_eq!(profile.candela(120.0, 0.0), 0.0, "Lit above the luminaire");
+
+            let down = Vector3D::with_values(0.0, -1.0, 0.0);
+            let x = Vector3D::with_values(1.0, 0.0, 0.0);
+            assert_eq!(profile.relative_intensity(down, down, x), 1.0);
+            let diagonal = Vector3D::with_values(1.0, -1.0, 0.0).unit_vector();
+            assert!((profile.relative_intensity(diagonal, down, x) - 0.5).abs() < 1e-9);
+        }
+
+        #[test]
+        fn ies_parse_symmetry() {
+            // Measured over a quadrant with a tilt, brighter towards the horizontal 90°
+            let text = "IESNA91
+TILT=INCLUDE
+1
+2
+0 90
+1, 1
+1 -1 1.0 2 2 1 1 0 0 0
+1 1 0
+0 90
+0 90
+10 0
+20 0
+";
+            let profile = IesProfile::parse(text).unwrap();
+
+            assert_eq!(profile.candela(0.0, 45.0), 15.0);
+            for h in [90.0, 270.0, -90.0] {
+                assert_eq!(profile.candela(0.0, h), 20.0, "Not mirrored at {}°", h);
+            }
+            assert_eq!(profile.candela(0.0, 180.0), 10.0);
+
+            // Horizontal 90° lies along -z for a nadir along -y and reference along +x
+            let down = Vector3D::with_values(0.0, -1.0, 0.0);
+            let x = Vector3D::with_values(1.0, 0.0, 0.0);
+            let towards = |d: Vector3D| profile.relative_intensity(d.unit_vector(), down, x);
+            assert!(towards(Vector3D::with_values(0.0, -1.0, -0.001)) > 0.99);
+            assert!(towards(Vector3D::with_values(0.001, -1.0, 0.0)) < 0.51);
+        }
+
+        #[test]
+        fn ies_parse_bilateral() {
+            // Measured from 90° to 270° with the tilt in a file of its own
+            let text = "IESNA:LM-63-2002
+TILT=lamp.tlt
+1 -1 1.0 1 3 1 1 0 0 0
+1 1 0
+0
+90 180 270
+10 20 30
+";
+            let profile = IesProfile::parse(text).unwrap();
+
+            assert_eq!(profile.candela(0.0, 180.0), 20.0);
+            assert_eq!(profile.candela(0.0, 0.0), 20.0, "Not mirrored at 0°");
+            assert_eq!(profile.candela(0.0, 45.0), 15.0, "Not mirrored at 45°");
+            assert!(
+                (profile.candela(0.0, -60.0) - 80.0 / 3.0).abs() < 1e-9,
+                "Not mirrored at -60°"
+            );
+        }
+
+        #[test]
+        fn ies_parse_invalid() {
+            assert!(
+                IesProfile::parse("IESNA:LM-63-2002\n").is_err(),
+                "Missing tilt"
+            );
+            assert!(IesProfile::parse(&DOWNLIGHT.replace("100 50 0", "100 50")).is_err());
+            assert!(IesProfile::parse(&DOWNLIGHT.replace("0.1 0.1", "x 0.1")).is_err());
+            assert!(
+                IesProfile::parse(&DOWNLIGHT.replace("1 2 0.1", "2 2 0.1")).is_err(),
+                "Type B photometry accepted"
+            );
+
+            // Counts that are not whole numbers fail instead of panicking or truncating
+            for counts in ["NaN 1 1", "3 inf 1", "2.5 1 1", "-3 1 1"] {
+                let text = DOWNLIGHT.replace("3 1 1", counts);
+                assert!(IesProfile::parse(&text).is_err(), "Counts {} read", counts);
+            }
+            let tilted = DOWNLIGHT.replace("TILT=NONE", "TILT=INCLUDE\n1\ninf");
+            assert!(IesProfile::parse(&tilted).is_err(), "Endless tilt read");
+        }
+    }
+}
+
+// --- LIGHT TREE --------------------------------------------------------------
+
+pub mod tree {
+    //! Module for choosing among many lights in the context of a raytracer.
+
+    use crate::light::{LightBounds, LightList};
+    use crate::vector3d::{Point3D, Vector3D};
+
+    /// Represents a node of the tree, either a single light or two subtrees.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct LightNode {
+        bounds: LightBounds,
+        /// The index of the light of a leaf, or of the second child, the first following the node.
+        index: usize,
+        is_leaf: bool,
+    }
+
+    /// Represents a bounding volume hierarchy over lights, choosing one light
+    /// per point roughly proportional to the light it casts there.
+    ///
+    /// Lights infinitely far away are not part of the tree, every point is lit
+    /// by all of them.
+    #[derive(Clone)]
+    pub struct LightTree {
+        bounded: LightList,
+        unbounded: LightList,
+        nodes: Vec<LightNode>,
+        /// The path from the root to every bounded light, the choice at depth i in bit i.
+        trails: Vec<u64>,
+        /// The index within `bounded` of every light the tree got created from.
+        positions: Vec<Option<usize>>,
+    }
+
+    impl LightTree {
+        /// Creates a new tree over the given lights.
+        pub fn new(lights: &LightList) -> Self {
+            let mut bounded = LightList::new();
+            let mut unbounded = LightList::new();
+            let mut leaves = Vec::new();
+            let mut positions = Vec::with_capacity(lights.len());
+            for light in lights {
+                match light.bounds() {
+                    Some(bounds) if bounds.power > 0.0 => {
+                        positions.push(Some(bounded.len()));
+                        leaves.push((bounded.len(), bounds));
+                        bounded.push(light.clone());
+                    }
+                    Some(_) => positions.push(None),
+                    None => {
+                        positions.push(None);
+                        unbounded.push(light.clone());
+                    }
+                }
+            }
+
+            let mut tree = LightTree {
+                trails: vec![0; bounded.len()],
+                bounded,
+                unbounded,
+                nodes: Vec::new(),
+                positions,
+            };
+            if !leaves.is_empty() {
+                tree.build(&mut leaves, 0, 0);
+            }
+            tree
+        }
+
+        /// Builds the subtree over `leaves`, splitting them at the median centroid
+        /// along the axis they spread furthest on.
+        ///
+        /// # Returns
+        ///
+        /// Returns the bounds of the subtree.
+        fn build(
+            &mut self,
+            leaves: &mut [(usize, LightBounds)],
+            trail: u64,
+            depth: u32,
+        ) -> LightBounds {
+            if leaves.len() == 1 {
+                let (index, bounds) = leaves[0];
+                self.trails[index] = trail;
+                self.nodes.push(LightNode {
+                    bounds,
+                    index,
+                    is_leaf: true,
+                });
+                return bounds;
+            }
+
+            let axis = Self::widest_axis(leaves);
+            leaves.sort_by(|a, b| {
+                let (a, b) = (a.1.centroid(), b.1.centroid());
+                let (a, b) = match axis {
+                    0 => (a.x(), b.x()),
+                    1 => (a.y(), b.y()),
+                    _ => (a.z(), b.z()),
+                };
+                a.total_cmp(&b)
+            });
+            let (first, second) = leaves.split_at_mut(leaves.len() / 2);
+
+            let node = self.nodes.len();
+            self.nodes.push(LightNode {
+                bounds: first[0].1,
+                index: 0,
+                is_leaf: false,
+            });
+
+            // Splitting at the median keeps paths far shorter than the 64 bits of a trail
+            let bit = 1u64 << depth.min(63);
+            let first_bounds = self.build(first, trail, depth + 1);
+            self.nodes[node].index = self.nodes.len();
+            let second_bounds = self.build(second, trail | bit, depth + 1);
+
+            let bounds = first_bounds.union(&second_bounds);
+            self.nodes[node].bounds = bounds;
+            bounds
+        }
+
+        /// Finds the axis the centroids of `leaves` are spread furthest along.
+        fn widest_axis(leaves: &[(usize, LightBounds)]) -> usize {
+            let extent = |coordinate: fn(Point3D) -> f64| {
+                let values = leaves.iter().map(|(_, b)| coordinate(b.centroid()));
+                let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                });
+                max - min
+            };
+
+            let extents = [extent(|p| p.x()), extent(|p| p.y()), extent(|p| p.z())];
+            if extents[0] >= extents[1] && extents[0] >= extents[2] {
+                0
+            } else if extents[1] >= extents[2] {
+                1
+            } else {
+                2
+            }
+        }
+
+        /// Gets the lights in the tree, the ones `sample` chooses from.
+        pub fn bounded(&self) -> &LightList {
+            &self.bounded
+        }
+
+        /// Gets the lights infinitely far away, lighting every point.
+        pub fn unbounded(&self) -> &LightList {
+            &self.unbounded
+        }
+
+        /// Finds a light of the list the tree got created from within `bounded`.
+        ///
+        /// # Arguments
+        ///
+        /// * `light` - The index of the light within the list.
+        ///
+        /// # Returns
+        ///
+        /// Returns the index of the light within `bounded`, `None` for lights outside the tree.
+        pub fn bounded_index(&self, light: usize) -> Option<usize> {
+            self.positions.get(light).copied().flatten()
+        }
+
+        /// Chooses a light in the tree by how much light it may cast onto a point.
+        ///
+        /// # Arguments
+        ///
+        /// * `p` - The illuminated point.
+        /// * `n` - The surface normal at the point.
+        /// * `u` - A random number within [0, 1).
+        ///
+        /// # Returns
+        ///
+        /// Returns the index of the light within `bounded` and the probability of
+        /// choosing it, `None` if no light can reach the point.
+        pub fn sample(&self, p: Point3D, n: Vector3D, u: f64) -> Option<(usize, f64)> {
+            if self.nodes.is_empty() {
+                return None;
+            }
+
+            let mut u = u;
+            let mut pmf = 1.0;
+            let mut node = 0;
+            loop {
+                let current = self.nodes[node];
+                if current.is_leaf {
+                    return (current.bounds.importance(p, n) > 0.0).then_some((current.index, pmf));
+                }
+
+                let (first, second) = (node + 1, current.index);
+                let importance = [
+                    self.nodes[first].bounds.importance(p, n),
+                    self.nodes[second].bounds.importance(p, n),
+                ];
+                let total = importance[0] + importance[1];
+                if total <= 0.0 {
+                    return None;
+                }
+
+                let p_first = importance[0] / total;
+                if u < p_first {
+                    u = (u / p_first).min(1.0 - f64::EPSILON);
+                    pmf *= p_first;
+                    node = first;
+                } else {
+                    u = ((u - p_first) / (1.0 - p_first)).min(1.0 - f64::EPSILON);
+                    pmf *= 1.0 - p_first;
+                    node = second;
+                }
+            }
+        }
+
+        /// Computes the probability of `sample` choosing a light for a point.
+        ///
+        /// # Arguments
+        ///
+        /// * `p`     - The illuminated point.
+        /// * `n`     - The surface normal at the point.
+        /// * `index` - The index of the light within `bounded`.
+        pub fn pmf(&self, p: Point3D, n: Vector3D, index: usize) -> f64 {
+            if index >= self.bounded.len() {
+                return 0.0;
+            }
+
+            let trail = self.trails[index];
+            let mut pmf = 1.0;
+            let mut node = 0;
+            let mut depth = 0;
+            loop {
+                let current = self.nodes[node];
+                if current.is_leaf {
+                    return if current.bounds.importance(p, n) > 0.0 {
+                        pmf
+                    } else {
+                        0.0
+                    };
+                }
+
+                let (first, second) = (node + 1, current.index);
+                let importance = [
+                    self.nodes[first].bounds.importance(p, n),
+                    self.nodes[second].bounds.importance(p, n),
+                ];
+                let total = importance[0] + importance[1];
+                if total <= 0.0 {
+                    return 0.0;
+                }
+
+                let take_second = trail & (1u64 << depth.min(63)) != 0;
+                let (next, importance) = if take_second {
+                    (second, importance[1])
+                } else {
+                    (first, importance[0])
+                };
+                pmf *= importance / total;
+                node = next;
+                depth += 1;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::light::directional::DirectionalLight;
+        use crate::light::point::PointLight;
+        use crate::light::spot::SpotLight;
+        use crate::light::tree::LightTree;
+        use crate::light::{Light, LightBounds, LightList};
+        use crate::sampler::random_f64;
+        use crate::vector3d::{Point3D, Vector3D};
+        use std::rc::Rc;
+
+        #[test]
+        fn light_bounds_importance() {
+            //   * near        * far            spot ->  p  (behind it: dark)
+            //   |             |
+            // --p-------------------
+            let white = Color::with_values(1.0, 1.0, 1.0);
+            let up = Vector3D::with_values(0.0, 1.0, 0.0);
+            let near = PointLight::new(Point3D::with_values(0.0, 1.0, 0.0), white);
+            let far = PointLight::new(Point3D::with_values(0.0, 5.0, 0.0), white);
+
+            let near = near.bounds().unwrap().importance(Point3D::new(), up);
+            let far = far.bounds().unwrap().importance(Point3D::new(), up);
+            assert!(
+                near > far && far > 0.0,
+                "Importance {} not beyond {}",
+                near,
+                far
+            );
+
+            let spot = SpotLight::new(
+                Point3D::with_values(1.0, 0.0, 0.0),
+                Vector3D::with_values(1.0, 0.0, 0.0),
+                white,
+                20.0,
+                30.0,
+            );
+            let bounds = spot.bounds().unwrap();
+            assert_eq!(
+                bounds.importance(Point3D::new(), up),
+                0.0,
+                "Lit behind a spot"
+            );
+            let facing = Vector3D::with_values(-1.0, 0.0, 0.0);
+            assert!(bounds.importance(Point3D::with_values(3.0, 0.0, 0.0), facing) > 0.0);
+
+            // Within the bounds the distance is clamped to half the diagonal, √300 here
+            let wide = LightBounds {
+                min: Point3D::with_values(-10.0, -10.0, -10.0),
+                max: Point3D::with_values(10.0, 10.0, 10.0),
+                power: 300.0,
+                direction: up,
+                cos_theta_o: -1.0,
+                cos_theta_e: -1.0,
+            };
+            let inside = wide.importance(Point3D::with_values(1.0, 0.0, 0.0), Vector3D::new());
+            assert!(
+                inside > 0.0 && inside <= 1.0 + 1e-9,
+                "Importance {} within the bounds",
+                inside
+            );
+        }
+
+        #[test]
+        fn light_tree_sample() {
+            // A row of 64 lights above the ground, one of them a lot brighter
+            let mut lights: LightList = (0..64)
+                .map(|i| {
+                    let intensity = if i == 40 { 100.0 } else { 1.0 };
+                    Rc::new(PointLight::new(
+                        Point3D::with_values(f64::from(i), 1.0, 0.0),
+                        Color::with_values(intensity, intensity, intensity),
+                    )) as Rc<dyn Light>
+                })
+                .collect();
+            lights.push(Rc::new(DirectionalLight::new(
+                Vector3D::with_values(0.0, -1.0, 0.0),
+                Color::with_values(1.0, 1.0, 1.0),
+            )));
+
+            let tree = LightTree::new(&lights);
+            assert_eq!(tree.bounded().len(), 64);
+            assert_eq!(tree.unbounded().len(), 1, "Sun not lighting every point");
+            assert_eq!(tree.bounded_index(40), Some(40));
+            assert_eq!(tree.bounded_index(64), None, "Sun within the tree");
+
+            let (p, n) = (
+                Point3D::with_values(10.0, 0.0, 0.0),
+                Vector3D::with_values(0.0, 1.0, 0.0),
+            );
+            let total: f64 = (0..64).map(|i| tree.pmf(p, n, i)).sum();
+            assert!(
+                (total - 1.0).abs() < 1e-9,
+                "Probabilities summing to {}",
+                total
+            );
+
+            let samples = 10000;
+            let mut counts = [0u32; 64];
+            for _ in 0..samples {
+                let (index, pmf) = tree.sample(p, n, random_f64()).unwrap();
+                assert!(
+                    (tree.pmf(p, n, index) - pmf).abs() < 1e-12,
+                    "Probabilities disagree"
+                );
+                counts[index] += 1;
+            }
+            for (i, &count) in counts.iter().enumerate() {
+                let expected = tree.pmf(p, n, i) * f64::from(samples);
+                assert!(
+                    (f64::from(count) - expected).abs() < 5.0 * expected.sqrt() + 5.0,
+                    "Light {} chosen {} times, expected {}",
+                    i,
+                    count,
+                    expected
+                );
+            }
+            assert!(
+                counts[10] > counts[30],
+                "Far light chosen as often as near one"
+            );
+            assert!(
+                counts[40] > counts[30],
+                "Bright light chosen as often as dim one"
+            );
+        }
+
+        #[test]
+        fn light_tree_empty() {
+            let tree = LightTree::new(&LightList::new());
+            let n = Vector3D::with_values(0.0, 1.0, 0.0);
+            assert_eq!(tree.sample(Point3D::new(), n, 0.5), None);
+            assert_eq!(tree.pmf(Point3D::new(), n, 0), 0.0);
+        }
+    }
+}
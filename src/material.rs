@@ -1,32 +1,1269 @@
-use color::Color;
-use hittable::HitRecord;
-use ray::Ray;
-use vector3d::{Point3D, Vector3D};
+//! Module for representing materials in the context of a raytracer.
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::ray::Ray;
+use crate::sampler::random_f64;
+use crate::texture::Texture;
+use crate::vector3d::Vector3D;
+use std::f64::consts::PI;
+use std::fmt;
 use std::rc::Rc;
 
 pub trait Material {
+    /// Scatters an incoming ray at the surface described by the hit record.
+    ///
+    /// # Arguments
+    ///
+    /// * `r_in` - The incoming ray.
+    /// * `rec`  - The hit record of the incoming ray with the surface.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the ray got scattered, the attenuation of the scattered
+    /// light and the scattered ray.
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray);
+
+    /// Evaluates the non-specular part of the BSDF.
+    ///
+    /// # Arguments
+    ///
+    /// * `rec` - The hit record of the surface.
+    /// * `wo`  - The unit direction the light leaves to.
+    /// * `wi`  - The unit direction the light arrives from.
+    ///
+    /// Both directions point away from the surface. Materials without a
+    /// non-specular part are black.
+    fn eval(&self, _rec: &HitRecord, _wo: Vector3D, _wi: Vector3D) -> Color {
+        Color::new()
+    }
+
+    /// Computes the probability density (per solid angle) of `scatter` sampling `wi`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rec` - The hit record of the surface.
+    /// * `wo`  - The unit direction the light leaves to.
+    /// * `wi`  - The unit direction the light arrives from.
+    ///
+    /// Specular materials and materials without a known density return 0.
+    fn pdf(&self, _rec: &HitRecord, _wo: Vector3D, _wi: Vector3D) -> f64 {
+        0.0
+    }
+
+    /// Returns `true` if the material scatters in directions `eval` and `pdf`
+    /// can't describe, like mirrors, glass or fuzzy metal.
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the material scatters light within a volume instead of at a
+    /// surface, so light arrives from every direction without foreshortening.
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the scattering depends on the wavelength of the ray,
+    /// so a spectral path can't carry several wavelengths past the surface.
+    fn is_dispersive(&self) -> bool {
+        false
+    }
+
+    /// Gets the light emitted by the surface towards the incoming ray of the hit.
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::new()
+    }
+
+    /// Returns every perfectly specular direction the incoming ray continues in.
+    ///
+    /// # Arguments
+    ///
+    /// * `r_in` - The incoming ray.
+    /// * `rec`  - The hit record of the incoming ray with the surface.
+    ///
+    /// # Returns
+    ///
+    /// Returns the weight and ray of every specular lobe, empty if there are none.
+    fn specular(&self, _r_in: &Ray, _rec: &HitRecord) -> Vec<(Color, Ray)> {
+        Vec::new()
+    }
 }
 
-#[derive(PartialEq, Debug, Clone)]
+/// Checks whether both optional materials are the very same material.
+pub(crate) fn same_material(a: &Option<Rc<dyn Material>>, b: &Option<Rc<dyn Material>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// --- LAMBERTIAN --------------------------------------------------------------
+
+/// An ideal diffuse material.
+#[derive(Clone)]
 pub struct Lambertian {
     albedo: Color,
+    texture: Option<Rc<dyn Texture>>,
 }
 
 impl Lambertian {
-    pub const default: Self = Self::new(Color::new());
+    /// The 50% gray material of surfaces without a material of their own.
+    pub const DEFAULT: Self = Self::new(Color::with_values(0.5, 0.5, 0.5));
 
+    /// Creates a new diffuse material reflecting the fraction `a` of light.
     pub const fn new(a: Color) -> Self {
-        Lambertian { albedo: a }
+        Lambertian {
+            albedo: a,
+            texture: None,
+        }
+    }
+
+    /// Returns the material with its albedo varying over the surface by the texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture` - The texture the albedo gets multiplied with.
+    pub fn with_texture(mut self, texture: Rc<dyn Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Gets the fraction of light reflected.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    /// Gets the texture the albedo gets multiplied with, if there is one.
+    pub fn texture(&self) -> Option<&Rc<dyn Texture>> {
+        self.texture.as_ref()
     }
 }
 
 impl Material for Lambertian {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        let scatter_direction = Vector3D::random_cosine_direction(rec.normal());
+        let scattered = Ray::create(rec.p(), scatter_direction);
+
+        (true, textured(self.albedo, &self.texture, rec), scattered)
+    }
+
+    fn eval(&self, rec: &HitRecord, _wo: Vector3D, wi: Vector3D) -> Color {
+        if rec.normal().dot(wi) <= 0.0 {
+            return Color::new();
+        }
+        textured(self.albedo, &self.texture, rec) / PI
+    }
+
+    fn pdf(&self, rec: &HitRecord, _wo: Vector3D, wi: Vector3D) -> f64 {
+        cosine_pdf(rec, wi)
+    }
+}
+
+/// The density of cosine distributed directions around the normal of the hit.
+fn cosine_pdf(rec: &HitRecord, wi: Vector3D) -> f64 {
+    rec.normal().dot(wi).max(0.0) / PI
+}
+
+/// Multiplies a color with the color of the surface and the texture at the hit, if there is one.
+fn textured(color: Color, texture: &Option<Rc<dyn Texture>>, rec: &HitRecord) -> Color {
+    match texture {
+        Some(texture) => color * rec.color() * texture.value(rec.u(), rec.v(), rec.p()),
+        None => color * rec.color(),
+    }
+}
+
+/// Checks whether both optional textures are the very same texture.
+fn same_texture(a: &Option<Rc<dyn Texture>>, b: &Option<Rc<dyn Texture>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+impl PartialEq for Lambertian {
+    // Textures are equal if they are the very same texture
+    fn eq(&self, other: &Self) -> bool {
+        self.albedo == other.albedo && same_texture(&self.texture, &other.texture)
+    }
+}
+
+impl fmt::Debug for Lambertian {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lambertian")
+            .field("albedo", &self.albedo)
+            .field("texture", &self.texture.as_ref().map(Rc::as_ptr))
+            .finish()
+    }
+}
+
+// --- METAL -------------------------------------------------------------------
+
+/// A reflecting material, mirror-like or blurred by `fuzz`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Metal {
+    albedo: Color,
+    fuzz: f64,
+}
+
+impl Metal {
+    /// Creates a new metal.
+    ///
+    /// # Arguments
+    ///
+    /// * `a`    - The fraction of light reflected.
+    /// * `fuzz` - The radius of the sphere reflections are randomized in, clamped to [0, 1].
+    pub fn new(a: Color, fuzz: f64) -> Self {
+        Metal {
+            albedo: a,
+            fuzz: fuzz.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Gets the fraction of light reflected.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    /// Gets the fuzziness of the reflections.
+    pub fn fuzz(&self) -> f64 {
+        self.fuzz
+    }
+}
+
+impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
-        let _scatter_direction = rec.clone().normal() + Vector3D::random_unit_vector();
-        let _scattered = Ray::create(rec.clone().p(), _scatter_direction);
-        let _attenuation = self.albedo;
+        let reflected = r_in.direction().unit_vector().reflect(rec.normal());
+        let scattered = Ray::create(
+            rec.p(),
+            reflected + self.fuzz * Vector3D::random_unit_vector(),
+        );
+
+        (
+            scattered.direction().dot(rec.normal()) > 0.0,
+            self.albedo,
+            scattered,
+        )
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    /// Reflects perfectly, ignoring the fuzziness.
+    fn specular(&self, r_in: &Ray, rec: &HitRecord) -> Vec<(Color, Ray)> {
+        let reflected = r_in.direction().unit_vector().reflect(rec.normal());
+        vec![(self.albedo, Ray::create(rec.p(), reflected))]
+    }
+}
+
+// --- DIELECTRIC --------------------------------------------------------------
+
+/// Describes how the index of refraction of a dielectric depends on the wavelength.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum RefractiveIndex {
+    /// The same index of refraction at every wavelength.
+    Constant(f64),
+    /// Cauchy's equation n = A + B/λ², with B in µm².
+    Cauchy { a: f64, b: f64 },
+    /// The Sellmeier equation n² = 1 + Σ Bᵢλ²/(λ² - Cᵢ), with Cᵢ in µm².
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl RefractiveIndex {
+    /// The wavelength of the sodium D line in nanometers, the usual reference for
+    /// the index of refraction of glasses.
+    pub const D_LINE: f64 = 589.3;
+
+    /// Computes the index of refraction at a wavelength in nanometers.
+    pub fn at(&self, wavelength: f64) -> f64 {
+        let lambda = wavelength / 1000.0;
+        let lambda2 = lambda * lambda;
+
+        match *self {
+            RefractiveIndex::Constant(n) => n,
+            RefractiveIndex::Cauchy { a, b } => a + b / lambda2,
+            RefractiveIndex::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c)
+                    .map(|(b, c)| b * lambda2 / (lambda2 - c))
+                    .sum::<f64>())
+            .sqrt(),
+        }
+    }
+
+    /// Returns `true` if the index of refraction depends on the wavelength.
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, RefractiveIndex::Constant(_))
+    }
+}
+
+/// A clear material like glass or water, which reflects and refracts light.
+///
+/// Rays tagged with a wavelength get refracted by the index of refraction at
+/// that wavelength, so dispersive glass splits white light into a rainbow.
+/// Untagged RGB rays use the index at the sodium D line.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Dielectric {
+    ior: RefractiveIndex,
+}
+
+impl Dielectric {
+    /// Creates a new dielectric with the index of refraction `index_of_refraction`.
+    pub fn new(index_of_refraction: f64) -> Self {
+        Self::with_refractive_index(RefractiveIndex::Constant(index_of_refraction))
+    }
+
+    /// Creates a new dielectric with a wavelength dependent index of refraction.
+    pub fn with_refractive_index(ior: RefractiveIndex) -> Self {
+        Dielectric { ior }
+    }
+
+    /// Creates a new dielectric of Schott N-BK7, the common borosilicate crown glass of optics.
+    pub fn bk7() -> Self {
+        Self::with_refractive_index(RefractiveIndex::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        })
+    }
+
+    /// Creates a new dielectric of hard crown glass (K5).
+    pub fn crown() -> Self {
+        Self::with_refractive_index(RefractiveIndex::Cauchy {
+            a: 1.5220,
+            b: 0.00459,
+        })
+    }
+
+    /// Creates a new dielectric of dense flint glass (SF10), dispersing strongly.
+    pub fn flint() -> Self {
+        Self::with_refractive_index(RefractiveIndex::Cauchy {
+            a: 1.7280,
+            b: 0.01342,
+        })
+    }
+
+    /// Creates a new dielectric of diamond.
+    pub fn diamond() -> Self {
+        Self::with_refractive_index(RefractiveIndex::Sellmeier {
+            b: [4.3356, 0.3306, 0.0],
+            c: [0.1060 * 0.1060, 0.1750 * 0.1750, 0.0],
+        })
+    }
+
+    /// Gets the index of refraction at the sodium D line.
+    pub fn ir(&self) -> f64 {
+        self.ior.at(RefractiveIndex::D_LINE)
+    }
+
+    /// Gets how the index of refraction depends on the wavelength.
+    pub fn refractive_index(&self) -> RefractiveIndex {
+        self.ior
+    }
+
+    /// Schlick's approximation of the fraction of light reflected.
+    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+        let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+
+    /// Creates a ray leaving the surface, tracing the same wavelength as `r_in`.
+    fn continue_ray(r_in: &Ray, rec: &HitRecord, direction: Vector3D) -> Ray {
+        let r = Ray::create(rec.p(), direction);
+        match r_in.wavelength() {
+            Some(wavelength) => r.with_wavelength(wavelength),
+            None => r,
+        }
+    }
+
+    /// Computes the reflected direction, the refracted direction if there is
+    /// no total internal reflection and the fraction of light reflected.
+    fn split(&self, r_in: &Ray, rec: &HitRecord) -> (Vector3D, Option<Vector3D>, f64) {
+        let ir = self
+            .ior
+            .at(r_in.wavelength().unwrap_or(RefractiveIndex::D_LINE));
+        let refraction_ratio = if rec.front_face() { 1.0 / ir } else { ir };
+
+        let unit_direction = r_in.direction().unit_vector();
+        let cos_theta = (-unit_direction).dot(rec.normal()).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let reflected = unit_direction.reflect(rec.normal());
+        if refraction_ratio * sin_theta > 1.0 {
+            return (reflected, None, 1.0);
+        }
+
+        (
+            reflected,
+            Some(unit_direction.refract(rec.normal(), refraction_ratio)),
+            Self::reflectance(cos_theta, refraction_ratio),
+        )
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        let attenuation = Color::with_values(1.0, 1.0, 1.0);
+
+        let direction = match self.split(r_in, rec) {
+            (_, Some(refracted), reflectance) if reflectance <= random_f64() => refracted,
+            (reflected, _, _) => reflected,
+        };
+
+        (true, attenuation, Self::continue_ray(r_in, rec, direction))
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+
+    fn is_dispersive(&self) -> bool {
+        self.ior.is_dispersive()
+    }
+
+    fn specular(&self, r_in: &Ray, rec: &HitRecord) -> Vec<(Color, Ray)> {
+        let (reflected, refracted, reflectance) = self.split(r_in, rec);
+
+        let mut lobes = vec![(
+            Color::with_values(reflectance, reflectance, reflectance),
+            Self::continue_ray(r_in, rec, reflected),
+        )];
+        if let Some(refracted) = refracted {
+            let transmittance = 1.0 - reflectance;
+            lobes.push((
+                Color::with_values(transmittance, transmittance, transmittance),
+                Self::continue_ray(r_in, rec, refracted),
+            ));
+        }
+        lobes
+    }
+}
+
+// --- PHONG -------------------------------------------------------------------
+
+/// A diffuse material with a glossy, energy normalized Blinn-Phong highlight.
+#[derive(Clone)]
+pub struct Phong {
+    diffuse: Color,
+    specular: Color,
+    shininess: f64,
+    texture: Option<Rc<dyn Texture>>,
+}
+
+impl Phong {
+    /// Creates a new Blinn-Phong material.
+    ///
+    /// # Arguments
+    ///
+    /// * `diffuse`   - The fraction of light reflected diffusely.
+    /// * `specular`  - The fraction of light reflected in the highlight.
+    /// * `shininess` - The Blinn-Phong exponent, larger values give smaller highlights.
+    pub fn new(diffuse: Color, specular: Color, shininess: f64) -> Self {
+        Phong {
+            diffuse,
+            specular,
+            shininess,
+            texture: None,
+        }
+    }
+
+    /// Returns the material with its diffuse reflection varying over the surface by the texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture` - The texture the diffuse fraction gets multiplied with.
+    pub fn with_texture(mut self, texture: Rc<dyn Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Gets the fraction of light reflected diffusely.
+    pub fn diffuse(&self) -> Color {
+        self.diffuse
+    }
+
+    /// Gets the fraction of light reflected in the highlight.
+    pub fn specular(&self) -> Color {
+        self.specular
+    }
+
+    /// Gets the Blinn-Phong exponent.
+    pub fn shininess(&self) -> f64 {
+        self.shininess
+    }
+
+    /// Gets the texture the diffuse fraction gets multiplied with, if there is one.
+    pub fn texture(&self) -> Option<&Rc<dyn Texture>> {
+        self.texture.as_ref()
+    }
+}
+
+impl PartialEq for Phong {
+    // Textures are equal if they are the very same texture
+    fn eq(&self, other: &Self) -> bool {
+        self.diffuse == other.diffuse
+            && self.specular == other.specular
+            && self.shininess == other.shininess
+            && same_texture(&self.texture, &other.texture)
+    }
+}
+
+impl fmt::Debug for Phong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Phong")
+            .field("diffuse", &self.diffuse)
+            .field("specular", &self.specular)
+            .field("shininess", &self.shininess)
+            .field("texture", &self.texture.as_ref().map(Rc::as_ptr))
+            .finish()
+    }
+}
+
+impl Material for Phong {
+    /// Scatters cosine distributed, weighting the ray by the whole BSDF.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        let direction = Vector3D::random_cosine_direction(rec.normal());
+        let wo = -r_in.direction().unit_vector();
+
+        // cos(θ) / pdf = π for cosine distributed directions
+        let attenuation = PI * self.eval(rec, wo, direction);
+
+        (true, attenuation, Ray::create(rec.p(), direction))
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> Color {
+        if rec.normal().dot(wi) <= 0.0 {
+            return Color::new();
+        }
+
+        let diffuse = textured(self.diffuse, &self.texture, rec);
+        let halfway = wo + wi;
+        if halfway.near_zero() {
+            return diffuse / PI;
+        }
+
+        let cos_h = rec.normal().dot(halfway.unit_vector()).max(0.0);
+        let normalization = (self.shininess + 8.0) / (8.0 * PI);
+
+        diffuse / PI + normalization * cos_h.powf(self.shininess) * self.specular
+    }
+
+    fn pdf(&self, rec: &HitRecord, _wo: Vector3D, wi: Vector3D) -> f64 {
+        cosine_pdf(rec, wi)
+    }
+}
+
+// --- ISOTROPIC ---------------------------------------------------------------
+
+/// The phase function of a participating medium scattering light equally into every direction.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Isotropic {
+    albedo: Color,
+}
+
+impl Isotropic {
+    /// Creates a new isotropic phase function scattering the fraction `a` of light.
+    pub fn new(a: Color) -> Self {
+        Isotropic { albedo: a }
+    }
+
+    /// Gets the fraction of light scattered.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        let scattered = Ray::create(rec.p(), Vector3D::random_unit_vector());
+
+        (true, self.albedo, scattered)
+    }
+
+    fn eval(&self, _rec: &HitRecord, _wo: Vector3D, _wi: Vector3D) -> Color {
+        self.albedo / (4.0 * PI)
+    }
+
+    fn pdf(&self, _rec: &HitRecord, _wo: Vector3D, _wi: Vector3D) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
+
+// --- HENYEY-GREENSTEIN -------------------------------------------------------
+
+/// The phase function of a participating medium scattering light mostly forwards
+/// or backwards, like clouds and smoke, after Henyey and Greenstein.
+#[derive(PartialEq, Debug, Clone)]
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    /// Creates a new Henyey-Greenstein phase function.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The fraction of light scattered.
+    /// * `g` - The mean cosine of scattering, from -1 (backwards) over 0 (isotropic) to 1 (forwards).
+    pub fn new(a: Color, g: f64) -> Self {
+        HenyeyGreenstein {
+            albedo: a,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    /// Gets the fraction of light scattered.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    /// Gets the mean cosine of scattering.
+    pub fn g(&self) -> f64 {
+        self.g
+    }
+
+    /// Computes the density of scattering light by the angle with cosine `cos_theta`
+    /// between the unit directions `wo` and `wi` both pointing away from the point.
+    fn phase(&self, cos_theta: f64) -> f64 {
+        let denom = 1.0 + self.g * self.g + 2.0 * self.g * cos_theta;
+        (1.0 - self.g * self.g) / (4.0 * PI * denom * denom.max(0.0).sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        // Sample the cosine around the direction of travel by inverting the distribution
+        let g = self.g;
+        let u = random_f64();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64();
+
+        let forward = r_in.direction().unit_vector();
+        let (a, b) = forward.orthonormal_basis();
+        let direction =
+            forward * cos_theta + a * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin());
+
+        (true, self.albedo, Ray::create(rec.p(), direction))
+    }
+
+    fn eval(&self, _rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> Color {
+        self.albedo * self.phase(wo.dot(wi))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> f64 {
+        self.phase(wo.dot(wi))
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
+
+// --- RAYLEIGH ----------------------------------------------------------------
+
+/// The phase function of particles far smaller than the wavelength of light, like
+/// the molecules of air, scattering as much light forwards as backwards.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Rayleigh {
+    albedo: Color,
+}
+
+impl Rayleigh {
+    /// Creates a new Rayleigh phase function scattering the fraction `a` of light.
+    pub fn new(a: Color) -> Self {
+        Rayleigh { albedo: a }
+    }
+
+    /// Gets the fraction of light scattered.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    /// Computes the density of scattering light by the angle with cosine `cos_theta`.
+    fn phase(cos_theta: f64) -> f64 {
+        3.0 / (16.0 * PI) * (1.0 + cos_theta * cos_theta)
+    }
+}
+
+impl Material for Rayleigh {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        // Invert the cumulative distribution, a cubic in the cosine
+        let zeta = 4.0 * random_f64() - 2.0;
+        let a = (zeta + (zeta * zeta + 1.0).sqrt()).cbrt();
+        let cos_theta = (a - 1.0 / a).clamp(-1.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_f64();
+
+        let forward = r_in.direction().unit_vector();
+        let (u, v) = forward.orthonormal_basis();
+        let direction =
+            forward * cos_theta + u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin());
+
+        (true, self.albedo, Ray::create(rec.p(), direction))
+    }
+
+    fn eval(&self, _rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> Color {
+        self.albedo * Self::phase(wo.dot(wi))
+    }
+
+    fn pdf(&self, _rec: &HitRecord, wo: Vector3D, wi: Vector3D) -> f64 {
+        Self::phase(wo.dot(wi))
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
+
+// --- SUBSURFACE --------------------------------------------------------------
+
+/// A translucent material like skin, wax or marble, light entering it taking a
+/// random walk through its volume before leaving it again somewhere else.
+///
+/// The walk takes one bounce per scattering within the volume, so thick objects
+/// made of a material of high albedo need rays allowed to bounce many times.
+/// Surfaces have to be closed, like spheres, for the walk to find its way out.
+/// The albedo is only reached by objects far thicker than the mean free path,
+/// thinner ones letting more light through and reflecting less.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Subsurface {
+    albedo: Color,
+    mean_free_path: f64,
+    single_scattering_albedo: Color,
+}
+
+impl Subsurface {
+    /// Creates a new subsurface scattering material.
+    ///
+    /// # Arguments
+    ///
+    /// * `albedo`         - The fraction of light leaving the surface after all of the walk.
+    /// * `mean_free_path` - The mean distance light travels within the volume between scatterings.
+    pub fn new(albedo: Color, mean_free_path: f64) -> Self {
+        // Invert the albedo after many scatterings into the one of a single scattering
+        // (Chiang et al., "Practical and Controllable Subsurface Scattering", 2016)
+        let invert = |a: f64| {
+            let a = a.clamp(0.0, 1.0);
+            let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            (1.0 - s * s).clamp(0.0, 1.0)
+        };
+
+        Subsurface {
+            albedo,
+            mean_free_path: mean_free_path.max(f64::EPSILON),
+            single_scattering_albedo: Color::with_values(
+                invert(albedo.x()),
+                invert(albedo.y()),
+                invert(albedo.z()),
+            ),
+        }
+    }
+
+    /// Gets the fraction of light leaving the surface after all of the walk.
+    pub fn albedo(&self) -> Color {
+        self.albedo
+    }
+
+    /// Gets the mean distance light travels within the volume between scatterings.
+    pub fn mean_free_path(&self) -> f64 {
+        self.mean_free_path
+    }
+
+    /// Gets the fraction of light scattered instead of absorbed at every step of the walk.
+    pub fn single_scattering_albedo(&self) -> Color {
+        self.single_scattering_albedo
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> (bool, Color, Ray) {
+        // Light hitting the outside enters the volume diffusely
+        if rec.front_face() {
+            let inwards = Vector3D::random_cosine_direction(-rec.normal());
+            return (
+                true,
+                Color::with_values(1.0, 1.0, 1.0),
+                Ray::create(rec.p(), inwards),
+            );
+        }
+
+        // Walking inside, the light may scatter before it reaches the surface
+        let length = r_in.direction().length();
+        let distance = -(1.0 - random_f64()).ln() * self.mean_free_path;
+        if distance < rec.t() * length {
+            let p = r_in.at(distance / length);
+            return (
+                true,
+                self.single_scattering_albedo,
+                Ray::create(p, Vector3D::random_unit_vector()),
+            );
+        }
+
+        // The walk has reached the surface from within, leaving diffusely
+        let outwards = Vector3D::random_cosine_direction(-rec.normal());
+        (
+            true,
+            Color::with_values(1.0, 1.0, 1.0),
+            Ray::create(rec.p(), outwards),
+        )
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+// --- DIFFUSE LIGHT -----------------------------------------------------------
+
+/// A material emitting light uniformly from the front faces of a surface, without reflecting any.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DiffuseLight {
+    emit: Color,
+}
+
+impl DiffuseLight {
+    /// Creates a new light emitting the radiance `emit`.
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight { emit }
+    }
+
+    /// Gets the emitted radiance.
+    pub fn emit(&self) -> Color {
+        self.emit
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, r_in: &Ray, _rec: &HitRecord) -> (bool, Color, Ray) {
+        (false, Color::new(), *r_in)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        if rec.front_face() {
+            self.emit
+        } else {
+            Color::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::hittable::HitRecord;
+    use crate::material::*;
+    use crate::ray::Ray;
+    use crate::texture::image::ImageTexture;
+    use crate::vector3d::{Point3D, Vector3D};
+
+    //   X<-----------o
+    // (0,0)        (1,1)
+    // ---------------------  surface with normal (0,1,0)
+    fn hit() -> (Ray, HitRecord) {
+        let ray = Ray::create(
+            Point3D::with_values(1.0, 1.0, 0.0),
+            Vector3D::with_values(-1.0, -1.0, 0.0),
+        );
+        let rec = HitRecord::new(
+            Point3D::new(),
+            Vector3D::with_values(0.0, 1.0, 0.0),
+            1.0,
+            true,
+        );
+        (ray, rec)
+    }
+
+    #[test]
+    fn lambertian_scatter() {
+        let (ray, rec) = hit();
+        let albedo = Color::with_values(0.1, 0.2, 0.3);
+        let (scattered, attenuation, r) = Lambertian::new(albedo).scatter(&ray, &rec);
+
+        assert!(scattered, "Lambertian did not scatter");
+        assert_eq!(attenuation, albedo, "Attenuation not the albedo");
+        assert!(
+            r.direction().dot(rec.normal()) >= 0.0,
+            "Scattered into surface"
+        );
+        assert!(Lambertian::DEFAULT.specular(&ray, &rec).is_empty());
+    }
+
+    #[test]
+    fn lambertian_eval_pdf() {
+        let (_, rec) = hit();
+        let lambertian = Lambertian::new(Color::with_values(PI, PI, PI));
+        let up = Vector3D::with_values(0.0, 1.0, 0.0);
+
+        assert_eq!(
+            lambertian.eval(&rec, up, up),
+            Color::with_values(1.0, 1.0, 1.0)
+        );
+        assert_eq!(lambertian.pdf(&rec, up, up), 1.0 / PI);
+        assert_eq!(
+            lambertian.eval(&rec, up, -up),
+            Color::new(),
+            "Lit from below"
+        );
+        assert_eq!(lambertian.pdf(&rec, up, -up), 0.0, "Scattered into surface");
+    }
+
+    #[test]
+    fn lambertian_texture() {
+        //  v
+        //  1 +---+---+
+        //    |   | W |   hit at (0.75, 0.25)
+        //  ½ +---+---+
+        //    |   | X |
+        //  0 +---+---+ u
+        let (ray, rec) = hit();
+        let rec = rec.with_uv(0.75, 0.25);
+        let black = Color::new();
+        let white = Color::with_values(1.0, 1.0, 1.0);
+        let image = ImageTexture::new(2, 2, vec![black, white, black, 0.5 * white]);
+        let lambertian =
+            Lambertian::new(Color::with_values(1.0, 0.5, 1.0)).with_texture(Rc::new(image));
+        let up = Vector3D::with_values(0.0, 1.0, 0.0);
+
+        let (_, attenuation, _) = lambertian.scatter(&ray, &rec);
+        assert_eq!(
+            attenuation,
+            Color::with_values(0.5, 0.25, 0.5),
+            "Texture not applied"
+        );
+        assert_eq!(lambertian.eval(&rec, up, up), attenuation / PI);
+        assert_ne!(lambertian, Lambertian::new(lambertian.albedo()));
+
+        let phong = Phong::new(white, black, 1.0).with_texture(Rc::new(0.5 * white));
+        assert_eq!(
+            phong.eval(&rec, up, up),
+            0.5 * white / PI,
+            "Diffuse not textured"
+        );
+
+        // Vertex colors tint the diffuse reflection too
+        let tinted = rec.with_color(Color::with_values(0.0, 1.0, 1.0));
+        let (_, attenuation, _) = lambertian.scatter(&ray, &tinted);
+        assert_eq!(
+            attenuation,
+            Color::with_values(0.0, 0.25, 0.5),
+            "Color not applied"
+        );
+    }
+
+    #[test]
+    fn metal_specular() {
+        let (ray, rec) = hit();
+        let albedo = Color::with_values(0.8, 0.8, 0.8);
+        let lobes = Metal::new(albedo, 0.0).specular(&ray, &rec);
+
+        assert_eq!(lobes.len(), 1, "Metal not one mirror lobe");
+        assert_eq!(lobes[0].0, albedo, "Mirror lobe not weighted by albedo");
+        assert!(
+            (lobes[0].1.direction() - Vector3D::with_values(-1.0, 1.0, 0.0).unit_vector())
+                .near_zero(),
+            "Mirror direction not reflected"
+        );
+
+        let (scattered, _, r) = Metal::new(albedo, 0.0).scatter(&ray, &rec);
+        assert!(scattered, "Mirror did not scatter");
+        assert_eq!(r.direction(), lobes[0].1.direction());
+    }
+
+    #[test]
+    fn dielectric_specular() {
+        let (ray, rec) = hit();
+        let lobes = Dielectric::new(1.5).specular(&ray, &rec);
+
+        assert_eq!(lobes.len(), 2, "Glass not reflecting and refracting");
+        assert_eq!(
+            lobes[0].0 + lobes[1].0,
+            Color::with_values(1.0, 1.0, 1.0),
+            "Glass not conserving energy"
+        );
+        assert!(lobes[1].1.direction().y() < 0.0, "Not refracted into glass");
+
+        // Leaving the glass at 45° is beyond the critical angle of ~41.8°
+        let inside = HitRecord::new(
+            Point3D::new(),
+            Vector3D::with_values(0.0, 1.0, 0.0),
+            1.0,
+            false,
+        );
+        let lobes = Dielectric::new(1.5).specular(&ray, &inside);
+        assert_eq!(lobes.len(), 1, "No total internal reflection");
+        assert_eq!(lobes[0].0, Color::with_values(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn refractive_index_presets() {
+        let d = RefractiveIndex::D_LINE;
+        assert!(
+            (Dielectric::bk7().ir() - 1.5168).abs() < 1e-3,
+            "BK7 not BK7"
+        );
+        assert!(
+            (Dielectric::crown().ir() - 1.535).abs() < 1e-3,
+            "Crown not K5"
+        );
+        assert!(
+            (Dielectric::flint().ir() - 1.7667).abs() < 1e-3,
+            "Flint not SF10"
+        );
+        assert!(
+            (Dielectric::diamond().ir() - 2.417).abs() < 1e-3,
+            "Diamond not diamond"
+        );
+        assert_eq!(Dielectric::new(1.5).refractive_index().at(d), 1.5);
+
+        for glass in [
+            Dielectric::bk7(),
+            Dielectric::crown(),
+            Dielectric::flint(),
+            Dielectric::diamond(),
+        ] {
+            let ior = glass.refractive_index();
+            assert!(ior.is_dispersive(), "{:?} not dispersive", ior);
+            assert!(
+                ior.at(400.0) > ior.at(d) && ior.at(d) > ior.at(700.0),
+                "{:?} not refracting blue more than red",
+                ior
+            );
+        }
+        assert!(!Dielectric::new(1.5).is_dispersive());
+    }
+
+    #[test]
+    fn dielectric_dispersion() {
+        //            o  (1,1)    blue and red light
+        //          /
+        // -------X-------------  flint glass
+        //       / |
+        //      /  |   red bent less towards the normal than blue
+        //     r   b
+        let (ray, rec) = hit();
+        let glass = Dielectric::flint();
+
+        let blue = glass.specular(&ray.with_wavelength(400.0), &rec);
+        let red = glass.specular(&ray.with_wavelength(700.0), &rec);
+        let (blue_refracted, red_refracted) = (blue[1].1, red[1].1);
+
+        assert_eq!(blue_refracted.wavelength(), Some(400.0), "Wavelength lost");
+        assert_eq!(red[0].1.wavelength(), Some(700.0), "Wavelength lost");
+        assert!(
+            blue_refracted.direction().x() > red_refracted.direction().x(),
+            "Blue not bent more towards the normal than red"
+        );
+        assert_eq!(
+            glass
+                .scatter(&ray.with_wavelength(550.0), &rec)
+                .2
+                .wavelength(),
+            Some(550.0)
+        );
+    }
+
+    #[test]
+    fn diffuse_light_emitted() {
+        let (ray, rec) = hit();
+        let emit = Color::with_values(4.0, 4.0, 4.0);
+        let light = DiffuseLight::new(emit);
+
+        assert_eq!(light.emitted(&rec), emit, "Front face not emitting");
+        assert_eq!(
+            light.emitted(&HitRecord::new(Point3D::new(), Vector3D::new(), 1.0, false)),
+            Color::new(),
+            "Back face emitting"
+        );
+        assert!(!light.scatter(&ray, &rec).0, "Light scattering");
+        assert_eq!(Lambertian::DEFAULT.emitted(&rec), Color::new());
+    }
+
+    #[test]
+    fn isotropic_eval_pdf() {
+        let (ray, rec) = hit();
+        let albedo = Color::with_values(0.8, 0.8, 0.8);
+        let isotropic = Isotropic::new(albedo);
+        assert!(isotropic.is_volumetric(), "Phase function not volumetric");
+
+        // Light arriving from below the "surface" scatters just the same
+        let below = Vector3D::with_values(0.0, -1.0, 0.0);
+        let above = Vector3D::with_values(0.0, 1.0, 0.0);
+        assert_eq!(isotropic.eval(&rec, above, below), albedo / (4.0 * PI));
+        assert_eq!(isotropic.pdf(&rec, above, below), 1.0 / (4.0 * PI));
+
+        let (scattered, attenuation, scattered_ray) = isotropic.scatter(&ray, &rec);
+        assert!(scattered);
+        assert_eq!(attenuation, albedo);
+        assert!((scattered_ray.direction().length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn henyey_greenstein_phase() {
+        let (ray, rec) = hit();
+        let forward = HenyeyGreenstein::new(Color::with_values(1.0, 1.0, 1.0), 0.7);
+        let wo = -ray.direction().unit_vector();
+
+        // The density over all directions integrates to 1
+        let steps = 2000;
+        let integral: f64 = (0..steps)
+            .map(|i| {
+                let cos_theta = -1.0 + 2.0 * (f64::from(i) + 0.5) / f64::from(steps);
+                let (a, _) = wo.orthonormal_basis();
+                let wi = wo * cos_theta + a * (1.0 - cos_theta * cos_theta).sqrt();
+                forward.pdf(&rec, wo, wi) * 2.0 * PI * 2.0 / f64::from(steps)
+            })
+            .sum();
+        assert!(
+            (integral - 1.0).abs() < 1e-3,
+            "Phase integrating to {}",
+            integral
+        );
+        assert!(
+            forward.pdf(&rec, wo, -wo) > forward.pdf(&rec, wo, wo),
+            "Light not scattered forwards"
+        );
+
+        // Scattered directions have the mean cosine g with the direction of travel
+        let samples = 20000;
+        let mut mean = 0.0;
+        for _ in 0..samples {
+            let (scattered, _, scattered_ray) = forward.scatter(&ray, &rec);
+            assert!(scattered);
+            mean += scattered_ray.direction().dot(-wo) / f64::from(samples);
+        }
+        assert!((mean - 0.7).abs() < 0.02, "Mean cosine {} not 0.7", mean);
+
+        let isotropic = HenyeyGreenstein::new(Color::with_values(1.0, 1.0, 1.0), 0.0);
+        assert!((isotropic.pdf(&rec, wo, wo) - 1.0 / (4.0 * PI)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rayleigh_phase() {
+        let (ray, rec) = hit();
+        let rayleigh = Rayleigh::new(Color::with_values(1.0, 1.0, 1.0));
+        let wo = -ray.direction().unit_vector();
+        let (side, _) = wo.orthonormal_basis();
+
+        assert!((rayleigh.pdf(&rec, wo, -wo) - rayleigh.pdf(&rec, wo, wo)).abs() < 1e-12);
+        assert!(
+            (rayleigh.pdf(&rec, wo, -wo) - 2.0 * rayleigh.pdf(&rec, wo, side)).abs() < 1e-12,
+            "Sideways scattering not half as likely"
+        );
+
+        // Sampled cosines have the mean square 2/5 of the distribution
+        let samples = 20000;
+        let mut mean_square = 0.0;
+        for _ in 0..samples {
+            let (_, attenuation, scattered_ray) = rayleigh.scatter(&ray, &rec);
+            assert_eq!(attenuation, Color::with_values(1.0, 1.0, 1.0));
+            mean_square += scattered_ray.direction().dot(wo).powi(2) / f64::from(samples);
+        }
+        assert!(
+            (mean_square - 0.4).abs() < 0.01,
+            "Mean square cosine {}",
+            mean_square
+        );
+    }
+
+    #[test]
+    fn subsurface_albedo() {
+        let white = Subsurface::new(Color::with_values(1.0, 1.0, 1.0), 0.1);
+        let white_error = white.single_scattering_albedo() - Color::with_values(1.0, 1.0, 1.0);
+        assert!(white_error.length() < 1e-6, "White albedo absorbing");
+        let black = Subsurface::new(Color::new(), 0.1);
+        assert!(black.single_scattering_albedo().length() < 1e-3);
+
+        // Surviving many scatterings takes a single scattering albedo close to 1
+        let mut previous = 0.0;
+        for i in 1..10 {
+            let a = f64::from(i) / 10.0;
+            let single = Subsurface::new(Color::with_values(a, a, a), 0.1)
+                .single_scattering_albedo()
+                .x();
+            assert!(
+                single > previous && single >= a,
+                "Albedo {} inverted to {}",
+                a,
+                single
+            );
+            previous = single;
+        }
+    }
+
+    #[test]
+    fn subsurface_walk() {
+        //   X<-----------o       entering at the front face,
+        // ------+--------------  walking inside below it
+        //       |
+        //       v  exits at the back face or scatters before
+        let (ray, rec) = hit();
+        let albedo = Color::with_values(0.8, 0.6, 0.4);
+        let dense = Subsurface::new(albedo, 1e-9);
+        assert!(dense.is_specular());
+
+        let (scattered, attenuation, entering) = dense.scatter(&ray, &rec);
+        assert!(scattered);
+        assert_eq!(attenuation, Color::with_values(1.0, 1.0, 1.0));
+        assert!(
+            entering.direction().y() < 0.0,
+            "Light not entering the volume"
+        );
+
+        // Reaching the surface from within at t = 2
+        let inside = Ray::create(
+            Point3D::with_values(0.0, -2.0, 0.0),
+            Vector3D::with_values(0.0, 1.0, 0.0),
+        );
+        let back = HitRecord::new(
+            Point3D::new(),
+            Vector3D::with_values(0.0, -1.0, 0.0),
+            2.0,
+            false,
+        );
+
+        let (_, attenuation, walking) = dense.scatter(&inside, &back);
+        assert_eq!(attenuation, dense.single_scattering_albedo());
+        assert!(
+            walking.origin().y() < -1.9,
+            "Dense volume not scattering right away"
+        );
+
+        let clear = Subsurface::new(albedo, 1e9);
+        let (_, attenuation, leaving) = clear.scatter(&inside, &back);
+        assert_eq!(attenuation, Color::with_values(1.0, 1.0, 1.0));
+        assert_eq!(leaving.origin(), back.p());
+        assert!(leaving.direction().y() > 0.0, "Walk not leaving the volume");
+    }
+
+    #[test]
+    fn phong_eval() {
+        let (_, rec) = hit();
+        let diffuse = Color::with_values(0.5, 0.5, 0.5);
+        let phong = Phong::new(diffuse, Color::with_values(0.5, 0.5, 0.5), 32.0);
+
+        let wi = Vector3D::with_values(1.0, 1.0, 0.0).unit_vector();
+        let mirror = Vector3D::with_values(-1.0, 1.0, 0.0).unit_vector();
+        let off = Vector3D::with_values(1.0, 0.2, 0.0).unit_vector();
 
-        (true, _attenuation, _scattered)
+        assert!(
+            phong.eval(&rec, mirror, wi).x() > phong.eval(&rec, off, wi).x(),
+            "Highlight not in mirror direction"
+        );
+        assert!(
+            phong.eval(&rec, off, wi).x() >= diffuse.x() / PI,
+            "Phong darker than its diffuse part"
+        );
     }
 }
@@ -7,6 +7,7 @@ use crate::vector3d::{Point3D, Vector3D};
 pub struct Ray {
     origin: Point3D,
     direction: Vector3D,
+    wavelength: Option<f64>,
 }
 
 impl Ray {
@@ -18,11 +19,22 @@ impl Ray {
     /// * `direction` - The direction vector of the ray.
     pub fn create(origin: Point3D, direction: Vector3D) -> Self {
         Ray {
-            origin: origin,
-            direction: direction,
+            origin,
+            direction,
+            wavelength: None,
         }
     }
 
+    /// Tags the ray with the wavelength of light it traces.
+    ///
+    /// # Arguments
+    ///
+    /// * `wavelength` - The wavelength in nanometers.
+    pub const fn with_wavelength(mut self, wavelength: f64) -> Self {
+        self.wavelength = Some(wavelength);
+        self
+    }
+
     /// Gets the origin point of the ray.
     pub const fn origin(self) -> Point3D {
         self.origin
@@ -33,6 +45,11 @@ impl Ray {
         self.direction
     }
 
+    /// Gets the wavelength traced by the ray in nanometers, `None` when tracing RGB.
+    pub const fn wavelength(self) -> Option<f64> {
+        self.wavelength
+    }
+
     /// Performs linear interpolation of the ray at a given time `t`.
     ///
     /// # Arguments
@@ -61,14 +78,17 @@ mod tests {
         let r_zero: Ray = Ray {
             origin: p_orig,
             direction: v_zero,
+            wavelength: None,
         };
         let r_one: Ray = Ray {
             origin: p_orig,
             direction: v_one,
+            wavelength: None,
         };
         let r_oone: Ray = Ray {
             origin: v_one,
             direction: v_one,
+            wavelength: None,
         };
 
         assert_eq!(
@@ -119,4 +139,13 @@ mod tests {
             "Ray (<1,1,1>, <1,1,1>) at t=-1.0 not at <2,2,2>"
         );
     }
+
+    #[test]
+    fn wavelength() {
+        let r = Ray::create(Point3D::new(), Vector3D::with_values(0.0, 0.0, -1.0));
+
+        assert_eq!(r.wavelength(), None, "RGB ray tracing a wavelength");
+        assert_eq!(r.with_wavelength(550.0).wavelength(), Some(550.0));
+        assert_eq!(r.with_wavelength(550.0).direction(), r.direction());
+    }
 }
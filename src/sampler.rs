@@ -0,0 +1,108 @@
+//! Module for the random numbers driving the raytracer.
+//!
+//! Every random decision of the raytracer draws its numbers from `random_f64`.
+//! By default these come from the thread's random number generator, but
+//! `with_sampler` lets integrators like Metropolis light transport provide
+//! and control the numbers themselves.
+
+use rand::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub trait Sampler {
+    /// Gets the next random number of the stream, within [0, 1).
+    fn next_f64(&mut self) -> f64;
+}
+
+thread_local! {
+    static SAMPLER: RefCell<Option<Rc<RefCell<dyn Sampler>>>> = const { RefCell::new(None) };
+}
+
+/// Returns a random number within [0, 1), drawn from the current sampler of the thread.
+pub fn random_f64() -> f64 {
+    SAMPLER.with(|current| match &*current.borrow() {
+        Some(sampler) => sampler.borrow_mut().next_f64(),
+        None => thread_rng().gen::<f64>(),
+    })
+}
+
+/// Returns a random number within [`min`, `max`), drawn from the current sampler of the thread.
+pub fn random_range(min: f64, max: f64) -> f64 {
+    min + (max - min) * random_f64()
+}
+
+/// Runs `f` with all random numbers of the thread drawn from `sampler`.
+///
+/// # Arguments
+///
+/// * `sampler` - The sampler providing the random numbers.
+/// * `f`       - The function to run.
+///
+/// # Returns
+///
+/// Returns the result of `f`.
+pub fn with_sampler<R>(sampler: Rc<RefCell<dyn Sampler>>, f: impl FnOnce() -> R) -> R {
+    /// Puts the previous sampler back once `f` returns or panics.
+    struct Restore(Option<Rc<RefCell<dyn Sampler>>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SAMPLER.with(|current| current.replace(previous));
+        }
+    }
+
+    let _restore = Restore(SAMPLER.with(|current| current.replace(Some(sampler))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::*;
+
+    /// Counts up in steps of 0.25.
+    struct Counter(f64);
+
+    impl Sampler for Counter {
+        fn next_f64(&mut self) -> f64 {
+            self.0 = (self.0 + 0.25) % 1.0;
+            self.0
+        }
+    }
+
+    #[test]
+    fn sampler_override() {
+        let counter = Rc::new(RefCell::new(Counter(0.0)));
+
+        let drawn = with_sampler(counter.clone(), || {
+            [random_f64(), random_f64(), random_range(2.0, 6.0)]
+        });
+
+        assert_eq!(
+            drawn,
+            [0.25, 0.5, 5.0],
+            "Numbers not drawn from the sampler"
+        );
+        assert_eq!(counter.borrow().0, 0.75, "Sampler not advanced");
+
+        let r = random_f64();
+        assert!((0.0..1.0).contains(&r), "{} not within [0, 1)", r);
+        assert_eq!(counter.borrow().0, 0.75, "Sampler still in use");
+    }
+
+    #[test]
+    fn sampler_restored_on_panic() {
+        let counter = Rc::new(RefCell::new(Counter(0.0)));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_sampler(counter.clone(), || {
+                random_f64();
+                panic!("Path failed");
+            })
+        }));
+        assert!(result.is_err());
+
+        random_f64();
+        assert_eq!(counter.borrow().0, 0.25, "Sampler still in use");
+    }
+}
@@ -0,0 +1,683 @@
+//! Module for handling spectra in the context of a raytracer.
+//!
+//! Spectral rendering traces light at a few wavelengths per path instead of
+//! the three RGB components. RGB colors of the scene are upsampled to smooth
+//! spectra, and the traced radiance gets weighted by the CIE 1931 color
+//! matching functions to CIE XYZ before turning it into RGB for output.
+//!
+//! The equal-energy white point is used throughout: a constant spectrum is
+//! white, so emitters and albedos keep their color when upsampled.
+
+use crate::color::Color;
+use crate::vector3d::Vector3D;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign};
+#[cfg(test)]
+use std::sync::OnceLock;
+
+mod table;
+
+/// The shortest wavelength traced, in nanometers.
+pub const LAMBDA_MIN: f64 = 360.0;
+/// The longest wavelength traced, in nanometers.
+pub const LAMBDA_MAX: f64 = 830.0;
+/// How many wavelengths are traced along one path.
+pub const SPECTRUM_SAMPLES: usize = 4;
+
+/// The integral of the Y color matching function over the traced wavelengths.
+const CIE_Y_INTEGRAL: f64 = 106.922;
+
+/// Evaluates the CIE 1931 color matching functions at a wavelength.
+///
+/// Uses the multi-lobe fit by Wyman, Sloan and Shirley, "Simple Analytic
+/// Approximations to the CIE XYZ Color Matching Functions" (2013).
+///
+/// # Arguments
+///
+/// * `lambda` - The wavelength in nanometers.
+///
+/// # Returns
+///
+/// Returns the x̄, ȳ and z̄ values as a 3D vector.
+pub fn cie_xyz(lambda: f64) -> Vector3D {
+    let lobe = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu {
+            sigma_below
+        } else {
+            sigma_above
+        };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+
+    Vector3D::with_values(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
+
+/// Converts CIE XYZ into linear sRGB.
+///
+/// The XYZ values get scaled from the equal-energy white to the D65 white of
+/// sRGB first, so XYZ (1, 1, 1) turns into white.
+pub fn xyz_to_rgb(xyz: Vector3D) -> Color {
+    let x = 0.95047 * xyz.x();
+    let y = xyz.y();
+    let z = 1.08883 * xyz.z();
+
+    Color::with_values(
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    )
+}
+
+// --- SAMPLED SPECTRUM --------------------------------------------------------
+
+/// Represents the values of a spectrum at the sampled wavelengths of a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledSpectrum([f64; SPECTRUM_SAMPLES]);
+
+impl SampledSpectrum {
+    /// Creates a new spectrum with the same value at every wavelength.
+    pub const fn new(value: f64) -> Self {
+        SampledSpectrum([value; SPECTRUM_SAMPLES])
+    }
+
+    /// Creates a new spectrum from its values at the sampled wavelengths.
+    pub const fn with_values(values: [f64; SPECTRUM_SAMPLES]) -> Self {
+        SampledSpectrum(values)
+    }
+
+    /// Gets the values at the sampled wavelengths.
+    pub fn values(&self) -> [f64; SPECTRUM_SAMPLES] {
+        self.0
+    }
+
+    /// Checks whether the spectrum is zero at every wavelength.
+    pub fn is_black(&self) -> bool {
+        self.0.iter().all(|&v| v == 0.0)
+    }
+}
+
+impl Default for SampledSpectrum {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, other: Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(mut self, other: Self) -> Self {
+        self *= other;
+        self
+    }
+}
+
+impl MulAssign for SampledSpectrum {
+    fn mul_assign(&mut self, other: Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a *= b;
+        }
+    }
+}
+
+impl Mul<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(self, t: f64) -> Self {
+        SampledSpectrum(self.0.map(|v| v * t))
+    }
+}
+
+impl Div<f64> for SampledSpectrum {
+    type Output = Self;
+
+    fn div(self, t: f64) -> Self {
+        self * (1.0 / t)
+    }
+}
+
+// --- SAMPLED WAVELENGTHS -----------------------------------------------------
+
+/// Represents the wavelengths traced along one path.
+///
+/// The first one is the hero wavelength, the others are spread evenly over
+/// the traced range relative to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+    lambda: [f64; SPECTRUM_SAMPLES],
+    pdf: [f64; SPECTRUM_SAMPLES],
+}
+
+impl SampledWavelengths {
+    /// Samples the wavelengths of a path uniformly with hero wavelength sampling.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` - A random number within [0, 1) selecting the hero wavelength.
+    pub fn sample_uniform(u: f64) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = (u + i as f64 / SPECTRUM_SAMPLES as f64).fract();
+            *l = LAMBDA_MIN + offset * range;
+        }
+
+        SampledWavelengths {
+            lambda,
+            pdf: [1.0 / range; SPECTRUM_SAMPLES],
+        }
+    }
+
+    /// Gets the sampled wavelengths in nanometers.
+    pub fn lambda(&self) -> [f64; SPECTRUM_SAMPLES] {
+        self.lambda
+    }
+
+    /// Gets the hero wavelength in nanometers.
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Gets the probability densities of the sampled wavelengths.
+    pub fn pdf(&self) -> [f64; SPECTRUM_SAMPLES] {
+        self.pdf
+    }
+
+    /// Stops tracing every wavelength but the hero, for paths following the
+    /// direction of the hero through a dispersive surface.
+    pub fn terminate_secondary(&mut self) {
+        if self.is_secondary_terminated() {
+            return;
+        }
+
+        self.pdf[0] /= SPECTRUM_SAMPLES as f64;
+        for pdf in self.pdf.iter_mut().skip(1) {
+            *pdf = 0.0;
+        }
+    }
+
+    /// Checks whether only the hero wavelength is traced any more.
+    pub fn is_secondary_terminated(&self) -> bool {
+        self.pdf.iter().skip(1).all(|&pdf| pdf == 0.0)
+    }
+
+    /// Turns radiance traced at the sampled wavelengths into an estimate of CIE XYZ.
+    pub fn to_xyz(&self, radiance: &SampledSpectrum) -> Vector3D {
+        let mut xyz = Vector3D::new();
+
+        for i in 0..SPECTRUM_SAMPLES {
+            if self.pdf[i] > 0.0 {
+                xyz += cie_xyz(self.lambda[i]) * (radiance.0[i] / self.pdf[i]);
+            }
+        }
+
+        xyz / (SPECTRUM_SAMPLES as f64 * CIE_Y_INTEGRAL)
+    }
+}
+
+// --- RGB SPECTRUM ------------------------------------------------------------
+
+/// How many nodes the coefficient table has along each of its axes.
+const TABLE_RESOLUTION: usize = 28;
+
+/// Gets the wavelengths integrating the colors of spectra and the color
+/// matching functions in RGB at them, balanced so the constant spectrum of 1 is
+/// exactly white.
+#[cfg(test)]
+fn integration_weights() -> &'static [(f64, Color)] {
+    const STEPS: usize = 94;
+    static WEIGHTS: OnceLock<Vec<(f64, Color)>> = OnceLock::new();
+
+    WEIGHTS.get_or_init(|| {
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / STEPS as f64;
+        let weights: Vec<(f64, Color)> = (0..STEPS)
+            .map(|i| {
+                let lambda = LAMBDA_MIN + (i as f64 + 0.5) * step;
+                (lambda, xyz_to_rgb(cie_xyz(lambda)))
+            })
+            .collect();
+        let white = weights.iter().fold(Color::new(), |sum, &(_, w)| sum + w);
+
+        weights
+            .into_iter()
+            .map(|(lambda, w)| {
+                let balanced =
+                    Color::with_values(w.x() / white.x(), w.y() / white.y(), w.z() / white.z());
+                (lambda, balanced)
+            })
+            .collect()
+    })
+}
+
+/// Represents the coefficients of the sigmoid polynomials reproducing colors
+/// within [0, 1], like the RGBToSpectrumTable of pbrt.
+///
+/// Colors are looked up by their largest component, the ratios of the other two
+/// to it and its value, interpolating trilinearly between the nodes. The nodes of
+/// the largest component are denser towards black and white, where the
+/// coefficients change the fastest. The coefficients are fitted offline and
+/// embedded in `table`.
+struct CoefficientTable;
+
+impl CoefficientTable {
+    /// Gets the value of the largest component at a node along its axis.
+    fn z_node(k: usize) -> f64 {
+        let x = k as f64 / (TABLE_RESOLUTION - 1) as f64;
+        x * x * (3.0 - 2.0 * x)
+    }
+
+    /// Gets the color at a node of the table.
+    #[cfg(test)]
+    fn color(largest: usize, k: usize, j: usize, i: usize) -> Color {
+        let node = |i: usize| i as f64 / (TABLE_RESOLUTION - 1) as f64;
+        let z = Self::z_node(k);
+        let mut rgb = [0.0; 3];
+        rgb[largest] = z;
+        rgb[(largest + 1) % 3] = node(i) * z;
+        rgb[(largest + 2) % 3] = node(j) * z;
+        Color::with_values(rgb[0], rgb[1], rgb[2])
+    }
+
+    /// Fits the coefficients at every node of the table, which takes seconds.
+    #[cfg(test)]
+    fn fit() -> Vec<Vector3D> {
+        let res = TABLE_RESOLUTION;
+        let mut coefficients = vec![Vector3D::new(); 3 * res * res * res];
+        let start = res / 5;
+        for largest in 0..3 {
+            for j in 0..res {
+                for i in 0..res {
+                    let fit = |k: usize, guess: Option<Vector3D>| {
+                        let target = Self::color(largest, k, j, i);
+
+                        // Start over if the last coefficients lead the fit astray
+                        let error = |c: Vector3D| (RgbSpectrum::rgb(c) - target).length_squared();
+                        let c = RgbSpectrum::fit(target, guess);
+                        if guess.is_some() && error(c) > 1e-8 {
+                            let restart = RgbSpectrum::fit(target, None);
+                            if error(restart) < error(c) {
+                                return restart;
+                            }
+                        }
+                        c
+                    };
+
+                    // Fit outwards from a medium brightness, every fit starting from the last
+                    let mut guess = None;
+                    for k in start..res {
+                        guess = Some(fit(k, guess));
+                        coefficients[Self::index(largest, k, j, i)] = guess.unwrap();
+                    }
+                    guess = Some(coefficients[Self::index(largest, start, j, i)]);
+                    for k in (0..start).rev() {
+                        guess = Some(fit(k, guess));
+                        coefficients[Self::index(largest, k, j, i)] = guess.unwrap();
+                    }
+                }
+            }
+        }
+
+        coefficients
+    }
+
+    /// Gets the position of a node in the table.
+    fn index(largest: usize, k: usize, j: usize, i: usize) -> usize {
+        ((largest * TABLE_RESOLUTION + k) * TABLE_RESOLUTION + j) * TABLE_RESOLUTION + i
+    }
+
+    /// Gets the coefficients at a node of the table.
+    fn coefficients(largest: usize, k: usize, j: usize, i: usize) -> Vector3D {
+        let [a, b, c] = table::COEFFICIENTS[Self::index(largest, k, j, i)];
+        Vector3D::with_values(f64::from(a), f64::from(b), f64::from(c))
+    }
+
+    /// Interpolates the coefficients of a color within [0, 1].
+    fn lookup(rgb: Color) -> Vector3D {
+        let res = TABLE_RESOLUTION;
+        let rgb = [rgb.x(), rgb.y(), rgb.z()].map(|c| c.clamp(0.0, 1.0));
+        let largest = (0..3).fold(0, |max, c| if rgb[c] > rgb[max] { c } else { max });
+        let z = rgb[largest];
+        let ratio = |c: f64| {
+            if z > 0.0 {
+                c / z * (res - 1) as f64
+            } else {
+                0.0
+            }
+        };
+        let (x, y) = (ratio(rgb[(largest + 1) % 3]), ratio(rgb[(largest + 2) % 3]));
+
+        let z_nodes: [f64; TABLE_RESOLUTION] = std::array::from_fn(Self::z_node);
+        let i = (x as usize).min(res - 2);
+        let j = (y as usize).min(res - 2);
+        let k = z_nodes.partition_point(|&n| n <= z).clamp(1, res - 1) - 1;
+        let (dx, dy) = (x - i as f64, y - j as f64);
+        let dz = (z - z_nodes[k]) / (z_nodes[k + 1] - z_nodes[k]);
+
+        let lerp = |a: Vector3D, b: Vector3D, t: f64| a + t * (b - a);
+        let at = |k: usize, j: usize, i: usize| Self::coefficients(largest, k, j, i);
+        let face = |k: usize| {
+            lerp(
+                lerp(at(k, j, i), at(k, j, i + 1), dx),
+                lerp(at(k, j + 1, i), at(k, j + 1, i + 1), dx),
+                dy,
+            )
+        };
+        lerp(face(k), face(k + 1), dz)
+    }
+}
+
+/// Represents a smooth spectrum upsampled from an RGB color.
+///
+/// Follows Jakob and Hanika, "A Low-Dimensional Function Space for Efficient
+/// Spectral Upsampling" (2019): the spectrum is a sigmoid of a quadratic
+/// polynomial, whose coefficients reproduce the color. They are fitted offline
+/// for a table of colors and interpolated from it. Colors within [0, 1] turn into
+/// spectra within [0, 1], so albedos stay energy conserving. Brighter colors
+/// get scaled down for the lookup and up afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RgbSpectrum {
+    coefficients: Vector3D,
+    scale: f64,
+}
+
+impl RgbSpectrum {
+    /// Creates a new spectrum upsampled from a color, negative components count as 0.
+    pub fn new(rgb: Color) -> Self {
+        let rgb = Color::with_values(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0));
+        let max = rgb.x().max(rgb.y()).max(rgb.z());
+        let scale = if max > 1.0 { 2.0 * max } else { 1.0 };
+
+        let coefficients = CoefficientTable::lookup(rgb / scale);
+
+        RgbSpectrum {
+            coefficients,
+            scale,
+        }
+    }
+
+    /// Evaluates the spectrum at a wavelength in nanometers.
+    pub fn eval(&self, lambda: f64) -> f64 {
+        self.scale * Self::sigmoid_polynomial(self.coefficients, lambda)
+    }
+
+    /// Evaluates the spectrum at the sampled wavelengths of a path.
+    pub fn sample(&self, lambda: &SampledWavelengths) -> SampledSpectrum {
+        SampledSpectrum(lambda.lambda.map(|l| self.eval(l)))
+    }
+
+    fn sigmoid_polynomial(c: Vector3D, lambda: f64) -> f64 {
+        let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+        let x = (c.x() * t + c.y()) * t + c.z();
+        0.5 + x / (2.0 * (1.0 + x * x).sqrt())
+    }
+
+    /// Integrates the color of the sigmoid polynomial with coefficients `c`.
+    #[cfg(test)]
+    fn rgb(c: Vector3D) -> Color {
+        integration_weights()
+            .iter()
+            .fold(Color::new(), |rgb, &(lambda, weight)| {
+                rgb + weight * Self::sigmoid_polynomial(c, lambda)
+            })
+    }
+
+    /// Integrates the derivatives of the color of the sigmoid polynomial with
+    /// coefficients `c`, one per coefficient.
+    #[cfg(test)]
+    fn jacobian(c: Vector3D) -> [Color; 3] {
+        let mut columns = [Color::new(); 3];
+
+        for &(lambda, weight) in integration_weights() {
+            let t = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+            let x = (c.x() * t + c.y()) * t + c.z();
+            let slope = weight * (0.5 / (1.0 + x * x).powf(1.5));
+            columns[0] += slope * (t * t);
+            columns[1] += slope * t;
+            columns[2] += slope;
+        }
+
+        columns
+    }
+
+    /// Fits the coefficients reproducing `target` with the Levenberg-Marquardt method.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The color within [0, 1] to reproduce.
+    /// * `guess`  - The coefficients to start from, those of the constant spectrum
+    ///   of the average brightness if there are none.
+    #[cfg(test)]
+    fn fit(target: Color, guess: Option<Vector3D>) -> Vector3D {
+        let clamp = |c: f64| c.clamp(1e-6, 1.0 - 1e-6);
+        let target = Color::with_values(clamp(target.x()), clamp(target.y()), clamp(target.z()));
+
+        let mut c = guess.unwrap_or_else(|| {
+            let a = 2.0 * (target.x() + target.y() + target.z()) / 3.0 - 1.0;
+            Vector3D::with_values(0.0, 0.0, a / (1.0 - a * a).sqrt())
+        });
+        let mut residual = Self::rgb(c) - target;
+        let mut damping = 1e-3;
+
+        for _ in 0..100 {
+            if residual.length_squared() < 1e-14 {
+                break;
+            }
+
+            let [jx, jy, jz] = Self::jacobian(c);
+
+            // Solve (JᵀJ + λI)·δ = -Jᵀr by Cramer's rule, the matrix is symmetric
+            let a0 = Vector3D::with_values(jx.dot(jx) + damping, jx.dot(jy), jx.dot(jz));
+            let a1 = Vector3D::with_values(jy.dot(jx), jy.dot(jy) + damping, jy.dot(jz));
+            let a2 = Vector3D::with_values(jz.dot(jx), jz.dot(jy), jz.dot(jz) + damping);
+            let g = -Vector3D::with_values(jx.dot(residual), jy.dot(residual), jz.dot(residual));
+            let det = a0.dot(a1.cross(a2));
+            let delta = Vector3D::with_values(
+                g.dot(a1.cross(a2)) / det,
+                a0.dot(g.cross(a2)) / det,
+                a0.dot(a1.cross(g)) / det,
+            );
+
+            let candidate = c + delta;
+            let candidate_residual = Self::rgb(candidate) - target;
+            if candidate_residual.length_squared() < residual.length_squared() {
+                c = candidate;
+                residual = candidate_residual;
+                damping *= 0.3;
+            } else {
+                damping *= 10.0;
+                if damping > 1e10 {
+                    break;
+                }
+            }
+        }
+
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::spectrum::*;
+    use std::fmt::Write;
+
+    fn assert_close(a: Color, b: Color, tolerance: f64) {
+        assert!((a - b).length() < tolerance, "{} not close to {}", a, b);
+    }
+
+    #[test]
+    fn flat_spectrum_white() {
+        let mut xyz = Vector3D::new();
+        for i in 0..1000 {
+            let lambda = SampledWavelengths::sample_uniform(f64::from(i) / 1000.0);
+            xyz += lambda.to_xyz(&SampledSpectrum::new(1.0));
+        }
+
+        assert_close(
+            xyz_to_rgb(xyz / 1000.0),
+            Color::with_values(1.0, 1.0, 1.0),
+            0.01,
+        );
+    }
+
+    #[test]
+    fn hero_wavelengths() {
+        let lambda = SampledWavelengths::sample_uniform(0.9);
+
+        assert_eq!(lambda.hero(), LAMBDA_MIN + 0.9 * (LAMBDA_MAX - LAMBDA_MIN));
+        for l in lambda.lambda() {
+            assert!((LAMBDA_MIN..LAMBDA_MAX).contains(&l), "{} not traced", l);
+        }
+
+        let mut sorted = lambda.lambda();
+        sorted.sort_by(f64::total_cmp);
+        for pair in sorted.windows(2) {
+            assert!(
+                (pair[1] - pair[0] - (LAMBDA_MAX - LAMBDA_MIN) / 4.0).abs() < 1e-9,
+                "Wavelengths not evenly spread"
+            );
+        }
+    }
+
+    #[test]
+    fn terminate_secondary() {
+        let mut lambda = SampledWavelengths::sample_uniform(0.3);
+        let radiance = SampledSpectrum::with_values([1.0, 0.0, 0.0, 0.0]);
+        let all = lambda.to_xyz(&radiance);
+
+        assert!(!lambda.is_secondary_terminated());
+        lambda.terminate_secondary();
+        lambda.terminate_secondary();
+        assert!(lambda.is_secondary_terminated(), "Secondaries still traced");
+        assert_eq!(lambda.pdf()[1..], [0.0; SPECTRUM_SAMPLES - 1]);
+
+        let hero = lambda.to_xyz(&SampledSpectrum::new(1.0));
+        assert!(
+            (hero - all * SPECTRUM_SAMPLES as f64).length() < 1e-12,
+            "Hero not standing in for all wavelengths"
+        );
+    }
+
+    #[test]
+    fn rgb_spectrum_round_trip() {
+        for rgb in [
+            Color::with_values(0.5, 0.5, 0.5),
+            Color::with_values(0.8, 0.3, 0.3),
+            Color::with_values(0.1, 0.2, 0.5),
+            Color::with_values(0.7, 0.6, 0.5),
+            Color::with_values(4.0, 2.0, 1.0),
+        ] {
+            let spectrum = RgbSpectrum::new(rgb);
+            assert_close(
+                RgbSpectrum::rgb(spectrum.coefficients) * spectrum.scale,
+                rgb,
+                0.01,
+            );
+        }
+    }
+
+    #[test]
+    fn rgb_spectrum_albedo_bounds() {
+        let spectrum = RgbSpectrum::new(Color::with_values(1.0, 0.0, 0.2));
+
+        for i in 0..=100 {
+            let v = spectrum.eval(LAMBDA_MIN + f64::from(i) * 4.7);
+            assert!(
+                (0.0..=1.0).contains(&v),
+                "Albedo {} not conserving energy",
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn rgb_spectrum_table() {
+        // Colors between the nodes of the table, all over [0, 1]³
+        for r in 0..10 {
+            for g in 0..10 {
+                for b in 0..10 {
+                    let rgb = Color::with_values(
+                        f64::from(r) * 0.107 + 0.01,
+                        f64::from(g) * 0.107 + 0.01,
+                        f64::from(b) * 0.107 + 0.01,
+                    );
+                    let spectrum = RgbSpectrum::new(rgb);
+
+                    assert_close(RgbSpectrum::rgb(spectrum.coefficients), rgb, 0.02);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rgb_spectrum_table_nodes() {
+        // The embedded coefficients reproduce the colors at the nodes like fitting them
+        for (largest, k, j, i) in [
+            (0, 14, 3, 20),
+            (1, 22, 13, 8),
+            (2, 9, 25, 2),
+            (0, 5, 10, 10),
+        ] {
+            let target = CoefficientTable::color(largest, k, j, i);
+            let fitted = RgbSpectrum::rgb(RgbSpectrum::fit(target, None));
+            let embedded = RgbSpectrum::rgb(CoefficientTable::coefficients(largest, k, j, i));
+
+            assert_close(embedded, fitted, 1e-4);
+            assert_close(embedded, target, 1e-3);
+        }
+    }
+
+    #[test]
+    #[ignore = "fits the coefficient table for seconds and rewrites src/spectrum/table.rs"]
+    fn generate_coefficient_table() {
+        let mut source = String::from(
+            "//! The coefficients of the sigmoid polynomials reproducing the colors at the\n\
+             //! nodes of the RGB spectrum table, generated by `generate_coefficient_table`.\n\
+             //!\n\
+             //! Regenerate with `cargo test --release -- --ignored generate_coefficient_table`.\n\
+             \n\
+             use crate::spectrum::TABLE_RESOLUTION;\n\
+             \n\
+             /// The number of nodes of the table.\n\
+             const NODES: usize = 3 * TABLE_RESOLUTION * TABLE_RESOLUTION * TABLE_RESOLUTION;\n\
+             \n\
+             /// The coefficients at every node, ordered by `CoefficientTable::index`.\n\
+             #[rustfmt::skip]\n\
+             #[allow(clippy::approx_constant)] // Fitted values may come close to constants like e\n\
+             pub(super) static COEFFICIENTS: [[f32; 3]; NODES] = [\n",
+        );
+        for row in CoefficientTable::fit().chunks(4) {
+            source.push_str("   ");
+            for c in row {
+                let [a, b, c] = [c.x(), c.y(), c.z()].map(|v| v as f32);
+                write!(source, " [{:?}, {:?}, {:?}],", a, b, c).expect("Failed to format");
+            }
+            source.push('\n');
+        }
+        source.push_str("];\n");
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/spectrum/table.rs");
+        std::fs::write(path, source).expect("Failed to write the coefficient table");
+    }
+}
@@ -0,0 +1,416 @@
+//! Module for representing textures, colors varying over surfaces, in the context of a raytracer.
+
+use crate::color::Color;
+use crate::vector3d::Point3D;
+
+pub trait Texture {
+    /// Looks up the color of the texture at a point of a surface.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` - The first surface coordinate of the point.
+    /// * `v` - The second surface coordinate of the point.
+    /// * `p` - The point itself.
+    fn value(&self, u: f64, v: f64, p: Point3D) -> Color;
+}
+
+/// A single color is the texture being that color everywhere.
+impl Texture for Color {
+    fn value(&self, _u: f64, _v: f64, _p: Point3D) -> Color {
+        *self
+    }
+}
+
+// --- IMAGE TEXTURE -----------------------------------------------------------
+
+pub mod image {
+    //! Module for textures given by images.
+
+    use crate::color::Color;
+    use crate::environment::map::read_hdr;
+    use crate::texture::Texture;
+    use crate::vector3d::Point3D;
+    use std::fs::File;
+    use std::io::{self, BufRead, BufReader, Cursor, Read};
+    use std::path::Path;
+    use zune_jpeg::zune_core::colorspace::ColorSpace;
+    use zune_jpeg::zune_core::options::DecoderOptions;
+    use zune_jpeg::JpegDecoder;
+
+    /// Represents a texture given by an image, repeated over the surface.
+    ///
+    /// The bottom left of the image is at the surface coordinates (0, 0),
+    /// its top right at (1, 1).
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ImageTexture {
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+    }
+
+    impl ImageTexture {
+        /// Creates a new image texture.
+        ///
+        /// # Arguments
+        ///
+        /// * `width`  - The width of the image in pixels.
+        /// * `height` - The height of the image in pixels.
+        /// * `pixels` - The linear color of every pixel, row by row from the top left.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the image is empty or `pixels` doesn't match its size.
+        pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+            assert!(width > 0 && height > 0, "Empty image texture");
+            assert_eq!(pixels.len(), width * height, "Pixels not matching the size");
+
+            ImageTexture {
+                width,
+                height,
+                pixels,
+            }
+        }
+
+        /// Loads an image texture from a PNG (.png), JPEG (.jpg, .jpeg), Radiance HDR (.hdr)
+        /// or, by any other extension, Netpbm image.
+        ///
+        /// # Arguments
+        ///
+        /// * `path` - The path of the image.
+        pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+            let extension = path
+                .as_ref()
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase());
+            let mut reader = BufReader::new(File::open(path)?);
+            let (width, height, pixels) = match extension.as_deref() {
+                Some("hdr") => read_hdr(&mut reader)?,
+                Some("png") => read_png(&mut reader)?,
+                Some("jpg") | Some("jpeg") => read_jpeg(&mut reader)?,
+                _ => read_ppm(&mut reader)?,
+            };
+
+            Ok(Self::new(width, height, pixels))
+        }
+
+        /// Gets the width of the image in pixels.
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        /// Gets the height of the image in pixels.
+        pub fn height(&self) -> usize {
+            self.height
+        }
+
+        /// Gets the color of the pixel in column `x` and row `y` from the top left.
+        pub fn pixel(&self, x: usize, y: usize) -> Color {
+            self.pixels[y * self.width + x]
+        }
+    }
+
+    impl Texture for ImageTexture {
+        fn value(&self, u: f64, v: f64, _p: Point3D) -> Color {
+            let u = u.rem_euclid(1.0);
+            let v = v.rem_euclid(1.0);
+
+            let x = ((u * self.width as f64) as usize).min(self.width - 1);
+            let y = (((1.0 - v) * self.height as f64) as usize).min(self.height - 1);
+            self.pixel(x, y)
+        }
+    }
+
+    /// Reads a Netpbm color image, plain (P3) or raw (P6).
+    ///
+    /// The values are linearized with the inverse of the gamma images get written with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the width, height and linear colors of the image, row by row from the top.
+    pub fn read_ppm<R: BufRead>(reader: &mut R) -> io::Result<(usize, usize, Vec<Color>)> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // Header tokens up to the maximum value, skipping comments
+        let mut tokens: Vec<String> = Vec::new();
+        let mut line = String::new();
+        while tokens.len() < 4 {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("Incomplete Netpbm header"));
+            }
+            let content = line.split('#').next().unwrap_or("");
+            tokens.extend(content.split_whitespace().map(str::to_string));
+        }
+
+        let raw = match tokens[0].as_str() {
+            "P3" => false,
+            "P6" => true,
+            _ => return Err(invalid("Not a PPM image")),
+        };
+        let number = |token: &str| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid("Invalid number in Netpbm header"))
+        };
+        let width = number(&tokens[1])?;
+        let height = number(&tokens[2])?;
+        let max = number(&tokens[3])?;
+        if width == 0 || height == 0 || max == 0 || max > 65535 {
+            return Err(invalid("Invalid image size or maximum value"));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| invalid("Image too large"))?;
+        let values: Vec<usize> = if raw {
+            if tokens.len() > 4 {
+                return Err(invalid("Missing whitespace before the raster"));
+            }
+            let bytes = if max < 256 { 1 } else { 2 };
+            let length = count
+                .checked_mul(bytes)
+                .ok_or_else(|| invalid("Image too large"))?;
+
+            // Grow the raster as it arrives, so crafted sizes can't allocate more than the file holds
+            let mut raster = Vec::new();
+            reader.take(length as u64).read_to_end(&mut raster)?;
+            if raster.len() != length {
+                return Err(invalid("Missing pixel values"));
+            }
+            raster
+                .chunks(bytes)
+                .map(|c| c.iter().fold(0, |value, &b| value << 8 | usize::from(b)))
+                .collect()
+        } else {
+            let mut rest = String::new();
+            reader.read_to_string(&mut rest)?;
+            let mut values = tokens[4..]
+                .iter()
+                .map(|t| number(t))
+                .collect::<io::Result<Vec<usize>>>()?;
+            for content in rest.lines().map(|l| l.split('#').next().unwrap_or("")) {
+                for token in content.split_whitespace() {
+                    values.push(number(token)?);
+                }
+            }
+            if values.len() < count {
+                return Err(invalid("Missing pixel values"));
+            }
+            values.truncate(count);
+            values
+        };
+
+        let linear = |value: usize| {
+            let gamma = value.min(max) as f64 / max as f64;
+            gamma * gamma
+        };
+        let pixels = values
+            .chunks(3)
+            .map(|c| Color::with_values(linear(c[0]), linear(c[1]), linear(c[2])))
+            .collect();
+
+        Ok((width, height, pixels))
+    }
+
+    /// Linearizes an 8-bit value with the inverse of the gamma images get written with.
+    fn linear(value: u8) -> f64 {
+        let gamma = f64::from(value) / 255.0;
+        gamma * gamma
+    }
+
+    /// Reads a PNG image of any bit depth and color type, ignoring its transparency.
+    ///
+    /// The values are linearized with the inverse of the gamma images get written with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the width, height and linear colors of the image, row by row from the top.
+    pub fn read_png<R: Read>(reader: &mut R) -> io::Result<(usize, usize, Vec<Color>)> {
+        let invalid = |error: png::DecodingError| {
+            io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+        };
+
+        // Palettes and low bit depths get expanded to 8 bits, 16 bits reduced to them
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(invalid)?;
+
+        let channels = info.color_type.samples();
+        let pixels = buffer[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|c| match info.color_type {
+                png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+                    let gray = linear(c[0]);
+                    Color::with_values(gray, gray, gray)
+                }
+                _ => Color::with_values(linear(c[0]), linear(c[1]), linear(c[2])),
+            })
+            .collect();
+
+        Ok((info.width as usize, info.height as usize, pixels))
+    }
+
+    /// Reads a baseline or progressive JPEG image.
+    ///
+    /// The values are linearized with the inverse of the gamma images get written with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the width, height and linear colors of the image, row by row from the top.
+    pub fn read_jpeg<R: Read>(reader: &mut R) -> io::Result<(usize, usize, Vec<Color>)> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGB);
+        let mut decoder = JpegDecoder::new_with_options(Cursor::new(bytes), options);
+        let raster = decoder.decode().map_err(|e| invalid(format!("{:?}", e)))?;
+        let (width, height) = decoder
+            .dimensions()
+            .ok_or_else(|| invalid("JPEG without size".to_string()))?;
+
+        let pixels = raster
+            .chunks_exact(3)
+            .map(|c| Color::with_values(linear(c[0]), linear(c[1]), linear(c[2])))
+            .collect();
+
+        Ok((width, height, pixels))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::color::Color;
+        use crate::texture::image::{read_jpeg, read_png, read_ppm, ImageTexture};
+        use crate::texture::Texture;
+        use crate::vector3d::Point3D;
+
+        #[test]
+        fn image_texture_value() {
+            //  v
+            //  1 +---+---+
+            //    | R | G |
+            //  ½ +---+---+
+            //    | B | W |
+            //  0 +---+---+ u
+            //    0   ½   1
+            let red = Color::with_values(1.0, 0.0, 0.0);
+            let green = Color::with_values(0.0, 1.0, 0.0);
+            let blue = Color::with_values(0.0, 0.0, 1.0);
+            let white = Color::with_values(1.0, 1.0, 1.0);
+            let image = ImageTexture::new(2, 2, vec![red, green, blue, white]);
+            let p = Point3D::new();
+
+            assert_eq!(image.value(0.25, 0.75, p), red);
+            assert_eq!(image.value(0.75, 0.75, p), green);
+            assert_eq!(image.value(0.25, 0.25, p), blue);
+            assert_eq!(image.value(1.0, 1.0, p), blue, "Edge not wrapped around");
+            assert_eq!(image.value(-0.25, 1.75, p), green, "Not repeated");
+            assert_eq!(red.value(0.75, 0.25, p), red, "Color not uniform");
+        }
+
+        #[test]
+        fn read_ppm_plain() {
+            let text = "P3\n# A comment\n2 1\n255\n255 0 0  # red\n0 0 51\n";
+            let (width, height, pixels) = read_ppm(&mut text.as_bytes()).unwrap();
+
+            assert_eq!((width, height), (2, 1));
+            assert_eq!(pixels[0], Color::with_values(1.0, 0.0, 0.0));
+            assert!((pixels[1].z() - 0.04).abs() < 1e-12, "Not linearized");
+        }
+
+        #[test]
+        fn read_ppm_raw() {
+            let mut bytes = b"P6\n1 2\n65535\n".to_vec();
+            bytes.extend([0xff, 0xff, 0x80, 0x00, 0x00, 0x00]);
+            bytes.extend([0x00, 0x00, 0x00, 0x00, 0xff, 0xff]);
+            let (width, height, pixels) = read_ppm(&mut bytes.as_slice()).unwrap();
+
+            assert_eq!((width, height), (1, 2));
+            assert_eq!(pixels[0].x(), 1.0);
+            assert!((pixels[0].y() - 0.25).abs() < 1e-4, "Not 16 bit");
+            assert_eq!(pixels[1], Color::with_values(0.0, 0.0, 1.0));
+        }
+
+        #[test]
+        fn read_ppm_invalid() {
+            assert!(read_ppm(&mut "P5\n1 1\n255\n".as_bytes()).is_err());
+            assert!(read_ppm(&mut "P3\n2 2\n255\n0 0 0\n".as_bytes()).is_err());
+            assert!(read_ppm(&mut "P3\n2".as_bytes()).is_err());
+            assert!(read_ppm(&mut b"P6\n1 1\n255\n\x00".as_slice()).is_err());
+
+            // Crafted sizes fail instead of overflowing or allocating terabytes
+            assert!(read_ppm(&mut b"P6\n4611686018427387904 2 255\n\x00".as_slice()).is_err());
+            assert!(read_ppm(&mut b"P6\n1000000 1000000 65535\n\x00".as_slice()).is_err());
+        }
+
+        #[test]
+        fn read_png_rgb_and_gray() {
+            let encode = |color: png::ColorType, depth: png::BitDepth, data: &[u8]| {
+                let mut bytes = Vec::new();
+                let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+                encoder.set_color(color);
+                encoder.set_depth(depth);
+                let mut writer = encoder.write_header().unwrap();
+                writer.write_image_data(data).unwrap();
+                writer.finish().unwrap();
+                bytes
+            };
+
+            let rgba = encode(
+                png::ColorType::Rgba,
+                png::BitDepth::Eight,
+                &[255, 0, 0, 255, 0, 0, 51, 0],
+            );
+            let (width, height, pixels) = read_png(&mut rgba.as_slice()).unwrap();
+            assert_eq!((width, height), (2, 1));
+            assert_eq!(pixels[0], Color::with_values(1.0, 0.0, 0.0));
+            assert!((pixels[1].z() - 0.04).abs() < 1e-12, "Not linearized");
+
+            let gray = encode(
+                png::ColorType::Grayscale,
+                png::BitDepth::Sixteen,
+                &[0xff, 0xff, 0x00, 0x00],
+            );
+            let (_, _, pixels) = read_png(&mut gray.as_slice()).unwrap();
+            assert_eq!(
+                pixels,
+                vec![Color::with_values(1.0, 1.0, 1.0), Color::new()]
+            );
+
+            assert!(read_png(&mut b"\x89PNG\r\n\x1a\n".as_slice()).is_err());
+        }
+
+        #[test]
+        fn read_jpeg_gray() {
+            // An 8x8 gray baseline JPEG of the value 160, its only coefficient the DC
+            // of 8·(160 - 128) = 256, in category 9 under the Huffman code 0
+            let mut bytes = vec![0xff, 0xd8];
+            bytes.extend([0xff, 0xdb, 0, 67, 0x00]);
+            bytes.extend([1; 64]);
+            bytes.extend([0xff, 0xc0, 0, 11, 8, 0, 8, 0, 8, 1, 1, 0x11, 0]);
+            for (class, symbol) in [(0x00, 9), (0x10, 0)] {
+                bytes.extend([0xff, 0xc4, 0, 20, class, 1]);
+                bytes.extend([0; 15]);
+                bytes.push(symbol);
+            }
+            bytes.extend([0xff, 0xda, 0, 8, 1, 1, 0x00, 0, 63, 0]);
+            // DC code 0, the 9 bits of 256, the end of block code 0, padded with ones
+            bytes.extend([0b0100_0000, 0b0001_1111]);
+            bytes.extend([0xff, 0xd9]);
+
+            let (width, height, pixels) = read_jpeg(&mut bytes.as_slice()).unwrap();
+            assert_eq!((width, height), (8, 8));
+            let expected = (160.0f64 / 255.0).powi(2);
+            for pixel in pixels {
+                assert!((pixel.x() - expected).abs() < 0.01, "Pixel {}", pixel);
+                assert_eq!(pixel.x(), pixel.z(), "Gray not gray");
+            }
+
+            assert!(read_jpeg(&mut b"\xff\xd8\xff".as_slice()).is_err());
+        }
+    }
+}
@@ -1,7 +1,6 @@
 //! Module for representing 3D vectors in the context of a raytracer.
 
-use nalgebra::{Point3, Vector3};
-use rand::prelude::*;
+use crate::sampler::{random_f64, random_range};
 use std::fmt;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
 
@@ -15,7 +14,7 @@ pub struct Vector3D {
 
 impl Vector3D {
     /// Creates a new vector with components initialized to zero.
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Vector3D {
             x: 0.0,
             y: 0.0,
@@ -30,7 +29,7 @@ impl Vector3D {
     /// * `x` - The x-component of the vector.
     /// * `y` - The y-component of the vector.
     /// * `z` - The z-component of the vector.
-    pub fn with_values(x: f64, y: f64, z: f64) -> Self {
+    pub const fn with_values(x: f64, y: f64, z: f64) -> Self {
         Vector3D { x, y, z }
     }
 
@@ -51,6 +50,15 @@ impl Vector3D {
         self.z
     }
 
+    /// Gets the component of the vector along the axis 0 (x), 1 (y) or 2 (z).
+    pub const fn axis(self, axis: usize) -> f64 {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+
     // Length
 
     /// Calculates the length of the vector.
@@ -65,17 +73,15 @@ impl Vector3D {
 
     /// Create a random Vector
     pub fn random() -> Self {
-        let mut rng = thread_rng();
-        Self::with_values(rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>())
+        Self::with_values(random_f64(), random_f64(), random_f64())
     }
 
     /// Create a random Vector whereas each component is within `min` and `max`
     pub fn random_within(min: f64, max: f64) -> Self {
-        let mut rng = thread_rng();
         Self::with_values(
-            rng.gen_range(min..max),
-            rng.gen_range(min..max),
-            rng.gen_range(min..max),
+            random_range(min, max),
+            random_range(min, max),
+            random_range(min, max),
         )
     }
 
@@ -134,6 +140,85 @@ impl Vector3D {
             -on_unit_hemisphere
         }
     }
+
+    /// Generates a random unit vector on the hemisphere oriented by the given normal.
+    ///
+    /// Unlike `random_on_hemisphere` the resulting vectors are cosine distributed,
+    /// meaning directions close to the `normal` are more likely than grazing ones.
+    pub fn random_cosine_direction(normal: Vector3D) -> Vector3D {
+        let direction = normal + Self::random_unit_vector();
+
+        // Catch degenerate directions, where the random vector cancels out the normal
+        if direction.near_zero() {
+            normal
+        } else {
+            direction.unit_vector()
+        }
+    }
+
+    /// Builds two unit vectors perpendicular to the unit vector and each other.
+    pub fn orthonormal_basis(self) -> (Vector3D, Vector3D) {
+        let helper = if self.x.abs() > 0.9 {
+            Vector3D::with_values(0.0, 1.0, 0.0)
+        } else {
+            Vector3D::with_values(1.0, 0.0, 0.0)
+        };
+        let u = self.cross(helper).unit_vector();
+        (u, self.cross(u))
+    }
+
+    /// Generates a random unit vector uniformly distributed over the cone
+    /// around `axis` with the opening half-angle θ.
+    ///
+    /// # Arguments
+    ///
+    /// * `axis`          - The unit axis of the cone.
+    /// * `cos_theta_max` - The cosine of the half-angle θ.
+    pub fn random_in_cone(axis: Vector3D, cos_theta_max: f64) -> Vector3D {
+        let cos_theta = 1.0 - random_f64() * (1.0 - cos_theta_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * random_f64();
+
+        let (u, v) = axis.orthonormal_basis();
+        (u * phi.cos() + v * phi.sin()) * sin_theta + axis * cos_theta
+    }
+
+    /// Reflects the vector on a surface with the given normal.
+    ///
+    /// # Arguments
+    ///
+    /// * `normal` - The unit normal of the reflecting surface.
+    pub fn reflect(self, normal: Vector3D) -> Vector3D {
+        self - 2.0 * self.dot(normal) * normal
+    }
+
+    /// Refracts the vector through a surface with the given normal using Snell's law.
+    ///
+    /// # Arguments
+    ///
+    /// * `normal`         - The unit normal of the surface, pointing against the vector.
+    /// * `etai_over_etat` - The ratio of the refractive indices of both sides of the surface.
+    ///
+    /// The vector is assumed to have unit length.
+    pub fn refract(self, normal: Vector3D, etai_over_etat: f64) -> Vector3D {
+        let cos_theta = (-self).dot(normal).min(1.0);
+        let r_out_perp = etai_over_etat * (self + cos_theta * normal);
+        let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * normal;
+        r_out_perp + r_out_parallel
+    }
+
+    /// Returns `true` if the vector is close to zero in all dimensions.
+    pub fn near_zero(self) -> bool {
+        let s = 1e-8;
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
+    }
+}
+
+impl Default for Vector3D {
+    // The default vector is the zero vector
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Implement Eq and PartialEq for Vector3D
@@ -284,6 +369,14 @@ mod tests {
         z: 1.0,
     };
 
+    #[test]
+    fn axis() {
+        assert_eq!(
+            [V_123.axis(0), V_123.axis(1), V_123.axis(2)],
+            [V_123.x(), V_123.y(), V_123.z()]
+        );
+    }
+
     #[test]
     fn negation() {
         assert_eq!(
@@ -381,6 +474,66 @@ mod tests {
         assert_eq!(V_ONE.cross(V_ONE), Vector3D::new());
     }
 
+    #[test]
+    fn orthonormal_basis() {
+        for axis in [V_X, V_Y, V_Z, V_123.unit_vector()] {
+            let (u, v) = axis.orthonormal_basis();
+            for (a, b) in [(axis, u), (axis, v), (u, v)] {
+                assert!(a.dot(b).abs() < 1e-12, "{} and {} not perpendicular", a, b);
+            }
+            assert!((u.length() - 1.0).abs() < 1e-12 && (v.length() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn random_in_cone() {
+        let cos_theta_max = 0.9;
+        for _ in 0..1000 {
+            let v = Vector3D::random_in_cone(V_Z, cos_theta_max);
+            assert!((v.length() - 1.0).abs() < 1e-12, "{} not unit", v);
+            assert!(
+                v.dot(V_Z) >= cos_theta_max - 1e-12,
+                "{} outside the cone",
+                v
+            );
+        }
+    }
+
+    #[test]
+    fn reflect() {
+        let v = Vector3D::with_values(1.0, -1.0, 0.0);
+        assert_eq!(v.reflect(V_Y), Vector3D::with_values(1.0, 1.0, 0.0));
+        assert_eq!(V_X.reflect(V_Y), V_X);
+    }
+
+    #[test]
+    fn refract() {
+        let v = Vector3D::with_values(1.0, -1.0, 0.0).unit_vector();
+
+        // Same medium on both sides, the vector passes straight through
+        let straight = v.refract(V_Y, 1.0);
+        assert!((straight - v).length() < 1e-12, "{} not {}", straight, v);
+
+        // Into a denser medium the vector bends towards the normal
+        let bent = v.refract(V_Y, 1.0 / 1.5);
+        assert!(
+            Interval::new(1.0 - 1e-12, 1.0 + 1e-12).contains(bent.length()),
+            "Refracted vector {} not of unit length",
+            bent
+        );
+        assert!(bent.x() < v.x() && bent.y() < 0.0, "{} not bent", bent);
+    }
+
+    #[test]
+    fn near_zero() {
+        assert!(Vector3D::new().near_zero(), "Zero vector not near zero");
+        assert!(
+            Vector3D::with_values(1e-9, -1e-9, 0.0).near_zero(),
+            "Tiny vector not near zero"
+        );
+        assert!(!V_X.near_zero(), "Unit vector near zero");
+    }
+
     #[test]
     fn random_different_components() {
         let r = Vector3D::random();
@@ -423,6 +576,7 @@ mod tests {
         let rius = Vector3D::random_in_unit_sphere();
         let v = Vector3D::with_values(1.0, 1.0, 1.0);
         let roh = Vector3D::random_on_hemisphere(v);
+        let rcd = Vector3D::random_cosine_direction(V_Y);
 
         assert!(
             (0.0 <= r.x() && r.x() < 1.0)
@@ -432,9 +586,9 @@ mod tests {
         );
 
         assert!(
-            (0.0 <= r.x() && r.x() < 100.0)
-                && (0.0 <= r.y() && r.y() < 100.0)
-                && (0.0 <= r.z() && r.z() < 100.0),
+            (0.0 <= rw.x() && rw.x() < 100.0)
+                && (0.0 <= rw.y() && rw.y() < 100.0)
+                && (0.0 <= rw.z() && rw.z() < 100.0),
             "random_within() - components not between [0, 100)!"
         );
 
@@ -460,5 +614,16 @@ mod tests {
             roh.dot(v) > 0.0,
             "random_on_hemisphere() - not in correct hemisphere"
         );
+
+        assert!(
+            Interval::new(1.0 - 1e-12, 1.0 + 1e-12).contains(rcd.length()),
+            "random_cosine_direction() - {} Not a unit vector!",
+            rcd.length()
+        );
+
+        assert!(
+            rcd.dot(V_Y) >= 0.0,
+            "random_cosine_direction() - not in correct hemisphere"
+        );
     }
 }